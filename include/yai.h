@@ -0,0 +1,36 @@
+/* The C interface of the rlisp interpreter, in the cdylib built with the
+ * `ffi` feature: cargo build --release --features ffi
+ *
+ * Strings returned belong to the interpreter and stay valid until its next
+ * yai_run, or until it is freed. Null pointers are errors, never read. */
+#ifndef YAI_H
+#define YAI_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Yai Yai;
+
+/* a new interpreter, or NULL when it could not be made */
+Yai *yai_new(void);
+
+void yai_free(Yai *yai);
+
+/* Runs a program, whose globals stay for the runs after it. Returns 0
+ * without errors, 64 for a null argument, 65 when the program could not
+ * start, 70 when one of its statements failed, and its status when it
+ * called exit(). */
+int yai_run(Yai *yai, const char *source);
+
+/* the errors and warnings of the last run, or NULL when it had none */
+const char *yai_last_error(const Yai *yai);
+
+/* what the last run printed */
+const char *yai_get_output(const Yai *yai);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,103 @@
+use std::ops::{Index, IndexMut};
+
+use crate::{Expr, Span, Stmt};
+
+// index of an expression in the `Ast` it was parsed into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(u32);
+
+// index of a statement in the `Ast` it was parsed into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StmtId(u32);
+
+// Every node of a program, stored side by side rather than each in a box of
+// its own. Nodes refer to their children by index, so an id only means
+// something in the `Ast` that handed it out.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Ast {
+    exprs: Vec<Expr>,
+    stmts: Vec<Stmt>,
+    // the source each node was parsed from, by the same index as the nodes
+    expr_extents: Vec<Option<Span>>,
+    stmt_extents: Vec<Option<Span>>,
+}
+
+impl Ast {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_expr(&mut self, expr: Expr) -> ExprId {
+        self.exprs.push(expr);
+        self.expr_extents.push(None);
+        ExprId(self.exprs.len() as u32 - 1)
+    }
+
+    pub fn add_stmt(&mut self, stmt: Stmt) -> StmtId {
+        self.stmts.push(stmt);
+        self.stmt_extents.push(None);
+        StmtId(self.stmts.len() as u32 - 1)
+    }
+
+    // The source `expr` was parsed from, from the start of its first token
+    // to the end of its last. None for a node made up by the parser rather
+    // than written, or read back from JSON.
+    pub fn expr_extent(&self, expr: ExprId) -> Option<&Span> {
+        self.expr_extents[expr.0 as usize].as_ref()
+    }
+
+    // like `expr_extent`, for a statement, which takes in its semicolon
+    pub fn stmt_extent(&self, stmt: StmtId) -> Option<&Span> {
+        self.stmt_extents[stmt.0 as usize].as_ref()
+    }
+
+    pub(crate) fn set_expr_extent(&mut self, expr: ExprId, extent: Span) {
+        self.expr_extents[expr.0 as usize] = Some(extent);
+    }
+
+    pub(crate) fn set_stmt_extent(&mut self, stmt: StmtId, extent: Span) {
+        self.stmt_extents[stmt.0 as usize] = Some(extent);
+    }
+}
+
+impl Index<ExprId> for Ast {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.0 as usize]
+    }
+}
+
+impl IndexMut<ExprId> for Ast {
+    fn index_mut(&mut self, id: ExprId) -> &mut Expr {
+        &mut self.exprs[id.0 as usize]
+    }
+}
+
+impl Index<StmtId> for Ast {
+    type Output = Stmt;
+
+    fn index(&self, id: StmtId) -> &Stmt {
+        &self.stmts[id.0 as usize]
+    }
+}
+
+impl IndexMut<StmtId> for Ast {
+    fn index_mut(&mut self, id: StmtId) -> &mut Stmt {
+        &mut self.stmts[id.0 as usize]
+    }
+}
+
+// A node together with the `Ast` holding its children, which is what it
+// takes to display it.
+#[derive(Clone, Copy)]
+pub struct Node<'a, Id> {
+    pub ast: &'a Ast,
+    pub id: Id,
+}
+
+impl Ast {
+    pub fn node<Id>(&self, id: Id) -> Node<'_, Id> {
+        Node { ast: self, id }
+    }
+}
@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use crate::{ast::StmtId, TokenInfo};
+
+// The comments of a source parsed with trivia, kept beside its `Ast` rather
+// than in it. A comment on a line of its own leads the statement that starts
+// after it. Any other, such as one after a statement on the same line or
+// before a closing brace, leads nothing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Comments {
+    leading: HashMap<StmtId, Vec<TokenInfo>>,
+    others: Vec<TokenInfo>,
+}
+
+impl Comments {
+    // the comments right before `stmt`, in the order of the source
+    pub fn leading(&self, stmt: StmtId) -> &[TokenInfo] {
+        self.leading.get(&stmt).map_or(&[], Vec::as_slice)
+    }
+
+    // the comments that lead no statement, in the order of the source
+    pub fn others(&self) -> &[TokenInfo] {
+        &self.others
+    }
+
+    pub fn len(&self) -> usize {
+        self.leading.values().map(Vec::len).sum::<usize>() + self.others.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn attach(&mut self, stmt: StmtId, comments: Vec<TokenInfo>) {
+        if !comments.is_empty() {
+            self.leading.entry(stmt).or_default().extend(comments);
+        }
+    }
+
+    pub(crate) fn push_others(&mut self, comments: impl IntoIterator<Item = TokenInfo>) {
+        self.others.extend(comments);
+    }
+}
@@ -1,97 +1,222 @@
-use crate::{ErrorInfo, LiteralType, Object, Span, TokenInfo, TokenType};
+use std::sync::Arc;
+
+use crate::{
+    ast::{Ast, ExprId, Node},
+    ErrorInfo, LiteralType, Object, Span, Symbol, TokenInfo,
+};
 use std::fmt;
 
 mod visitor;
 pub use visitor::Visitor;
 
+// Where the resolver found the local a name refers to: `depth` scopes out
+// from the reference, at `index` among that scope's declarations.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expr {
     Assign {
-        name: String,
-        value: Box<Expr>,
+        name: Symbol,
+        value: ExprId,
         span: Span,
+        slot: Option<Slot>,
     },
     Binary {
-        left: Box<Expr>,
+        left: ExprId,
         op: TokenInfo,
-        right: Box<Expr>,
+        right: ExprId,
     },
     Call {
-        callee: Box<Expr>,
-        args: Vec<Expr>,
+        callee: ExprId,
+        args: Vec<ExprId>,
         span: Span,
     },
     Get {
-        object: Box<Expr>,
-        name: String,
+        object: ExprId,
+        name: Symbol,
         span: Span,
     },
     Grouping {
-        expr: Box<Expr>,
+        expr: ExprId,
         span: Span,
     },
     Literal {
         value: LiteralType,
     },
     Set {
-        object: Box<Expr>,
-        name: String,
-        value: Box<Expr>,
+        object: ExprId,
+        name: Symbol,
+        value: ExprId,
         span: Span,
     },
     Super {
-        name: String,
+        name: Symbol,
         span: Span,
+        slot: Option<Slot>,
     },
     Unary {
         op: TokenInfo,
-        right: Box<Expr>,
+        right: ExprId,
     },
     Variable {
-        name: String,
+        name: Symbol,
         span: Span,
+        slot: Option<Slot>,
     },
 }
 
 impl Expr {
-    pub fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<Object, ErrorInfo> {
+    pub fn accept<V: Visitor>(&self, ast: &Arc<Ast>, visitor: &mut V) -> Result<Object, ErrorInfo> {
         match self {
-            Expr::Assign { name, value, span } => visitor.visit_assign_expr(name, value, span),
-            Expr::Binary { left, op, right } => visitor.visit_binary_expr(left, op, right),
-            Expr::Call { callee, args, span } => visitor.visit_call_expr(callee, args, span),
-            Expr::Get { object, name, span } => visitor.visit_get_expr(object, name, span),
-            Expr::Grouping { expr, span } => visitor.visit_grouping_expr(expr, span),
-            Expr::Literal { value } => visitor.visit_literal_expr(value),
+            Expr::Assign {
+                name,
+                value,
+                span,
+                slot,
+            } => visitor.visit_assign_expr(ast, *name, *value, span, *slot),
+            Expr::Binary { left, op, right } => visitor.visit_binary_expr(ast, *left, op, *right),
+            Expr::Call { callee, args, span } => visitor.visit_call_expr(ast, *callee, args, span),
+            Expr::Get { object, name, span } => visitor.visit_get_expr(ast, *object, *name, span),
+            Expr::Grouping { expr, span } => visitor.visit_grouping_expr(ast, *expr, span),
+            Expr::Literal { value } => visitor.visit_literal_expr(ast, value),
             Expr::Set {
                 object,
                 name,
                 value,
                 span,
-            } => visitor.visit_set_expr(object, name, value, span),
-            Expr::Super { name, span } => visitor.visit_super_expr(name, span),
-            Expr::Unary { op, right } => visitor.visit_unary_expr(op, right),
-            Expr::Variable { name, span } => visitor.visit_variable_expr(name, span),
+            } => visitor.visit_set_expr(ast, *object, *name, *value, span),
+            Expr::Super { name, span, slot } => visitor.visit_super_expr(ast, *name, span, *slot),
+            Expr::Unary { op, right } => visitor.visit_unary_expr(ast, op, *right),
+            Expr::Variable { name, span, slot } => {
+                visitor.visit_variable_expr(ast, *name, span, *slot)
+            }
+        }
+    }
+
+    // Position of the leftmost part of the expression that has one, None
+    // for a lone literal.
+    pub fn span<'a>(&'a self, ast: &'a Ast) -> Option<&'a Span> {
+        match self {
+            Expr::Assign { span, .. }
+            | Expr::Grouping { span, .. }
+            | Expr::Super { span, .. }
+            | Expr::Variable { span, .. } => Some(span),
+            Expr::Binary { left, op, .. } => ast[*left].span(ast).or(Some(&op.span)),
+            Expr::Call { callee, span, .. } => ast[*callee].span(ast).or(Some(span)),
+            Expr::Get { object, span, .. } | Expr::Set { object, span, .. } => {
+                ast[*object].span(ast).or(Some(span))
+            }
+            Expr::Literal { .. } => None,
+            Expr::Unary { op, .. } => Some(&op.span),
         }
     }
 }
 
-impl fmt::Display for Expr {
+// Nodes are equal when the trees under them are, wherever they sit in their
+// arenas, so a program read back from JSON equals the one that was parsed.
+impl PartialEq for Node<'_, ExprId> {
+    fn eq(&self, other: &Self) -> bool {
+        let same = |a: &ExprId, b: &ExprId| self.ast.node(*a) == other.ast.node(*b);
+        match (&self.ast[self.id], &other.ast[other.id]) {
+            (
+                Expr::Assign {
+                    name,
+                    value,
+                    span,
+                    slot,
+                },
+                Expr::Assign {
+                    name: n,
+                    value: v,
+                    span: s,
+                    slot: sl,
+                },
+            ) => name == n && span == s && slot == sl && same(value, v),
+            (
+                Expr::Binary { left, op, right },
+                Expr::Binary {
+                    left: l,
+                    op: o,
+                    right: r,
+                },
+            ) => op == o && same(left, l) && same(right, r),
+            (
+                Expr::Call { callee, args, span },
+                Expr::Call {
+                    callee: c,
+                    args: a,
+                    span: s,
+                },
+            ) => {
+                span == s
+                    && same(callee, c)
+                    && args.len() == a.len()
+                    && args.iter().zip(a).all(|(x, y)| same(x, y))
+            }
+            (
+                Expr::Get { object, name, span },
+                Expr::Get {
+                    object: o,
+                    name: n,
+                    span: s,
+                },
+            ) => name == n && span == s && same(object, o),
+            (Expr::Grouping { expr, span }, Expr::Grouping { expr: e, span: s }) => {
+                span == s && same(expr, e)
+            }
+            (
+                Expr::Set {
+                    object,
+                    name,
+                    value,
+                    span,
+                },
+                Expr::Set {
+                    object: o,
+                    name: n,
+                    value: v,
+                    span: s,
+                },
+            ) => name == n && span == s && same(object, o) && same(value, v),
+            (Expr::Unary { op, right }, Expr::Unary { op: o, right: r }) => {
+                op == o && same(right, r)
+            }
+            // the rest hold no other nodes
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl fmt::Display for Node<'_, ExprId> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Expr::Assign { name, value, .. } => write!(f, "(= {name} {value})"),
-            Expr::Binary { left, op, right } => write!(f, "({} {left} {right})", op.token),
-            Expr::Call { callee, args, .. } => write!(f, "(call {callee} {:?})", args),
-            Expr::Get { object, name, .. } => write!(f, "(get {object} {name})"),
-            Expr::Grouping { expr, .. } => write!(f, "{expr}"),
+        let node = |id: &ExprId| self.ast.node(*id);
+        match &self.ast[self.id] {
+            Expr::Assign { name, value, .. } => write!(f, "(= {name} {})", node(value)),
+            Expr::Binary { left, op, right } => {
+                write!(f, "({} {} {})", op.token, node(left), node(right))
+            }
+            Expr::Call { callee, args, .. } => {
+                write!(f, "(call {}", node(callee))?;
+                for arg in args {
+                    write!(f, " {}", node(arg))?;
+                }
+                write!(f, ")")
+            }
+            Expr::Get { object, name, .. } => write!(f, "(get {} {name})", node(object)),
+            Expr::Grouping { expr, .. } => write!(f, "{}", node(expr)),
             Expr::Literal { value } => write!(f, "{:?}", value),
             Expr::Set {
                 object,
                 name,
                 value,
                 ..
-            } => write!(f, "(set {object} {name} {value})"),
+            } => write!(f, "(set {} {name} {})", node(object), node(value)),
             Expr::Super { name, .. } => write!(f, "(super {name})"),
-            Expr::Unary { op, right } => write!(f, "({} {right})", op.token),
+            Expr::Unary { op, right } => write!(f, "({} {})", op.token, node(right)),
             Expr::Variable { name, .. } => write!(f, "{name}"),
         }
     }
@@ -1,44 +1,77 @@
-use crate::{ErrorInfo, Expr, LiteralType, Object, Span, TokenType, TokenInfo};
+use std::sync::Arc;
+
+use crate::{
+    ast::{Ast, ExprId, Slot},
+    ErrorInfo, LiteralType, Object, Span, Symbol, TokenInfo,
+};
 
 pub trait Visitor {
     fn visit_assign_expr(
         &mut self,
-        name: &String,
-        value: &Box<Expr>,
+        ast: &Arc<Ast>,
+        name: Symbol,
+        value: ExprId,
         span: &Span,
+        slot: Option<Slot>,
     ) -> Result<Object, ErrorInfo>;
     fn visit_binary_expr(
         &mut self,
-        left: &Box<Expr>,
+        ast: &Arc<Ast>,
+        left: ExprId,
         op: &TokenInfo,
-        right: &Box<Expr>,
+        right: ExprId,
     ) -> Result<Object, ErrorInfo>;
     fn visit_call_expr(
         &mut self,
-        callee: &Box<Expr>,
-        args: &Vec<Expr>,
+        ast: &Arc<Ast>,
+        callee: ExprId,
+        args: &[ExprId],
         span: &Span,
     ) -> Result<Object, ErrorInfo>;
     fn visit_get_expr(
         &mut self,
-        object: &Box<Expr>,
-        name: &String,
+        ast: &Arc<Ast>,
+        object: ExprId,
+        name: Symbol,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo>;
+    fn visit_grouping_expr(
+        &mut self,
+        ast: &Arc<Ast>,
+        expr: ExprId,
         span: &Span,
     ) -> Result<Object, ErrorInfo>;
-    fn visit_grouping_expr(&mut self, expr: &Box<Expr>, span: &Span) -> Result<Object, ErrorInfo>;
-    fn visit_literal_expr(&mut self, value: &LiteralType) -> Result<Object, ErrorInfo>;
+    fn visit_literal_expr(
+        &mut self,
+        ast: &Arc<Ast>,
+        value: &LiteralType,
+    ) -> Result<Object, ErrorInfo>;
     fn visit_set_expr(
         &mut self,
-        object: &Box<Expr>,
-        name: &String,
-        value: &Box<Expr>,
+        ast: &Arc<Ast>,
+        object: ExprId,
+        name: Symbol,
+        value: ExprId,
         span: &Span,
     ) -> Result<Object, ErrorInfo>;
-    fn visit_super_expr(&mut self, name: &String, span: &Span) -> Result<Object, ErrorInfo>;
+    fn visit_super_expr(
+        &mut self,
+        ast: &Arc<Ast>,
+        name: Symbol,
+        span: &Span,
+        slot: Option<Slot>,
+    ) -> Result<Object, ErrorInfo>;
     fn visit_unary_expr(
         &mut self,
+        ast: &Arc<Ast>,
         op: &TokenInfo,
-        right: &Box<Expr>,
+        right: ExprId,
+    ) -> Result<Object, ErrorInfo>;
+    fn visit_variable_expr(
+        &mut self,
+        ast: &Arc<Ast>,
+        name: Symbol,
+        span: &Span,
+        slot: Option<Slot>,
     ) -> Result<Object, ErrorInfo>;
-    fn visit_variable_expr(&mut self, name: &String, span: &Span) -> Result<Object, ErrorInfo>;
 }
@@ -0,0 +1,1069 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use crate::{
+    ast::{Ast, ExprId, Node, StmtId},
+    builtins::json::quote,
+    Error, Expr, LiteralType, Program, Slot, Span, Stmt, Symbol, TokenInfo, TokenType,
+};
+
+// The AST as JSON, for caching parsed programs and diffing them between
+// versions of a script. Every statement and expression is an object naming
+// its variant in "kind", with the variant's fields under their own names:
+//
+//   {"kind": "Print", "exprs": [{"kind": "Literal", "value": 1}],
+//    "span": {"line": 1, "start": 0, "end": 5, "column": 1, "end_column": 6,
+//             "file": "main.rl"}}
+//
+// Literals are plain JSON values, operators are their tokens as written,
+// such as "+" or "&&", and missing values and slots are null. The slots
+// the resolver filled in are kept, so a resolved tree loads back resolved:
+// a declaration's is its index in its scope, a reference's is an object
+// with "depth" and "index".
+impl Program {
+    // the statements as a JSON array, one top level statement per line
+    pub fn to_json(&self) -> String {
+        if self.stmts.is_empty() {
+            return "[]".to_string();
+        }
+        let stmts: Vec<String> = self
+            .stmts
+            .iter()
+            .map(|stmt| self.ast.node(*stmt).to_json())
+            .collect();
+        format!("[\n  {}\n]", stmts.join(",\n  "))
+    }
+
+    pub fn from_json(text: &str) -> Result<Program, Error> {
+        let json = Reader::new(text).document()?;
+        let mut ast = Ast::new();
+        let mut decoder = Decoder::new(&mut ast);
+        let stmts = array(&json, "program")?
+            .iter()
+            .map(|stmt| decoder.stmt(stmt))
+            .collect::<Result<_, _>>()?;
+        Ok(Program::new(ast, stmts))
+    }
+}
+
+impl Node<'_, StmtId> {
+    pub fn to_json(&self) -> String {
+        stmt(self.ast, self.id).to_string()
+    }
+}
+
+impl Node<'_, ExprId> {
+    pub fn to_json(&self) -> String {
+        expr(self.ast, self.id).to_string()
+    }
+}
+
+impl Ast {
+    // adds the statement `text` encodes, and everything in it, to the nodes
+    pub fn stmt_from_json(&mut self, text: &str) -> Result<StmtId, Error> {
+        Decoder::new(self).stmt(&Reader::new(text).document()?)
+    }
+
+    pub fn expr_from_json(&mut self, text: &str) -> Result<ExprId, Error> {
+        Decoder::new(self).expr(&Reader::new(text).document()?)
+    }
+}
+
+impl LiteralType {
+    pub fn to_json(&self) -> String {
+        literal(self).to_string()
+    }
+
+    pub fn from_json(text: &str) -> Result<LiteralType, Error> {
+        literal_from(&Reader::new(text).document()?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Boolean(b) => write!(f, "{b}"),
+            Json::Number(n) if n.is_finite() => write!(f, "{n}"),
+            // only an overflowing literal is infinite, and reads back as one
+            Json::Number(n) if n.is_sign_positive() => write!(f, "1e999"),
+            Json::Number(_) => write!(f, "-1e999"),
+            Json::String(s) => write!(f, "{}", quote(s)),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { ", " };
+                    write!(f, "{separator}{item}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { ", " };
+                    write!(f, "{separator}{}: {value}", quote(name))?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn node(kind: &str, fields: Vec<(&str, Json)>) -> Json {
+    let kind = ("kind".to_string(), Json::String(kind.to_string()));
+    let fields = fields
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value));
+    Json::Object(std::iter::once(kind).chain(fields).collect())
+}
+
+fn string(s: &str) -> Json {
+    Json::String(s.to_string())
+}
+
+fn symbol(name: Symbol) -> Json {
+    string(name.as_str())
+}
+
+fn number(n: usize) -> Json {
+    Json::Number(n as f64)
+}
+
+fn optional<T>(value: Option<T>, encode: impl FnOnce(T) -> Json) -> Json {
+    value.map_or(Json::Null, encode)
+}
+
+fn stmts(ast: &Ast, stmts: &[StmtId]) -> Json {
+    Json::Array(stmts.iter().map(|id| stmt(ast, *id)).collect())
+}
+
+fn exprs(ast: &Ast, exprs: &[ExprId]) -> Json {
+    Json::Array(exprs.iter().map(|id| expr(ast, *id)).collect())
+}
+
+fn span(span: &Span) -> Json {
+    let mut fields = vec![
+        ("line".to_string(), number(span.line)),
+        ("start".to_string(), number(span.start)),
+        ("end".to_string(), number(span.end)),
+    ];
+    if span.column > 0 {
+        fields.push(("column".to_string(), number(span.column)));
+        fields.push(("end_column".to_string(), number(span.end_column)));
+    }
+    if let Some(file) = &span.file {
+        fields.push(("file".to_string(), string(file)));
+    }
+    Json::Object(fields)
+}
+
+fn slot(slot: Slot) -> Json {
+    Json::Object(vec![
+        ("depth".to_string(), number(slot.depth)),
+        ("index".to_string(), number(slot.index)),
+    ])
+}
+
+fn operator(op: &TokenInfo) -> Json {
+    Json::Object(vec![
+        ("token".to_string(), string(&op.token.to_string())),
+        ("span".to_string(), span(&op.span)),
+    ])
+}
+
+fn literal(value: &LiteralType) -> Json {
+    match value {
+        LiteralType::String(s) => string(s),
+        LiteralType::Number(n) => Json::Number(*n),
+        LiteralType::Boolean(b) => Json::Boolean(*b),
+        LiteralType::Nil => Json::Null,
+    }
+}
+
+fn stmt(ast: &Ast, id: StmtId) -> Json {
+    let expr = |id: ExprId| self::expr(ast, id);
+    let stmt = |id: StmtId| self::stmt(ast, id);
+    match &ast[id] {
+        Stmt::Expr { expr: value } => node("Expr", vec![("expr", expr(*value))]),
+        Stmt::Print {
+            exprs: values,
+            span: at,
+        } => node(
+            "Print",
+            vec![("exprs", exprs(ast, values)), ("span", span(at))],
+        ),
+        Stmt::Let {
+            name,
+            value,
+            is_const,
+            span: at,
+            slot: index,
+        } => node(
+            "Let",
+            vec![
+                ("name", symbol(*name)),
+                ("value", optional(*value, expr)),
+                ("is_const", Json::Boolean(*is_const)),
+                ("span", span(at)),
+                ("slot", optional(*index, number)),
+            ],
+        ),
+        Stmt::Block { stmts: body } => node("Block", vec![("stmts", stmts(ast, body))]),
+        Stmt::If {
+            condition,
+            truthy,
+            falsy,
+            span: at,
+        } => node(
+            "If",
+            vec![
+                ("condition", expr(*condition)),
+                ("truthy", stmt(*truthy)),
+                ("falsy", optional(*falsy, stmt)),
+                ("span", span(at)),
+            ],
+        ),
+        Stmt::While {
+            condition,
+            body,
+            span: at,
+        } => node(
+            "While",
+            vec![
+                ("condition", expr(*condition)),
+                ("body", stmt(*body)),
+                ("span", span(at)),
+            ],
+        ),
+        Stmt::Function {
+            name,
+            params,
+            body,
+            span: at,
+            slot: index,
+        } => node(
+            "Function",
+            vec![
+                ("name", symbol(*name)),
+                (
+                    "params",
+                    Json::Array(params.iter().copied().map(symbol).collect()),
+                ),
+                ("body", stmts(ast, body)),
+                ("span", span(at)),
+                ("slot", optional(*index, number)),
+            ],
+        ),
+        Stmt::Return { value, span: at } => node(
+            "Return",
+            vec![("value", optional(*value, expr)), ("span", span(at))],
+        ),
+        Stmt::Class {
+            name,
+            super_class,
+            methods,
+            span: at,
+        } => node(
+            "Class",
+            vec![
+                ("name", symbol(*name)),
+                ("super_class", optional(*super_class, symbol)),
+                ("methods", stmts(ast, methods)),
+                ("span", span(at)),
+            ],
+        ),
+        Stmt::Break { span: at } => node("Break", vec![("span", span(at))]),
+        Stmt::Continue { span: at } => node("Continue", vec![("span", span(at))]),
+    }
+}
+
+fn expr(ast: &Ast, id: ExprId) -> Json {
+    let expr = |id: ExprId| self::expr(ast, id);
+    match &ast[id] {
+        Expr::Assign {
+            name,
+            value,
+            span: at,
+            slot: resolved,
+        } => node(
+            "Assign",
+            vec![
+                ("name", symbol(*name)),
+                ("value", expr(*value)),
+                ("span", span(at)),
+                ("slot", optional(*resolved, slot)),
+            ],
+        ),
+        Expr::Binary { left, op, right } => node(
+            "Binary",
+            vec![
+                ("left", expr(*left)),
+                ("op", operator(op)),
+                ("right", expr(*right)),
+            ],
+        ),
+        Expr::Call {
+            callee,
+            args,
+            span: at,
+        } => node(
+            "Call",
+            vec![
+                ("callee", expr(*callee)),
+                ("args", exprs(ast, args)),
+                ("span", span(at)),
+            ],
+        ),
+        Expr::Get {
+            object,
+            name,
+            span: at,
+        } => node(
+            "Get",
+            vec![
+                ("object", expr(*object)),
+                ("name", symbol(*name)),
+                ("span", span(at)),
+            ],
+        ),
+        Expr::Grouping {
+            expr: inner,
+            span: at,
+        } => node("Grouping", vec![("expr", expr(*inner)), ("span", span(at))]),
+        Expr::Literal { value } => node("Literal", vec![("value", literal(value))]),
+        Expr::Set {
+            object,
+            name,
+            value,
+            span: at,
+        } => node(
+            "Set",
+            vec![
+                ("object", expr(*object)),
+                ("name", symbol(*name)),
+                ("value", expr(*value)),
+                ("span", span(at)),
+            ],
+        ),
+        Expr::Super {
+            name,
+            span: at,
+            slot: resolved,
+        } => node(
+            "Super",
+            vec![
+                ("name", symbol(*name)),
+                ("span", span(at)),
+                ("slot", optional(*resolved, slot)),
+            ],
+        ),
+        Expr::Unary { op, right } => {
+            node("Unary", vec![("op", operator(op)), ("right", expr(*right))])
+        }
+        Expr::Variable {
+            name,
+            span: at,
+            slot: resolved,
+        } => node(
+            "Variable",
+            vec![
+                ("name", symbol(*name)),
+                ("span", span(at)),
+                ("slot", optional(*resolved, slot)),
+            ],
+        ),
+    }
+}
+
+fn invalid(reason: impl fmt::Display) -> Error {
+    Error::Value(format!("invalid AST JSON: {reason}"))
+}
+
+fn expected(what: &str, name: &str) -> Error {
+    invalid(format!("expected {what} for \"{name}\""))
+}
+
+fn array<'a>(json: &'a Json, name: &str) -> Result<&'a [Json], Error> {
+    match json {
+        Json::Array(items) => Ok(items),
+        _ => Err(expected("an array", name)),
+    }
+}
+
+// the fields of a JSON object, looked up by name
+struct Fields<'a>(&'a [(String, Json)]);
+
+impl<'a> Fields<'a> {
+    fn of(json: &'a Json, name: &str) -> Result<Self, Error> {
+        match json {
+            Json::Object(fields) => Ok(Fields(fields)),
+            _ => Err(expected("an object", name)),
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<&'a Json> {
+        self.0
+            .iter()
+            .find_map(|(field, value)| (field == name).then_some(value))
+    }
+
+    fn get(&self, name: &str) -> Result<&'a Json, Error> {
+        self.find(name)
+            .ok_or_else(|| invalid(format!("missing \"{name}\"")))
+    }
+
+    // the field, or None when it is null
+    fn nullable(&self, name: &str) -> Result<Option<&'a Json>, Error> {
+        Ok(Some(self.get(name)?).filter(|value| **value != Json::Null))
+    }
+
+    fn string(&self, name: &str) -> Result<String, Error> {
+        match self.get(name)? {
+            Json::String(s) => Ok(s.clone()),
+            _ => Err(expected("a string", name)),
+        }
+    }
+
+    fn symbol(&self, name: &str) -> Result<Symbol, Error> {
+        match self.get(name)? {
+            Json::String(s) => Ok(Symbol::intern(s)),
+            _ => Err(expected("a string", name)),
+        }
+    }
+
+    fn boolean(&self, name: &str) -> Result<bool, Error> {
+        match self.get(name)? {
+            Json::Boolean(b) => Ok(*b),
+            _ => Err(expected("a boolean", name)),
+        }
+    }
+
+    fn index(&self, name: &str) -> Result<usize, Error> {
+        index(self.get(name)?, name)
+    }
+
+    fn array(&self, name: &str) -> Result<&'a [Json], Error> {
+        array(self.get(name)?, name)
+    }
+}
+
+fn index(json: &Json, name: &str) -> Result<usize, Error> {
+    match json {
+        Json::Number(n) if n.fract() == 0.0 && *n >= 0.0 && *n <= usize::MAX as f64 => {
+            Ok(*n as usize)
+        }
+        _ => Err(expected("a whole number", name)),
+    }
+}
+
+fn literal_from(json: &Json) -> Result<LiteralType, Error> {
+    match json {
+        Json::String(s) => Ok(LiteralType::String(s.clone())),
+        Json::Number(n) => Ok(LiteralType::Number(*n)),
+        Json::Boolean(b) => Ok(LiteralType::Boolean(*b)),
+        Json::Null => Ok(LiteralType::Nil),
+        _ => Err(expected("a string, number, boolean or null", "value")),
+    }
+}
+
+const OPERATORS: &[TokenType] = &[
+    TokenType::Plus,
+    TokenType::Minus,
+    TokenType::Times,
+    TokenType::Divide,
+    TokenType::Mod,
+    TokenType::LShift,
+    TokenType::RShift,
+    TokenType::And,
+    TokenType::Or,
+    TokenType::Xor,
+    TokenType::Not,
+    TokenType::Lt,
+    TokenType::Gt,
+    TokenType::Eq,
+    TokenType::Ne,
+    TokenType::Lte,
+    TokenType::Gte,
+    TokenType::LogicalAnd,
+    TokenType::LogicalOr,
+];
+
+// Builds the tree back from JSON into `ast`. The spans of a file share one
+// name, as they do when the file is parsed.
+struct Decoder<'a> {
+    ast: &'a mut Ast,
+    files: HashMap<String, Arc<str>>,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(ast: &'a mut Ast) -> Self {
+        Self {
+            ast,
+            files: HashMap::new(),
+        }
+    }
+
+    fn stmt(&mut self, json: &Json) -> Result<StmtId, Error> {
+        let fields = Fields::of(json, "statement")?;
+        let stmt = match fields.string("kind")?.as_str() {
+            "Expr" => Stmt::Expr {
+                expr: self.expr(fields.get("expr")?)?,
+            },
+            "Print" => Stmt::Print {
+                exprs: self.exprs(fields.array("exprs")?)?,
+                span: self.span(&fields)?,
+            },
+            "Let" => Stmt::Let {
+                name: fields.symbol("name")?,
+                value: self.optional_expr(&fields, "value")?,
+                is_const: fields.boolean("is_const")?,
+                span: self.span(&fields)?,
+                slot: index_from(&fields)?,
+            },
+            "Block" => Stmt::Block {
+                stmts: self.stmts(fields.array("stmts")?)?,
+            },
+            "If" => Stmt::If {
+                condition: self.expr(fields.get("condition")?)?,
+                truthy: self.stmt(fields.get("truthy")?)?,
+                falsy: match fields.nullable("falsy")? {
+                    Some(falsy) => Some(self.stmt(falsy)?),
+                    None => None,
+                },
+                span: self.span(&fields)?,
+            },
+            "While" => Stmt::While {
+                condition: self.expr(fields.get("condition")?)?,
+                body: self.stmt(fields.get("body")?)?,
+                span: self.span(&fields)?,
+            },
+            "Function" => Stmt::Function {
+                name: fields.symbol("name")?,
+                params: fields
+                    .array("params")?
+                    .iter()
+                    .map(|param| match param {
+                        Json::String(param) => Ok(Symbol::intern(param)),
+                        _ => Err(expected("a string", "params")),
+                    })
+                    .collect::<Result<_, _>>()?,
+                body: self.stmts(fields.array("body")?)?,
+                span: self.span(&fields)?,
+                slot: index_from(&fields)?,
+            },
+            "Return" => Stmt::Return {
+                value: self.optional_expr(&fields, "value")?,
+                span: self.span(&fields)?,
+            },
+            "Class" => Stmt::Class {
+                name: fields.symbol("name")?,
+                super_class: match fields.nullable("super_class")? {
+                    Some(Json::String(name)) => Some(Symbol::intern(name)),
+                    Some(_) => return Err(expected("a string", "super_class")),
+                    None => None,
+                },
+                methods: self.stmts(fields.array("methods")?)?,
+                span: self.span(&fields)?,
+            },
+            "Break" => Stmt::Break {
+                span: self.span(&fields)?,
+            },
+            "Continue" => Stmt::Continue {
+                span: self.span(&fields)?,
+            },
+            kind => return Err(invalid(format!("unknown statement kind '{kind}'"))),
+        };
+        Ok(self.ast.add_stmt(stmt))
+    }
+
+    fn expr(&mut self, json: &Json) -> Result<ExprId, Error> {
+        let fields = Fields::of(json, "expression")?;
+        let expr = match fields.string("kind")?.as_str() {
+            "Assign" => Expr::Assign {
+                name: fields.symbol("name")?,
+                value: self.expr(fields.get("value")?)?,
+                span: self.span(&fields)?,
+                slot: slot_from(&fields)?,
+            },
+            "Binary" => Expr::Binary {
+                left: self.expr(fields.get("left")?)?,
+                op: self.operator(fields.get("op")?)?,
+                right: self.expr(fields.get("right")?)?,
+            },
+            "Call" => Expr::Call {
+                callee: self.expr(fields.get("callee")?)?,
+                args: self.exprs(fields.array("args")?)?,
+                span: self.span(&fields)?,
+            },
+            "Get" => Expr::Get {
+                object: self.expr(fields.get("object")?)?,
+                name: fields.symbol("name")?,
+                span: self.span(&fields)?,
+            },
+            "Grouping" => Expr::Grouping {
+                expr: self.expr(fields.get("expr")?)?,
+                span: self.span(&fields)?,
+            },
+            "Literal" => Expr::Literal {
+                value: literal_from(fields.get("value")?)?,
+            },
+            "Set" => Expr::Set {
+                object: self.expr(fields.get("object")?)?,
+                name: fields.symbol("name")?,
+                value: self.expr(fields.get("value")?)?,
+                span: self.span(&fields)?,
+            },
+            "Super" => Expr::Super {
+                name: fields.symbol("name")?,
+                span: self.span(&fields)?,
+                slot: slot_from(&fields)?,
+            },
+            "Unary" => Expr::Unary {
+                op: self.operator(fields.get("op")?)?,
+                right: self.expr(fields.get("right")?)?,
+            },
+            "Variable" => Expr::Variable {
+                name: fields.symbol("name")?,
+                span: self.span(&fields)?,
+                slot: slot_from(&fields)?,
+            },
+            kind => return Err(invalid(format!("unknown expression kind '{kind}'"))),
+        };
+        Ok(self.ast.add_expr(expr))
+    }
+
+    fn stmts(&mut self, items: &[Json]) -> Result<Vec<StmtId>, Error> {
+        items.iter().map(|item| self.stmt(item)).collect()
+    }
+
+    fn exprs(&mut self, items: &[Json]) -> Result<Vec<ExprId>, Error> {
+        items.iter().map(|item| self.expr(item)).collect()
+    }
+
+    fn optional_expr(&mut self, fields: &Fields, name: &str) -> Result<Option<ExprId>, Error> {
+        fields
+            .nullable(name)?
+            .map(|value| self.expr(value))
+            .transpose()
+    }
+
+    fn operator(&mut self, json: &Json) -> Result<TokenInfo, Error> {
+        let fields = Fields::of(json, "op")?;
+        let text = fields.string("token")?;
+        let Some(token) = OPERATORS.iter().find(|op| op.to_string() == text) else {
+            return Err(invalid(format!("unknown operator '{text}'")));
+        };
+        Ok(TokenInfo {
+            token: token.clone(),
+            span: self.span(&fields)?,
+        })
+    }
+
+    // the "span" field of `fields`
+    fn span(&mut self, fields: &Fields) -> Result<Span, Error> {
+        let span = Fields::of(fields.get("span")?, "span")?;
+        let file = match span.find("file") {
+            Some(Json::String(file)) => Some(
+                self.files
+                    .entry(file.clone())
+                    .or_insert_with(|| Arc::from(file.as_str()))
+                    .clone(),
+            ),
+            Some(Json::Null) | None => None,
+            Some(_) => return Err(expected("a string", "file")),
+        };
+        // the columns are missing from trees written before they were kept
+        let column = |name| span.find(name).map_or(Ok(0), |json| index(json, name));
+        Ok(Span {
+            line: span.index("line")?,
+            start: span.index("start")?,
+            end: span.index("end")?,
+            column: column("column")?,
+            end_column: column("end_column")?,
+            file,
+        })
+    }
+}
+
+// the "slot" of a declaration
+fn index_from(fields: &Fields) -> Result<Option<usize>, Error> {
+    fields
+        .nullable("slot")?
+        .map(|slot| index(slot, "slot"))
+        .transpose()
+}
+
+// the "slot" of a reference
+fn slot_from(fields: &Fields) -> Result<Option<Slot>, Error> {
+    let Some(slot) = fields.nullable("slot")? else {
+        return Ok(None);
+    };
+    let slot = Fields::of(slot, "slot")?;
+    Ok(Some(Slot {
+        depth: slot.index("depth")?,
+        index: slot.index("index")?,
+    }))
+}
+
+// Recursive descent over the bytes of a JSON text into a `Json`, taking
+// the escapes `quote` writes along with any other JSON has.
+struct Reader<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    fn document(&mut self) -> Result<Json, Error> {
+        let value = self.value()?;
+        self.skip_whitespace();
+        if self.pos < self.text.len() {
+            return Err(self.error("unexpected trailing characters"));
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, Error> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'n') => self.keyword("null", Json::Null),
+            Some(b't') => self.keyword("true", Json::Boolean(true)),
+            Some(b'f') => self.keyword("false", Json::Boolean(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.object(),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, Error> {
+        if self.text[self.pos..].starts_with(word) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, Error> {
+        let start = self.pos;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        match self.text[start..self.pos].parse() {
+            Ok(n) => Ok(Json::Number(n)),
+            Err(_) => {
+                self.pos = start;
+                Err(self.error("invalid number"))
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, Error> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.eat(b']') {
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            if self.eat(b']') {
+                return Ok(Json::Array(items));
+            }
+            if !self.eat(b',') {
+                return Err(self.error("expected ',' or ']'"));
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, Error> {
+        self.pos += 1;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.eat(b'}') {
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a field name"));
+            }
+            let name = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(b':') {
+                return Err(self.error("expected ':'"));
+            }
+            fields.push((name, self.value()?));
+            self.skip_whitespace();
+            if self.eat(b'}') {
+                return Ok(Json::Object(fields));
+            }
+            if !self.eat(b',') {
+                return Err(self.error("expected ',' or '}'"));
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let Some(ch) = self.text[self.pos..].chars().next() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += ch.len_utf8();
+            match ch {
+                '"' => return Ok(s),
+                '\\' => s.push(self.escape()?),
+                ch => s.push(ch),
+            }
+        }
+    }
+
+    // the character of the escape sequence after a backslash
+    fn escape(&mut self) -> Result<char, Error> {
+        let ch = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.pos += 1;
+                let high = self.hex()?;
+                if !(0xD800..0xDC00).contains(&high) {
+                    return char::from_u32(high).ok_or_else(|| self.error("unpaired surrogate"));
+                }
+                // a high surrogate must be followed by its low half
+                if !self.text[self.pos..].starts_with("\\u") {
+                    return Err(self.error("unpaired surrogate"));
+                }
+                self.pos += 2;
+                let low = self.hex()?;
+                let code = 0x10000 + ((high - 0xD800) << 10) + low.wrapping_sub(0xDC00);
+                return match char::from_u32(code) {
+                    Some(ch) if (0xDC00..0xE000).contains(&low) => Ok(ch),
+                    _ => Err(self.error("unpaired surrogate")),
+                };
+            }
+            _ => return Err(self.error("invalid escape")),
+        };
+        self.pos += 1;
+        Ok(ch)
+    }
+
+    fn hex(&mut self) -> Result<u32, Error> {
+        let digits = self.text.get(self.pos..self.pos + 4).unwrap_or_default();
+        if digits.len() < 4 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(self.error("invalid unicode escape"));
+        }
+        self.pos += 4;
+        Ok(u32::from_str_radix(digits, 16).unwrap())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn error(&self, reason: &str) -> Error {
+        invalid(format!("{reason} at byte {}", self.pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interpretor::Capture, Ast, Error, ErrorFormat, Interpretor, Lexer, LiteralType, Parser,
+        Program, Resolver, Stmt,
+    };
+
+    const SOURCE: &str = "
+        let a = 1 + 2 * -3;
+        const name = \"say ü\";
+        fn add(x, y) {
+            if (x > y && !false) { return x - y; } else return;
+        }
+        let i = 0;
+        while (i < 3) { i += 1; print (i), add(i, 1), nil; }
+        class B < A { f() { return super.f(this.x = 2); } }
+        for (let j = 0; j < 1; j = j + 1) {}
+        print \"ünïcode\", HUGE, 0.1;";
+
+    // with a literal too long for a float, which lexes as infinite
+    fn source() -> String {
+        SOURCE.replace("HUGE", &format!("1{}", "0".repeat(400)))
+    }
+
+    fn parse(source: &str, file: &str) -> Program {
+        let lexer = Lexer::from_str(source).with_name(file);
+        Parser::new(lexer).parse_program().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = parse(&source(), "main.rl");
+        let json = program.to_json();
+        assert_eq!(json.lines().count(), program.stmts.len() + 2);
+        let loaded = Program::from_json(&json).unwrap();
+        assert_eq!(loaded, program);
+        assert_eq!(loaded.to_json(), json);
+        // the names of the file are shared again
+        let Stmt::Let { span: first, .. } = &loaded.ast[loaded.stmts[0]] else {
+            panic!("not a let")
+        };
+        let Stmt::Let { span: second, .. } = &loaded.ast[loaded.stmts[1]] else {
+            panic!("not a let")
+        };
+        let (first, second) = (first.file.as_ref(), second.file.as_ref());
+        assert!(std::sync::Arc::ptr_eq(first.unwrap(), second.unwrap()));
+
+        // resolved slots are kept
+        let mut resolved = program.clone();
+        Resolver::new().resolve(&mut resolved).unwrap();
+        assert_ne!(resolved, program);
+        assert_eq!(Program::from_json(&resolved.to_json()).unwrap(), resolved);
+
+        let mut ast = Ast::new();
+        for stmt in &program.stmts {
+            let json = program.ast.node(*stmt).to_json();
+            let loaded = ast.stmt_from_json(&json).unwrap();
+            assert!(ast.node(loaded) == program.ast.node(*stmt));
+        }
+        let empty = Program::new(Ast::new(), vec![]);
+        assert_eq!(Program::from_json("[]"), Ok(empty.clone()));
+        assert_eq!(empty.to_json(), "[]");
+    }
+
+    #[test]
+    fn test_format() {
+        let program = parse("-x;", "f.rl");
+        let Stmt::Expr { expr } = program.ast[program.stmts[0]] else {
+            panic!("not an expression")
+        };
+        assert_eq!(
+            program.ast.node(expr).to_json(),
+            "{\"kind\": \"Unary\", \"op\": {\"token\": \"-\", \"span\": \
+             {\"line\": 1, \"start\": 0, \"end\": 1, \"column\": 1, \"end_column\": 2, \
+             \"file\": \"f.rl\"}}, \"right\": {\"kind\": \"Variable\", \"name\": \"x\", \
+             \"span\": {\"line\": 1, \"start\": 1, \"end\": 2, \"column\": 2, \
+             \"end_column\": 3, \"file\": \"f.rl\"}, \"slot\": null}}"
+        );
+        let literal = LiteralType::String("a\"\n".to_string());
+        assert_eq!(literal.to_json(), "\"a\\\"\\n\"");
+        assert_eq!(LiteralType::from_json(&literal.to_json()), Ok(literal));
+        assert_eq!(LiteralType::from_json(" null "), Ok(LiteralType::Nil));
+        // a span without a file or columns leaves them out
+        let mut ast = Ast::new();
+        let expr = ast
+            .expr_from_json(
+                "{\"kind\": \"Grouping\", \"expr\": {\"kind\": \"Literal\", \"value\": true},
+                  \"span\": {\"line\": 2, \"start\": 4, \"end\": 5}}",
+            )
+            .unwrap();
+        assert_eq!(ast[expr].span(&ast).unwrap().file, None);
+        assert!(ast
+            .node(expr)
+            .to_json()
+            .ends_with("\"span\": {\"line\": 2, \"start\": 4, \"end\": 5}}"));
+    }
+
+    #[test]
+    fn test_runs_identically() {
+        let run = |program: Program| {
+            let (output, diagnostics) = (Capture::default(), Capture::default());
+            let mut interpretor = Interpretor::new()
+                .with_output(Box::new(output.clone()))
+                .with_diagnostics(Box::new(diagnostics.clone()))
+                .with_error_format(ErrorFormat::Json);
+            assert_eq!(interpretor.interpret(program), Ok(()));
+            (output.text(), diagnostics.text())
+        };
+        let source = "
+            fn fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+            let total = 0;
+            for (let i = 0; i < 10; i = i + 1) total += fib(i);
+            print total, \"done\" + \"!\";
+            print 1 / 0;";
+        let program = parse(source, "fib.rl");
+        let loaded = Program::from_json(&program.to_json()).unwrap();
+        let ran = run(program);
+        assert_eq!(ran.0, "88 done!\n");
+        assert!(ran.1.contains("\"line\": 6"), "{}", ran.1);
+        assert_eq!(run(loaded), ran);
+    }
+
+    #[test]
+    fn test_errors() {
+        fn error<T>(message: &str) -> Result<T, Error> {
+            Err(Error::Value(format!("invalid AST JSON: {message}")))
+        }
+        assert_eq!(
+            Program::from_json("[1,"),
+            error("unexpected end of input at byte 3")
+        );
+        assert_eq!(
+            Program::from_json("{}"),
+            error("expected an array for \"program\"")
+        );
+        assert_eq!(Ast::new().stmt_from_json("{}"), error("missing \"kind\""));
+        assert_eq!(
+            Ast::new().stmt_from_json("{\"kind\": \"Loop\"}"),
+            error("unknown statement kind 'Loop'")
+        );
+        assert_eq!(
+            Ast::new().stmt_from_json("{\"kind\": \"Break\", \"span\": {\"line\": -1}}"),
+            error("expected a whole number for \"line\"")
+        );
+        assert_eq!(
+            Ast::new().expr_from_json(
+                "{\"kind\": \"Unary\", \"op\": {\"token\": \"print\", \"span\": {}}, \"right\": null}"
+            ),
+            error("unknown operator 'print'")
+        );
+        assert_eq!(
+            Ast::new().expr_from_json("{\"kind\": \"Literal\", \"value\": [1]}"),
+            error("expected a string, number, boolean or null for \"value\"")
+        );
+        assert_eq!(
+            LiteralType::from_json("\"\\ud83d\""),
+            error("unpaired surrogate at byte 7")
+        );
+        assert_eq!(
+            LiteralType::from_json("1 2"),
+            error("unexpected trailing characters at byte 2")
+        );
+    }
+}
@@ -1,33 +1,72 @@
-use std::fmt;
+use std::{fmt, sync::Arc};
+
+mod arena;
+pub use arena::{Ast, ExprId, Node, StmtId};
 
 mod literal;
 pub use literal::LiteralType;
 
 mod expr;
-pub use expr::Expr;
+pub use expr::{Expr, Slot};
 
 mod stmt;
 pub use stmt::Stmt;
 
+mod comments;
+pub use comments::Comments;
+
+mod json;
+mod pretty;
+mod printer;
+pub mod query;
+pub mod visit;
+
 pub mod visitor {
     pub use crate::ast::{expr::Visitor as Expr, stmt::Visitor as Stmt};
 }
 
+// A parsed program: its top level statements and the `Ast` holding them
+// and everything in them. It holds none of an interpreter's state and is
+// Send and Sync, so a program parsed once can be shared between threads
+// behind an `Arc`, each running a clone of it on an interpreter of its own.
+// The interpreters and their values stay on the thread that made them.
+#[derive(Debug, Clone)]
 pub struct Program {
-    pub stmts: Vec<Stmt>,
+    pub ast: Arc<Ast>,
+    pub stmts: Vec<StmtId>,
 }
 
 impl Program {
-    pub fn new(stmts: Vec<Stmt>) -> Self {
-        Self { stmts }
+    pub fn new(ast: Ast, stmts: Vec<StmtId>) -> Self {
+        Self {
+            ast: Arc::new(ast),
+            stmts,
+        }
     }
 }
 
+// equal when their statements are, by the trees under them
+impl PartialEq for Program {
+    fn eq(&self, other: &Self) -> bool {
+        let (ast, other_ast) = (&self.ast, &other.ast);
+        self.stmts.len() == other.stmts.len()
+            && (self.stmts.iter().zip(&other.stmts))
+                .all(|(a, b)| ast.node(*a) == other_ast.node(*b))
+    }
+}
+
+// A line typed at a REPL: statements run as in a file, or a lone
+// expression whose value is shown, along with the program holding it.
+pub enum ReplLine {
+    Stmts(Program),
+    Expr(Program, ExprId),
+}
+
 impl fmt::Display for Program {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "(")?;
         for stmt in &self.stmts {
-            write!(f, "{}", stmt)?;
+            write!(f, "{}", self.ast.node(*stmt))?;
         }
         write!(f, ")")?;
         Ok(())
@@ -0,0 +1,164 @@
+use crate::{
+    ast::{Ast, Node, Program, StmtId},
+    token::join,
+    Stmt,
+};
+
+const INDENT: usize = 2;
+
+impl Program {
+    // The statements as an indented tree of s-expressions, one top level
+    // statement after another. Statements holding others put each on a line
+    // of its own, while expressions stay on one line, so precedence shows
+    // in how they nest.
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        for stmt in &self.stmts {
+            write_stmt(&mut out, &self.ast, *stmt, 0);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl Node<'_, StmtId> {
+    // The statement on one line, with the statements it holds left out as
+    // `...`, for tracing what runs.
+    pub fn summary(&self) -> String {
+        let ast = self.ast;
+        match &ast[self.id] {
+            Stmt::Block { .. } => "(block ...)".to_string(),
+            Stmt::If { condition, .. } => format!("(if {} ...)", ast.node(*condition)),
+            Stmt::While { condition, .. } => format!("(while {} ...)", ast.node(*condition)),
+            Stmt::Function { name, params, .. } => {
+                format!("(fn {name} ({}) ...)", join(params, " "))
+            }
+            Stmt::Class { name, .. } => format!("(class {name} ...)"),
+            _ => self.to_string(),
+        }
+    }
+}
+
+fn write_stmt(out: &mut String, ast: &Ast, stmt: StmtId, depth: usize) {
+    match &ast[stmt] {
+        Stmt::Block { stmts } => {
+            out.push_str("(block");
+            write_children(out, ast, stmts.iter().copied(), depth);
+        }
+        Stmt::If {
+            condition,
+            truthy,
+            falsy,
+            ..
+        } => {
+            out.push_str(&format!("(if {}", ast.node(*condition)));
+            write_children(out, ast, std::iter::once(*truthy).chain(*falsy), depth);
+        }
+        Stmt::While {
+            condition, body, ..
+        } => {
+            out.push_str(&format!("(while {}", ast.node(*condition)));
+            write_children(out, ast, std::iter::once(*body), depth);
+        }
+        Stmt::Function {
+            name, params, body, ..
+        } => {
+            out.push_str(&format!("(fn {name} ({})", join(params, " ")));
+            write_children(out, ast, body.iter().copied(), depth);
+        }
+        Stmt::Class {
+            name,
+            super_class,
+            methods,
+            ..
+        } => {
+            out.push_str(&format!("(class {name}"));
+            if let Some(super_class) = super_class {
+                out.push_str(&format!(" < {super_class}"));
+            }
+            write_children(out, ast, methods.iter().copied(), depth);
+        }
+        _ => out.push_str(&ast.node(stmt).to_string()),
+    }
+}
+
+// each of `stmts` on a line of its own, one level deeper, and then the
+// parenthesis closing their parent
+fn write_children(out: &mut String, ast: &Ast, stmts: impl Iterator<Item = StmtId>, depth: usize) {
+    for stmt in stmts {
+        out.push('\n');
+        out.push_str(&" ".repeat((depth + 1) * INDENT));
+        write_stmt(out, ast, stmt, depth + 1);
+    }
+    out.push(')');
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Parser};
+
+    fn pretty(input: &str) -> String {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let Ok(program) = parser.parse_program() else {
+            panic!("{input} does not parse")
+        };
+        program.pretty()
+    }
+
+    #[test]
+    fn test_pretty() {
+        let input = "
+        let a = 1 + 2 * 3;
+        fn add(x, y) {
+            if (x > y) { return x - y; } else return -(x + y);
+        }
+        while (a < 10) a = add(a, 1);
+        let b;";
+        let expected = "\
+(let a (+ 1 (* 2 3)))
+(fn add (x y)
+  (if (> x y)
+    (block
+      (return (- x y)))
+    (return (- (+ x y)))))
+(while (< a 10)
+  (= a (call add a 1)))
+(let b)
+";
+        assert_eq!(pretty(input), expected);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut parser = Parser::new(Lexer::new(
+            "if (a) { print 1; } while (b) {} fn f(x) {} print a + 1;".to_string(),
+        ));
+        let Ok(program) = parser.parse_program() else {
+            panic!("does not parse")
+        };
+        let summaries: Vec<String> = program
+            .stmts
+            .iter()
+            .map(|stmt| program.ast.node(*stmt).summary())
+            .collect();
+        assert_eq!(
+            summaries,
+            [
+                "(if a ...)",
+                "(while b ...)",
+                "(fn f (x) ...)",
+                "(print (+ a 1))"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_associativity() {
+        assert_eq!(pretty("1 - 2 - 3;"), "(- (- 1 2) 3)\n");
+        assert_eq!(pretty("a = b == c && d;"), "(= a (&& (== b c) d))\n");
+        assert_eq!(
+            pretty("class B < A { f() {} }"),
+            "(class B < A\n  (fn f ()))\n"
+        );
+    }
+}
@@ -0,0 +1,632 @@
+use crate::{
+    ast::{Ast, Comments, ExprId, Node, Program, StmtId},
+    token::join,
+    Expr, LiteralType, Span, Stmt, TokenType,
+};
+
+const INDENT: usize = 4;
+
+// How tightly each kind of expression binds, following the functions of the
+// parser from `assignment` to `primary`. An operand binding less tightly
+// than its place needs is put in parentheses.
+const ASSIGNMENT: u8 = 1;
+const OR: u8 = 2;
+const AND: u8 = 3;
+const EQUALITY: u8 = 4;
+const COMPARISON: u8 = 5;
+const TERM: u8 = 6;
+const FACTOR: u8 = 7;
+const UNARY: u8 = 8;
+const CALL: u8 = 9;
+const PRIMARY: u8 = 10;
+
+impl Program {
+    // The program as source text that parses back to the same statements:
+    // one statement per line, blocks indented by four spaces, a space on
+    // either side of binary operators and parentheses only where precedence
+    // needs them. Comments are not kept, and loops and compound assignments
+    // come out as what the parser turned them into.
+    pub fn to_source(&self) -> String {
+        Printer::new(&self.ast, None).program(&self.stmts)
+    }
+
+    // `to_source` for the program parsed from `source`, which writes the
+    // `for` loops and compound assignments back as they were written, and
+    // each comment leading a statement on a line of its own before it
+    pub fn format(&self, source: &str, comments: &Comments) -> String {
+        let mut printer = Printer::new(&self.ast, Some(source));
+        printer.comments = Some(comments);
+        printer.program(&self.stmts)
+    }
+}
+
+impl Node<'_, StmtId> {
+    // the statement as `Program::to_source` writes it, without a newline
+    pub fn to_source(&self) -> String {
+        let mut printer = Printer::new(self.ast, None);
+        printer.stmt(self.id, 0);
+        printer.out
+    }
+}
+
+impl Node<'_, ExprId> {
+    pub fn to_source(&self) -> String {
+        let mut printer = Printer::new(self.ast, None);
+        printer.expr(self.id, ASSIGNMENT);
+        printer.out
+    }
+}
+
+// the parts of a `for` loop, from the block the parser turns it into
+struct ForLoop {
+    init: Option<StmtId>,
+    condition: ExprId,
+    increment: Option<ExprId>,
+    body: StmtId,
+}
+
+struct Printer<'a> {
+    ast: &'a Ast,
+    // the text the tree was parsed from, to tell the nodes the parser made
+    // of a `for` loop or a compound assignment from those written out
+    source: Option<&'a str>,
+    comments: Option<&'a Comments>,
+    out: String,
+}
+
+impl<'a> Printer<'a> {
+    fn new(ast: &'a Ast, source: Option<&'a str>) -> Self {
+        Self {
+            ast,
+            source,
+            comments: None,
+            out: String::new(),
+        }
+    }
+
+    fn program(mut self, stmts: &[StmtId]) -> String {
+        for stmt in stmts {
+            self.line(*stmt, 0);
+            self.out.push('\n');
+        }
+        self.out
+    }
+
+    // `stmt` at the start of a line indented `depth` levels
+    fn line(&mut self, stmt: StmtId, depth: usize) {
+        self.comments(stmt, depth);
+        self.stmt(stmt, depth);
+    }
+
+    // the comments leading `stmt`, each on a line of its own
+    fn comments(&mut self, stmt: StmtId, depth: usize) {
+        if let Some(comments) = self.comments {
+            for comment in comments.leading(stmt) {
+                self.out.push_str(&comment.token.to_string());
+                self.indent(depth);
+            }
+        }
+    }
+
+    fn indent(&mut self, depth: usize) {
+        self.out.push('\n');
+        self.out.push_str(&" ".repeat(depth * INDENT));
+    }
+
+    // `stmt` from where the output is, the lines after its first indented
+    // `depth` levels
+    fn stmt(&mut self, stmt: StmtId, depth: usize) {
+        let ast = self.ast;
+        match &ast[stmt] {
+            Stmt::Expr { expr } => {
+                self.expr(*expr, ASSIGNMENT);
+                self.out.push(';');
+            }
+            Stmt::Print { exprs, .. } => {
+                self.out.push_str("print ");
+                self.exprs(exprs);
+                self.out.push(';');
+            }
+            Stmt::Let {
+                name,
+                value,
+                is_const,
+                ..
+            } => {
+                self.out.push_str(if *is_const { "const " } else { "let " });
+                self.out.push_str(name.as_str());
+                if let Some(value) = value {
+                    self.out.push_str(" = ");
+                    self.expr(*value, ASSIGNMENT);
+                }
+                self.out.push(';');
+            }
+            Stmt::Block { stmts } => match self.for_loop(stmt) {
+                Some(for_loop) => self.for_stmt(for_loop, depth),
+                None => self.block(stmts, depth),
+            },
+            Stmt::If {
+                condition,
+                truthy,
+                falsy,
+                ..
+            } => {
+                self.out.push_str("if (");
+                self.expr(*condition, ASSIGNMENT);
+                self.out.push(')');
+                let Some(falsy) = falsy else {
+                    self.body(*truthy, depth);
+                    return;
+                };
+                let braced = ends_in_open_if(ast, *truthy);
+                if braced {
+                    // so the `else` is not taken for that `if`'s
+                    self.out.push_str(" {");
+                    self.indent(depth + 1);
+                    self.line(*truthy, depth + 1);
+                    self.indent(depth);
+                    self.out.push('}');
+                } else {
+                    self.body(*truthy, depth);
+                }
+                if braced || self.is_block(*truthy) {
+                    self.out.push(' ');
+                } else {
+                    self.indent(depth);
+                }
+                self.out.push_str("else");
+                if let Stmt::If { .. } = ast[*falsy] {
+                    self.out.push(' ');
+                    self.stmt(*falsy, depth);
+                } else {
+                    self.body(*falsy, depth);
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.out.push_str("while (");
+                self.expr(*condition, ASSIGNMENT);
+                self.out.push(')');
+                self.body(*body, depth);
+            }
+            Stmt::Function { .. } => {
+                self.out.push_str("fn ");
+                self.function(stmt, depth);
+            }
+            Stmt::Return { value, .. } => {
+                self.out.push_str("return");
+                if let Some(value) = value {
+                    self.out.push(' ');
+                    self.expr(*value, ASSIGNMENT);
+                }
+                self.out.push(';');
+            }
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+                ..
+            } => {
+                self.out.push_str(&format!("class {name} "));
+                if let Some(super_class) = super_class {
+                    self.out.push_str(&format!("< {super_class} "));
+                }
+                self.out.push('{');
+                for method in methods {
+                    self.indent(depth + 1);
+                    self.comments(*method, depth + 1);
+                    self.function(*method, depth + 1);
+                }
+                if !methods.is_empty() {
+                    self.indent(depth);
+                }
+                self.out.push('}');
+            }
+            Stmt::Break { .. } => self.out.push_str("break;"),
+            Stmt::Continue { .. } => self.out.push_str("continue;"),
+        }
+    }
+
+    // the name, parameters and body of a function, which for a method is
+    // all there is
+    fn function(&mut self, stmt: StmtId, depth: usize) {
+        if let Stmt::Function {
+            name, params, body, ..
+        } = &self.ast[stmt]
+        {
+            self.out
+                .push_str(&format!("{name}({}) ", join(params, ", ")));
+            self.block(body, depth);
+        }
+    }
+
+    fn block(&mut self, stmts: &[StmtId], depth: usize) {
+        self.out.push('{');
+        for stmt in stmts {
+            self.indent(depth + 1);
+            self.line(*stmt, depth + 1);
+        }
+        if !stmts.is_empty() {
+            self.indent(depth);
+        }
+        self.out.push('}');
+    }
+
+    // the body of an `if` or a loop: a block on the same line, anything
+    // else on a line of its own one level deeper
+    fn body(&mut self, body: StmtId, depth: usize) {
+        if self.is_block(body) {
+            self.out.push(' ');
+            self.stmt(body, depth);
+        } else {
+            self.indent(depth + 1);
+            self.line(body, depth + 1);
+        }
+    }
+
+    // whether `stmt` is written in braces of its own
+    fn is_block(&self, stmt: StmtId) -> bool {
+        matches!(self.ast[stmt], Stmt::Block { .. }) && self.for_loop(stmt).is_none()
+    }
+
+    fn for_stmt(&mut self, for_loop: ForLoop, depth: usize) {
+        self.out.push_str("for (");
+        match for_loop.init {
+            Some(init) => self.stmt(init, depth),
+            None => self.out.push(';'),
+        }
+        self.out.push(' ');
+        self.expr(for_loop.condition, ASSIGNMENT);
+        self.out.push(';');
+        if let Some(increment) = for_loop.increment {
+            self.out.push(' ');
+            self.expr(increment, ASSIGNMENT);
+        }
+        self.out.push(')');
+        self.body(for_loop.body, depth);
+    }
+
+    // The `for` loop `stmt` was parsed from, if it was: the block of the
+    // initializer and a `while` starting at the `for`, whose body ends with
+    // the increment when one came before the body.
+    fn for_loop(&self, stmt: StmtId) -> Option<ForLoop> {
+        let ast = self.ast;
+        let Stmt::Block { stmts } = &ast[stmt] else {
+            return None;
+        };
+        let (init, last) = match stmts.as_slice() {
+            [last] => (None, *last),
+            [init, last] if matches!(ast[*init], Stmt::Let { .. } | Stmt::Expr { .. }) => {
+                (Some(*init), *last)
+            }
+            _ => return None,
+        };
+        let Stmt::While {
+            condition,
+            body,
+            span,
+        } = &ast[last]
+        else {
+            return None;
+        };
+        if self.text(span)? != "for" {
+            return None;
+        }
+        let header_end = header_end(self.source?, span.start)?;
+        let mut for_loop = ForLoop {
+            init,
+            condition: *condition,
+            increment: None,
+            body: *body,
+        };
+        if let Stmt::Block { stmts } = &ast[*body] {
+            if let [body, increment] = stmts.as_slice() {
+                if let Stmt::Expr { expr } = ast[*increment] {
+                    let span = ast[expr].span(ast);
+                    if span.is_some_and(|span| span.start < header_end) {
+                        for_loop.increment = Some(expr);
+                        for_loop.body = *body;
+                    }
+                }
+            }
+        }
+        Some(for_loop)
+    }
+
+    // the source text `span` was taken from
+    fn text(&self, span: &Span) -> Option<&'a str> {
+        self.source?.get(span.start..span.end)
+    }
+
+    fn exprs(&mut self, exprs: &[ExprId]) {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.expr(*expr, ASSIGNMENT);
+        }
+    }
+
+    // `expr`, in parentheses when it binds less tightly than `min`
+    fn expr(&mut self, expr: ExprId, min: u8) {
+        let ast = self.ast;
+        if precedence(ast, expr) < min {
+            self.out.push('(');
+            self.expr(expr, ASSIGNMENT);
+            self.out.push(')');
+            return;
+        }
+        match &ast[expr] {
+            Expr::Assign { name, value, .. } => {
+                self.out.push_str(name.as_str());
+                self.assigned(*value);
+            }
+            Expr::Binary { left, op, right } => {
+                let precedence = binary_precedence(&op.token);
+                self.expr(*left, precedence);
+                self.out.push_str(&format!(" {} ", op.token));
+                self.expr(*right, precedence + 1);
+            }
+            Expr::Call { callee, args, .. } => {
+                self.expr(*callee, CALL);
+                self.out.push('(');
+                self.exprs(args);
+                self.out.push(')');
+            }
+            Expr::Get { object, name, .. } => {
+                self.expr(*object, CALL);
+                self.out.push_str(&format!(".{name}"));
+            }
+            Expr::Grouping { expr, .. } => self.expr(*expr, min),
+            Expr::Literal { value } => match value {
+                LiteralType::String(s) => self.out.push_str(&format!("\"{s}\"")),
+                LiteralType::Number(n) => self.out.push_str(&n.to_string()),
+                LiteralType::Boolean(b) => self.out.push_str(&b.to_string()),
+                LiteralType::Nil => self.out.push_str("nil"),
+            },
+            Expr::Set {
+                object,
+                name,
+                value,
+                ..
+            } => {
+                self.expr(*object, CALL);
+                self.out.push_str(&format!(".{name}"));
+                self.assigned(*value);
+            }
+            Expr::Super { name, .. } => self.out.push_str(&format!("super.{name}")),
+            Expr::Unary { op, right } => {
+                self.out.push_str(&op.token.to_string());
+                self.expr(*right, UNARY);
+            }
+            Expr::Variable { name, .. } => self.out.push_str(name.as_str()),
+        }
+    }
+
+    // The value given to a variable or a property, after its `=`, or after
+    // the operator of the compound assignment it was parsed from. The
+    // parser makes `a += b` into `a = a + b`, with the `+` where the `+=`
+    // was written, and the target read back through the same node.
+    fn assigned(&mut self, value: ExprId) {
+        if let Expr::Binary { op, right, .. } = &self.ast[value] {
+            let compound = format!("{}=", op.token);
+            if self.text(&op.span) == Some(compound.as_str()) {
+                self.out.push_str(&format!(" {compound} "));
+                self.expr(*right, OR);
+                return;
+            }
+        }
+        self.out.push_str(" = ");
+        self.expr(value, OR);
+    }
+}
+
+// Offset of the `)` closing the header of the `for` loop at `start`, past
+// the parentheses, strings and comments inside it.
+fn header_end(source: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut chars = source.get(start..)?.char_indices();
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '(' => depth += 1,
+            ')' if depth == 1 => return Some(start + i),
+            ')' => depth -= 1,
+            '"' => {
+                chars.find(|(_, ch)| *ch == '"')?;
+            }
+            '#' => {
+                chars.find(|(_, ch)| *ch == '\n');
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Whether an `else` written after `stmt` would belong to an `if` inside it.
+// The parser never makes such a tree, it gives the `else` to the inner `if`.
+fn ends_in_open_if(ast: &Ast, stmt: StmtId) -> bool {
+    match &ast[stmt] {
+        Stmt::If { falsy: None, .. } => true,
+        Stmt::If {
+            falsy: Some(body), ..
+        }
+        | Stmt::While { body, .. } => ends_in_open_if(ast, *body),
+        _ => false,
+    }
+}
+
+fn precedence(ast: &Ast, expr: ExprId) -> u8 {
+    match &ast[expr] {
+        Expr::Assign { .. } | Expr::Set { .. } => ASSIGNMENT,
+        Expr::Binary { op, .. } => binary_precedence(&op.token),
+        Expr::Unary { .. } => UNARY,
+        Expr::Call { .. } | Expr::Get { .. } => CALL,
+        // the parentheses of a grouping are written only where needed
+        Expr::Grouping { expr, .. } => precedence(ast, *expr),
+        Expr::Literal { .. } | Expr::Super { .. } | Expr::Variable { .. } => PRIMARY,
+    }
+}
+
+fn binary_precedence(op: &TokenType) -> u8 {
+    match op {
+        TokenType::LogicalOr => OR,
+        TokenType::LogicalAnd => AND,
+        TokenType::Eq | TokenType::Ne => EQUALITY,
+        TokenType::Gt | TokenType::Gte | TokenType::Lt | TokenType::Lte => COMPARISON,
+        TokenType::Times | TokenType::Divide => FACTOR,
+        // `|` too, which `term` takes before `or` can
+        _ => TERM,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{Lexer, Parser, Stmt};
+
+    fn to_source(input: &str) -> String {
+        let Ok(program) = Parser::from_str(input).parse_program() else {
+            panic!("{input} does not parse")
+        };
+        program.to_source()
+    }
+
+    #[test]
+    fn test_to_source() {
+        let input = "
+        let  a=1+2*3 ;const b=  \"x y\";
+        fn add(x,y){
+            if (x>y) { return x-y; } else if (x == y) return 0; else return -(x+y);
+        }
+        while(a<10)a=add(a,1);
+        class B<A { init(n) { this.n = n; super.init(); } f() {} }
+        class C {}
+        print a, b.len(), !true, nil;
+        {}";
+        let expected = "\
+let a = 1 + 2 * 3;
+const b = \"x y\";
+fn add(x, y) {
+    if (x > y) {
+        return x - y;
+    } else if (x == y)
+        return 0;
+    else
+        return -(x + y);
+}
+while (a < 10)
+    a = add(a, 1);
+class B < A {
+    init(n) {
+        this.n = n;
+        super.init();
+    }
+    f() {}
+}
+class C {}
+print a, b.len(), !true, nil;
+{}
+";
+        assert_eq!(to_source(input), expected);
+        assert_eq!(to_source(expected), expected);
+    }
+
+    #[test]
+    fn test_parentheses() {
+        let cases = [
+            // only those precedence needs are kept
+            ("((1 + 2)) * (3);", "(1 + 2) * 3;"),
+            ("1 + (2 * 3);", "1 + 2 * 3;"),
+            ("(1 - 2) - 3;", "1 - 2 - 3;"),
+            ("1 - (2 - 3);", "1 - (2 - 3);"),
+            ("(a && b) == c;", "(a && b) == c;"),
+            ("-(-a);", "--a;"),
+            ("(-a).abs();", "(-a).abs();"),
+            ("(f)(1)(2);", "f(1)(2);"),
+            ("(a + b).c = d;", "(a + b).c = d;"),
+            ("a = (b = c);", "a = (b = c);"),
+            ("f(a = 1);", "f(a = 1);"),
+            ("(1 | 2) + 3;", "1 | 2 + 3;"),
+            ("2.5.round();", "2.5.round();"),
+            // compound assignments come out as the parser desugars them
+            ("a += 1 * 2;", "a = a + 1 * 2;"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(to_source(input), format!("{expected}\n"), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_dangling_else() {
+        // `if (a) if (b) x; else y;` with the `else` moved to the outer `if`,
+        // which only braces can say
+        let mut program = Parser::from_str("if (a) if (b) x; else y;")
+            .parse_program()
+            .unwrap();
+        let ast = Arc::get_mut(&mut program.ast).unwrap();
+        let Stmt::If { truthy, .. } = ast[program.stmts[0]] else {
+            panic!("an if")
+        };
+        let Stmt::If { falsy, .. } = &mut ast[truthy] else {
+            panic!("an if")
+        };
+        let moved = falsy.take();
+        let Stmt::If { falsy, .. } = &mut ast[program.stmts[0]] else {
+            panic!("an if")
+        };
+        *falsy = moved;
+        let expected = "if (a) {\n    if (b)\n        x;\n} else\n    y;\n";
+        assert_eq!(program.to_source(), expected);
+        let reparsed = Parser::from_str(expected).parse_program().unwrap();
+        assert_eq!(reparsed.to_string(), "((if a then ((if b then x)) else y))");
+    }
+
+    #[test]
+    fn test_format() {
+        let format = |input: &str| {
+            let mut parser = Parser::new(Lexer::from_str(input).with_trivia(true));
+            let program = parser.parse_program().unwrap();
+            program.format(input, &parser.take_comments())
+        };
+        let input = "
+        for (let i=0;i<3;i+=1) print i;
+        for (;;) { x.n *= 2; if (f(\")(\")) break_out(); }
+        for (i = 0; i < 3;) { a; b; }
+        # a for loop written out, which stays as it is
+        { let j = 0; while (j < 3) {
+            #   counting
+            print j; j = j + 1; } }";
+        let expected = "\
+for (let i = 0; i < 3; i += 1)
+    print i;
+for (; true;) {
+    x.n *= 2;
+    if (f(\")(\"))
+        break_out();
+}
+for (i = 0; i < 3;) {
+    a;
+    b;
+}
+# a for loop written out, which stays as it is
+{
+    let j = 0;
+    while (j < 3) {
+        #   counting
+        print j;
+        j = j + 1;
+    }
+}
+";
+        assert_eq!(format(input), expected);
+        assert_eq!(format(expected), expected);
+        // the same tree either way
+        let parsed = Parser::from_str(input).parse_program().unwrap();
+        let formatted = Parser::from_str(expected).parse_program().unwrap();
+        assert_eq!(formatted.to_string(), parsed.to_string());
+        assert_eq!(formatted.to_source(), parsed.to_source());
+    }
+}
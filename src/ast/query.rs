@@ -0,0 +1,176 @@
+use crate::{
+    ast::{
+        visit::{walk_expr, walk_stmt, Visitor},
+        Program,
+    },
+    Ast, ExprId, Span, StmtId,
+};
+
+// A statement or an expression of a program, by its id in the program's
+// `Ast`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeRef {
+    Stmt(StmtId),
+    Expr(ExprId),
+}
+
+impl NodeRef {
+    // the source the node was parsed from, as `Ast::expr_extent` gives it
+    pub fn extent(self, ast: &Ast) -> Option<&Span> {
+        match self {
+            NodeRef::Stmt(stmt) => ast.stmt_extent(stmt),
+            NodeRef::Expr(expr) => ast.expr_extent(expr),
+        }
+    }
+}
+
+// The innermost node of `program` whose extent covers the byte at `offset`,
+// followed by the nodes containing it out to the top level statement, or
+// nothing when no statement covers it. An extent covers from the start of
+// its first token up to, but not including, the end of its last, so:
+//
+// - an offset at the start of a token is covered by the node of the token,
+//   and one just past its end only by the nodes going on after it
+// - an offset in the whitespace or comments between the statements of a
+//   block gives the block, and between top level statements nothing
+// - the offset at the end of the source gives nothing
+//
+// The nodes the parser made up rather than read, such as the block a `for`
+// loop runs its body and increment in, have no extent. They are searched
+// through, and are in the chain when what they hold covers the offset.
+pub fn node_at(program: &Program, offset: usize) -> Vec<NodeRef> {
+    let mut finder = Finder {
+        offset,
+        chain: Vec::new(),
+    };
+    for stmt in &program.stmts {
+        finder.visit_stmt(&program.ast, *stmt);
+        if !finder.chain.is_empty() {
+            break;
+        }
+    }
+    finder.chain
+}
+
+// walks into the nodes covering `offset`, adding each to the chain on the
+// way back out, once what is under it has been
+struct Finder {
+    offset: usize,
+    chain: Vec<NodeRef>,
+}
+
+impl Finder {
+    fn find(&mut self, ast: &Ast, node: NodeRef, walk: impl FnOnce(&mut Self)) {
+        let extent = node.extent(ast);
+        if extent.is_some_and(|extent| !(extent.start..extent.end).contains(&self.offset)) {
+            return;
+        }
+        let found = self.chain.len();
+        walk(self);
+        if extent.is_some() || self.chain.len() > found {
+            self.chain.push(node);
+        }
+    }
+}
+
+impl Visitor for Finder {
+    fn visit_stmt(&mut self, ast: &Ast, stmt: StmtId) {
+        self.find(ast, NodeRef::Stmt(stmt), |finder| {
+            walk_stmt(finder, ast, stmt)
+        });
+    }
+
+    fn visit_expr(&mut self, ast: &Ast, expr: ExprId) {
+        self.find(ast, NodeRef::Expr(expr), |finder| {
+            walk_expr(finder, ast, expr)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    const SOURCE: &str = "let a = 1 + f(2);
+fn f(x) {
+  print x;
+
+  return (x);
+}
+for (let i = 0; i < 3; i = i + 1) a = i;
+";
+
+    // the source of each node at `offset`, innermost first
+    fn texts(program: &Program, offset: usize) -> Vec<&'static str> {
+        node_at(program, offset)
+            .into_iter()
+            .map(|node| match node.extent(&program.ast) {
+                Some(extent) => &SOURCE[extent.start..extent.end],
+                None => "?",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_node_at() {
+        let program = Parser::from_str(SOURCE).parse_program().unwrap();
+        let at = |text: &str| SOURCE.find(text).unwrap();
+
+        // the start of a token, its inside and just past its end
+        assert_eq!(
+            texts(&program, at("1 +")),
+            ["1", "1 + f(2)", "let a = 1 + f(2);"]
+        );
+        assert_eq!(
+            texts(&program, at(" + f")),
+            ["1 + f(2)", "let a = 1 + f(2);"]
+        );
+        assert_eq!(
+            texts(&program, at("f(2)") + 1),
+            ["f(2)", "1 + f(2)", "let a = 1 + f(2);"]
+        );
+        assert_eq!(texts(&program, at("let a")), ["let a = 1 + f(2);"]);
+        assert_eq!(texts(&program, at(";\nfn")), ["let a = 1 + f(2);"]);
+
+        // between statements, in a block and at the top level
+        let function = "fn f(x) {\n  print x;\n\n  return (x);\n}";
+        assert_eq!(texts(&program, at("\n  return")), [function]);
+        assert_eq!(texts(&program, at("\nfn")), Vec::<&str>::new());
+        assert_eq!(
+            texts(&program, at("(x);") + 1),
+            ["x", "(x)", "return (x);", function]
+        );
+
+        // in the loop a `for` is made into, through the block the parser
+        // made up for its body and increment
+        let desugared = "for (let i = 0; i < 3; i = i + 1) a = i;";
+        let chain = texts(&program, at("i + 1"));
+        assert_eq!(
+            chain,
+            ["i", "i + 1", "i = i + 1", "?", "?", desugared, desugared]
+        );
+        assert_eq!(
+            texts(&program, at("a = i") + 4)[..3],
+            ["i", "a = i", "a = i;"]
+        );
+
+        // the end of the source and past it
+        assert_eq!(node_at(&program, SOURCE.len()), []);
+        assert_eq!(node_at(&program, SOURCE.len() + 10), []);
+    }
+
+    #[test]
+    fn test_node_at_ends() {
+        // a statement ending the source covers its last byte but not the end
+        let source = "print 1;";
+        let program = Parser::from_str(source).parse_program().unwrap();
+        assert_eq!(node_at(&program, source.len() - 1).len(), 1);
+        assert_eq!(node_at(&program, source.len()), []);
+        assert_eq!(node_at(&program, 0).len(), 1);
+        assert_eq!(node_at(&program, 6).len(), 2);
+
+        let program = Parser::from_str("").parse_program().unwrap();
+        assert_eq!(node_at(&program, 0), []);
+    }
+}
@@ -1,4 +1,10 @@
-use crate::{ErrorInfo, Expr, Object, Span};
+use std::sync::Arc;
+
+use crate::{
+    ast::{Ast, ExprId, Node, StmtId},
+    token::join,
+    ErrorInfo, Span, Symbol,
+};
 
 mod visitor;
 pub use visitor::Visitor;
@@ -8,43 +14,49 @@ use std::fmt;
 #[derive(Debug, PartialEq, Clone)]
 pub enum Stmt {
     Expr {
-        expr: Expr,
+        expr: ExprId,
     },
     Print {
-        expr: Expr,
+        exprs: Vec<ExprId>,
+        span: Span,
     },
     Let {
-        name: String,
-        value: Option<Expr>,
+        name: Symbol,
+        value: Option<ExprId>,
         is_const: bool,
         span: Span,
+        // index among the declarations of its scope, None for a global
+        slot: Option<usize>,
     },
     Block {
-        stmts: Vec<Stmt>,
+        stmts: Vec<StmtId>,
     },
     If {
-        condition: Expr,
-        truthy: Box<Stmt>,
-        falsy: Option<Box<Stmt>>,
+        condition: ExprId,
+        truthy: StmtId,
+        falsy: Option<StmtId>,
+        span: Span,
     },
     While {
-        condition: Expr,
-        body: Box<Stmt>,
+        condition: ExprId,
+        body: StmtId,
+        span: Span,
     },
     Function {
-        name: String,
-        params: Vec<String>,
-        body: Vec<Stmt>,
+        name: Symbol,
+        params: Vec<Symbol>,
+        body: Vec<StmtId>,
         span: Span,
+        slot: Option<usize>,
     },
     Return {
-        value: Option<Expr>,
+        value: Option<ExprId>,
         span: Span,
     },
     Class {
-        name: String,
-        super_class: Option<String>,
-        methods: Vec<Stmt>,
+        name: Symbol,
+        super_class: Option<Symbol>,
+        methods: Vec<StmtId>,
         span: Span,
     },
     Break {
@@ -56,64 +68,205 @@ pub enum Stmt {
 }
 
 impl Stmt {
-    pub fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ErrorInfo> {
+    pub fn accept<V: Visitor>(&self, ast: &Arc<Ast>, visitor: &mut V) -> Result<(), ErrorInfo> {
         match self {
-            Stmt::Expr { expr } => visitor.visit_expr_stmt(expr),
-            Stmt::Print { expr } => visitor.visit_print_stmt(expr),
+            Stmt::Expr { expr } => visitor.visit_expr_stmt(ast, *expr),
+            Stmt::Print { exprs, span } => visitor.visit_print_stmt(ast, exprs, span),
             Stmt::Let {
                 name,
                 value,
                 is_const,
                 span,
-            } => visitor.visit_let_stmt(name, value, *is_const, span),
-            Stmt::Block { stmts } => visitor.visit_block_stmt(stmts),
+                slot,
+            } => visitor.visit_let_stmt(ast, *name, *value, *is_const, span, *slot),
+            Stmt::Block { stmts } => visitor.visit_block_stmt(ast, stmts),
             Stmt::If {
                 condition,
                 truthy,
                 falsy,
-            } => visitor.visit_if_stmt(condition, truthy, falsy),
-            Stmt::While { condition, body } => visitor.visit_while_stmt(condition, body),
+                ..
+            } => visitor.visit_if_stmt(ast, *condition, *truthy, *falsy),
+            Stmt::While {
+                condition,
+                body,
+                span,
+            } => visitor.visit_while_stmt(ast, *condition, *body, span),
             Stmt::Function {
                 name,
                 params,
                 body,
                 span,
-            } => visitor.visit_function_stmt(name, params, body, span),
-            Stmt::Return { value, span } => visitor.visit_return_stmt(value, span),
+                slot,
+            } => visitor.visit_function_stmt(ast, *name, params, body, span, *slot),
+            Stmt::Return { value, span } => visitor.visit_return_stmt(ast, *value, span),
             Stmt::Class {
                 name,
                 super_class,
                 methods,
                 span,
-            } => visitor.visit_class_stmt(name, super_class, methods, span),
-            Stmt::Break { span } => visitor.visit_break_stmt(span),
-            Stmt::Continue { span } => visitor.visit_continue_stmt(span),
+            } => visitor.visit_class_stmt(ast, *name, *super_class, methods, span),
+            Stmt::Break { span } => visitor.visit_break_stmt(ast, span),
+            Stmt::Continue { span } => visitor.visit_continue_stmt(ast, span),
+        }
+    }
+
+    // Position of the statement for diagnostics, taken from its first
+    // expression when it has no span of its own.
+    pub fn span<'a>(&'a self, ast: &'a Ast) -> Option<&'a Span> {
+        match self {
+            Stmt::Expr { expr } => ast[*expr].span(ast),
+            Stmt::Block { stmts } => stmts.iter().find_map(|stmt| ast[*stmt].span(ast)),
+            Stmt::Print { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::While { span, .. }
+            | Stmt::Let { span, .. }
+            | Stmt::Function { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Class { span, .. }
+            | Stmt::Break { span }
+            | Stmt::Continue { span } => Some(span),
         }
     }
 }
 
-impl fmt::Display for Stmt {
+// like the equality of expression nodes, by the trees under them
+impl PartialEq for Node<'_, StmtId> {
+    fn eq(&self, other: &Self) -> bool {
+        let (ast, other_ast) = (self.ast, other.ast);
+        let expr = |a: &ExprId, b: &ExprId| ast.node(*a) == other_ast.node(*b);
+        let stmt = |a: &StmtId, b: &StmtId| ast.node(*a) == other_ast.node(*b);
+        let exprs = |a: &[ExprId], b: &[ExprId]| {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| expr(a, b))
+        };
+        let stmts = |a: &[StmtId], b: &[StmtId]| {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| stmt(a, b))
+        };
+        match (&ast[self.id], &other_ast[other.id]) {
+            (Stmt::Expr { expr: a }, Stmt::Expr { expr: b }) => expr(a, b),
+            (Stmt::Print { exprs: a, span }, Stmt::Print { exprs: b, span: s }) => {
+                span == s && exprs(a, b)
+            }
+            (
+                Stmt::Let {
+                    name,
+                    value,
+                    is_const,
+                    span,
+                    slot,
+                },
+                Stmt::Let {
+                    name: n,
+                    value: v,
+                    is_const: c,
+                    span: s,
+                    slot: sl,
+                },
+            ) => {
+                name == n
+                    && is_const == c
+                    && span == s
+                    && slot == sl
+                    && exprs(value.as_slice(), v.as_slice())
+            }
+            (Stmt::Block { stmts: a }, Stmt::Block { stmts: b }) => stmts(a, b),
+            (
+                Stmt::If {
+                    condition,
+                    truthy,
+                    falsy,
+                    span,
+                },
+                Stmt::If {
+                    condition: c,
+                    truthy: t,
+                    falsy: f,
+                    span: s,
+                },
+            ) => {
+                span == s
+                    && expr(condition, c)
+                    && stmt(truthy, t)
+                    && stmts(falsy.as_slice(), f.as_slice())
+            }
+            (
+                Stmt::While {
+                    condition,
+                    body,
+                    span,
+                },
+                Stmt::While {
+                    condition: c,
+                    body: b,
+                    span: s,
+                },
+            ) => span == s && expr(condition, c) && stmt(body, b),
+            (
+                Stmt::Function {
+                    name,
+                    params,
+                    body,
+                    span,
+                    slot,
+                },
+                Stmt::Function {
+                    name: n,
+                    params: p,
+                    body: b,
+                    span: s,
+                    slot: sl,
+                },
+            ) => name == n && params == p && span == s && slot == sl && stmts(body, b),
+            (Stmt::Return { value, span }, Stmt::Return { value: v, span: s }) => {
+                span == s && exprs(value.as_slice(), v.as_slice())
+            }
+            (
+                Stmt::Class {
+                    name,
+                    super_class,
+                    methods,
+                    span,
+                },
+                Stmt::Class {
+                    name: n,
+                    super_class: c,
+                    methods: m,
+                    span: s,
+                },
+            ) => name == n && super_class == c && span == s && stmts(methods, m),
+            // the rest hold no other nodes
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl fmt::Display for Node<'_, StmtId> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Stmt::Expr { expr } => write!(f, "{}", expr),
-            Stmt::Print { expr } => write!(f, "(print {})", expr),
+        let ast = self.ast;
+        match &ast[self.id] {
+            Stmt::Expr { expr } => write!(f, "{}", ast.node(*expr)),
+            Stmt::Print { exprs, .. } => {
+                write!(f, "(print")?;
+                for expr in exprs {
+                    write!(f, " {}", ast.node(*expr))?;
+                }
+                write!(f, ")")
+            }
             Stmt::Let {
                 name,
                 value,
                 is_const,
-                span: _,
+                ..
             } => {
-                if *is_const {
-                    write!(f, "(const {} {})", name, value.as_ref().unwrap())
-                } else {
-                    write!(f, "(let {} {})", name, value.as_ref().unwrap())
+                let keyword = if *is_const { "const" } else { "let" };
+                match value {
+                    Some(value) => write!(f, "({keyword} {name} {})", ast.node(*value)),
+                    None => write!(f, "({keyword} {name})"),
                 }
             }
             Stmt::Block { stmts } => {
-                let mut s = String::new();
                 write!(f, "(")?;
                 for stmt in stmts {
-                    write!(f, "{}", stmt)?;
+                    write!(f, "{}", ast.node(*stmt))?;
                 }
                 write!(f, ")")
             }
@@ -121,54 +274,44 @@ impl fmt::Display for Stmt {
                 condition,
                 truthy,
                 falsy,
+                ..
             } => {
-                write!(f, "(if {condition} then {truthy}")?;
+                write!(f, "(if {} then {}", ast.node(*condition), ast.node(*truthy))?;
                 if let Some(else_block) = falsy {
-                    write!(f, " else {else_block})")?;
+                    write!(f, " else {}", ast.node(*else_block))?;
                 }
-                Ok(())
+                write!(f, ")")
             }
-            Stmt::While { condition, body } => write!(f, "(while ({}) {})", condition, body),
+            Stmt::While {
+                condition, body, ..
+            } => write!(f, "(while ({}) {})", ast.node(*condition), ast.node(*body)),
             Stmt::Function {
-                name,
-                params,
-                body,
-                span: _,
+                name, params, body, ..
             } => {
-                let mut s = String::new();
-                write!(f, "function {name} (")?;
-                for (i, param) in params.iter().enumerate() {
-                    if i != 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{param}")?;
-                }
-                write!(f, ") ")
-            }
-            Stmt::Return { value, span: _ } => {
-                if let Some(value) = value {
-                    write!(f, "return {}", value)
-                } else {
-                    write!(f, "return")
+                write!(f, "(fn {name} ({})", join(params, " "))?;
+                for stmt in body {
+                    write!(f, " {}", ast.node(*stmt))?;
                 }
+                write!(f, ")")
             }
+            Stmt::Return { value, span: _ } => match value {
+                Some(value) => write!(f, "(return {})", ast.node(*value)),
+                None => write!(f, "(return)"),
+            },
             Stmt::Class {
                 name,
                 super_class,
                 methods,
                 span: _,
             } => {
-                let mut s = String::new();
-                s.push_str(&format!("class {} ", name));
+                write!(f, "(class {name}")?;
                 if let Some(super_class) = super_class {
-                    s.push_str(&format!("extends {} ", super_class));
+                    write!(f, " < {super_class}")?;
                 }
-                s.push_str("{\n");
                 for method in methods {
-                    s.push_str(&format!("{}\n", method));
+                    write!(f, " {}", ast.node(*method))?;
                 }
-                s.push_str("}");
-                write!(f, "{}", s)
+                write!(f, ")")
             }
             Stmt::Break { span: _ } => write!(f, "break"),
             Stmt::Continue { span: _ } => write!(f, "continue"),
@@ -1,38 +1,65 @@
-use crate::{ErrorInfo, Expr, Span, Stmt};
+use std::sync::Arc;
+
+use crate::{
+    ast::{Ast, ExprId, StmtId},
+    ErrorInfo, Span, Symbol,
+};
 
 pub trait Visitor {
-    fn visit_expr_stmt(&mut self, expr: &Expr) -> Result<(), ErrorInfo>;
-    fn visit_print_stmt(&mut self, expr: &Expr) -> Result<(), ErrorInfo>;
-    fn visit_block_stmt(&mut self, stmts: &Vec<Stmt>) -> Result<(), ErrorInfo>;
+    fn visit_expr_stmt(&mut self, ast: &Arc<Ast>, expr: ExprId) -> Result<(), ErrorInfo>;
+    fn visit_print_stmt(
+        &mut self,
+        ast: &Arc<Ast>,
+        exprs: &[ExprId],
+        span: &Span,
+    ) -> Result<(), ErrorInfo>;
+    fn visit_block_stmt(&mut self, ast: &Arc<Ast>, stmts: &[StmtId]) -> Result<(), ErrorInfo>;
     fn visit_function_stmt(
         &mut self,
-        name: &String,
-        params: &Vec<String>,
-        body: &Vec<Stmt>,
+        ast: &Arc<Ast>,
+        name: Symbol,
+        params: &[Symbol],
+        body: &[StmtId],
         span: &Span,
+        slot: Option<usize>,
     ) -> Result<(), ErrorInfo>;
     fn visit_if_stmt(
         &mut self,
-        condition: &Expr,
-        truthy: &Box<Stmt>,
-        falsy: &Option<Box<Stmt>>,
+        ast: &Arc<Ast>,
+        condition: ExprId,
+        truthy: StmtId,
+        falsy: Option<StmtId>,
     ) -> Result<(), ErrorInfo>;
     fn visit_let_stmt(
         &mut self,
-        name: &String,
-        value: &Option<Expr>,
+        ast: &Arc<Ast>,
+        name: Symbol,
+        value: Option<ExprId>,
         is_const: bool,
         span: &Span,
+        slot: Option<usize>,
+    ) -> Result<(), ErrorInfo>;
+    fn visit_return_stmt(
+        &mut self,
+        ast: &Arc<Ast>,
+        value: Option<ExprId>,
+        span: &Span,
+    ) -> Result<(), ErrorInfo>;
+    fn visit_while_stmt(
+        &mut self,
+        ast: &Arc<Ast>,
+        condition: ExprId,
+        body: StmtId,
+        span: &Span,
     ) -> Result<(), ErrorInfo>;
-    fn visit_return_stmt(&mut self, value: &Option<Expr>, span: &Span) -> Result<(), ErrorInfo>;
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &Box<Stmt>) -> Result<(), ErrorInfo>;
     fn visit_class_stmt(
         &mut self,
-        name: &String,
-        super_class: &Option<String>,
-        methods: &Vec<Stmt>,
+        ast: &Arc<Ast>,
+        name: Symbol,
+        super_class: Option<Symbol>,
+        methods: &[StmtId],
         span: &Span,
     ) -> Result<(), ErrorInfo>;
-    fn visit_break_stmt(&mut self, span: &Span) -> Result<(), ErrorInfo>;
-    fn visit_continue_stmt(&mut self, span: &Span) -> Result<(), ErrorInfo>;
+    fn visit_break_stmt(&mut self, ast: &Arc<Ast>, span: &Span) -> Result<(), ErrorInfo>;
+    fn visit_continue_stmt(&mut self, ast: &Arc<Ast>, span: &Span) -> Result<(), ErrorInfo>;
 }
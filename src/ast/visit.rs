@@ -0,0 +1,276 @@
+use crate::{Ast, Expr, ExprId, Stmt, StmtId};
+
+// Walks the tree of a program, one node at a time. Each method defaults to
+// walking the children of its node, so a pass overrides `visit_expr` or
+// `visit_stmt`, matches the variants it cares about and hands the rest to
+// `walk_expr` or `walk_stmt`, calling them itself where it still wants to
+// reach the children of the ones it handles.
+pub trait Visitor {
+    fn visit_stmt(&mut self, ast: &Ast, stmt: StmtId) {
+        walk_stmt(self, ast, stmt);
+    }
+
+    fn visit_expr(&mut self, ast: &Ast, expr: ExprId) {
+        walk_expr(self, ast, expr);
+    }
+}
+
+// Visits the children of `stmt` in the order they were written: the
+// expressions and statements under it, the body of a function and the
+// methods of a class.
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, ast: &Ast, stmt: StmtId) {
+    match &ast[stmt] {
+        Stmt::Expr { expr } => visitor.visit_expr(ast, *expr),
+        Stmt::Print { exprs, .. } => exprs.iter().for_each(|expr| visitor.visit_expr(ast, *expr)),
+        Stmt::Let { value, .. } | Stmt::Return { value, .. } => {
+            if let Some(value) = value {
+                visitor.visit_expr(ast, *value);
+            }
+        }
+        Stmt::Block { stmts: body }
+        | Stmt::Function { body, .. }
+        | Stmt::Class { methods: body, .. } => {
+            body.iter().for_each(|stmt| visitor.visit_stmt(ast, *stmt))
+        }
+        Stmt::If {
+            condition,
+            truthy,
+            falsy,
+            ..
+        } => {
+            visitor.visit_expr(ast, *condition);
+            visitor.visit_stmt(ast, *truthy);
+            if let Some(falsy) = falsy {
+                visitor.visit_stmt(ast, *falsy);
+            }
+        }
+        Stmt::While {
+            condition, body, ..
+        } => {
+            visitor.visit_expr(ast, *condition);
+            visitor.visit_stmt(ast, *body);
+        }
+        Stmt::Break { .. } | Stmt::Continue { .. } => {}
+    }
+}
+
+// visits the subexpressions of `expr` from left to right
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, ast: &Ast, expr: ExprId) {
+    match &ast[expr] {
+        Expr::Assign { value: child, .. }
+        | Expr::Get { object: child, .. }
+        | Expr::Grouping { expr: child, .. }
+        | Expr::Unary { right: child, .. } => visitor.visit_expr(ast, *child),
+        Expr::Binary { left, right, .. }
+        | Expr::Set {
+            object: left,
+            value: right,
+            ..
+        } => {
+            visitor.visit_expr(ast, *left);
+            visitor.visit_expr(ast, *right);
+        }
+        Expr::Call { callee, args, .. } => {
+            visitor.visit_expr(ast, *callee);
+            args.iter().for_each(|arg| visitor.visit_expr(ast, *arg));
+        }
+        Expr::Literal { .. } | Expr::Super { .. } | Expr::Variable { .. } => {}
+    }
+}
+
+// `Visitor` for a pass that writes to the nodes it walks, such as the
+// resolver, and may stop at the first error it finds.
+pub trait VisitorMut {
+    type Error;
+
+    fn visit_stmt(&mut self, ast: &mut Ast, stmt: StmtId) -> Result<(), Self::Error> {
+        walk_stmt_mut(self, ast, stmt)
+    }
+
+    fn visit_expr(&mut self, ast: &mut Ast, expr: ExprId) -> Result<(), Self::Error> {
+        walk_expr_mut(self, ast, expr)
+    }
+}
+
+// `walk_stmt` for a `VisitorMut`, stopping at the first child that fails
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    ast: &mut Ast,
+    stmt: StmtId,
+) -> Result<(), V::Error> {
+    // the children are copied out so the visitor can write to the nodes
+    // while walking them
+    let (exprs, stmts) = match &ast[stmt] {
+        Stmt::Expr { expr } => (vec![*expr], Vec::new()),
+        Stmt::Print { exprs, .. } => (exprs.clone(), Vec::new()),
+        Stmt::Let { value, .. } | Stmt::Return { value, .. } => {
+            (value.iter().copied().collect(), Vec::new())
+        }
+        Stmt::Block { stmts: body }
+        | Stmt::Function { body, .. }
+        | Stmt::Class { methods: body, .. } => (Vec::new(), body.clone()),
+        Stmt::If {
+            condition,
+            truthy,
+            falsy,
+            ..
+        } => (
+            vec![*condition],
+            [*truthy].into_iter().chain(*falsy).collect(),
+        ),
+        Stmt::While {
+            condition, body, ..
+        } => (vec![*condition], vec![*body]),
+        Stmt::Break { .. } | Stmt::Continue { .. } => (Vec::new(), Vec::new()),
+    };
+    for expr in exprs {
+        visitor.visit_expr(ast, expr)?;
+    }
+    for stmt in stmts {
+        visitor.visit_stmt(ast, stmt)?;
+    }
+    Ok(())
+}
+
+// `walk_expr` for a `VisitorMut`, stopping at the first child that fails
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    ast: &mut Ast,
+    expr: ExprId,
+) -> Result<(), V::Error> {
+    let children = match &ast[expr] {
+        Expr::Assign { value: child, .. }
+        | Expr::Get { object: child, .. }
+        | Expr::Grouping { expr: child, .. }
+        | Expr::Unary { right: child, .. } => vec![*child],
+        Expr::Binary { left, right, .. }
+        | Expr::Set {
+            object: left,
+            value: right,
+            ..
+        } => vec![*left, *right],
+        Expr::Call { callee, args, .. } => {
+            [*callee].into_iter().chain(args.iter().copied()).collect()
+        }
+        Expr::Literal { .. } | Expr::Super { .. } | Expr::Variable { .. } => Vec::new(),
+    };
+    for child in children {
+        visitor.visit_expr(ast, child)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{Lexer, Parser};
+
+    // how many nodes of each kind a program has
+    #[derive(Default)]
+    struct Counter(BTreeMap<&'static str, usize>);
+
+    impl Visitor for Counter {
+        fn visit_stmt(&mut self, ast: &Ast, stmt: StmtId) {
+            let kind = match &ast[stmt] {
+                Stmt::Expr { .. } => "expr",
+                Stmt::Print { .. } => "print",
+                Stmt::Let { .. } => "let",
+                Stmt::Block { .. } => "block",
+                Stmt::If { .. } => "if",
+                Stmt::While { .. } => "while",
+                Stmt::Function { .. } => "fn",
+                Stmt::Return { .. } => "return",
+                Stmt::Class { .. } => "class",
+                Stmt::Break { .. } => "break",
+                Stmt::Continue { .. } => "continue",
+            };
+            *self.0.entry(kind).or_default() += 1;
+            walk_stmt(self, ast, stmt);
+        }
+
+        fn visit_expr(&mut self, ast: &Ast, expr: ExprId) {
+            let kind = match &ast[expr] {
+                Expr::Assign { .. } => "assign",
+                Expr::Binary { .. } => "binary",
+                Expr::Call { .. } => "call",
+                Expr::Get { .. } => "get",
+                Expr::Grouping { .. } => "grouping",
+                Expr::Literal { .. } => "literal",
+                Expr::Set { .. } => "set",
+                Expr::Super { .. } => "super",
+                Expr::Unary { .. } => "unary",
+                Expr::Variable { .. } => "variable",
+            };
+            *self.0.entry(kind).or_default() += 1;
+            walk_expr(self, ast, expr);
+        }
+    }
+
+    // only overrides what it needs: the names a program reads
+    struct Reads(Vec<String>);
+
+    impl Visitor for Reads {
+        fn visit_expr(&mut self, ast: &Ast, expr: ExprId) {
+            match &ast[expr] {
+                Expr::Variable { name, .. } => self.0.push(name.to_string()),
+                _ => walk_expr(self, ast, expr),
+            }
+        }
+    }
+
+    const FIXTURE: &str = "
+        class A { init(n) { this.n = n; } get() { return this.n; } }
+        class B < A { get() { return super.get() * 2; } }
+        fn twice(f, x) { return f(f(x)); }
+        let b = B(-1);
+        for (let i = 0; i < 3; i = i + 1) {
+            if (i == 1) {} else if (i > 5) return;
+            print twice(b.get, (i)), !true;
+        }
+        while (false) {}
+        b.n = nil;";
+
+    #[test]
+    fn test_visitor() {
+        let program = Parser::new(Lexer::new(FIXTURE.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut counter = Counter::default();
+        for stmt in &program.stmts {
+            counter.visit_stmt(&program.ast, *stmt);
+        }
+        let counts: Vec<_> = counter.0.into_iter().collect();
+        assert_eq!(
+            counts,
+            [
+                ("assign", 1),
+                ("binary", 5),
+                ("block", 5),
+                ("call", 5),
+                ("class", 2),
+                ("expr", 3),
+                ("fn", 4),
+                ("get", 2),
+                ("grouping", 1),
+                ("if", 2),
+                ("let", 2),
+                ("literal", 10),
+                ("print", 1),
+                ("return", 4),
+                ("set", 2),
+                ("super", 1),
+                ("unary", 2),
+                ("variable", 15),
+                ("while", 2),
+            ]
+        );
+
+        let mut reads = Reads(Vec::new());
+        for stmt in &program.stmts {
+            reads.visit_stmt(&program.ast, *stmt);
+        }
+        assert_eq!(reads.0.join(" "), "this n this f f x B i i i twice b i i b");
+    }
+}
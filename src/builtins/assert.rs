@@ -0,0 +1,48 @@
+use crate::{Environment, Error, Object};
+
+pub(crate) fn register(globals: &mut Environment) {
+    // the message is only formatted once the assertion has failed
+    super::define(globals, "assert", 1..=2, |_, args| {
+        if args[0].is_truthy() {
+            return Ok(Object::Nil);
+        }
+        let message = match args.get(1) {
+            Some(message) => format!("assertion failed: {message}"),
+            None => "assertion failed".to_string(),
+        };
+        Err(Error::Runtime(message))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::eval;
+    use crate::{Error, Interpretor, Lexer, Object, Parser};
+
+    #[test]
+    fn test_assert() {
+        assert_eq!(eval("assert(1 < 2)"), Ok(Object::Nil));
+        assert_eq!(eval("assert(\"x\", \"unused\")"), Ok(Object::Nil));
+        assert_eq!(
+            eval("assert(1 > 2)"),
+            Err(Error::Runtime("assertion failed".to_string()))
+        );
+        assert_eq!(
+            eval("assert(nil, \"expected a value\")"),
+            Err(Error::Runtime(
+                "assertion failed: expected a value".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_assert_line() {
+        let program = Parser::new(Lexer::new("let a = 1;\n\nassert(a == 2);".to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new();
+        interpretor.exec_in(&program, program.stmts[0]).unwrap();
+        let err = interpretor.exec_in(&program, program.stmts[1]).unwrap_err();
+        assert_eq!(err.span.line, 3);
+    }
+}
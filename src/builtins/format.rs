@@ -0,0 +1,149 @@
+use crate::{Environment, Error, Object};
+
+pub(crate) fn register(globals: &mut Environment) {
+    // fills `{}` placeholders left to right, or `{n}` by position, with the
+    // values the way print shows them; `{{` and `}}` are literal braces
+    super::define(globals, "format", 1.., |interpretor, args| {
+        let template = match &args[0] {
+            Object::String(template) => template,
+            value => {
+                return Err(Error::Type(format!(
+                    "format() template must be a string, not '{}'",
+                    value.type_name()
+                )))
+            }
+        };
+        let text = format(template, &args[1..])?;
+        interpretor.reserve(text.len())?;
+        Ok(Object::String(text.into()))
+    });
+}
+
+fn format(template: &str, values: &[Object]) -> Result<String, Error> {
+    let mut text = String::new();
+    // placeholders without an index, and whether any had one
+    let (mut automatic, mut indexed) = (0, false);
+    let mut chars = template.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let Some(end) = rest.find('}') else {
+                    return Err(error("has an unmatched '{'"));
+                };
+                let placeholder = &rest[..end];
+                let index = if placeholder.is_empty() {
+                    automatic += 1;
+                    automatic - 1
+                } else {
+                    indexed = true;
+                    placeholder.parse::<usize>().map_err(|_| {
+                        error(&format!("placeholder {{{placeholder}}} is not a position"))
+                    })?
+                };
+                match values.get(index) {
+                    Some(value) => text.push_str(&value.to_string()),
+                    // too few arguments for `{}` is reported with the counts
+                    None if placeholder.is_empty() => {}
+                    None => {
+                        return Err(error(&format!(
+                            "placeholder {{{index}}} is out of range for {} arguments",
+                            values.len()
+                        )))
+                    }
+                }
+                chars = rest[end + 1..].chars();
+            }
+            '}' => return Err(error("has an unmatched '}'")),
+            ch => text.push(ch),
+        }
+    }
+    if automatic > 0 && indexed {
+        return Err(error("cannot mix {} and {n} placeholders"));
+    }
+    if !indexed && automatic != values.len() {
+        return Err(error(&format!(
+            "has {automatic} placeholders but {} arguments were given",
+            values.len()
+        )));
+    }
+    Ok(text)
+}
+
+fn error(message: &str) -> Error {
+    Error::Value(format!("format() {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::eval;
+    use crate::{Error, Object};
+
+    fn string(s: &str) -> Result<Object, Error> {
+        Ok(Object::String(s.into()))
+    }
+
+    fn error(message: &str) -> Result<Object, Error> {
+        Err(Error::Value(message.to_string()))
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(eval("format(\"({}, {})\", 1, 2.5)"), string("(1, 2.5)"));
+        assert_eq!(
+            eval("format(\"{} {} {}\", \"a\", nil, true)"),
+            string("a nil true")
+        );
+        assert_eq!(eval("format(\"{1}{0}{1}\", \"a\", \"b\")"), string("bab"));
+        assert_eq!(eval("format(\"{{}} {{{}}}\", 1)"), string("{} {1}"));
+        assert_eq!(eval("format(\"plain\")"), string("plain"));
+        // the template can come from anywhere
+        assert_eq!(eval("format(\"{}-\" * 2, \"é\", 3)"), string("é-3-"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            eval("format(\"{} {}\", 1)"),
+            error("format() has 2 placeholders but 1 arguments were given")
+        );
+        assert_eq!(
+            eval("format(\"{}\", 1, 2)"),
+            error("format() has 1 placeholders but 2 arguments were given")
+        );
+        assert_eq!(
+            eval("format(\"{2}\", 1)"),
+            error("format() placeholder {2} is out of range for 1 arguments")
+        );
+        assert_eq!(
+            eval("format(\"{} {0}\", 1)"),
+            error("format() cannot mix {} and {n} placeholders")
+        );
+        assert_eq!(
+            eval("format(\"{x}\", 1)"),
+            error("format() placeholder {x} is not a position")
+        );
+        assert_eq!(
+            eval("format(\"{\")"),
+            error("format() has an unmatched '{'")
+        );
+        assert_eq!(
+            eval("format(\"}\")"),
+            error("format() has an unmatched '}'")
+        );
+        assert_eq!(
+            eval("format(1)"),
+            Err(Error::Type(
+                "format() template must be a string, not 'number'".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::{fs, io::Write};
+
+use crate::{Environment, Error, Interpretor, Object};
+
+pub(crate) fn register(globals: &mut Environment) {
+    super::define(globals, "read_file", 1, |interpretor, args| {
+        let path = path_arg(interpretor, "read_file", &args[0])?;
+        let contents =
+            fs::read_to_string(path).map_err(|e| io_error("read_file", "read", path, e))?;
+        interpretor.reserve(contents.len())?;
+        Ok(Object::String(contents.into()))
+    });
+    super::define(globals, "write_file", 2, |interpretor, args| {
+        let path = path_arg(interpretor, "write_file", &args[0])?;
+        let contents = contents_arg("write_file", &args[1])?;
+        fs::write(path, contents).map_err(|e| io_error("write_file", "write", path, e))?;
+        Ok(Object::Nil)
+    });
+    // creates the file if it does not exist yet
+    super::define(globals, "append_file", 2, |interpretor, args| {
+        let path = path_arg(interpretor, "append_file", &args[0])?;
+        let contents = contents_arg("append_file", &args[1])?;
+        fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map_err(|e| io_error("append_file", "append to", path, e))?;
+        Ok(Object::Nil)
+    });
+    super::define(globals, "file_exists", 1, |interpretor, args| {
+        let path = path_arg(interpretor, "file_exists", &args[0])?;
+        Ok(Object::Boolean(fs::metadata(path).is_ok()))
+    });
+}
+
+// the path argument of a file builtin, once the host has allowed access
+fn path_arg<'a>(
+    interpretor: &Interpretor,
+    function: &str,
+    value: &'a Object,
+) -> Result<&'a str, Error> {
+    if !interpretor.filesystem_access {
+        return Err(Error::Runtime("filesystem access is disabled".to_string()));
+    }
+    match value {
+        Object::String(path) => Ok(path),
+        value => Err(Error::Type(format!(
+            "{function}() path must be a string, not '{}'",
+            value.type_name()
+        ))),
+    }
+}
+
+fn contents_arg<'a>(function: &str, value: &'a Object) -> Result<&'a str, Error> {
+    match value {
+        Object::String(contents) => Ok(contents),
+        value => Err(Error::Type(format!(
+            "{function}() contents must be a string, not '{}'",
+            value.type_name()
+        ))),
+    }
+}
+
+fn io_error(function: &str, action: &str, path: &str, error: std::io::Error) -> Error {
+    Error::Runtime(format!("{function}() could not {action} '{path}': {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use crate::{Error, Interpretor, Lexer, Object, Parser};
+
+    // a fresh directory per test, removed again when the test is done
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("rlisp-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn path(&self, file: &str) -> String {
+            self.0.join(file).to_str().unwrap().to_string()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn run(interpretor: &mut Interpretor, input: &str) -> Result<Object, Error> {
+        let program = Parser::new(Lexer::new(format!("let result = {input};")))
+            .parse_program()
+            .unwrap();
+        interpretor
+            .exec_in(&program, program.stmts[0])
+            .map_err(|e| e.error)?;
+        Ok(interpretor.get_global("result").unwrap())
+    }
+
+    #[test]
+    fn test_read_and_write() {
+        let dir = TempDir::new("read-and-write");
+        let file = dir.path("notes.txt");
+        let mut interpretor = Interpretor::new().with_filesystem_access(true);
+        let mut run = |input: &str| run(&mut interpretor, &input.replace("FILE", &file));
+        assert_eq!(run("file_exists(\"FILE\")"), Ok(Object::Boolean(false)));
+        assert_eq!(run("write_file(\"FILE\", \"one\")"), Ok(Object::Nil));
+        assert_eq!(run("append_file(\"FILE\", \" two\")"), Ok(Object::Nil));
+        assert_eq!(run("file_exists(\"FILE\")"), Ok(Object::Boolean(true)));
+        assert_eq!(
+            run("read_file(\"FILE\")"),
+            Ok(Object::String("one two".into()))
+        );
+        assert_eq!(fs::read_to_string(&file).unwrap(), "one two");
+        assert_eq!(
+            run("write_file(\"FILE\", 1)"),
+            Err(Error::Type(
+                "write_file() contents must be a string, not 'number'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_io_errors() {
+        let dir = TempDir::new("io-errors");
+        let missing = dir.path("missing.txt");
+        let mut interpretor = Interpretor::new().with_filesystem_access(true);
+        let Err(Error::Runtime(message)) =
+            run(&mut interpretor, &format!("read_file(\"{missing}\")"))
+        else {
+            panic!("reading a missing file should fail");
+        };
+        assert!(
+            message.starts_with(&format!("read_file() could not read '{missing}': ")),
+            "{message}"
+        );
+
+        // root may write anywhere, so only check the error where the
+        // permissions are actually enforced
+        let locked = dir.path("locked.txt");
+        fs::write(&locked, "").unwrap();
+        let mut permissions = fs::metadata(&locked).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&locked, permissions).unwrap();
+        if fs::write(&locked, "").is_err() {
+            let Err(Error::Runtime(message)) = run(
+                &mut interpretor,
+                &format!("write_file(\"{locked}\", \"x\")"),
+            ) else {
+                panic!("writing a read only file should fail");
+            };
+            assert!(
+                message.starts_with(&format!("write_file() could not write '{locked}': ")),
+                "{message}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_access_disabled() {
+        let dir = TempDir::new("access-disabled");
+        let file = dir.path("file.txt");
+        let mut interpretor = Interpretor::new().with_filesystem_access(false);
+        for call in [
+            format!("read_file(\"{file}\")"),
+            format!("write_file(\"{file}\", \"x\")"),
+            format!("append_file(\"{file}\", \"x\")"),
+            format!("file_exists(\"{file}\")"),
+        ] {
+            assert_eq!(
+                run(&mut interpretor, &call),
+                Err(Error::Runtime("filesystem access is disabled".to_string())),
+                "{call}"
+            );
+        }
+        assert!(!std::path::Path::new(&file).exists());
+    }
+}
@@ -0,0 +1,147 @@
+use std::env;
+
+use crate::{Environment, Error, Object};
+
+pub(crate) fn register(globals: &mut Environment) {
+    // reads a line, optionally after writing a prompt, or nil at the end of
+    // the input
+    super::define(globals, "input", 0..=1, |interpretor, args| {
+        let line = interpretor.read_line(args.first())?;
+        Ok(line.map(Object::from).unwrap_or(Object::Nil))
+    });
+    // stops the program with a status code for the host, 0 by default
+    super::define(globals, "exit", 0..=1, |_, args| match args.first() {
+        None => Err(Error::Exit(0)),
+        Some(Object::Number(n)) if n.fract() == 0.0 && (0.0..=255.0).contains(n) => {
+            Err(Error::Exit(*n as i32))
+        }
+        Some(code) => Err(Error::Value(format!(
+            "exit() code must be a whole number from 0 to 255, not {code}"
+        ))),
+    });
+    // value of an environment variable, or nil when it is not set
+    super::define(globals, "env", 1, |_, args| match &args[0] {
+        Object::String(name) => match env::var(&**name) {
+            Ok(value) => Ok(Object::String(value.into())),
+            Err(env::VarError::NotPresent) => Ok(Object::Nil),
+            Err(env::VarError::NotUnicode(_)) => Err(Error::Value(format!(
+                "env() value of '{name}' is not valid unicode"
+            ))),
+        },
+        value => Err(Error::Type(format!(
+            "env() argument must be a string, not '{}'",
+            value.type_name()
+        ))),
+    });
+    // like the print statement but without the trailing newline
+    super::define(globals, "write", 0.., |interpretor, args| {
+        interpretor.write_values(args, "")?;
+        Ok(Object::Nil)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{interpretor::Capture, Error, Interpretor, Lexer, Object, Parser};
+
+    fn run(input: &str, interpretor: &mut Interpretor) -> Result<(), Error> {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        for stmt in &program.stmts {
+            interpretor.exec_in(&program, *stmt).map_err(|e| e.error)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_input() {
+        let output = Capture::default();
+        let mut interpretor = Interpretor::new()
+            .with_input(Box::new(Cursor::new("first\nsecond\r\nthird")))
+            .with_output(Box::new(output.clone()));
+        run(
+            "let a = input(); let b = input(\"name: \"); let c = input(); let d = input();",
+            &mut interpretor,
+        )
+        .unwrap();
+        let get = |name| interpretor.get_global(name).unwrap();
+        assert_eq!(get("a"), Object::String("first".into()));
+        assert_eq!(get("b"), Object::String("second".into()));
+        assert_eq!(get("c"), Object::String("third".into()));
+        assert_eq!(get("d"), Object::Nil);
+        // the prompt is not followed by a newline
+        assert_eq!(output.text(), "name: ");
+    }
+
+    #[test]
+    fn test_exit() {
+        let program = |input: &str| {
+            Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap()
+        };
+        let output = Capture::default();
+        let mut interpretor = Interpretor::new().with_output(Box::new(output.clone()));
+        let err = interpretor
+            .interpret(program("fn quit() { exit(3); } print 1; quit(); print 2;"))
+            .unwrap_err();
+        assert_eq!(err.error, Error::Exit(3));
+        assert_eq!(output.text(), "1\n");
+
+        let mut interpretor = Interpretor::new();
+        let err = interpretor.interpret(program("exit();")).unwrap_err();
+        assert_eq!(err.error, Error::Exit(0));
+        assert_eq!(
+            run("exit(1.5);", &mut interpretor),
+            Err(Error::Value(
+                "exit() code must be a whole number from 0 to 255, not 1.5".to_string()
+            ))
+        );
+        assert_eq!(
+            run("exit(256);", &mut interpretor),
+            Err(Error::Value(
+                "exit() code must be a whole number from 0 to 255, not 256".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_env() {
+        let mut interpretor = Interpretor::new();
+        let path = std::env::var("PATH").unwrap();
+        run(
+            "let a = env(\"PATH\"); let b = env(\"RLISP_SURELY_NOT_SET\");",
+            &mut interpretor,
+        )
+        .unwrap();
+        assert_eq!(
+            interpretor.get_global("a"),
+            Some(Object::String(path.into()))
+        );
+        assert_eq!(interpretor.get_global("b"), Some(Object::Nil));
+        assert_eq!(
+            run("env(1);", &mut interpretor),
+            Err(Error::Type(
+                "env() argument must be a string, not 'number'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_input_disabled() {
+        let mut interpretor = Interpretor::new().without_input();
+        assert_eq!(
+            run("input();", &mut interpretor),
+            Err(Error::Runtime("input() is disabled".to_string()))
+        );
+        assert_eq!(
+            run("input(1, 2);", &mut interpretor),
+            Err(Error::Type(
+                "input() takes 0 to 1 arguments but 2 were given".to_string()
+            ))
+        );
+    }
+}
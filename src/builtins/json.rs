@@ -0,0 +1,371 @@
+use std::fmt::Write;
+
+use crate::{Environment, Error, Object};
+
+pub(crate) fn register(globals: &mut Environment) {
+    super::define(globals, "json_parse", 1, |interpretor, args| {
+        match &args[0] {
+            Object::String(text) => {
+                interpretor.reserve(text.len())?;
+                Decoder::new(text).document()
+            }
+            value => Err(Error::Type(format!(
+                "json_parse() argument must be a string, not '{}'",
+                value.type_name()
+            ))),
+        }
+    });
+    // the indent is accepted for when there are arrays and maps to lay out,
+    // scalars always come out on one line
+    super::define(globals, "json_stringify", 1..=2, |interpretor, args| {
+        match args.get(1) {
+            None => {}
+            Some(Object::Number(n)) if n.fract() == 0.0 && *n >= 0.0 => {}
+            Some(indent) => {
+                return Err(Error::Value(format!(
+                    "json_stringify() indent must be a whole number of at least 0, not {indent}"
+                )))
+            }
+        }
+        let json = encode(&args[0])?;
+        interpretor.reserve(json.len())?;
+        Ok(Object::String(json.into()))
+    });
+}
+
+fn encode(value: &Object) -> Result<String, Error> {
+    match value {
+        Object::Nil => Ok("null".to_string()),
+        Object::Boolean(b) => Ok(b.to_string()),
+        Object::Number(n) if n.is_finite() => Ok(n.to_string()),
+        Object::String(s) => Ok(quote(s)),
+        Object::Number(n) => Err(Error::Value(format!(
+            "json_stringify() cannot serialize the number {n}"
+        ))),
+        value => Err(Error::Type(format!(
+            "json_stringify() cannot serialize a value of type '{}'",
+            value.type_name()
+        ))),
+    }
+}
+
+pub(crate) fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => write!(quoted, "\\u{:04x}", ch as u32).unwrap(),
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Recursive descent over the bytes of a JSON text. Arrays and objects are
+// recognised only to reject them, there is nothing to decode them into yet.
+struct Decoder<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    fn document(&mut self) -> Result<Object, Error> {
+        self.skip_whitespace();
+        let value = self.value()?;
+        self.skip_whitespace();
+        if self.pos < self.text.len() {
+            return Err(self.error("unexpected trailing characters"));
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Object, Error> {
+        match self.peek() {
+            Some(b'n') => self.keyword("null", Object::Nil),
+            Some(b't') => self.keyword("true", Object::Boolean(true)),
+            Some(b'f') => self.keyword("false", Object::Boolean(false)),
+            Some(b'"') => self.string().map(Object::from),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b'[') => Err(self.error("arrays are not supported")),
+            Some(b'{') => Err(self.error("objects are not supported")),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Object) -> Result<Object, Error> {
+        if self.text[self.pos..].starts_with(word) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn number(&mut self) -> Result<Object, Error> {
+        let start = self.pos;
+        self.eat(b'-');
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => self.digits(),
+            _ => return Err(self.error("expected a digit")),
+        }
+        if self.eat(b'.') {
+            self.required_digits()?;
+        }
+        if self.eat(b'e') || self.eat(b'E') {
+            if !self.eat(b'+') {
+                self.eat(b'-');
+            }
+            self.required_digits()?;
+        }
+        let n: f64 = self.text[start..self.pos].parse().unwrap();
+        if n.is_infinite() {
+            self.pos = start;
+            return Err(self.error("number out of range"));
+        }
+        Ok(Object::Number(n))
+    }
+
+    fn required_digits(&mut self) -> Result<(), Error> {
+        match self.peek() {
+            Some(b'0'..=b'9') => {
+                self.digits();
+                Ok(())
+            }
+            _ => Err(self.error("expected a digit")),
+        }
+    }
+
+    fn digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let rest = &self.text[self.pos..];
+            let Some(ch) = rest.chars().next() else {
+                return Err(self.error("unterminated string"));
+            };
+            match ch {
+                '"' => {
+                    self.pos += 1;
+                    return Ok(s);
+                }
+                '\\' => {
+                    self.pos += 1;
+                    s.push(self.escape()?);
+                }
+                ch if (ch as u32) < 0x20 => return Err(self.error("control character in string")),
+                ch => {
+                    self.pos += ch.len_utf8();
+                    s.push(ch);
+                }
+            }
+        }
+    }
+
+    // the character of the escape sequence after a backslash
+    fn escape(&mut self) -> Result<char, Error> {
+        let ch = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                let start = self.pos - 1;
+                self.pos += 1;
+                let high = self.hex()?;
+                let code = if (0xD800..0xDC00).contains(&high) {
+                    // a high surrogate must be followed by its low half
+                    if !self.text[self.pos..].starts_with("\\u") {
+                        self.pos = start;
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    self.pos += 2;
+                    let low = self.hex()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        self.pos = start;
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                } else {
+                    high
+                };
+                return char::from_u32(code).ok_or_else(|| {
+                    self.pos = start;
+                    self.error("unpaired surrogate")
+                });
+            }
+            _ => return Err(self.error("invalid escape")),
+        };
+        self.pos += 1;
+        Ok(ch)
+    }
+
+    fn hex(&mut self) -> Result<u32, Error> {
+        let digits = self.text.get(self.pos..self.pos + 4);
+        match digits.and_then(|digits| u32::from_str_radix(digits, 16).ok()) {
+            Some(code) if digits.unwrap().bytes().all(|b| b.is_ascii_hexdigit()) => {
+                self.pos += 4;
+                Ok(code)
+            }
+            _ => Err(self.error("invalid unicode escape")),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    // `reason` at the current byte offset, with the text found there
+    fn error(&self, reason: &str) -> Error {
+        let snippet: String = self.text[self.pos..].chars().take(10).collect();
+        if snippet.is_empty() {
+            Error::Value(format!("json_parse() {reason} at byte {}", self.pos))
+        } else {
+            Error::Value(format!(
+                "json_parse() {reason} at byte {} near '{snippet}'",
+                self.pos
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode, Decoder};
+    use crate::builtins::eval;
+    use crate::{Error, Object};
+
+    fn string(s: &str) -> Result<Object, Error> {
+        Ok(Object::String(s.into()))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        assert_eq!(eval("json_parse(\" null \")"), Ok(Object::Nil));
+        assert_eq!(eval("json_parse(\"true\")"), Ok(Object::Boolean(true)));
+        assert_eq!(eval("json_parse(\"-12.5e1\")"), Ok(Object::Number(-125.0)));
+        assert_eq!(eval("json_stringify(nil)"), string("null"));
+        assert_eq!(eval("json_stringify(1.5)"), string("1.5"));
+        assert_eq!(eval("json_stringify(3, 2)"), string("3"));
+        assert_eq!(eval("json_stringify(\"a\\b\")"), string("\"a\\\\b\""));
+        for value in ["nil", "false", "42", "0.25", "\"plain\"", "\"a\\\\b\""] {
+            assert_eq!(
+                eval(&format!("json_parse(json_stringify({value}))")),
+                eval(value),
+                "{value}"
+            );
+        }
+    }
+
+    // string literals have no escapes, so quoted JSON is checked from here
+    #[test]
+    fn test_strings() {
+        let decode = |text: &str| Decoder::new(text).document();
+        assert_eq!(
+            decode(r#""tab\t \"q\" \u00e9 \ud83d\ude00 é""#),
+            string("tab\t \"q\" é 😀 é")
+        );
+        let text = "line\nbreak \"quoted\" \\ \u{1}";
+        let json = encode(&Object::String(text.into())).unwrap();
+        assert_eq!(json, r#""line\nbreak \"quoted\" \\ \u0001""#);
+        assert_eq!(decode(&json), string(text));
+        assert_eq!(
+            decode(r#""\ud83d x""#),
+            Err(Error::Value(
+                "json_parse() unpaired surrogate at byte 1 near '\\ud83d x\"'".to_string()
+            ))
+        );
+        assert_eq!(
+            decode(r#""\q""#),
+            Err(Error::Value(
+                "json_parse() invalid escape at byte 2 near 'q\"'".to_string()
+            ))
+        );
+        assert_eq!(
+            decode("\"open"),
+            Err(Error::Value(
+                "json_parse() unterminated string at byte 5".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |message: &str| Err(Error::Value(message.to_string()));
+        assert_eq!(
+            eval("json_parse(\"[1, 2]\")"),
+            error("json_parse() arrays are not supported at byte 0 near '[1, 2]'")
+        );
+        assert_eq!(
+            eval("json_parse(\"  tru\")"),
+            error("json_parse() expected a value at byte 2 near 'tru'")
+        );
+        assert_eq!(
+            eval("json_parse(\"1.\")"),
+            error("json_parse() expected a digit at byte 2")
+        );
+        assert_eq!(
+            eval("json_parse(\"01\")"),
+            error("json_parse() unexpected trailing characters at byte 1 near '1'")
+        );
+        assert_eq!(
+            eval("json_parse(\"1e999\")"),
+            error("json_parse() number out of range at byte 0 near '1e999'")
+        );
+        assert_eq!(
+            eval("json_parse(\"\")"),
+            error("json_parse() unexpected end of input at byte 0")
+        );
+        assert_eq!(
+            encode(&Object::Number(f64::INFINITY)),
+            Err(Error::Value(
+                "json_stringify() cannot serialize the number inf".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("json_stringify(len)"),
+            Err(Error::Type(
+                "json_stringify() cannot serialize a value of type 'function'".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("json_stringify(1, -1)"),
+            error("json_stringify() indent must be a whole number of at least 0, not -1")
+        );
+    }
+}
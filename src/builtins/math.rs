@@ -0,0 +1,183 @@
+use std::{cmp::Ordering, f64::consts};
+
+use crate::{Environment, Error, Object, Symbol};
+
+pub(crate) fn register(globals: &mut Environment) {
+    constant(globals, "PI", consts::PI);
+    constant(globals, "E", consts::E);
+
+    // invalid inputs such as sqrt(-1) give NaN rather than an error
+    super::define_native(globals, "sqrt", 1, |args| {
+        Ok(Object::Number(number("sqrt", &args[0])?.sqrt()))
+    });
+    super::define_native(globals, "abs", 1, |args| {
+        Ok(Object::Number(number("abs", &args[0])?.abs()))
+    });
+    super::define_native(globals, "floor", 1, |args| {
+        Ok(Object::Number(number("floor", &args[0])?.floor()))
+    });
+    super::define_native(globals, "ceil", 1, |args| {
+        Ok(Object::Number(number("ceil", &args[0])?.ceil()))
+    });
+    // halfway cases round away from zero
+    super::define_native(globals, "round", 1, |args| {
+        Ok(Object::Number(number("round", &args[0])?.round()))
+    });
+    super::define_native(globals, "pow", 2, |args| {
+        let base = number("pow", &args[0])?;
+        Ok(Object::Number(base.powf(number("pow", &args[1])?)))
+    });
+    // natural logarithm
+    super::define_native(globals, "log", 1, |args| {
+        Ok(Object::Number(number("log", &args[0])?.ln()))
+    });
+    super::define_native(globals, "exp", 1, |args| {
+        Ok(Object::Number(number("exp", &args[0])?.exp()))
+    });
+
+    super::define_native(globals, "min", 1.., |args| {
+        extreme("min", args, Ordering::Less)
+    });
+    super::define_native(globals, "max", 1.., |args| {
+        extreme("max", args, Ordering::Greater)
+    });
+    super::define_native(globals, "sum", 0.., |args| {
+        let mut total = 0.0;
+        for arg in args {
+            total += number("sum", arg)?;
+        }
+        Ok(Object::Number(total))
+    });
+}
+
+// the first of `args` that no other argument is ordered before, comparing
+// numbers with numbers and strings with strings
+fn extreme(function: &str, args: &[Object], wanted: Ordering) -> Result<Object, Error> {
+    let mut best = &args[0];
+    for arg in &args[1..] {
+        let ordering = match (arg, best) {
+            (Object::Number(a), Object::Number(b)) => a.partial_cmp(b),
+            (Object::String(a), Object::String(b)) => Some(a.cmp(b)),
+            _ => {
+                return Err(Error::Type(format!(
+                    "{function}() cannot compare '{}' with '{}'",
+                    arg.type_name(),
+                    best.type_name()
+                )))
+            }
+        };
+        if ordering == Some(wanted) {
+            best = arg;
+        }
+    }
+    match best {
+        Object::Number(_) | Object::String(_) => Ok(best.clone()),
+        value => Err(Error::Type(format!(
+            "{function}() argument must be a number or a string, not '{}'",
+            value.type_name()
+        ))),
+    }
+}
+
+fn constant(globals: &mut Environment, name: &str, value: f64) {
+    globals
+        .define(Symbol::intern(name), Object::Number(value), false)
+        .unwrap();
+}
+
+fn number(function: &str, value: &Object) -> Result<f64, Error> {
+    match value {
+        Object::Number(n) => Ok(*n),
+        value => Err(Error::Type(format!(
+            "{function}() argument must be a number, not '{}'",
+            value.type_name()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::eval;
+    use crate::{Error, Object};
+
+    fn number(input: &str) -> f64 {
+        match eval(input) {
+            Ok(Object::Number(n)) => n,
+            result => panic!("{input}: {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(number("sqrt(16)"), 4.0);
+        assert!(number("sqrt(-1)").is_nan());
+        assert_eq!(number("abs(-2.5)"), 2.5);
+        assert_eq!(number("floor(-1.5)"), -2.0);
+        assert_eq!(number("ceil(1.2)"), 2.0);
+        assert_eq!(number("round(2.5)"), 3.0);
+        assert_eq!(number("round(-2.5)"), -3.0);
+        assert_eq!(number("pow(2, 10)"), 1024.0);
+        assert_eq!(number("log(E)"), 1.0);
+        assert_eq!(number("exp(0)"), 1.0);
+        assert_eq!(number("floor(PI * 100)"), 314.0);
+    }
+
+    #[test]
+    fn test_min_max_sum() {
+        assert_eq!(number("min(3, 1, 2)"), 1.0);
+        assert_eq!(number("max(3, 1, 2)"), 3.0);
+        assert_eq!(number("max(-1)"), -1.0);
+        assert_eq!(
+            eval("min(\"b\", \"a\", \"c\")"),
+            Ok(Object::String("a".into()))
+        );
+        assert_eq!(number("sum(1, 2, 3.5)"), 6.5);
+        assert_eq!(number("sum()"), 0.0);
+        assert_eq!(
+            eval("min()"),
+            Err(Error::Type(
+                "min() takes at least 1 arguments but 0 were given".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("max(1, \"a\")"),
+            Err(Error::Type(
+                "max() cannot compare 'string' with 'number'".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("min(true)"),
+            Err(Error::Type(
+                "min() argument must be a number or a string, not 'boolean'".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("sum(1, nil)"),
+            Err(Error::Type(
+                "sum() argument must be a number, not 'nil'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            eval("sqrt(\"4\")"),
+            Err(Error::Type(
+                "sqrt() argument must be a number, not 'string'".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("pow(2, nil)"),
+            Err(Error::Type(
+                "pow() argument must be a number, not 'nil'".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("pow(2)"),
+            Err(Error::Type(
+                "pow() takes 2 arguments but 1 were given".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use std::{ops::RangeInclusive, rc::Rc};
+
+use crate::{
+    object::{Arity, Function, Native, NativeMethod},
+    Environment, Error, Interpretor, Object, Symbol,
+};
+
+mod assert;
+mod format;
+mod fs;
+mod io;
+pub(crate) mod json;
+mod math;
+mod number;
+mod random;
+mod regex;
+mod string;
+mod time;
+mod values;
+
+pub(crate) use random::Rng;
+
+// Defines every native function in `globals`, the scope the interpreter puts
+// around the program's globals. Declaring a builtin's name with `let`, `const`
+// or `fn`, at the top level or anywhere else, shadows it like any outer
+// variable, while assigning to it without declaring it first is an error.
+// `builtin(name)` reaches the original even where it is shadowed.
+pub(crate) fn register(globals: &mut Environment) {
+    assert::register(globals);
+    fs::register(globals);
+    format::register(globals);
+    io::register(globals);
+    json::register(globals);
+    math::register(globals);
+    random::register(globals);
+    regex::register(globals);
+    time::register(globals);
+    values::register(globals);
+}
+
+// the builtins that reach outside the interpreter: the files, the
+// environment, stdin, and the host's time through `sleep()`
+const AMBIENT: [&str; 7] = [
+    "read_file",
+    "write_file",
+    "append_file",
+    "file_exists",
+    "env",
+    "input",
+    "sleep",
+];
+
+// Replaces each of the `AMBIENT` builtins with one that fails with
+// `Error::NotPermitted` whatever it is given.
+pub(crate) fn sandbox(globals: &mut Environment) {
+    for name in AMBIENT {
+        define(globals, name, 0.., move |_, _| {
+            Err(Error::NotPermitted(name.to_string()))
+        });
+    }
+}
+
+// Looks up `value.name` on a builtin value. Only methods exist so far, and
+// they come back bound to `value`, ready to be called.
+pub(crate) fn property(value: &Object, name: &str) -> Result<Object, Error> {
+    let method = match value {
+        Object::Number(_) => number::method(name),
+        Object::String(_) => string::method(name),
+        _ => None,
+    };
+    match method {
+        Some((name, arity, func)) => Ok(Object::Function(Rc::new(Function::Method {
+            name,
+            arity,
+            receiver: value.clone(),
+            func: Box::new(func),
+        }))),
+        None => Err(Error::Type(format!(
+            "'{}' has no property \"{name}\"",
+            value.type_name()
+        ))),
+    }
+}
+
+// name, arity and implementation of a method of a builtin type
+type Method = (&'static str, RangeInclusive<usize>, NativeMethod);
+
+// Defines a native that only needs its arguments, the way an embedder
+// registers one with `Interpretor::register_native`.
+pub(crate) fn define_native(
+    globals: &mut Environment,
+    name: &str,
+    arity: impl Into<Arity>,
+    func: impl Fn(&[Object]) -> Result<Object, Error> + 'static,
+) {
+    define(globals, name, arity, move |_, args| func(args));
+}
+
+// defines a native that also reaches the state of the interpreter calling it
+pub(crate) fn define(
+    globals: &mut Environment,
+    name: &str,
+    arity: impl Into<Arity>,
+    func: impl Fn(&mut Interpretor, &[Object]) -> Result<Object, Error> + 'static,
+) {
+    let function = Function::Inbuilt {
+        name: name.to_string(),
+        arity: arity.into().0,
+        func: Native::new(func),
+    };
+    globals
+        .define(
+            Symbol::intern(name),
+            Object::Function(Rc::new(function)),
+            false,
+        )
+        .unwrap();
+}
+
+// evaluates a single expression for the tests of the natives and values
+#[cfg(test)]
+pub(crate) fn eval(input: &str) -> Result<Object, crate::Error> {
+    use crate::{Interpretor, Lexer, Parser};
+
+    let program = Parser::new(Lexer::new(format!("let result = {input};")))
+        .parse_program()
+        .unwrap();
+    let mut interpretor = Interpretor::new();
+    interpretor
+        .exec_in(&program, program.stmts[0])
+        .map_err(|e| e.error)?;
+    Ok(interpretor.get_global("result").unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, env, fs, rc::Rc};
+
+    use crate::{
+        interpretor::{on_large_stack, Capture},
+        Error, ErrorInfo, Interpretor, Object, Resolver,
+    };
+
+    // runs `source` on `interpretor` until a statement fails
+    fn run(interpretor: &mut Interpretor, source: &str) -> Result<(), ErrorInfo> {
+        let mut program = interpretor.parse(source, "native.rl").unwrap();
+        Resolver::new().resolve(&mut program)?;
+        for stmt in &program.stmts {
+            interpretor.exec_in(&program, *stmt)?;
+        }
+        Ok(())
+    }
+
+    fn interpretor() -> (Interpretor, Capture) {
+        let output = Capture::default();
+        let mut interpretor = Interpretor::new().with_output(Box::new(output.clone()));
+        interpretor.register_native("hash", 1, |args| {
+            let text: String = args[0].get()?;
+            Ok(text.bytes().map(f64::from).sum::<f64>().into())
+        });
+        interpretor.register_native("count", 1.., |args| Ok((args.len() as f64).into()));
+        (interpretor, output)
+    }
+
+    #[test]
+    fn test_register_native() {
+        let (mut interpretor, output) = interpretor();
+        run(
+            &mut interpretor,
+            "print hash(\"ab\"), count(1, 2, 3);
+            fn apply(f, x) { return f(x); }
+            let h = hash;
+            print apply(h, \"a\"), h == hash, type(hash);",
+        )
+        .unwrap();
+        assert_eq!(output.text(), "195 3\n97 true function\n");
+
+        // kept by a reset like the other builtins
+        interpretor.reset();
+        run(&mut interpretor, "print builtin(\"hash\")(\"b\");").unwrap();
+        assert_eq!(output.text(), "195 3\n97 true function\n98\n");
+    }
+
+    #[test]
+    fn test_native_errors() {
+        let (mut interpretor, _) = interpretor();
+        let err = run(&mut interpretor, "let a = 1;\nhash(a);").unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Type("expected string, found number".to_string())
+        );
+        // at the call
+        assert_eq!((err.span.line, err.span.start), (2, 15));
+
+        let err = run(&mut interpretor, "hash(\"a\", \"b\");").unwrap_err();
+        let message = "hash() takes 1 arguments but 2 were given";
+        assert_eq!(err.error, Error::Type(message.to_string()));
+        let err = run(&mut interpretor, "count();").unwrap_err();
+        let message = "count() takes at least 1 arguments but 0 were given";
+        assert_eq!(err.error, Error::Type(message.to_string()));
+    }
+
+    #[test]
+    fn test_callbacks() {
+        let (mut interpretor, output) = interpretor();
+        // calls its callback at once, twice
+        interpretor.register_native_with_interpretor("twice", 1, |interpretor, args| {
+            let mut total = 0.0;
+            for n in [1.0, 2.0] {
+                let value = interpretor.invoke(&args[0], &[n.into()]);
+                total += value.map_err(|err| err.error)?.get::<f64>()?;
+            }
+            Ok(total.into())
+        });
+        // keeps its callback for the host to call later
+        let ticks = Rc::new(RefCell::new(Vec::new()));
+        let kept = ticks.clone();
+        interpretor.register_native("on_tick", 1, move |args| {
+            kept.borrow_mut().push(args[0].clone());
+            Ok(Object::Nil)
+        });
+        run(
+            &mut interpretor,
+            "let total = 0;
+            fn tens(n) { return n * 10; }
+            print twice(tens);
+            # a callback calling back into the native that called it
+            fn nested(n) {
+                fn add(m) { return n + m; }
+                return twice(add);
+            }
+            print twice(nested);
+            fn tick(step) { total = total + step; print \"tick\", step; }
+            on_tick(tick);",
+        )
+        .unwrap();
+        assert_eq!(output.text(), "30\n12\n");
+
+        let tick = ticks.borrow()[0].clone();
+        for step in [1.0, 2.0] {
+            assert_eq!(interpretor.invoke(&tick, &[step.into()]), Ok(Object::Nil));
+        }
+        assert_eq!(output.text(), "30\n12\ntick 1\ntick 2\n");
+        assert_eq!(interpretor.get_global("total"), Some(3.0.into()));
+
+        // the callback's errors reach the host
+        run(
+            &mut interpretor,
+            "fn fail(step) { return step / 0; } on_tick(fail);",
+        )
+        .unwrap();
+        let failing = ticks.borrow()[1].clone();
+        let err = interpretor.invoke(&failing, &[1.0.into()]).unwrap_err();
+        assert_eq!(err.error, Error::ZeroDivision);
+        let err = run(&mut interpretor, "twice(fail);").unwrap_err();
+        assert_eq!(err.error, Error::ZeroDivision);
+        let err = interpretor.invoke(&1.0.into(), &[]).unwrap_err();
+        assert_eq!(err.error, Error::Type("1 is not callable".to_string()));
+    }
+
+    #[test]
+    fn test_sandboxed() {
+        let path = env::temp_dir().join(format!("rlisp-sandboxed-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let calls = [
+            format!("read_file(\"{path}\")"),
+            format!("write_file(\"{path}\", \"x\")"),
+            format!("append_file(\"{path}\", \"x\")"),
+            format!("file_exists(\"{path}\")"),
+            "env(\"HOME\")".to_string(),
+            "input()".to_string(),
+            "sleep(1)".to_string(),
+            // the original is gone, not just shadowed
+            "builtin(\"env\")(\"HOME\")".to_string(),
+        ];
+        let output = Capture::default();
+        let mut interpretor =
+            Interpretor::sandboxed(10_000, 1 << 20).with_output(Box::new(output.clone()));
+        for call in &calls {
+            let err = run(&mut interpretor, &format!("{call};")).unwrap_err();
+            assert_eq!(err.error.code(), "E0405", "{call}");
+            assert!(
+                err.error
+                    .to_string()
+                    .starts_with("PermissionError: operation not permitted in sandboxed mode: "),
+                "{call}"
+            );
+        }
+        assert!(fs::metadata(path).is_err());
+        let err = run(&mut interpretor, "env();").unwrap_err();
+        assert_eq!(err.error, Error::NotPermitted("env".to_string()));
+
+        // the rest of the builtins still work, within the limits
+        run(&mut interpretor, "print len(\"abc\"), str(1 + 1);").unwrap();
+        assert_eq!(output.text(), "3 2\n");
+        assert!(interpretor.fuel().unwrap() < 10_000);
+        let err = run(&mut interpretor, "while (true) {}").unwrap_err();
+        assert_eq!(err.error.code(), "E0400");
+        // recursing without end fails well before the fuel runs out
+        on_large_stack(|| {
+            let mut interpretor = Interpretor::sandboxed(u64::MAX, 1 << 20);
+            let err = run(&mut interpretor, "fn f() { return 1 + f(); } f();").unwrap_err();
+            let expected = Error::Runtime("maximum recursion depth exceeded".to_string());
+            assert_eq!(err.error, expected);
+        });
+
+        // unlike a capability the host turned off on its own
+        let mut interpretor = Interpretor::new().with_filesystem_access(false);
+        let call = format!("read_file(\"{path}\");");
+        let err = run(&mut interpretor, &call).unwrap_err();
+        assert_eq!(err.error.code(), "E0400");
+    }
+}
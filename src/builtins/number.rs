@@ -0,0 +1,99 @@
+use super::Method;
+use crate::{Error, Object};
+
+pub(crate) fn method(name: &str) -> Option<Method> {
+    let method: Method = match name {
+        // Exactly `digits` places after the point. The stored binary value is
+        // rounded, and a tie, which only an exactly representable value such
+        // as 2.5 can be, goes to the even digit. The sign of a negative value
+        // that rounds to zero is kept, the same way print shows -0.
+        "to_fixed" => ("to_fixed", 1..=1, |_, n, args| {
+            let digits = match &args[0] {
+                Object::Number(d) if d.fract() == 0.0 && (0.0..=20.0).contains(d) => *d as usize,
+                Object::Number(d) => {
+                    return Err(Error::Value(format!(
+                        "to_fixed() digits must be a whole number from 0 to 20, not {d}"
+                    )))
+                }
+                value => {
+                    return Err(Error::Type(format!(
+                        "to_fixed() argument must be a number, not '{}'",
+                        value.type_name()
+                    )))
+                }
+            };
+            Ok(Object::String(format!("{:.*}", digits, number(n)).into()))
+        }),
+        _ => return None,
+    };
+    Some(method)
+}
+
+// the receiver of a number method
+fn number(value: &Object) -> f64 {
+    match value {
+        Object::Number(n) => *n,
+        value => unreachable!("{value} is not a number"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::eval;
+    use crate::{Error, Object};
+
+    #[test]
+    fn test_to_fixed() {
+        let cases = [
+            ("3.14159", "2", "3.14"),
+            ("2", "3", "2.000"),
+            ("1.5", "0", "2"),
+            ("2.5", "0", "2"),
+            ("0.125", "2", "0.12"),
+            // just below 1.005 in binary
+            ("1.005", "2", "1.00"),
+            ("(-1.25)", "1", "-1.2"),
+            ("(-0.001)", "2", "-0.00"),
+            ("(-0)", "2", "-0.00"),
+            ("1000000000000000000000", "2", "1000000000000000000000.00"),
+            ("0.1", "20", "0.10000000000000000555"),
+        ];
+        for (n, digits, expected) in cases {
+            let call = format!("{n}.to_fixed({digits})");
+            assert_eq!(eval(&call), Ok(Object::String(expected.into())), "{call}");
+        }
+        // print and to_fixed(0) agree on whole numbers
+        assert_eq!(
+            eval("str(42) == (42).to_fixed(0)"),
+            Ok(Object::Boolean(true))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            eval("(1).to_fixed(21)"),
+            Err(Error::Value(
+                "to_fixed() digits must be a whole number from 0 to 20, not 21".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("(1).to_fixed(1.5)"),
+            Err(Error::Value(
+                "to_fixed() digits must be a whole number from 0 to 20, not 1.5".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("(1).to_fixed(\"2\")"),
+            Err(Error::Type(
+                "to_fixed() argument must be a number, not 'string'".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("(1).round"),
+            Err(Error::Type(
+                "'number' has no property \"round\"".to_string()
+            ))
+        );
+    }
+}
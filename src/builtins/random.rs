@@ -0,0 +1,145 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{interpretor::HOSTED, Environment, Error, Object};
+
+// what `random()` is seeded with when built for wasm, which has no clock
+const FIXED_SEED: u64 = 0x5eed;
+
+// SplitMix64, small and good enough for scripts; not for cryptography
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn from_time() -> Self {
+        if !HOSTED {
+            return Self::new(FIXED_SEED);
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        Self::new(now.as_nanos() as u64)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+pub(crate) fn register(globals: &mut Environment) {
+    super::define(globals, "random", 0, |interpretor, _args| {
+        Ok(Object::Number(interpretor.rng.next_f64()))
+    });
+    // both bounds are inclusive
+    super::define(globals, "random_int", 2, |interpretor, args| {
+        let lo = integer(&args[0])?;
+        let hi = integer(&args[1])?;
+        if lo > hi {
+            return Err(Error::Value(format!(
+                "random_int() lower bound {lo} is greater than upper bound {hi}"
+            )));
+        }
+        let offset = (interpretor.rng.next_f64() * (hi - lo + 1.0)).floor();
+        Ok(Object::Number(lo + offset))
+    });
+}
+
+fn integer(value: &Object) -> Result<f64, Error> {
+    match value {
+        Object::Number(n) if n.fract() == 0.0 => Ok(*n),
+        Object::Number(n) => Err(Error::Value(format!(
+            "random_int() bounds must be whole numbers, not {n}"
+        ))),
+        value => Err(Error::Type(format!(
+            "random_int() argument must be a number, not '{}'",
+            value.type_name()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Interpretor, Lexer, Object, Parser};
+
+    fn numbers(interpretor: &mut Interpretor, input: &str, count: usize) -> Vec<Object> {
+        let program = Parser::new(Lexer::new(format!("let result = {input};")))
+            .parse_program()
+            .unwrap();
+        (0..count)
+            .map(|_| {
+                interpretor.exec_in(&program, program.stmts[0]).unwrap();
+                interpretor.get_global("result").unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_seed() {
+        let first = numbers(&mut Interpretor::new().with_rng_seed(7), "random()", 10);
+        let second = numbers(&mut Interpretor::new().with_rng_seed(7), "random()", 10);
+        assert_eq!(first, second);
+        let other = numbers(&mut Interpretor::new().with_rng_seed(8), "random()", 10);
+        assert_ne!(first, other);
+        for value in first {
+            let Object::Number(n) = value else { panic!() };
+            assert!((0.0..1.0).contains(&n));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "wasm")]
+    fn test_fixed_seed() {
+        // there is no clock to seed from
+        let first = numbers(&mut Interpretor::new(), "random()", 10);
+        assert_eq!(first, numbers(&mut Interpretor::new(), "random()", 10));
+    }
+
+    #[test]
+    fn test_random_int() {
+        let mut interpretor = Interpretor::new().with_rng_seed(1);
+        let values = numbers(&mut interpretor, "random_int(-2, 2)", 200);
+        for n in -2..=2 {
+            assert!(values.contains(&Object::Number(n as f64)), "{n}");
+        }
+        assert!(values
+            .iter()
+            .all(|v| matches!(v, Object::Number(n) if (-2.0..=2.0).contains(n))));
+        assert_eq!(
+            numbers(&mut interpretor, "random_int(3, 3)", 1),
+            [Object::Number(3.0)]
+        );
+    }
+
+    #[test]
+    fn test_random_int_errors() {
+        let program = |input: &str| {
+            Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap()
+        };
+        let mut interpretor = Interpretor::new();
+        let mut error = |input: &str| {
+            let program = program(input);
+            let stmt = program.stmts[0];
+            interpretor.exec_in(&program, stmt).unwrap_err().error
+        };
+        assert_eq!(
+            error("random_int(2, 1);"),
+            Error::Value("random_int() lower bound 2 is greater than upper bound 1".to_string())
+        );
+        assert_eq!(
+            error("random_int(0.5, 1);"),
+            Error::Value("random_int() bounds must be whole numbers, not 0.5".to_string())
+        );
+    }
+}
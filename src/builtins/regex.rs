@@ -0,0 +1,389 @@
+use crate::{Environment, Error, Object};
+
+// A small regular expression engine, enough for validating and cleaning up
+// text without a dependency. The supported syntax is
+//
+//   a       a literal character
+//   .       any character
+//   [abc]   one of a set, with ranges such as [a-z] and [^abc] to negate
+//   \d \w \s and \D \W \S   digits, word characters, whitespace and their
+//           complements, also inside sets
+//   \.      any other escaped character stands for itself
+//   * + ?   zero or more, one or more, zero or one of the previous item
+//   ^ $     the start and end of the text
+//
+// Groups, alternation and counted repetition are rejected as invalid. Loops
+// are greedy and matching backtracks, so a pattern is found at the leftmost
+// position where it matches.
+pub(crate) fn register(globals: &mut Environment) {
+    // whether the pattern matches anywhere in the text, anchor it with ^ and
+    // $ to match the whole text
+    super::define(globals, "re_match", 2, |_interpretor, args| {
+        let (regex, text) = arguments("re_match", args)?;
+        Ok(Object::Boolean(regex.find(&text, 0).is_some()))
+    });
+    // the first match, or nil
+    super::define(globals, "re_find", 2, |_interpretor, args| {
+        let (regex, text) = arguments("re_find", args)?;
+        Ok(match regex.find(&text, 0) {
+            Some((start, end)) => {
+                Object::String(text[start..end].iter().collect::<String>().into())
+            }
+            None => Object::Nil,
+        })
+    });
+    // every non-overlapping match replaced by the replacement as it is
+    super::define(globals, "re_replace", 3, |interpretor, args| {
+        let (regex, text) = arguments("re_replace", args)?;
+        let replacement = match &args[2] {
+            Object::String(s) => s,
+            value => {
+                return Err(Error::Type(format!(
+                    "re_replace() replacement must be a string, not '{}'",
+                    value.type_name()
+                )))
+            }
+        };
+        let mut result = String::new();
+        let mut pos = 0;
+        while let Some((start, end)) = regex.find(&text, pos) {
+            result.extend(&text[pos..start]);
+            result.push_str(replacement);
+            if end == start {
+                // step over a character so an empty match cannot repeat
+                if let Some(ch) = text.get(end) {
+                    result.push(*ch);
+                }
+                pos = end + 1;
+            } else {
+                pos = end;
+            }
+        }
+        if pos < text.len() {
+            result.extend(&text[pos..]);
+        }
+        interpretor.reserve(result.len())?;
+        Ok(Object::String(result.into()))
+    });
+}
+
+fn arguments(function: &str, args: &[Object]) -> Result<(Regex, Vec<char>), Error> {
+    let (Object::String(pattern), Object::String(text)) = (&args[0], &args[1]) else {
+        let value = if let Object::String(_) = args[0] {
+            &args[1]
+        } else {
+            &args[0]
+        };
+        return Err(Error::Type(format!(
+            "{function}() arguments must be strings, not '{}'",
+            value.type_name()
+        )));
+    };
+    let regex = Regex::new(pattern)
+        .map_err(|e| Error::Value(format!("{function}() invalid pattern: {e}")))?;
+    Ok((regex, text.chars().collect()))
+}
+
+#[derive(Debug)]
+enum Node {
+    Char(char),
+    Any,
+    // inclusive ranges of characters, matching those outside when negated
+    Class(Vec<(char, char)>, bool),
+    Start,
+    End,
+}
+
+impl Node {
+    fn matches(&self, ch: char) -> bool {
+        match self {
+            Node::Char(c) => *c == ch,
+            Node::Any => true,
+            Node::Class(ranges, negated) => {
+                ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&ch)) != *negated
+            }
+            Node::Start | Node::End => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Piece {
+    node: Node,
+    min: usize,
+    max: usize,
+}
+
+struct Regex {
+    pieces: Vec<Piece>,
+}
+
+impl Regex {
+    fn new(pattern: &str) -> Result<Self, String> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut pieces: Vec<Piece> = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let node = match chars[i] {
+                '*' | '+' | '?' => {
+                    let (min, max) = match chars[i] {
+                        '*' => (0, usize::MAX),
+                        '+' => (1, usize::MAX),
+                        _ => (0, 1),
+                    };
+                    match pieces.last_mut() {
+                        Some(piece)
+                            if piece.min == 1
+                                && piece.max == 1
+                                && !matches!(piece.node, Node::Start | Node::End) =>
+                        {
+                            (piece.min, piece.max) = (min, max);
+                        }
+                        _ => return Err(format!("nothing to repeat at position {i}")),
+                    }
+                    i += 1;
+                    continue;
+                }
+                '.' => Node::Any,
+                '^' => Node::Start,
+                '$' => Node::End,
+                '[' => {
+                    let (node, end) = class(&chars, i)?;
+                    i = end;
+                    node
+                }
+                '\\' => {
+                    i += 1;
+                    match chars.get(i) {
+                        Some(ch) => match shorthand(*ch) {
+                            Some((ranges, negated)) => Node::Class(ranges, negated),
+                            None => Node::Char(*ch),
+                        },
+                        None => return Err("trailing backslash".to_string()),
+                    }
+                }
+                ch @ ('(' | ')' | '|' | '{' | '}') => {
+                    return Err(format!("unsupported syntax '{ch}' at position {i}"))
+                }
+                ch => Node::Char(ch),
+            };
+            pieces.push(Piece {
+                node,
+                min: 1,
+                max: 1,
+            });
+            i += 1;
+        }
+        Ok(Self { pieces })
+    }
+
+    // start and end of the leftmost match at or after `from`
+    fn find(&self, text: &[char], from: usize) -> Option<(usize, usize)> {
+        (from..=text.len())
+            .find_map(|start| match_here(&self.pieces, text, start).map(|end| (start, end)))
+    }
+}
+
+// the end of the greedy match of `pieces` starting exactly at `pos`
+fn match_here(pieces: &[Piece], text: &[char], pos: usize) -> Option<usize> {
+    let Some((piece, rest)) = pieces.split_first() else {
+        return Some(pos);
+    };
+    match piece.node {
+        Node::Start => return (pos == 0).then(|| match_here(rest, text, pos)).flatten(),
+        Node::End => {
+            return (pos == text.len())
+                .then(|| match_here(rest, text, pos))
+                .flatten()
+        }
+        _ => {}
+    }
+    let available = text[pos..]
+        .iter()
+        .take(piece.max)
+        .take_while(|ch| piece.node.matches(**ch))
+        .count();
+    (piece.min..=available)
+        .rev()
+        .find_map(|count| match_here(rest, text, pos + count))
+}
+
+// a bracketed set starting at `start`, and the index of its closing bracket
+fn class(chars: &[char], start: usize) -> Result<(Node, usize), String> {
+    let mut i = start + 1;
+    let negated = chars.get(i) == Some(&'^');
+    if negated {
+        i += 1;
+    }
+    let mut ranges = Vec::new();
+    // a bracket right at the start is a member rather than the end
+    let first = i;
+    loop {
+        let ch = match chars.get(i) {
+            None => return Err(format!("unterminated set at position {start}")),
+            Some(']') if i > first => return Ok((Node::Class(ranges, negated), i)),
+            Some('\\') => {
+                i += 1;
+                let Some(ch) = chars.get(i) else {
+                    return Err("trailing backslash".to_string());
+                };
+                if let Some((class, false)) = shorthand(*ch) {
+                    ranges.extend(class);
+                    i += 1;
+                    continue;
+                }
+                if shorthand(*ch).is_some() {
+                    return Err(format!(
+                        "\\{ch} is not supported inside a set at position {}",
+                        i - 1
+                    ));
+                }
+                *ch
+            }
+            Some(ch) => *ch,
+        };
+        match (chars.get(i + 1), chars.get(i + 2)) {
+            (Some('-'), Some(&hi)) if hi != ']' => {
+                if hi < ch {
+                    return Err(format!("invalid range {ch}-{hi} at position {i}"));
+                }
+                ranges.push((ch, hi));
+                i += 3;
+            }
+            _ => {
+                ranges.push((ch, ch));
+                i += 1;
+            }
+        }
+    }
+}
+
+// the ranges behind \d, \w and \s, and whether the upper case form negates
+fn shorthand(ch: char) -> Option<(Vec<(char, char)>, bool)> {
+    let ranges = match ch.to_ascii_lowercase() {
+        'd' => vec![('0', '9')],
+        'w' => vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')],
+        's' => vec![
+            (' ', ' '),
+            ('\t', '\t'),
+            ('\n', '\n'),
+            ('\r', '\r'),
+            ('\u{b}', '\u{c}'),
+        ],
+        _ => return None,
+    };
+    Some((ranges, ch.is_ascii_uppercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::eval;
+    use crate::{Error, Object};
+
+    fn is_match(pattern: &str, text: &str) -> bool {
+        let call = format!("re_match(\"{pattern}\", \"{text}\")");
+        match eval(&call) {
+            Ok(Object::Boolean(b)) => b,
+            result => panic!("{call}: {result:?}"),
+        }
+    }
+
+    fn find(pattern: &str, text: &str) -> Object {
+        eval(&format!("re_find(\"{pattern}\", \"{text}\")")).unwrap()
+    }
+
+    #[test]
+    fn test_literals_and_dot() {
+        assert!(is_match("bc", "abcd"));
+        assert!(!is_match("bd", "abcd"));
+        assert!(is_match("a.c", "abc"));
+        assert!(!is_match("a.c", "ac"));
+        assert!(is_match("", "anything"));
+        assert!(is_match("a\\.c", "a.c"));
+        assert!(!is_match("a\\.c", "abc"));
+    }
+
+    #[test]
+    fn test_repetition() {
+        assert!(is_match("^ab*c$", "ac"));
+        assert!(is_match("^ab*c$", "abbbc"));
+        assert!(!is_match("^ab+c$", "ac"));
+        assert!(is_match("^ab+c$", "abbc"));
+        assert!(is_match("^colou?r$", "color"));
+        assert!(is_match("^colou?r$", "colour"));
+        assert!(!is_match("^colou?r$", "colouur"));
+        // greedy, but backtracks to let the rest match
+        assert_eq!(find("a.*b", "xaxbxbx"), Object::String("axbxb".into()));
+        assert_eq!(find("\\d+", "abc 123 45"), Object::String("123".into()));
+    }
+
+    #[test]
+    fn test_sets() {
+        assert!(is_match("^[a-c]+$", "abcabc"));
+        assert!(!is_match("^[a-c]+$", "abcd"));
+        assert!(is_match("^[^0-9]+$", "abc"));
+        assert!(!is_match("^[^0-9]+$", "ab1"));
+        assert!(is_match("^[\\d.]+$", "3.14"));
+        assert!(is_match("^[]a]+$", "]a]"));
+        assert!(is_match("^[a-]+$", "a-a"));
+        assert!(is_match("^\\w+\\s\\w+$", "hello world_1"));
+        assert!(is_match("^\\D\\W\\S$", "a-b"));
+        assert!(!is_match("^\\D$", "1"));
+    }
+
+    #[test]
+    fn test_anchors() {
+        assert!(is_match("^ab", "abc"));
+        assert!(!is_match("^bc", "abc"));
+        assert!(is_match("bc$", "abc"));
+        assert!(!is_match("ab$", "abc"));
+        assert!(is_match("^$", ""));
+    }
+
+    #[test]
+    fn test_find_and_replace() {
+        assert_eq!(find("[0-9]+", "no digits"), Object::Nil);
+        assert_eq!(find("é+", "café"), Object::String("é".into()));
+        let replace = |pattern: &str, text: &str, with: &str| {
+            eval(&format!(
+                "re_replace(\"{pattern}\", \"{text}\", \"{with}\")"
+            ))
+            .unwrap()
+        };
+        assert_eq!(
+            replace("\\s+", "a  b \t c", " "),
+            Object::String("a b c".into())
+        );
+        assert_eq!(replace("\\d", "a1b22", "#"), Object::String("a#b##".into()));
+        assert_eq!(replace("x*", "ab", "-"), Object::String("-a-b-".into()));
+        assert_eq!(replace("^", "ab", ">"), Object::String(">ab".into()));
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        let error = |pattern: &str, message: &str| {
+            assert_eq!(
+                eval(&format!("re_match(\"{pattern}\", \"\")")),
+                Err(Error::Value(format!(
+                    "re_match() invalid pattern: {message}"
+                ))),
+                "{pattern}"
+            );
+        };
+        error("*a", "nothing to repeat at position 0");
+        error("a**", "nothing to repeat at position 2");
+        error("^*", "nothing to repeat at position 1");
+        error("[ab", "unterminated set at position 0");
+        error("[z-a]", "invalid range z-a at position 1");
+        error("a\\", "trailing backslash");
+        error("(a)", "unsupported syntax '(' at position 0");
+        error("a|b", "unsupported syntax '|' at position 1");
+        error("[\\D]", "\\D is not supported inside a set at position 1");
+        assert_eq!(
+            eval("re_find(\"a\", 1)"),
+            Err(Error::Type(
+                "re_find() arguments must be strings, not 'number'".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use super::Method;
+use crate::{object::utils::repeated_len, Error, Object};
+
+pub(crate) fn method(name: &str) -> Option<Method> {
+    let method: Method = match name {
+        "upper" => ("upper", 0..=0, |_, s, _| {
+            Ok(Object::String(text(s).to_uppercase().into()))
+        }),
+        "lower" => ("lower", 0..=0, |_, s, _| {
+            Ok(Object::String(text(s).to_lowercase().into()))
+        }),
+        "trim" => ("trim", 0..=0, |_, s, _| {
+            Ok(Object::String(text(s).trim().into()))
+        }),
+        "trim_start" => ("trim_start", 0..=0, |_, s, _| {
+            Ok(Object::String(text(s).trim_start().into()))
+        }),
+        "trim_end" => ("trim_end", 0..=0, |_, s, _| {
+            Ok(Object::String(text(s).trim_end().into()))
+        }),
+        "contains" => ("contains", 1..=1, |_, s, args| {
+            let sub = string_arg("contains", &args[0])?;
+            Ok(Object::Boolean(text(s).contains(sub)))
+        }),
+        "starts_with" => ("starts_with", 1..=1, |_, s, args| {
+            let prefix = string_arg("starts_with", &args[0])?;
+            Ok(Object::Boolean(text(s).starts_with(prefix)))
+        }),
+        "ends_with" => ("ends_with", 1..=1, |_, s, args| {
+            let suffix = string_arg("ends_with", &args[0])?;
+            Ok(Object::Boolean(text(s).ends_with(suffix)))
+        }),
+        // in characters, or -1 when absent
+        "index_of" => ("index_of", 1..=1, |_, s, args| {
+            let s = text(s);
+            let sub = string_arg("index_of", &args[0])?;
+            let index = match s.find(sub) {
+                Some(byte) => s[..byte].chars().count() as f64,
+                None => -1.0,
+            };
+            Ok(Object::Number(index))
+        }),
+        // every non-overlapping occurrence, left to right
+        "replace" => ("replace", 2..=2, |interpretor, s, args| {
+            let from = string_arg("replace", &args[0])?;
+            let to = string_arg("replace", &args[1])?;
+            if from.is_empty() {
+                return Err(Error::Value(
+                    "replace() cannot replace an empty string".to_string(),
+                ));
+            }
+            let s = text(s);
+            let count = s.matches(from).count();
+            interpretor.reserve((s.len() - count * from.len()).saturating_add(count * to.len()))?;
+            Ok(Object::String(s.replace(from, to).into()))
+        }),
+        "repeat" => ("repeat", 1..=1, |interpretor, s, args| {
+            let count = match &args[0] {
+                Object::Number(n) if n.fract() == 0.0 && *n >= 0.0 => *n as usize,
+                Object::Number(n) => {
+                    return Err(Error::Value(format!(
+                        "repeat() count must be a whole number of at least 0, not {n}"
+                    )))
+                }
+                value => {
+                    return Err(Error::Type(format!(
+                        "repeat() argument must be a number, not '{}'",
+                        value.type_name()
+                    )))
+                }
+            };
+            let s = text(s);
+            interpretor.reserve(repeated_len(s, count)?)?;
+            Ok(Object::String(s.repeat(count).into()))
+        }),
+        _ => return None,
+    };
+    Some(method)
+}
+
+// the receiver of a string method
+fn text(value: &Object) -> &str {
+    match value {
+        Object::String(s) => s,
+        value => unreachable!("{value} is not a string"),
+    }
+}
+
+fn string_arg<'a>(method: &str, value: &'a Object) -> Result<&'a str, Error> {
+    match value {
+        Object::String(s) => Ok(s),
+        value => Err(Error::Type(format!(
+            "{method}() argument must be a string, not '{}'",
+            value.type_name()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::eval;
+    use crate::{Error, Object};
+
+    fn string(input: &str) -> Object {
+        Object::String(input.into())
+    }
+
+    #[test]
+    fn test_case_and_trim() {
+        assert_eq!(eval("\"Straße\".upper()"), Ok(string("STRASSE")));
+        assert_eq!(eval("\"ÀB\".lower()"), Ok(string("àb")));
+        assert_eq!(eval("\"  a b  \".trim()"), Ok(string("a b")));
+        assert_eq!(eval("\"  a \".trim_start()"), Ok(string("a ")));
+        assert_eq!(eval("\" a  \".trim_end()"), Ok(string(" a")));
+    }
+
+    #[test]
+    fn test_chaining() {
+        assert_eq!(eval("\"  Ada \".trim().lower().upper()"), Ok(string("ADA")));
+        // a method looked up without calling it stays bound to its string
+        assert_eq!(eval("(\"ab\".upper)()"), Ok(string("AB")));
+        assert_eq!(eval("type(\"ab\".upper)"), Ok(string("function")));
+    }
+
+    #[test]
+    fn test_search() {
+        assert_eq!(
+            eval("\"héllo\".contains(\"éll\")"),
+            Ok(Object::Boolean(true))
+        );
+        assert_eq!(eval("\"abc\".contains(\"\")"), Ok(Object::Boolean(true)));
+        assert_eq!(
+            eval("\"abc\".starts_with(\"ab\")"),
+            Ok(Object::Boolean(true))
+        );
+        assert_eq!(
+            eval("\"abc\".ends_with(\"ab\")"),
+            Ok(Object::Boolean(false))
+        );
+        // indices count characters, not bytes
+        assert_eq!(eval("\"héllo\".index_of(\"l\")"), Ok(Object::Number(2.0)));
+        assert_eq!(eval("\"abc\".index_of(\"z\")"), Ok(Object::Number(-1.0)));
+        assert_eq!(
+            eval("\"abc\".contains(1)"),
+            Err(Error::Type(
+                "contains() argument must be a string, not 'number'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_replace_and_repeat() {
+        assert_eq!(eval("\"a-b-c\".replace(\"-\", \"+\")"), Ok(string("a+b+c")));
+        // overlapping matches are replaced left to right without overlap
+        assert_eq!(eval("\"aaa\".replace(\"aa\", \"b\")"), Ok(string("ba")));
+        assert_eq!(eval("\"ça va\".replace(\"ç\", \"c\")"), Ok(string("ca va")));
+        assert_eq!(
+            eval("\"abc\".replace(\"\", \"x\")"),
+            Err(Error::Value(
+                "replace() cannot replace an empty string".to_string()
+            ))
+        );
+        assert_eq!(eval("\"ab\".repeat(3)"), Ok(string("ababab")));
+        assert_eq!(eval("\"ab\".repeat(0)"), Ok(string("")));
+        assert_eq!(
+            eval("\"ab\".repeat(-1)"),
+            Err(Error::Value(
+                "repeat() count must be a whole number of at least 0, not -1".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("\"ab\".repeat(10000000000000000000)"),
+            Err(Error::Value(
+                "repeated string would be longer than 1073741824 bytes".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            eval("\"a\".upper(1)"),
+            Err(Error::Type(
+                "upper() takes 0 arguments but 1 were given".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("\"a\".shout()"),
+            Err(Error::Type(
+                "'string' has no property \"shout\"".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("(1).upper()"),
+            Err(Error::Type(
+                "'number' has no property \"upper\"".to_string()
+            ))
+        );
+    }
+}
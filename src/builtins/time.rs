@@ -0,0 +1,161 @@
+use std::{
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::Environment;
+use crate::{interpretor::HOSTED, Error, Object};
+
+// longest stretch `sleep()` goes without looking for a cancellation
+const SLEEP_SLICE: Duration = Duration::from_millis(10);
+
+pub(crate) fn register(globals: &mut Environment) {
+    // seconds since the interpreter was created, for timing code
+    let started = HOSTED.then(Instant::now);
+    super::define_native(globals, "clock", 0, move |_args| match started {
+        Some(started) => Ok(Object::Number(started.elapsed().as_secs_f64())),
+        None => Err(unavailable("clock")),
+    });
+    // seconds since the Unix epoch
+    super::define(globals, "time", 0, |_interpretor, _args| {
+        if !HOSTED {
+            return Err(unavailable("time"));
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        Ok(Object::Number(now.as_secs_f64()))
+    });
+    // pauses for a number of milliseconds, waking early if cancelled
+    super::define(globals, "sleep", 1, |interpretor, args| {
+        if !HOSTED {
+            return Err(unavailable("sleep"));
+        }
+        let duration = match &args[0] {
+            Object::Number(ms) if *ms >= 0.0 && ms.is_finite() => {
+                Duration::from_secs_f64(ms / 1000.0)
+            }
+            value => {
+                return Err(Error::Value(format!(
+                    "sleep() duration must be a number of milliseconds of at least 0, not {value}"
+                )))
+            }
+        };
+        let token = interpretor.cancellation_token();
+        let deadline = Instant::now() + duration;
+        loop {
+            if token.is_cancelled() {
+                return Err(Error::Interrupted);
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(Object::Nil);
+            }
+            thread::sleep(left.min(SLEEP_SLICE));
+        }
+    });
+}
+
+// std has no clock on wasm32, and panics when asked for the time or to sleep
+fn unavailable(name: &str) -> Error {
+    Error::Runtime(format!("{name}() is not available when built for wasm"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::eval;
+    use crate::{Error, Interpretor, Lexer, Object, Parser};
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn test_clock() {
+        let Ok(Object::Number(elapsed)) = eval("clock()") else {
+            panic!()
+        };
+        assert!((0.0..60.0).contains(&elapsed));
+        let Ok(Object::Boolean(true)) = eval("clock() <= clock()") else {
+            panic!()
+        };
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn test_time() {
+        // some time after 2020
+        let Ok(Object::Number(now)) = eval("time()") else {
+            panic!()
+        };
+        assert!(now > 1.6e9);
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn test_sleep() {
+        let mut interpretor = Interpretor::new();
+        let program = Parser::new(Lexer::new("sleep(20); sleep(0);".to_string()))
+            .parse_program()
+            .unwrap();
+        let started = std::time::Instant::now();
+        interpretor.interpret(program).unwrap();
+        assert!(started.elapsed() >= std::time::Duration::from_millis(20));
+        for arg in ["-1", "\"1\"", "nil"] {
+            let shown = eval(arg).unwrap();
+            assert_eq!(
+                eval(&format!("sleep({arg})")),
+                Err(Error::Value(format!(
+                    "sleep() duration must be a number of milliseconds of at least 0, not {shown}"
+                )))
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn test_cancel_sleep() {
+        let program = Parser::new(Lexer::new("sleep(60000);".to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new();
+        let token = interpretor.cancellation_token();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            token.cancel();
+        });
+        let started = std::time::Instant::now();
+        let err = interpretor.interpret(program).unwrap_err();
+        canceller.join().unwrap();
+        assert_eq!(err.error, Error::Interrupted);
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    #[cfg(feature = "wasm")]
+    fn test_unavailable() {
+        for name in ["clock", "time"] {
+            let expected = format!("{name}() is not available when built for wasm");
+            assert_eq!(eval(&format!("{name}()")), Err(Error::Runtime(expected)));
+        }
+        let expected = "sleep() is not available when built for wasm".to_string();
+        assert_eq!(eval("sleep(1)"), Err(Error::Runtime(expected)));
+    }
+
+    #[test]
+    fn test_arity() {
+        assert_eq!(
+            eval("clock(1)"),
+            Err(Error::Type(
+                "clock() takes 0 arguments but 1 were given".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let program = Parser::new(Lexer::new(
+            "let time = 5; fn clock() { return 1; } let a = time + clock();".to_string(),
+        ))
+        .parse_program()
+        .unwrap();
+        let mut interpretor = Interpretor::new();
+        interpretor.interpret(program).unwrap();
+        assert_eq!(interpretor.get_global("a"), Some(Object::Number(6.0)));
+    }
+}
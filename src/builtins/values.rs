@@ -0,0 +1,338 @@
+use crate::{Environment, Error, Object, Symbol};
+
+pub(crate) fn register(globals: &mut Environment) {
+    super::define_native(globals, "len", 1, |args| match &args[0] {
+        // characters rather than bytes
+        Object::String(s) => Ok(Object::Number(s.chars().count() as f64)),
+        value => Err(Error::Type(format!(
+            "object of type '{}' has no len()",
+            value.type_name()
+        ))),
+    });
+    // the native called `name`, whatever the program has declared since
+    super::define(globals, "builtin", 1, |interpretor, args| match &args[0] {
+        Object::String(name) => Symbol::lookup(name)
+            .and_then(|symbol| interpretor.builtins.borrow_mut().get(symbol).ok())
+            .ok_or_else(|| Error::Value(format!("no builtin called \"{name}\""))),
+        value => Err(Error::Type(format!(
+            "builtin() argument must be a string, not '{}'",
+            value.type_name()
+        ))),
+    });
+    super::define(globals, "type", 1, |_interpretor, args| {
+        Ok(Object::String(args[0].type_name().into()))
+    });
+    // formatted exactly like `print`
+    super::define(globals, "str", 1, |_interpretor, args| {
+        Ok(Object::String(args[0].to_string().into()))
+    });
+    // code point of a one character string
+    super::define(globals, "ord", 1, |_interpretor, args| {
+        let mut chars = match &args[0] {
+            Object::String(s) => s.chars(),
+            value => {
+                return Err(Error::Type(format!(
+                    "ord() argument must be a string, not '{}'",
+                    value.type_name()
+                )))
+            }
+        };
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => Ok(Object::Number(ch as u32 as f64)),
+            _ => Err(Error::Value(format!(
+                "ord() expected a single character, not \"{}\"",
+                args[0]
+            ))),
+        }
+    });
+    // rejects surrogates and anything above 0x10FFFF
+    super::define(globals, "chr", 1, |_interpretor, args| match &args[0] {
+        Object::Number(n) if n.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(n) => {
+            match char::from_u32(*n as u32) {
+                Some(ch) => Ok(Object::String(ch.to_string().into())),
+                None => Err(Error::Value(format!("chr() {n} is not a valid code point"))),
+            }
+        }
+        Object::Number(n) => Err(Error::Value(format!("chr() {n} is not a valid code point"))),
+        value => Err(Error::Type(format!(
+            "chr() argument must be a number, not '{}'",
+            value.type_name()
+        ))),
+    });
+    super::define(globals, "num", 1, |_interpretor, args| match &args[0] {
+        Object::Number(n) => Ok(Object::Number(*n)),
+        Object::Boolean(b) => Ok(Object::Number(if *b { 1.0 } else { 0.0 })),
+        Object::String(s) => parse_number(s),
+        value => Err(Error::Type(format!(
+            "num() argument must be a string, number or boolean, not '{}'",
+            value.type_name()
+        ))),
+    });
+    // whole numbers in bases 2 to 36, 10 by default
+    super::define(globals, "parse_int", 1..=2, |_interpretor, args| {
+        let s = parse_arg("parse_int", &args[0])?;
+        let radix = match args.get(1) {
+            None => 10,
+            Some(Object::Number(n)) if n.fract() == 0.0 && (2.0..=36.0).contains(n) => *n as u32,
+            Some(radix) => {
+                return Err(Error::Value(format!(
+                    "parse_int() base must be a whole number from 2 to 36, not {radix}"
+                )))
+            }
+        };
+        parse_int(s, radix).ok_or_else(|| {
+            Error::Value(format!(
+                "parse_int() invalid literal for base {radix}: '{s}'"
+            ))
+        })
+    });
+    super::define(globals, "parse_float", 1, |_interpretor, args| {
+        let s = parse_arg("parse_float", &args[0])?;
+        parse_number(s).map_err(|_| Error::Value(format!("parse_float() invalid number: '{s}'")))
+    });
+}
+
+fn parse_arg<'a>(function: &str, value: &'a Object) -> Result<&'a str, Error> {
+    match value {
+        Object::String(s) => Ok(s),
+        value => Err(Error::Type(format!(
+            "{function}() argument must be a string, not '{}'",
+            value.type_name()
+        ))),
+    }
+}
+
+// an optional sign and at least one digit of `radix`, ignoring surrounding
+// whitespace; digits past what f64 can hold exactly are rounded
+fn parse_int(s: &str, radix: u32) -> Option<Object> {
+    let text = s.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    let mut n = 0.0;
+    for ch in digits.chars() {
+        n = n * radix as f64 + ch.to_digit(radix)? as f64;
+    }
+    Some(Object::Number(if negative { -n } else { n }))
+}
+
+// Accepts the number syntax of the language with an optional sign and
+// exponent, ignoring surrounding whitespace. Words such as "inf" and "nan"
+// that Rust would also parse are rejected.
+fn parse_number(s: &str) -> Result<Object, Error> {
+    let text = s.trim();
+    let is_numeric = text
+        .chars()
+        .all(|ch| ch.is_ascii_digit() || matches!(ch, '+' | '-' | '.' | 'e' | 'E'));
+    match text.parse::<f64>() {
+        Ok(n) if is_numeric => Ok(Object::Number(n)),
+        _ => Err(Error::Value(format!("invalid number:'{s}'"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::eval;
+    use crate::{Error, Interpretor, Lexer, Object, Parser, Resolver};
+
+    #[test]
+    fn test_shadowing_builtins() {
+        let mut interpretor = Interpretor::new();
+        let mut run = |input: &str| {
+            let mut program = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap();
+            Resolver::new().resolve(&mut program).unwrap();
+            program
+                .stmts
+                .iter()
+                .try_for_each(|stmt| interpretor.exec_in(&program, *stmt).map_err(|e| e.error))
+        };
+        run("fn f() { let str = 1; return str; } let a = f(); let b = str(2);").unwrap();
+        run("let len = 5; let c = len; let d = builtin(\"len\")(\"abc\");").unwrap();
+        // once shadowed the global is an ordinary variable
+        run("len = 6;").unwrap();
+        assert_eq!(
+            run("type = 1;"),
+            Err(Error::BuiltinAssigned("type".to_string()))
+        );
+        assert_eq!(
+            run("builtin(\"nope\");"),
+            Err(Error::Value("no builtin called \"nope\"".to_string()))
+        );
+        let get = |name| interpretor.get_global(name).unwrap();
+        assert_eq!(get("a"), Object::Number(1.0));
+        assert_eq!(get("b"), Object::String("2".into()));
+        assert_eq!(get("c"), Object::Number(5.0));
+        assert_eq!(get("d"), Object::Number(3.0));
+        assert_eq!(get("len"), Object::Number(6.0));
+        assert!(matches!(get("type"), Object::Function(_)));
+    }
+
+    #[test]
+    fn test_len() {
+        assert_eq!(eval("len(\"\")"), Ok(Object::Number(0.0)));
+        assert_eq!(eval("len(\"héllo\")"), Ok(Object::Number(5.0)));
+        assert_eq!(
+            eval("len(1)"),
+            Err(Error::Type(
+                "object of type 'number' has no len()".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("len(\"a\", \"b\")"),
+            Err(Error::Type(
+                "len() takes 1 arguments but 2 were given".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_type() {
+        let cases = [
+            ("1.5", "number"),
+            ("\"a\"", "string"),
+            ("true", "boolean"),
+            ("nil", "nil"),
+            ("len", "function"),
+            ("type(1)", "string"),
+        ];
+        for (input, expected) in cases {
+            let expected = Object::String(expected.into());
+            assert_eq!(eval(&format!("type({input})")), Ok(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_str() {
+        let cases = [
+            ("1.5", "1.5"),
+            ("100", "100"),
+            ("\"a\"", "a"),
+            ("true", "true"),
+            ("nil", "nil"),
+            ("len", "<function>"),
+        ];
+        for (input, expected) in cases {
+            let expected = Object::String(expected.into());
+            assert_eq!(eval(&format!("str({input})")), Ok(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_ord_chr() {
+        assert_eq!(eval("ord(\"A\")"), Ok(Object::Number(65.0)));
+        assert_eq!(eval("ord(\"é\")"), Ok(Object::Number(233.0)));
+        assert_eq!(eval("chr(65)"), Ok(Object::String("A".into())));
+        assert_eq!(eval("chr(128512)"), Ok(Object::String("😀".into())));
+        assert_eq!(eval("chr(ord(\"z\"))"), Ok(Object::String("z".into())));
+        assert_eq!(
+            eval("ord(\"ab\")"),
+            Err(Error::Value(
+                "ord() expected a single character, not \"ab\"".to_string()
+            ))
+        );
+        assert_eq!(
+            eval("ord(\"\")"),
+            Err(Error::Value(
+                "ord() expected a single character, not \"\"".to_string()
+            ))
+        );
+        // a surrogate and one past the last code point
+        for n in ["55296", "1114112", "-1", "1.5"] {
+            assert_eq!(
+                eval(&format!("chr({n})")),
+                Err(Error::Value(format!("chr() {n} is not a valid code point")))
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_int_and_float() {
+        let cases = [
+            ("parse_int(\"42\")", 42.0),
+            ("parse_int(\" -17 \")", -17.0),
+            ("parse_int(\"ff\", 16)", 255.0),
+            ("parse_int(\"FF\", 16)", 255.0),
+            ("parse_int(\"+101\", 2)", 5.0),
+            ("parse_int(\"zz\", 36)", 1295.0),
+            ("parse_float(\"3.5e2\")", 350.0),
+            ("parse_float(\" -.5 \")", -0.5),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(eval(input), Ok(Object::Number(expected)), "{input}");
+        }
+        let error = |message: &str| Err(Error::Value(message.to_string()));
+        assert_eq!(
+            eval("parse_int(\"12abc\")"),
+            error("parse_int() invalid literal for base 10: '12abc'")
+        );
+        assert_eq!(
+            eval("parse_int(\"1.5\")"),
+            error("parse_int() invalid literal for base 10: '1.5'")
+        );
+        assert_eq!(
+            eval("parse_int(\"2\", 2)"),
+            error("parse_int() invalid literal for base 2: '2'")
+        );
+        assert_eq!(
+            eval("parse_int(\"-\")"),
+            error("parse_int() invalid literal for base 10: '-'")
+        );
+        assert_eq!(
+            eval("parse_int(\"1\", 37)"),
+            error("parse_int() base must be a whole number from 2 to 36, not 37")
+        );
+        assert_eq!(
+            eval("parse_float(\"3.5x\")"),
+            error("parse_float() invalid number: '3.5x'")
+        );
+        assert_eq!(
+            eval("parse_float(\"nan\")"),
+            error("parse_float() invalid number: 'nan'")
+        );
+        assert_eq!(
+            eval("parse_float(1)"),
+            Err(Error::Type(
+                "parse_float() argument must be a string, not 'number'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_num() {
+        let cases = [
+            ("\" 42 \"", 42.0),
+            ("\"-1.5\"", -1.5),
+            ("\"+2e3\"", 2000.0),
+            ("7", 7.0),
+            ("true", 1.0),
+            ("false", 0.0),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                eval(&format!("num({input})")),
+                Ok(Object::Number(expected)),
+                "{input}"
+            );
+        }
+        assert_eq!(
+            eval("num(\"12abc\")"),
+            Err(Error::Value("invalid number:'12abc'".to_string()))
+        );
+        assert_eq!(
+            eval("num(\"inf\")"),
+            Err(Error::Value("invalid number:'inf'".to_string()))
+        );
+        assert_eq!(
+            eval("num(nil)"),
+            Err(Error::Type(
+                "num() argument must be a string, number or boolean, not 'nil'".to_string()
+            ))
+        );
+    }
+}
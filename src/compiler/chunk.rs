@@ -0,0 +1,74 @@
+use crate::{Object, Span, Symbol, TokenType};
+
+// Jump targets are absolute instruction indices within the same chunk.
+#[derive(Debug, PartialEq, Clone)]
+pub enum OpCode {
+    Constant(usize),
+    Nil,
+    True,
+    False,
+    Pop,
+    GetLocal(usize),
+    SetLocal(usize),
+    // name and whether the binding is a constant
+    DefineGlobal(Symbol, bool),
+    GetGlobal(Symbol),
+    SetGlobal(Symbol),
+    // property looked up on the value on top of the stack
+    GetProperty(Symbol),
+    Unary(TokenType),
+    Binary(TokenType),
+    // number of values to print
+    Print(usize),
+    Jump(usize),
+    JumpIfFalse(usize),
+    Loop(usize),
+    Call(usize),
+    Return,
+    // fails with the same error as `Environment::define` if the value on top
+    // of the stack is nil, guarding local constants
+    AssertValue,
+    // fails with the same error as `Environment::assign` on a constant, which
+    // the tree-walker only detects once the statement runs
+    ReassignConstant,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Chunk {
+    pub code: Vec<OpCode>,
+    // source position of every instruction, used for error reporting
+    pub spans: Vec<Span>,
+    pub constants: Vec<Object>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(&mut self, op: OpCode, span: Span) -> usize {
+        self.code.push(op);
+        self.spans.push(span);
+        self.code.len() - 1
+    }
+
+    pub fn add_constant(&mut self, value: Object) -> usize {
+        if let Some(index) = self
+            .constants
+            .iter()
+            .position(|c| is_same_constant(c, &value))
+        {
+            return index;
+        }
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+}
+
+fn is_same_constant(a: &Object, b: &Object) -> bool {
+    match (a, b) {
+        (Object::Number(a), Object::Number(b)) => a.to_bits() == b.to_bits(),
+        (Object::String(a), Object::String(b)) => a == b,
+        _ => false,
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt::Write;
+
+use crate::{object::Function, Chunk, Object, OpCode, Symbol};
+
+// Renders a chunk in the style of clox's debug output: one instruction per
+// line with its offset, source line ("|" when unchanged), opcode and
+// operands. Functions found in the constants are listed after the chunk.
+pub fn disassemble(chunk: &Chunk, name: &str) -> String {
+    let mut out = String::new();
+    write_chunk(&mut out, chunk, name);
+    out
+}
+
+fn write_chunk(out: &mut String, chunk: &Chunk, name: &str) {
+    writeln!(out, "== {name} ==").unwrap();
+    for (offset, op) in chunk.code.iter().enumerate() {
+        write!(out, "{offset:04} ").unwrap();
+        let line = chunk.spans[offset].line;
+        if offset > 0 && line == chunk.spans[offset - 1].line {
+            write!(out, "   | ").unwrap();
+        } else {
+            write!(out, "{line:4} ").unwrap();
+        }
+        writeln!(out, "{}", instruction(chunk, offset, op)).unwrap();
+    }
+    for constant in &chunk.constants {
+        if let Object::Function(function) = constant {
+            if let Function::Compiled { name, chunk, .. } = function.as_ref() {
+                writeln!(out).unwrap();
+                write_chunk(out, chunk, name);
+            }
+        }
+    }
+}
+
+fn instruction(chunk: &Chunk, offset: usize, op: &OpCode) -> String {
+    let constant =
+        |name: &str, index: &usize| format!("{name:<16} {index:4} '{}'", chunk.constants[*index]);
+    let operand = |name: &str, value: String| format!("{name:<16} {value:>4}");
+    let symbol = |name: &str, symbol: &Symbol| format!("{name:<16}      '{symbol}'");
+    let jump = |name: &str, target: &usize| format!("{name:<16} {offset:4} -> {target}");
+    match op {
+        OpCode::Constant(index) => constant("OP_CONSTANT", index),
+        OpCode::Nil => "OP_NIL".to_string(),
+        OpCode::True => "OP_TRUE".to_string(),
+        OpCode::False => "OP_FALSE".to_string(),
+        OpCode::Pop => "OP_POP".to_string(),
+        OpCode::GetLocal(slot) => operand("OP_GET_LOCAL", slot.to_string()),
+        OpCode::SetLocal(slot) => operand("OP_SET_LOCAL", slot.to_string()),
+        OpCode::DefineGlobal(name, false) => symbol("OP_DEFINE_GLOBAL", name),
+        OpCode::DefineGlobal(name, true) => symbol("OP_DEFINE_CONST", name),
+        OpCode::GetGlobal(name) => symbol("OP_GET_GLOBAL", name),
+        OpCode::SetGlobal(name) => symbol("OP_SET_GLOBAL", name),
+        OpCode::GetProperty(name) => symbol("OP_GET_PROPERTY", name),
+        OpCode::Unary(op) => operand("OP_UNARY", op.to_string()),
+        OpCode::Binary(op) => operand("OP_BINARY", op.to_string()),
+        OpCode::Print(count) => operand("OP_PRINT", count.to_string()),
+        OpCode::Jump(target) => jump("OP_JUMP", target),
+        OpCode::JumpIfFalse(target) => jump("OP_JUMP_IF_FALSE", target),
+        OpCode::Loop(target) => jump("OP_LOOP", target),
+        OpCode::Call(count) => operand("OP_CALL", count.to_string()),
+        OpCode::Return => "OP_RETURN".to_string(),
+        OpCode::AssertValue => "OP_ASSERT_VALUE".to_string(),
+        OpCode::ReassignConstant => "OP_REASSIGN_CONSTANT".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, Lexer, Parser, Resolver};
+
+    fn listing(input: &str) -> String {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let mut program = parser.parse_program().unwrap();
+        Resolver::new().resolve(&mut program).unwrap();
+        let chunk = Compiler::compile(&program.ast, &program.stmts).unwrap();
+        disassemble(&chunk, "<script>")
+    }
+
+    #[test]
+    fn test_expression() {
+        assert_eq!(
+            listing("let a = 1 + 2;\nprint a * 3;"),
+            "\
+== <script> ==
+0000    1 OP_CONSTANT         0 '1'
+0001    | OP_CONSTANT         1 '2'
+0002    | OP_BINARY           +
+0003    | OP_DEFINE_GLOBAL      'a'
+0004    2 OP_GET_GLOBAL         'a'
+0005    | OP_CONSTANT         2 '3'
+0006    | OP_BINARY           *
+0007    | OP_PRINT            1
+0008    | OP_NIL
+0009    | OP_RETURN
+"
+        );
+    }
+
+    #[test]
+    fn test_control_flow() {
+        assert_eq!(
+            listing(
+                "let i = 0;
+while (i < 3) {
+  if (i == 1) print i; else print -i;
+  i = i + 1;
+}"
+            ),
+            "\
+== <script> ==
+0000    1 OP_CONSTANT         0 '0'
+0001    | OP_DEFINE_GLOBAL      'i'
+0002    2 OP_GET_GLOBAL         'i'
+0003    | OP_CONSTANT         1 '3'
+0004    | OP_BINARY           <
+0005    | OP_JUMP_IF_FALSE    5 -> 22
+0006    3 OP_GET_GLOBAL         'i'
+0007    | OP_CONSTANT         2 '1'
+0008    | OP_BINARY          ==
+0009    | OP_JUMP_IF_FALSE    9 -> 13
+0010    | OP_GET_GLOBAL         'i'
+0011    | OP_PRINT            1
+0012    | OP_JUMP            12 -> 16
+0013    | OP_GET_GLOBAL         'i'
+0014    | OP_UNARY            -
+0015    | OP_PRINT            1
+0016    4 OP_GET_GLOBAL         'i'
+0017    | OP_CONSTANT         2 '1'
+0018    | OP_BINARY           +
+0019    | OP_SET_GLOBAL         'i'
+0020    | OP_POP
+0021    2 OP_LOOP            21 -> 2
+0022    4 OP_NIL
+0023    | OP_RETURN
+"
+        );
+    }
+
+    #[test]
+    fn test_function() {
+        assert_eq!(
+            listing(
+                "fn add(a, b) {
+  let sum = a + b;
+  return sum;
+}
+print add(1, 2);"
+            ),
+            "\
+== <script> ==
+0000    1 OP_CONSTANT         0 '<function>'
+0001    | OP_DEFINE_GLOBAL      'add'
+0002    5 OP_GET_GLOBAL         'add'
+0003    | OP_CONSTANT         1 '1'
+0004    | OP_CONSTANT         2 '2'
+0005    | OP_CALL             2
+0006    | OP_PRINT            1
+0007    | OP_NIL
+0008    | OP_RETURN
+
+== add ==
+0000    2 OP_GET_LOCAL        1
+0001    | OP_GET_LOCAL        2
+0002    | OP_BINARY           +
+0003    3 OP_GET_LOCAL        3
+0004    | OP_RETURN
+0005    1 OP_NIL
+0006    | OP_RETURN
+"
+        );
+    }
+}
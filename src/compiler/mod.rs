@@ -0,0 +1,357 @@
+use std::rc::Rc;
+
+use crate::{
+    object::Function, Ast, Error, ErrorInfo, Expr, ExprId, LiteralType, Object, Span, Stmt, StmtId,
+    Symbol,
+};
+
+mod chunk;
+pub use chunk::{Chunk, OpCode};
+
+mod disassemble;
+pub use disassemble::disassemble;
+
+struct Local {
+    name: Symbol,
+    depth: usize,
+    is_const: bool,
+}
+
+// state of the function currently being compiled
+struct Frame {
+    chunk: Chunk,
+    locals: Vec<Local>,
+    scope_depth: usize,
+}
+
+impl Frame {
+    fn new(scope_depth: usize) -> Self {
+        Self {
+            chunk: Chunk::new(),
+            // slot 0 holds the function being run
+            locals: vec![Local {
+                name: Symbol::intern(""),
+                depth: 0,
+                is_const: true,
+            }],
+            scope_depth,
+        }
+    }
+}
+
+// Lowers statements into bytecode for the `vm`. Locals live in stack slots
+// and globals are looked up by name in the interpreter's globals, the same
+// environment the tree-walker uses, so both backends see the same builtins.
+// Functions may only reach their own locals and the globals; capturing the
+// locals of an enclosing function, classes and loop control are rejected.
+pub struct Compiler<'a> {
+    // the nodes of the program being compiled
+    ast: &'a Ast,
+    frames: Vec<Frame>,
+    // position of the innermost node being compiled that has one, given to
+    // instructions without a position of their own such as literals
+    span: Span,
+}
+
+impl<'a> Compiler<'a> {
+    pub fn compile(ast: &'a Ast, stmts: &[StmtId]) -> Result<Chunk, ErrorInfo> {
+        let mut compiler = Self {
+            ast,
+            frames: vec![Frame::new(0)],
+            span: Span::default(),
+        };
+        for stmt in stmts {
+            compiler.stmt(*stmt)?;
+        }
+        compiler.emit(OpCode::Nil, Span::default());
+        compiler.emit(OpCode::Return, Span::default());
+        Ok(compiler.frames.pop().unwrap().chunk)
+    }
+
+    fn stmt(&mut self, stmt: StmtId) -> Result<(), ErrorInfo> {
+        let stmt = &self.ast[stmt];
+        if let Stmt::Let { span, .. } | Stmt::Function { span, .. } | Stmt::Return { span, .. } =
+            stmt
+        {
+            self.span = span.to_owned();
+        }
+        match stmt {
+            Stmt::Expr { expr } => {
+                self.expr(*expr)?;
+                self.emit(OpCode::Pop, Span::default());
+            }
+            Stmt::Print { exprs, span } => {
+                for expr in exprs {
+                    self.expr(*expr)?;
+                }
+                self.emit(OpCode::Print(exprs.len()), span.to_owned());
+            }
+            Stmt::Let {
+                name,
+                value,
+                is_const,
+                span,
+                ..
+            } => {
+                match value {
+                    Some(value) => self.expr(*value)?,
+                    None => {
+                        self.emit(OpCode::Nil, span.to_owned());
+                    }
+                }
+                self.declare(*name, *is_const, span);
+            }
+            Stmt::Block { stmts } => {
+                self.frame().scope_depth += 1;
+                for stmt in stmts {
+                    self.stmt(*stmt)?;
+                }
+                self.end_scope();
+            }
+            Stmt::If {
+                condition,
+                truthy,
+                falsy,
+                ..
+            } => {
+                self.expr(*condition)?;
+                let else_jump = self.emit(OpCode::JumpIfFalse(0), Span::default());
+                self.stmt(*truthy)?;
+                match falsy {
+                    Some(falsy) => {
+                        let end_jump = self.emit(OpCode::Jump(0), Span::default());
+                        self.patch_jump(else_jump);
+                        self.stmt(*falsy)?;
+                        self.patch_jump(end_jump);
+                    }
+                    None => self.patch_jump(else_jump),
+                }
+            }
+            Stmt::While {
+                condition,
+                body,
+                span,
+            } => {
+                let start = self.frame().chunk.code.len();
+                self.expr(*condition)?;
+                let exit_jump = self.emit(OpCode::JumpIfFalse(0), span.to_owned());
+                self.stmt(*body)?;
+                self.emit(OpCode::Loop(start), span.to_owned());
+                self.patch_jump(exit_jump);
+            }
+            Stmt::Function {
+                name,
+                params,
+                body,
+                span,
+                ..
+            } => {
+                let function = self.function(*name, params, body, span)?;
+                self.emit_constant(function, span.to_owned());
+                self.declare(*name, false, span);
+            }
+            Stmt::Return { value, span } => {
+                match value {
+                    Some(value) => self.expr(*value)?,
+                    None => {
+                        self.emit(OpCode::Nil, span.to_owned());
+                    }
+                }
+                self.emit(OpCode::Return, span.to_owned());
+            }
+            Stmt::Class { span, .. } => return Err(unsupported("classes", span)),
+            Stmt::Break { span } => return Err(unsupported("break", span)),
+            Stmt::Continue { span } => return Err(unsupported("continue", span)),
+        }
+        Ok(())
+    }
+
+    fn function(
+        &mut self,
+        name: Symbol,
+        params: &[Symbol],
+        body: &[StmtId],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        // parameters and body share one scope, mirroring `Function::call`
+        self.frames.push(Frame::new(1));
+        let result = (|| -> Result<(), ErrorInfo> {
+            for param in params {
+                self.add_local(*param, false);
+            }
+            for stmt in body {
+                self.stmt(*stmt)?;
+            }
+            Ok(())
+        })();
+        self.emit(OpCode::Nil, span.to_owned());
+        self.emit(OpCode::Return, span.to_owned());
+        let frame = self.frames.pop().unwrap();
+        result?;
+        Ok(Object::Function(Rc::new(Function::Compiled {
+            name: name.to_string(),
+            arity: params.len(),
+            chunk: Rc::new(frame.chunk),
+        })))
+    }
+
+    fn expr(&mut self, expr: ExprId) -> Result<(), ErrorInfo> {
+        let expr = &self.ast[expr];
+        match expr {
+            Expr::Binary { op, .. } | Expr::Unary { op, .. } => self.span = op.span.to_owned(),
+            Expr::Literal { .. } => {}
+            Expr::Assign { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::Get { span, .. }
+            | Expr::Grouping { span, .. }
+            | Expr::Set { span, .. }
+            | Expr::Super { span, .. }
+            | Expr::Variable { span, .. } => self.span = span.to_owned(),
+        }
+        match expr {
+            Expr::Assign {
+                name, value, span, ..
+            } => {
+                self.expr(*value)?;
+                match self.resolve(*name, span)? {
+                    Some((_, true)) => {
+                        self.emit(OpCode::ReassignConstant, span.to_owned());
+                    }
+                    Some((slot, false)) => {
+                        self.emit(OpCode::SetLocal(slot), span.to_owned());
+                    }
+                    None => {
+                        self.emit(OpCode::SetGlobal(*name), span.to_owned());
+                    }
+                }
+            }
+            Expr::Binary { left, op, right } => {
+                self.expr(*left)?;
+                self.expr(*right)?;
+                self.emit(OpCode::Binary(op.token.clone()), op.span.to_owned());
+            }
+            Expr::Call { callee, args, span } => {
+                self.expr(*callee)?;
+                for arg in args {
+                    self.expr(*arg)?;
+                }
+                self.emit(OpCode::Call(args.len()), span.to_owned());
+            }
+            Expr::Get { object, name, span } => {
+                self.expr(*object)?;
+                self.emit(OpCode::GetProperty(*name), span.to_owned());
+            }
+            Expr::Set { span, .. } => return Err(unsupported("property assignments", span)),
+            Expr::Super { span, .. } => return Err(unsupported("super", span)),
+            Expr::Grouping { expr, .. } => self.expr(*expr)?,
+            Expr::Literal { value } => {
+                let span = Span::default();
+                match value {
+                    LiteralType::Nil => self.emit(OpCode::Nil, span),
+                    LiteralType::Boolean(true) => self.emit(OpCode::True, span),
+                    LiteralType::Boolean(false) => self.emit(OpCode::False, span),
+                    LiteralType::Number(n) => self.emit_constant(Object::Number(*n), span),
+                    LiteralType::String(s) => self.emit_constant(s.as_str().into(), span),
+                };
+            }
+            Expr::Unary { op, right } => {
+                self.expr(*right)?;
+                self.emit(OpCode::Unary(op.token.clone()), op.span.to_owned());
+            }
+            Expr::Variable { name, span, .. } => match self.resolve(*name, span)? {
+                Some((slot, _)) => {
+                    self.emit(OpCode::GetLocal(slot), span.to_owned());
+                }
+                None => {
+                    self.emit(OpCode::GetGlobal(*name), span.to_owned());
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+impl Compiler<'_> {
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn emit(&mut self, op: OpCode, span: Span) -> usize {
+        let span = if span == Span::default() {
+            self.span.to_owned()
+        } else {
+            span
+        };
+        self.frame().chunk.write(op, span)
+    }
+
+    fn emit_constant(&mut self, value: Object, span: Span) -> usize {
+        let index = self.frame().chunk.add_constant(value);
+        self.emit(OpCode::Constant(index), span)
+    }
+
+    // points the jump at `index` to the next instruction to be emitted
+    fn patch_jump(&mut self, index: usize) {
+        let chunk = &mut self.frame().chunk;
+        let target = chunk.code.len();
+        match &mut chunk.code[index] {
+            OpCode::Jump(to) | OpCode::JumpIfFalse(to) => *to = target,
+            op => unreachable!("{op:?} is not a jump"),
+        }
+    }
+
+    // binds the value on top of the stack to `name`
+    fn declare(&mut self, name: Symbol, is_const: bool, span: &Span) {
+        if self.frame().scope_depth == 0 {
+            self.emit(OpCode::DefineGlobal(name, is_const), span.to_owned());
+        } else {
+            if is_const {
+                self.emit(OpCode::AssertValue, span.to_owned());
+            }
+            self.add_local(name, is_const);
+        }
+    }
+
+    fn add_local(&mut self, name: Symbol, is_const: bool) {
+        let frame = self.frame();
+        frame.locals.push(Local {
+            name,
+            depth: frame.scope_depth,
+            is_const,
+        });
+    }
+
+    fn end_scope(&mut self) {
+        self.frame().scope_depth -= 1;
+        let depth = self.frame().scope_depth;
+        while self
+            .frame()
+            .locals
+            .last()
+            .is_some_and(|local| local.depth > depth)
+        {
+            self.frame().locals.pop();
+            self.emit(OpCode::Pop, Span::default());
+        }
+    }
+
+    // the stack slot and constness of a local, or None for a global
+    fn resolve(&self, name: Symbol, span: &Span) -> Result<Option<(usize, bool)>, ErrorInfo> {
+        let (current, enclosing) = self.frames.split_last().unwrap();
+        if let Some(slot) = current.locals.iter().rposition(|local| local.name == name) {
+            return Ok(Some((slot, current.locals[slot].is_const)));
+        }
+        let captured = enclosing
+            .iter()
+            .any(|frame| frame.locals.iter().any(|local| local.name == name));
+        if captured {
+            return Err(unsupported("closures", span));
+        }
+        Ok(None)
+    }
+}
+
+fn unsupported(feature: &str, span: &Span) -> ErrorInfo {
+    let error = Error::Syntax(format!("{feature} not supported by the bytecode compiler"));
+    ErrorInfo::new_with_span(error, span.to_owned())
+}
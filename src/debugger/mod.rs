@@ -0,0 +1,334 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    io::Write,
+    rc::Rc,
+};
+
+use crate::{
+    Error, ErrorInfo, Interpretor, Node, Object, Parser, ReadLine, ReplLine, Span, Stmt, StmtId,
+};
+
+const PROMPT: &str = "(debug) ";
+// what the program outside of any function shows as in a backtrace
+const SCRIPT: &str = "<script>";
+
+const HELP: &str = "\
+break <line>   stop before the statements on a line
+run            start the program, or carry on once it stopped
+continue       carry on until the next breakpoint
+step           stop again before the next statement
+print <expr>   show the value of an expression where the program stopped
+locals         list the variables in scope where the program stopped
+backtrace      list the calls being run
+quit           end the program
+";
+
+// Asks for commands before the program's first statement, and again each
+// time it is about to run a statement on a line with a breakpoint, or the
+// next statement after a `step`. Breakpoints are lines of whichever file is
+// running, and only the tree-walker stops at them.
+pub struct Debugger {
+    input: Box<dyn ReadLine>,
+    output: Box<dyn Write>,
+    breakpoints: BTreeSet<usize>,
+    stepping: bool,
+    started: bool,
+    // name of each user function being run, innermost last, with the line
+    // it was called on
+    pub(crate) frames: Vec<(String, usize)>,
+}
+
+impl Debugger {
+    // Takes its commands from `input` and writes what they show to `output`.
+    pub fn new(input: Box<dyn ReadLine>, output: Box<dyn Write>) -> Self {
+        Self {
+            input,
+            output,
+            breakpoints: BTreeSet::new(),
+            stepping: false,
+            started: false,
+            frames: Vec::new(),
+        }
+    }
+
+    // called before each statement the interpreter runs
+    fn before(
+        &mut self,
+        interpretor: &mut Interpretor,
+        stmt: Node<'_, StmtId>,
+    ) -> Result<(), ErrorInfo> {
+        // a block stops at the statements in it instead
+        if let Stmt::Block { .. } = stmt.ast[stmt.id] {
+            return Ok(());
+        }
+        let line = stmt.ast[stmt.id].span(stmt.ast).map(|span| span.line);
+        if !self.started {
+            self.started = true;
+            self.prompt(interpretor, line)?;
+        }
+        let at_breakpoint = line.is_some_and(|line| self.breakpoints.contains(&line));
+        if !self.stepping && !at_breakpoint {
+            return Ok(());
+        }
+        self.stepping = false;
+        let _ = writeln!(
+            self.output,
+            "stopped at line {}: {}",
+            show_line(line),
+            stmt.summary()
+        );
+        self.prompt(interpretor, line)
+    }
+
+    // Runs commands until one resumes the program. Once the commands run
+    // out the rest of the program runs without stopping.
+    fn prompt(
+        &mut self,
+        interpretor: &mut Interpretor,
+        line: Option<usize>,
+    ) -> Result<(), ErrorInfo> {
+        // so what the program printed shows before the prompt
+        let _ = interpretor.output.flush();
+        loop {
+            let command = match self.input.read_line(PROMPT) {
+                Ok(Some(command)) => command,
+                Ok(None) | Err(_) => {
+                    self.breakpoints.clear();
+                    return Ok(());
+                }
+            };
+            let command = command.trim();
+            let (command, argument) = command.split_once(' ').unwrap_or((command, ""));
+            let argument = argument.trim();
+            match command {
+                "" => {}
+                "break" | "b" => match argument.parse::<usize>() {
+                    Ok(line) => {
+                        self.breakpoints.insert(line);
+                        let _ = writeln!(self.output, "breakpoint at line {line}");
+                    }
+                    Err(_) => {
+                        let _ = writeln!(self.output, "break takes a line number");
+                    }
+                },
+                "run" | "r" | "continue" | "c" => return Ok(()),
+                "step" | "s" => {
+                    self.stepping = true;
+                    return Ok(());
+                }
+                "print" | "p" => self.print(interpretor, argument),
+                "locals" => self.locals(interpretor),
+                "backtrace" | "bt" => self.backtrace(line),
+                "quit" | "q" => {
+                    return Err(ErrorInfo::new_with_span(Error::Exit(0), Span::default()))
+                }
+                _ => {
+                    let _ = write!(self.output, "{HELP}");
+                }
+            }
+        }
+    }
+
+    fn print(&mut self, interpretor: &mut Interpretor, source: &str) {
+        let (program, expr) = match Parser::from_str(source).parse_repl_line() {
+            Ok(ReplLine::Expr(program, expr)) => (program, expr),
+            _ => {
+                let _ = writeln!(self.output, "print takes an expression");
+                return;
+            }
+        };
+        // The expression was never resolved, so its names are looked up in
+        // the globals, which the paused scope stands in for. Looking up from
+        // it outward finds its variables, those it encloses, and the globals.
+        let scope = interpretor.environment.clone();
+        let globals = std::mem::replace(&mut interpretor.globals, scope);
+        let value = interpretor.eval_in(&program, expr);
+        interpretor.globals = globals;
+        let _ = match value {
+            Ok(value) => writeln!(self.output, "{}", show_value(&value)),
+            Err(err) => writeln!(self.output, "error: {}", err.error),
+        };
+    }
+
+    // The variables of the paused scope and of those around it, innermost
+    // first and without the ones they shadow. The globals are only listed
+    // when the program stopped outside of any block or function.
+    fn locals(&mut self, interpretor: &Interpretor) {
+        let mut scope = interpretor.environment.clone();
+        let mut listed = HashSet::new();
+        loop {
+            for (name, value) in scope.borrow().entries() {
+                if listed.insert(name.clone()) {
+                    let _ = writeln!(self.output, "{name} = {}", show_value(&value));
+                }
+            }
+            if Rc::ptr_eq(&scope, &interpretor.globals) {
+                break;
+            }
+            let enclosing = scope.borrow().enclosing();
+            match enclosing {
+                Some(enclosing) if !Rc::ptr_eq(&enclosing, &interpretor.globals) => {
+                    scope = enclosing
+                }
+                _ => break,
+            }
+        }
+    }
+
+    // the calls being run, innermost first, each at the line it is at
+    fn backtrace(&mut self, line: Option<usize>) {
+        let mut line = line;
+        for (depth, (name, called_at)) in self.frames.iter().rev().enumerate() {
+            let _ = writeln!(self.output, "#{depth} {name} at line {}", show_line(line));
+            line = Some(*called_at);
+        }
+        let depth = self.frames.len();
+        let _ = writeln!(self.output, "#{depth} {SCRIPT} at line {}", show_line(line));
+    }
+}
+
+impl Interpretor {
+    // Lets `debugger` stop the programs run from now on and inspect them.
+    pub fn with_debugger(mut self, debugger: Debugger) -> Self {
+        self.debugger = Some(debugger);
+        self
+    }
+
+    pub(crate) fn debug(&mut self, stmt: Node<'_, StmtId>) -> Result<(), ErrorInfo> {
+        // taken out while it runs, so what `print` evaluates does not stop
+        let Some(mut debugger) = self.debugger.take() else {
+            return Ok(());
+        };
+        let result = debugger.before(self, stmt);
+        self.debugger = Some(debugger);
+        result
+    }
+}
+
+fn show_line(line: Option<usize>) -> String {
+    line.map_or("?".to_string(), |line| line.to_string())
+}
+
+fn show_value(value: &Object) -> String {
+    match value {
+        Object::String(value) => format!("{value:?}"),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, io};
+
+    use super::Debugger;
+    use crate::{interpretor::Capture, Error, Interpretor, ReadLine};
+
+    // commands typed one after another
+    struct Commands(VecDeque<String>);
+
+    impl ReadLine for Commands {
+        fn read_line(&mut self, _prompt: &str) -> io::Result<Option<String>> {
+            Ok(self.0.pop_front())
+        }
+    }
+
+    // runs `source` under the debugger, returning what it and the program
+    // wrote, and the error that stopped the program
+    fn debug(source: &str, commands: &str) -> (String, String, Option<Error>) {
+        let (shown, printed) = (Capture::default(), Capture::default());
+        let commands = Commands(commands.lines().map(str::to_string).collect());
+        let debugger = Debugger::new(Box::new(commands), Box::new(shown.clone()));
+        let mut interpretor = Interpretor::new()
+            .with_output(Box::new(printed.clone()))
+            .with_debugger(debugger);
+        let program = interpretor.parse(source, "debug.rl").unwrap();
+        let error = interpretor.interpret(program).err().map(|err| err.error);
+        drop(interpretor);
+        (shown.text(), printed.text(), error)
+    }
+
+    const PROGRAM: &str = "\
+fn area(w, h) {
+  let a = w * h;
+  return a;
+}
+fn twice(x) {
+  let y = area(x, 2);
+  return y + y;
+}
+let name = \"box\";
+print twice(3);
+print name;";
+
+    #[test]
+    fn test_breakpoints() {
+        let (shown, printed, error) = debug(
+            PROGRAM,
+            "break 3\nbreak x\nrun\nprint a + 1\nprint w * h == a\nprint name\nlocals\nbacktrace\ncontinue",
+        );
+        let expected = "\
+breakpoint at line 3
+break takes a line number
+stopped at line 3: (return a)
+7
+true
+\"box\"
+a = 6
+h = 2
+w = 3
+#0 area at line 3
+#1 twice at line 6
+#2 <script> at line 10
+";
+        assert_eq!(shown, expected);
+        assert_eq!(printed, "12\nbox\n");
+        assert_eq!(error, None);
+    }
+
+    #[test]
+    fn test_step() {
+        let (shown, _, _) = debug(
+            PROGRAM,
+            "break 10\nrun\nstep\nstep\nlocals\nstep\nbt\ncontinue",
+        );
+        let expected = "\
+breakpoint at line 10
+stopped at line 10: (print (call twice 3))
+stopped at line 6: (let y (call area x 2))
+stopped at line 2: (let a (* w h))
+h = 2
+w = 3
+stopped at line 3: (return a)
+#0 area at line 3
+#1 twice at line 6
+#2 <script> at line 10
+";
+        assert_eq!(shown, expected);
+    }
+
+    #[test]
+    fn test_commands_run_out() {
+        // without commands the program runs to its end
+        let (shown, printed, error) = debug(PROGRAM, "break 3");
+        assert_eq!(shown, "breakpoint at line 3\n");
+        assert_eq!(printed, "12\nbox\n");
+        assert_eq!(error, None);
+
+        let (shown, printed, error) =
+            debug(PROGRAM, "step\nlocals\nprint nothing\nprint let\nquit");
+        assert_eq!(
+            shown,
+            "stopped at line 1: (fn area (w h) ...)\n\
+             error: NameError: undefined variable \"nothing\"\n\
+             print takes an expression\n"
+        );
+        assert_eq!(printed, "");
+        assert_eq!(error, Some(Error::Exit(0)));
+    }
+
+    #[test]
+    fn test_help() {
+        let (shown, _, _) = debug("print 1;", "help\nrun");
+        assert_eq!(shown, super::HELP);
+    }
+}
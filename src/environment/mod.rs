@@ -1,98 +1,392 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use crate::{Error, Object};
+use crate::{object::Function, Error, Object, Slot, Symbol};
 
+// A scope of variables. The globals and builtins are kept by name, while
+// the locals of a block or call sit at the slots the resolver gave them,
+// which is what the evaluator reaches them by. Names are still kept with the
+// locals for the debugger, which looks them up as it would a global.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Environment {
-    values: HashMap<String, (Object, bool)>,
+    values: HashMap<Symbol, (Object, bool)>,
+    // None until the declaration of that slot has run
+    slots: Vec<Option<(Symbol, Object, bool)>>,
     enclosing: Option<Rc<RefCell<Environment>>>,
+    // set on the scope holding the builtins, whose names can be shadowed by
+    // a declaration but never assigned to
+    read_only: bool,
 }
 
 impl Environment {
     pub fn new() -> Environment {
         Self {
             values: HashMap::new(),
+            slots: Vec::new(),
             enclosing: None,
+            read_only: false,
         }
     }
 
     pub fn new_from_closure(enclosing: &Rc<RefCell<Environment>>) -> Self {
         Self {
             values: HashMap::new(),
+            slots: Vec::new(),
             enclosing: Some(Rc::clone(enclosing)),
+            read_only: false,
         }
     }
 
-    pub fn define(&mut self, name: String, value: Object, is_const: bool) -> Result<(), Error> {
-        if is_const && value.is_nil() {
-            return Err(Error::Syntax(
-                "cannot declare a constant without a value".to_string(),
-            ));
+    // stops `assign` from changing any value of this scope
+    pub fn freeze(&mut self) {
+        self.read_only = true;
+    }
+
+    pub fn define(&mut self, name: Symbol, value: Object, is_const: bool) -> Result<(), Error> {
+        check_define(
+            self.values.get(&name).map(|(_, constant)| *constant),
+            &value,
+            is_const,
+        )?;
+        let value = self.store(value);
+        self.values.insert(name, (value, is_const));
+        Ok(())
+    }
+
+    // defines the local the resolver gave `slot`
+    pub fn define_at(
+        &mut self,
+        slot: usize,
+        name: Symbol,
+        value: Object,
+        is_const: bool,
+    ) -> Result<(), Error> {
+        if slot >= self.slots.len() {
+            self.slots.resize(slot + 1, None);
         }
-        if let Some((_, constant)) = self.values.get(&name) {
-            if *constant {
-                return Err(Error::Syntax(
-                    "cannot reassign a constant variable".to_string(),
-                ));
+        let constant = self.slots[slot].as_ref().map(|(_, _, constant)| *constant);
+        check_define(constant, &value, is_const)?;
+        let value = self.store(value);
+        self.slots[slot] = Some((name, value, is_const));
+        Ok(())
+    }
+
+    // the value of `name` in this scope itself and whether it is constant,
+    // wherever it is kept
+    fn find(&mut self, name: Symbol) -> Option<(&mut Object, bool)> {
+        if let Some((value, is_const)) = self.values.get_mut(&name) {
+            return Some((value, *is_const));
+        }
+        self.slots
+            .iter_mut()
+            .flatten()
+            .find(|(local, ..)| *local == name)
+            .map(|(_, value, is_const)| (value, *is_const))
+    }
+
+    // the scope this one is nested in, None for the outermost
+    pub(crate) fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
+
+    // Functions stored in the scope they capture only hold it weakly, so that
+    // the two do not keep each other alive, and hold it again once read out.
+    fn store(&self, value: Object) -> Object {
+        if let Object::Function(function) = &value {
+            if let Some(stored) = Function::stored_in(function, self) {
+                return Object::Function(Rc::new(stored));
             }
         }
-        self.values.insert(name, (value.clone(), is_const));
-        Ok(())
+        value
+    }
+
+    fn load(value: &Object) -> Object {
+        if let Object::Function(function) = value {
+            if let Some(loaded) = function.loaded() {
+                return Object::Function(Rc::new(loaded));
+            }
+        }
+        value.clone()
     }
 
-    pub fn get(&mut self, name: &String) -> Result<Object, Error> {
-        if let Some(value) = self.values.get(&name.to_string()) {
-            Ok(value.0.clone())
+    pub fn get(&mut self, name: Symbol) -> Result<Object, Error> {
+        if let Some((value, _)) = self.find(name) {
+            Ok(Self::load(value))
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow_mut().get(name)
         } else {
-            Err(Error::Name(name.to_string()))
+            Err(Error::Name(name.to_string(), Vec::new()))
         }
     }
 
-    pub fn assign(&mut self, name: &String, value: Object) -> Result<Object, Error> {
-        if let Some((_, is_const)) = self.values.get(&name.to_string()) {
-            if *is_const {
-                return Err(Error::Syntax(
-                    "cannot reassign to a constant variable".to_string(),
-                ));
+    pub fn assign(&mut self, name: Symbol, value: Object) -> Result<Object, Error> {
+        let read_only = self.read_only;
+        let stored = self.store(value.clone());
+        if let Some((current, is_const)) = self.find(name) {
+            if read_only {
+                return Err(Error::BuiltinAssigned(name.to_string()));
+            }
+            if is_const {
+                return Err(Error::ConstantReassigned);
             }
-            self.values
-                .insert(name.to_string(), (value.clone(), *is_const));
-            return Ok(value);
+            *current = stored;
+            Ok(value)
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow_mut().assign(name, value)
         } else {
-            Err(Error::Name(name.to_string()))
+            Err(Error::Name(name.to_string(), Vec::new()))
         }
     }
+
+    // `slot.depth` is the number of scopes between the reference and its
+    // declaration, as computed by the resolver along with `slot.index`.
+    // `name` is only for the error when the declaration has not run yet.
+    pub fn get_at(&mut self, slot: Slot, name: Symbol) -> Result<Object, Error> {
+        if slot.depth > 0 {
+            return match &self.enclosing {
+                Some(enclosing) => {
+                    let outer = Slot {
+                        depth: slot.depth - 1,
+                        ..slot
+                    };
+                    enclosing.borrow_mut().get_at(outer, name)
+                }
+                None => Err(Error::Name(name.to_string(), Vec::new())),
+            };
+        }
+        match self.slots.get(slot.index) {
+            Some(Some((_, value, _))) => Ok(Self::load(value)),
+            _ => Err(Error::Name(name.to_string(), Vec::new())),
+        }
+    }
+
+    pub fn assign_at(&mut self, slot: Slot, name: Symbol, value: Object) -> Result<Object, Error> {
+        if slot.depth > 0 {
+            return match &self.enclosing {
+                Some(enclosing) => {
+                    let outer = Slot {
+                        depth: slot.depth - 1,
+                        ..slot
+                    };
+                    enclosing.borrow_mut().assign_at(outer, name, value)
+                }
+                None => Err(Error::Name(name.to_string(), Vec::new())),
+            };
+        }
+        let stored = self.store(value.clone());
+        match self.slots.get_mut(slot.index) {
+            Some(Some((_, _, true))) => Err(Error::ConstantReassigned),
+            Some(Some((_, current, _))) => {
+                *current = stored;
+                Ok(value)
+            }
+            _ => Err(Error::Name(name.to_string(), Vec::new())),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.slots.clear();
+    }
+
+    // every value of this scope itself, with its name
+    fn bindings(&self) -> impl Iterator<Item = (Symbol, &Object)> {
+        let values = self.values.iter().map(|(name, (value, _))| (*name, value));
+        let slots = self
+            .slots
+            .iter()
+            .flatten()
+            .map(|(name, value, _)| (*name, value));
+        values.chain(slots)
+    }
+
+    // the names and values defined in this scope itself, sorted by name
+    pub fn entries(&self) -> Vec<(String, Object)> {
+        let mut entries: Vec<_> = self
+            .bindings()
+            .map(|(name, value)| (name.to_string(), Self::load(value)))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    // every name visible from this scope, innermost first
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.bindings().map(|(name, _)| name.to_string()).collect();
+        names.sort();
+        if let Some(enclosing) = &self.enclosing {
+            for name in enclosing.borrow().names() {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+}
+
+// the checks `define` and `define_at` share, `existing` telling whether the
+// name declared there before was a constant
+fn check_define(existing: Option<bool>, value: &Object, is_const: bool) -> Result<(), Error> {
+    if is_const && value.is_nil() {
+        return Err(Error::ConstantWithoutValue);
+    }
+    if existing == Some(true) {
+        return Err(Error::ConstantRedeclared);
+    }
+    Ok(())
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{Environment, Object};
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{
+        object::{Closure, Declaration, Function},
+        Environment, Error, Object, Slot, Span,
+    };
 
     #[test]
     fn test_assign() {
         let mut env = Environment::new();
-        env
-            .define("a".to_string(), Object::Nil, false)
+        env.define("a".into(), Object::Nil, false).unwrap();
+        assert_eq!(env.get("a".into()).unwrap(), Object::Nil);
+
+        env.assign("a".into(), Object::Number(1.0)).unwrap();
+        assert_eq!(env.get("a".into()).unwrap(), Object::Number(1.0));
+    }
+
+    #[test]
+    fn test_entries() {
+        let global = Rc::new(RefCell::new(Environment::new()));
+        global
+            .borrow_mut()
+            .define("outer".into(), Object::Nil, false)
             .unwrap();
-        assert_eq!(env.get(&"a".to_string()).unwrap(), Object::Nil);
+        let mut local = Environment::new_from_closure(&global);
+        local
+            .define("b".into(), Object::Number(2.0), false)
+            .unwrap();
+        local.define("a".into(), Object::Number(1.0), true).unwrap();
+        assert_eq!(
+            local.entries(),
+            [
+                ("a".to_string(), Object::Number(1.0)),
+                ("b".to_string(), Object::Number(2.0))
+            ]
+        );
+    }
 
-        env
-            .assign(&"a".to_string(), Object::Number(1.0))
+    #[test]
+    fn test_get_at() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer
+            .borrow_mut()
+            .define_at(0, "a".into(), Object::Number(1.0), false)
             .unwrap();
+        let mut local = Environment::new_from_closure(&outer);
+        local
+            .define_at(1, "a".into(), Object::Number(2.0), false)
+            .unwrap();
+        let at = |depth, index| Slot { depth, index };
+
         assert_eq!(
-            env.get(&"a".to_string()).unwrap(),
+            local.get_at(at(0, 1), "a".into()).unwrap(),
+            Object::Number(2.0)
+        );
+        assert_eq!(
+            local.get_at(at(1, 0), "a".into()).unwrap(),
             Object::Number(1.0)
         );
+        // a slot whose declaration has not run
+        let err = local.get_at(at(0, 0), "b".into()).unwrap_err();
+        assert_eq!(err, Error::Name("b".to_string(), Vec::new()));
+
+        local.assign_at(at(1, 0), "a".into(), Object::Nil).unwrap();
+        assert_eq!(
+            outer.borrow_mut().get_at(at(0, 0), "a".into()).unwrap(),
+            Object::Nil
+        );
+        assert_eq!(
+            local.get_at(at(0, 1), "a".into()).unwrap(),
+            Object::Number(2.0)
+        );
+
+        // and by name, as the debugger looks them up
+        assert_eq!(local.get("a".into()).unwrap(), Object::Number(2.0));
+        local.assign("a".into(), Object::Number(3.0)).unwrap();
+        assert_eq!(
+            local.get_at(at(0, 1), "a".into()).unwrap(),
+            Object::Number(3.0)
+        );
+        assert_eq!(local.entries(), [("a".to_string(), Object::Number(3.0))]);
+    }
+
+    #[test]
+    fn test_const_slot() {
+        let mut env = Environment::new();
+        env.define_at(0, "a".into(), Object::Number(1.0), true)
+            .unwrap();
+        let at = Slot { depth: 0, index: 0 };
+        assert!(env.assign_at(at, "a".into(), Object::Nil).is_err());
+        assert!(env.define_at(0, "a".into(), Object::Nil, false).is_err());
+    }
+
+    fn function_in(environment: &Rc<RefCell<Environment>>) -> Object {
+        let declaration = Declaration {
+            name: "f".into(),
+            params: vec![],
+            ast: Default::default(),
+            body: vec![],
+            is_initializer: false,
+            span: Span::new(0, 0, 0),
+        };
+        Object::Function(Rc::new(Function::User {
+            declaration: Rc::new(declaration),
+            closure: Closure::Strong(environment.clone()),
+        }))
+    }
+
+    #[test]
+    fn test_self_reference_released() {
+        let environment = Rc::new(RefCell::new(Environment::new()));
+        let function = function_in(&environment);
+        environment
+            .borrow_mut()
+            .define("f".into(), function, false)
+            .unwrap();
+        let weak = Rc::downgrade(&environment);
+        drop(environment);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_escaped_closure_keeps_scope() {
+        let environment = Rc::new(RefCell::new(Environment::new()));
+        environment
+            .borrow_mut()
+            .define("f".into(), function_in(&environment), false)
+            .unwrap();
+        let function = environment.borrow_mut().get("f".into()).unwrap();
+        let weak = Rc::downgrade(&environment);
+        drop(environment);
+        // the copy read out of the scope still needs it
+        let environment = weak.upgrade().unwrap();
+        assert_eq!(environment.borrow_mut().get("f".into()).unwrap(), function);
+        drop(environment);
+        drop(function);
+        assert!(weak.upgrade().is_none());
     }
 
     #[test]
     fn test_const_with_nil() {
         let mut env = Environment::new();
-        let out = env.define("a".to_string(), Object::Nil, true);
+        let out = env.define("a".into(), Object::Nil, true);
         assert!(out.is_err());
     }
 }
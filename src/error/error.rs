@@ -1,32 +1,455 @@
-use std::fmt;
+use std::{fmt, rc::Rc};
 
-use crate::Object;
+use crate::{object::Function, Object};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Error {
     Syntax(String),
     Value(String),
-    Parse(String),
     Runtime(String),
-    Name(String),
+    Name(String, Vec<String>),
     ZeroDivision,
     TooManyParamerters,
     Return(Object),
+    TailCall(Rc<Function>, Vec<Object>),
     Type(String),
+    Interrupted,
+    // raised by a builtin a sandboxed interpreter has taken away, naming it
+    NotPermitted(String),
+    // raised by `exit()`, unwinds to the host with the status code
+    Exit(i32),
+    // assigning to a constant, or declaring one again or without a value
+    ConstantReassigned,
+    ConstantRedeclared,
+    ConstantWithoutValue,
+    // the name of the builtin assigned to
+    BuiltinAssigned(String),
+
+    // lexer
+    UnterminatedString,
+    UnknownCharacter(char),
+    InvalidNumber(String),
+    // parser
+    // descriptions of the tokens that could have come next, such as "';'"
+    // or "an expression", and the token found instead
+    ExpectedOneOf(Vec<String>, String),
+    // the token that was expected and the one found instead
+    UnexpectedToken(String, String),
+    InvalidAssignmentTarget,
+    InheritsFromItself,
+    // what the semicolon should have ended, such as "expression"
+    MissingSemicolon(String),
+    // what nested past the parser's limit, "expression" or "statement"
+    NestingTooDeep(String),
+    // resolver
+    ReturnOutsideFunction,
+    ReturnFromInitializer,
+    AlreadyDeclared(String),
+    ReadInOwnInitializer(String),
+    ThisOutsideClass,
+    SuperOutsideClass,
+    SuperWithoutSuperclass,
+    // the number of warnings found when the host denies them
+    WarningsDenied(usize),
+
+    // warnings
+    UnusedVariable(String),
+    // the `return`, `break` or `continue` before it
+    Unreachable(String),
+}
+
+// Whether a diagnostic stops the program. Warnings are reported and the
+// program runs anyway, unless the host denies them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Error {
+    // Name of the category of error, which starts its rendered message. The
+    // values are stable so tools can match on them.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Syntax(_)
+            | Error::UnterminatedString
+            | Error::UnknownCharacter(_)
+            | Error::ReturnOutsideFunction
+            | Error::ReturnFromInitializer
+            | Error::AlreadyDeclared(_)
+            | Error::ReadInOwnInitializer(_)
+            | Error::ThisOutsideClass
+            | Error::SuperOutsideClass
+            | Error::SuperWithoutSuperclass
+            | Error::WarningsDenied(_) => "SyntaxError",
+            Error::Value(_) | Error::InvalidNumber(_) => "ValueError",
+            Error::ExpectedOneOf(..)
+            | Error::UnexpectedToken(..)
+            | Error::InvalidAssignmentTarget
+            | Error::InheritsFromItself
+            | Error::MissingSemicolon(_)
+            | Error::NestingTooDeep(_) => "ParseError",
+            Error::Runtime(_)
+            | Error::ConstantReassigned
+            | Error::ConstantRedeclared
+            | Error::ConstantWithoutValue
+            | Error::BuiltinAssigned(_) => "RuntimeError",
+            Error::Name(..) => "NameError",
+            Error::ZeroDivision => "ZeroDivisionError",
+            Error::TooManyParamerters => "TooManyParamerters",
+            Error::Type(_) => "TypeError",
+            Error::Interrupted => "Interrupted",
+            Error::NotPermitted(_) => "PermissionError",
+            // control flow rather than failures
+            Error::Return(_) => "Return",
+            Error::TailCall(..) => "TailCall",
+            Error::Exit(_) => "Exit",
+            Error::UnusedVariable(_) | Error::Unreachable(_) => "Warning",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            Error::UnusedVariable(_) | Error::Unreachable(_) => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    // Stable code of the failure, so tests and documentation need not match
+    // on messages. The hundreds give the phase that found it: 1 the lexer,
+    // 2 the parser, 3 the resolver and 4 running the program. Errors still
+    // told apart only by their message share the code of their category,
+    // and warnings start with a W instead.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Syntax(_) => "E0100",
+            Error::UnterminatedString => "E0101",
+            Error::UnknownCharacter(_) => "E0102",
+            Error::InvalidNumber(_) => "E0103",
+            Error::ExpectedOneOf(..) => "E0201",
+            Error::UnexpectedToken(..) => "E0202",
+            Error::InvalidAssignmentTarget => "E0203",
+            Error::InheritsFromItself => "E0204",
+            Error::TooManyParamerters => "E0205",
+            Error::MissingSemicolon(_) => "E0206",
+            Error::NestingTooDeep(_) => "E0207",
+            Error::ReturnOutsideFunction => "E0301",
+            Error::ReturnFromInitializer => "E0302",
+            Error::AlreadyDeclared(_) => "E0303",
+            Error::ReadInOwnInitializer(_) => "E0304",
+            Error::Name(..) => "E0305",
+            Error::ThisOutsideClass => "E0306",
+            Error::SuperOutsideClass => "E0307",
+            Error::SuperWithoutSuperclass => "E0308",
+            Error::WarningsDenied(_) => "E0309",
+            Error::Runtime(_) => "E0400",
+            Error::ZeroDivision => "E0401",
+            Error::Type(_) => "E0402",
+            Error::Value(_) => "E0403",
+            Error::Interrupted => "E0404",
+            Error::NotPermitted(_) => "E0405",
+            Error::ConstantReassigned => "E0406",
+            Error::ConstantRedeclared => "E0407",
+            Error::ConstantWithoutValue => "E0408",
+            Error::BuiltinAssigned(_) => "E0409",
+            Error::UnusedVariable(_) => "W0301",
+            Error::Unreachable(_) => "W0302",
+            // never reported as diagnostics
+            Error::Return(_) | Error::TailCall(..) | Error::Exit(_) => "E0000",
+        }
+    }
+
+    // Status a process exits with when this error ends its program: the
+    // code given to `exit()`, EX_SOFTWARE (70) for an error found while it
+    // ran and EX_DATAERR (65) for one found in its source before that.
+    pub fn exit_status(&self) -> i32 {
+        match self {
+            Error::Exit(code) => *code,
+            _ if self.code().starts_with("E04") => 70,
+            _ => 65,
+        }
+    }
+
+    // the text after the kind
+    pub fn message(&self) -> String {
+        match self {
+            Error::Syntax(x) | Error::Value(x) | Error::Runtime(x) | Error::Type(x) => x.to_owned(),
+            Error::ZeroDivision => "division by zero".to_string(),
+            Error::Name(x, suggestions) => {
+                let message = format!("undefined variable \"{x}\"");
+                match suggestions.as_slice() {
+                    [] => message,
+                    [a] => format!("{message}, did you mean \"{a}\"?"),
+                    [a, b, ..] => format!("{message}, did you mean \"{a}\" or \"{b}\"?"),
+                }
+            }
+            Error::TooManyParamerters => "excedded maximum number of parameters".to_string(),
+            Error::Interrupted => "execution was cancelled".to_string(),
+            Error::NotPermitted(name) => {
+                format!("operation not permitted in sandboxed mode: {name}()")
+            }
+            Error::Return(x) => format!("return {x}"),
+            Error::TailCall(..) => "tail call".to_string(),
+            Error::Exit(code) => format!("exit {code}"),
+            Error::ConstantReassigned => "cannot reassign to a constant variable".to_string(),
+            Error::ConstantRedeclared => "cannot reassign a constant variable".to_string(),
+            Error::ConstantWithoutValue => "cannot declare a constant without a value".to_string(),
+            Error::BuiltinAssigned(name) => {
+                format!("cannot assign to builtin \"{name}\", declare it with let to shadow it")
+            }
+            Error::UnterminatedString => "unterminated string".to_string(),
+            Error::UnknownCharacter(ch) => format!("unknown character:'{ch}'"),
+            Error::InvalidNumber(x) => format!("invalid number:'{x}'"),
+            Error::ExpectedOneOf(expected, found) => {
+                let expected = match expected.as_slice() {
+                    [] => "nothing".to_string(),
+                    [only] => only.to_owned(),
+                    [first, second] => format!("{first} or {second}"),
+                    [rest @ .., last] => format!("{}, or {last}", rest.join(", ")),
+                };
+                format!("Expected one of {expected}, found '{found}'")
+            }
+            Error::UnexpectedToken(expected, found) => {
+                format!("Expected: \"{expected}\" Found: \"{found}\"")
+            }
+            Error::InvalidAssignmentTarget => "Invalid assignment target".to_string(),
+            Error::InheritsFromItself => "Cannot inherit from itself".to_string(),
+            Error::MissingSemicolon(what) => format!("Expected ';' after {what}"),
+            Error::NestingTooDeep(what) => format!("{what} nesting too deep"),
+            Error::ReturnOutsideFunction => "cannot return from top-level code".to_string(),
+            Error::ReturnFromInitializer => "cannot return a value from an initializer".to_string(),
+            Error::AlreadyDeclared(name) => {
+                format!("variable \"{name}\" is already declared in this scope")
+            }
+            Error::ReadInOwnInitializer(name) => {
+                format!("cannot read local variable \"{name}\" in its own initializer")
+            }
+            Error::ThisOutsideClass => "cannot use \"this\" outside of a class".to_string(),
+            Error::SuperOutsideClass => "cannot use \"super\" outside of a class".to_string(),
+            Error::SuperWithoutSuperclass => {
+                "cannot use \"super\" in a class with no superclass".to_string()
+            }
+            Error::UnusedVariable(name) => format!(
+                "variable \"{name}\" is never read, name it \"_{name}\" if that is intended"
+            ),
+            Error::Unreachable(terminator) => {
+                format!("unreachable statement after \"{terminator}\"")
+            }
+            Error::WarningsDenied(count) => {
+                format!("{count} warnings were found and warnings are denied")
+            }
+        }
+    }
 }
 
+impl std::error::Error for Error {}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::Syntax(x) => write!(f, "SyntaxError: {x}"),
-            Error::Value(x) => write!(f, "ValueError: {x}"),
-            Error::Parse(x) => write!(f, "ParseError: {x}"),
-            Error::Runtime(x) => write!(f, "ParseError: {x}"),
-            Error::ZeroDivision => write!(f, "ZeroDivisionError: division by zero"),
-            Error::Name(x) => write!(f, "NameError: undefined variable \"{x}\""),
-            Error::TooManyParamerters => write!(f, "TooManyParamerters: excedded maximum number of parameters"),
-            Error:: Return(x) => write!(f, "return {x}"),
-            Error::Type(x) => write!(f, "TypeError: {x}"),
+            Error::Return(_) | Error::TailCall(..) | Error::Exit(_) => {
+                write!(f, "{}", self.message())
+            }
+            _ => write!(f, "{}: {}", self.kind(), self.message()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let message = || "message".to_string();
+        let cases = [
+            (Error::Syntax(message()), "SyntaxError: message"),
+            (Error::Value(message()), "ValueError: message"),
+            (Error::Runtime(message()), "RuntimeError: message"),
+            (
+                Error::Name("a".to_string(), vec![]),
+                "NameError: undefined variable \"a\"",
+            ),
+            (
+                Error::Name("a".to_string(), vec!["b".to_string(), "c".to_string()]),
+                "NameError: undefined variable \"a\", did you mean \"b\" or \"c\"?",
+            ),
+            (Error::ZeroDivision, "ZeroDivisionError: division by zero"),
+            (
+                Error::TooManyParamerters,
+                "TooManyParamerters: excedded maximum number of parameters",
+            ),
+            (Error::Type(message()), "TypeError: message"),
+            (Error::Interrupted, "Interrupted: execution was cancelled"),
+            (Error::Return(Object::Nil), "return nil"),
+            (Error::Exit(2), "exit 2"),
+        ];
+        for (error, expected) in cases {
+            assert!(error.code().starts_with('E') && error.code().len() == 5);
+            assert_eq!(error.severity(), Severity::Error);
+            let rendered = error.to_string();
+            assert_eq!(rendered, expected);
+            if !matches!(error, Error::Return(_) | Error::Exit(_)) {
+                assert!(rendered.starts_with(&format!("{}: ", error.kind())));
+            }
         }
     }
+
+    #[test]
+    fn test_codes() {
+        let cases = [
+            (
+                Error::UnterminatedString,
+                "E0101",
+                "SyntaxError: unterminated string",
+            ),
+            (
+                Error::UnknownCharacter('@'),
+                "E0102",
+                "SyntaxError: unknown character:'@'",
+            ),
+            (
+                Error::InvalidNumber("1.2.3".to_string()),
+                "E0103",
+                "ValueError: invalid number:'1.2.3'",
+            ),
+            (
+                Error::ExpectedOneOf(
+                    vec![
+                        "';'".to_string(),
+                        "'let'".to_string(),
+                        "an expression".to_string(),
+                    ],
+                    "class".to_string(),
+                ),
+                "E0201",
+                "ParseError: Expected one of ';', 'let', or an expression, found 'class'",
+            ),
+            (
+                Error::UnexpectedToken(";".to_string(), "}".to_string()),
+                "E0202",
+                "ParseError: Expected: \";\" Found: \"}\"",
+            ),
+            (
+                Error::InvalidAssignmentTarget,
+                "E0203",
+                "ParseError: Invalid assignment target",
+            ),
+            (
+                Error::MissingSemicolon("expression".to_string()),
+                "E0206",
+                "ParseError: Expected ';' after expression",
+            ),
+            (
+                Error::NestingTooDeep("expression".to_string()),
+                "E0207",
+                "ParseError: expression nesting too deep",
+            ),
+            (
+                Error::InheritsFromItself,
+                "E0204",
+                "ParseError: Cannot inherit from itself",
+            ),
+            (
+                Error::ReturnOutsideFunction,
+                "E0301",
+                "SyntaxError: cannot return from top-level code",
+            ),
+            (
+                Error::ReturnFromInitializer,
+                "E0302",
+                "SyntaxError: cannot return a value from an initializer",
+            ),
+            (
+                Error::AlreadyDeclared("a".to_string()),
+                "E0303",
+                "SyntaxError: variable \"a\" is already declared in this scope",
+            ),
+            (
+                Error::ReadInOwnInitializer("a".to_string()),
+                "E0304",
+                "SyntaxError: cannot read local variable \"a\" in its own initializer",
+            ),
+            (
+                Error::Name("a".to_string(), vec![]),
+                "E0305",
+                "NameError: undefined variable \"a\"",
+            ),
+            (
+                Error::ThisOutsideClass,
+                "E0306",
+                "SyntaxError: cannot use \"this\" outside of a class",
+            ),
+            (
+                Error::SuperOutsideClass,
+                "E0307",
+                "SyntaxError: cannot use \"super\" outside of a class",
+            ),
+            (
+                Error::SuperWithoutSuperclass,
+                "E0308",
+                "SyntaxError: cannot use \"super\" in a class with no superclass",
+            ),
+            (
+                Error::WarningsDenied(2),
+                "E0309",
+                "SyntaxError: 2 warnings were found and warnings are denied",
+            ),
+            (
+                Error::ZeroDivision,
+                "E0401",
+                "ZeroDivisionError: division by zero",
+            ),
+            (
+                Error::NotPermitted("env".to_string()),
+                "E0405",
+                "PermissionError: operation not permitted in sandboxed mode: env()",
+            ),
+            (
+                Error::ConstantReassigned,
+                "E0406",
+                "RuntimeError: cannot reassign to a constant variable",
+            ),
+            (
+                Error::ConstantRedeclared,
+                "E0407",
+                "RuntimeError: cannot reassign a constant variable",
+            ),
+            (
+                Error::ConstantWithoutValue,
+                "E0408",
+                "RuntimeError: cannot declare a constant without a value",
+            ),
+            (
+                Error::BuiltinAssigned("len".to_string()),
+                "E0409",
+                "RuntimeError: cannot assign to builtin \"len\", declare it with let to shadow it",
+            ),
+            (
+                Error::UnusedVariable("a".to_string()),
+                "W0301",
+                "Warning: variable \"a\" is never read, name it \"_a\" if that is intended",
+            ),
+            (
+                Error::Unreachable("break".to_string()),
+                "W0302",
+                "Warning: unreachable statement after \"break\"",
+            ),
+        ];
+        for (error, code, expected) in cases {
+            assert_eq!(error.code(), code, "{error}");
+            assert_eq!(error.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_exit_status() {
+        assert_eq!(Error::Exit(3).exit_status(), 3);
+        assert_eq!(Error::ZeroDivision.exit_status(), 70);
+        assert_eq!(Error::Interrupted.exit_status(), 70);
+        assert_eq!(Error::ConstantReassigned.exit_status(), 70);
+        assert_eq!(Error::WarningsDenied(1).exit_status(), 65);
+        assert_eq!(Error::ReturnOutsideFunction.exit_status(), 65);
+        assert_eq!(Error::UnterminatedString.exit_status(), 65);
+    }
 }
@@ -0,0 +1,135 @@
+use super::ErrorInfo;
+use crate::{builtins::json::quote, LineIndex, Severity, Span};
+
+// How diagnostics are written: rendered with the offending line for people,
+// or as one JSON object per line for editors and other tools.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+impl ErrorInfo {
+    // One line of JSON describing the error:
+    //
+    //   {"severity": "error", "code": "E0401", "kind": "ZeroDivisionError",
+    //    "message": "division by zero", "line": 3, "column": 11,
+    //    "end_column": 12, "file": "main.rl"}
+    //
+    // Columns are 1-based and count characters, `end_column` is the one just
+    // past the span. The position fields are null for an error without one,
+    // the columns are null without the source the span was taken from, and
+    // the file is null when the source has no name.
+    pub fn to_json(&self, source: Option<&str>) -> String {
+        self.to_json_with_index(source.map(LineIndex::new).as_ref())
+    }
+
+    // `to_json` given the lines of the source, as `render_with_index` is
+    pub fn to_json_with_index(&self, index: Option<&LineIndex>) -> String {
+        let severity = match self.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let null = || "null".to_string();
+        let (line, column, end_column) = match index {
+            _ if self.span == Span::default() => (null(), null(), null()),
+            None => (self.span.line.to_string(), null(), null()),
+            Some(index) => self.columns(index),
+        };
+        format!(
+            "{{\"severity\": \"{severity}\", \"code\": \"{}\", \"kind\": \"{}\", \"message\": {}, \
+             \"line\": {line}, \"column\": {column}, \"end_column\": {end_column}, \"file\": {}}}",
+            self.error.code(),
+            self.error.kind(),
+            quote(&self.error.message()),
+            self.file().map_or_else(null, quote),
+        )
+    }
+
+    // line, column and end column of the span in the source of `index`
+    fn columns(&self, index: &LineIndex) -> (String, String, String) {
+        let (line, column) = index.line_column(self.span.start);
+        let (end_line, end_column) = index.line_column(self.span.end.max(self.span.start));
+        // a span running onto later lines ends with its first one
+        let end_column = if end_line == line {
+            end_column.max(column + 1)
+        } else {
+            index.line(line).chars().count() + 1
+        };
+        (line.to_string(), column.to_string(), end_column.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{Error, ErrorInfo, Span};
+
+    // the value of `field` in a line written by `to_json`, undoing the
+    // escapes `quote` adds to strings
+    fn field(json: &str, field: &str) -> String {
+        let key = format!("\"{field}\": ");
+        let rest = &json[json.find(&key).unwrap() + key.len()..];
+        let Some(rest) = rest.strip_prefix('"') else {
+            return rest[..rest.find([',', '}']).unwrap()].to_string();
+        };
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '"' => return value,
+                '\\' => match chars.next().unwrap() {
+                    'n' => value.push('\n'),
+                    'r' => value.push('\r'),
+                    't' => value.push('\t'),
+                    ch => value.push(ch),
+                },
+                ch => value.push(ch),
+            }
+        }
+        panic!("unterminated string in {json}")
+    }
+
+    #[test]
+    fn test_to_json() {
+        let source = "let a = 1;\nlet é = a +* 2;";
+        let start = source.find('*').unwrap();
+        let mut error = ErrorInfo::new(Error::Syntax("bad".to_string()), 2, start, start + 1);
+        error.span.file = Some(Arc::from("main.rl"));
+        assert_eq!(
+            error.to_json(Some(source)),
+            "{\"severity\": \"error\", \"code\": \"E0100\", \"kind\": \"SyntaxError\", \
+             \"message\": \"bad\", \"line\": 2, \"column\": 12, \"end_column\": 13, \
+             \"file\": \"main.rl\"}"
+        );
+    }
+
+    #[test]
+    fn test_fields() {
+        let mut error = ErrorInfo::new(Error::UnusedVariable("b".to_string()), 1, 6, 7);
+        error.span.file = Some(Arc::from("dir\\a \"b\".rl"));
+        let json = error.to_json(Some("{ let b = 1; }"));
+        assert!(!json.contains('\n'));
+        assert_eq!(field(&json, "severity"), "warning");
+        assert_eq!(field(&json, "code"), "W0301");
+        assert_eq!(field(&json, "message"), error.error.message());
+        assert_eq!(field(&json, "line"), "1");
+        assert_eq!(field(&json, "column"), "7");
+        assert_eq!(field(&json, "end_column"), "8");
+        assert_eq!(field(&json, "file"), "dir\\a \"b\".rl");
+
+        // the line is known without the source but the columns are not
+        let json = error.to_json(None);
+        assert_eq!(field(&json, "line"), "1");
+        assert_eq!(field(&json, "column"), "null");
+
+        let error = ErrorInfo::new_with_span(Error::Interrupted, Span::default());
+        let json = error.to_json(None);
+        assert_eq!(field(&json, "message"), "execution was cancelled");
+        assert_eq!(field(&json, "line"), "null");
+        assert_eq!(field(&json, "end_column"), "null");
+        assert_eq!(field(&json, "file"), "null");
+    }
+}
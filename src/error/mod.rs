@@ -1,30 +1,198 @@
+#[allow(clippy::module_inception)]
 mod error;
-pub use error::Error;
+pub use error::{Error, Severity};
+
+mod suggest;
+pub use suggest::suggest;
+
+mod render;
+pub use render::ColorChoice;
+
+mod json;
+pub use json::ErrorFormat;
+
+use std::fmt;
 
 use crate::Span;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct ErrorInfo {
     pub error: Error,
-    span: Span,
+    pub span: Span,
 }
 
 impl ErrorInfo {
-    pub fn new(error: Error, line: usize, line_start: usize, start: usize, end: usize) -> Self {
+    pub fn new(error: Error, line: usize, start: usize, end: usize) -> Self {
         Self {
             error,
-            span: Span::new(line, line_start, start, end),
+            span: Span::new(line, start, end),
         }
     }
 
     pub fn new_with_span(error: Error, span: Span) -> Self {
-        Self {
-            error,
-            span,
-        }
+        Self { error, span }
+    }
+
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn line(&self) -> usize {
+        self.span.line
+    }
+
+    // name of the source the error is in, when it was given one
+    pub fn file(&self) -> Option<&str> {
+        self.span.file.as_deref()
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.error.severity()
     }
 
     pub fn report(&self) {
-        eprintln!("{}, line {}, pos {}", self.error, self.span.line, self.span.start - self.span.line_start);
+        eprintln!("{self}");
+    }
+}
+
+// `RuntimeError: message (line 12, columns 5–9)`, or `(main.rl, line 12,
+// columns 5–9)` when the source has a name. The columns are left out when
+// the span does not know them, and the line too when the error has no
+// position.
+impl fmt::Display for ErrorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if self.span == Span::default() {
+            return Ok(());
+        }
+        write!(f, " (")?;
+        if let Some(file) = self.file() {
+            write!(f, "{file}, ")?;
+        }
+        write!(f, "line {}", self.span.line)?;
+        let Span {
+            column, end_column, ..
+        } = self.span;
+        if column > 0 && end_column > column + 1 {
+            write!(f, ", columns {column}–{}", end_column - 1)?;
+        } else if column > 0 {
+            write!(f, ", column {column}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl std::error::Error for ErrorInfo {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    use crate::{Compiler, Interpretor, Lexer, LineIndex, Parser, Resolver, Vm};
+
+    // the first error running `input` on the vm
+    fn run(input: &str) -> ErrorInfo {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let mut program = parser.parse_program().unwrap();
+        Resolver::new().resolve(&mut program).unwrap();
+        let mut interpretor = Interpretor::new();
+        for stmt in &program.stmts {
+            let result = Compiler::compile(&program.ast, std::slice::from_ref(stmt))
+                .and_then(|chunk| Vm::new(&mut interpretor).run(Rc::new(chunk)));
+            if let Err(err) = result {
+                return err;
+            }
+        }
+        panic!("{input} ran without errors")
+    }
+
+    #[test]
+    fn test_display() {
+        // with the 1-based column the span starts at, which the message
+        // gives too
+        let cases = [
+            (
+                "let a = 1;\n  a = 1 / 0;",
+                "ZeroDivisionError: division by zero (line 2, column 9)",
+                9,
+            ),
+            (
+                "let a = 1;\nlet b = aa;",
+                "NameError: undefined variable \"aa\", did you mean \"a\"? (line 2, columns 9–10)",
+                9,
+            ),
+            (
+                "print -\"a\";",
+                "TypeError: cannot apply '-' to string (\"a\") (line 1, column 7)",
+                7,
+            ),
+            (
+                "print clock(1);",
+                "TypeError: clock() takes 0 arguments but 1 were given (line 1, column 12)",
+                12,
+            ),
+            (
+                "print num(\"x\");",
+                "ValueError: invalid number:'x' (line 1, column 10)",
+                10,
+            ),
+            (
+                "class A {}",
+                "SyntaxError: classes not supported by the bytecode compiler (line 1, column 7)",
+                7,
+            ),
+            // the span is in bytes and the column in characters
+            (
+                "print \"é\" + 1 / 0;",
+                "ZeroDivisionError: division by zero (line 1, column 15)",
+                15,
+            ),
+        ];
+        for (input, expected, column) in cases {
+            let err = run(input);
+            assert_eq!(err.to_string(), expected, "{input}");
+            let index = LineIndex::new(input);
+            assert_eq!(
+                index.line_column(err.span.start),
+                (err.line(), column),
+                "{input}"
+            );
+            assert_eq!(err.span.column, column, "{input}");
+        }
+        let error = ErrorInfo::new_with_span(Error::Interrupted, Span::default());
+        assert_eq!(error.to_string(), "Interrupted: execution was cancelled");
+        // a span without columns, as made by hand
+        let error = ErrorInfo::new(Error::ZeroDivision, 12, 0, 1);
+        assert_eq!(
+            error.to_string(),
+            "ZeroDivisionError: division by zero (line 12)"
+        );
+        let span = Span::new(12, 40, 45).with_columns(5, 10);
+        let error = ErrorInfo::new_with_span(Error::ZeroDivision, span);
+        assert_eq!(
+            error.to_string(),
+            "ZeroDivisionError: division by zero (line 12, columns 5–9)"
+        );
+    }
+
+    #[test]
+    fn test_source() {
+        use std::error::Error as _;
+
+        let error = run("let a = 1;\n\nlet b = 1 / 0;");
+        assert_eq!(error.line(), 3);
+        assert_eq!(error.error(), &Error::ZeroDivision);
+        assert_eq!(error.span().start, 22);
+        let source = error.source().unwrap().downcast_ref::<Error>();
+        assert_eq!(source, Some(&Error::ZeroDivision));
     }
 }
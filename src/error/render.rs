@@ -0,0 +1,299 @@
+use std::io::IsTerminal;
+
+use super::ErrorInfo;
+use crate::{LineIndex, Severity, Span};
+
+// columns a tab is expanded to, so the underline lines up with the text
+const TAB_WIDTH: usize = 4;
+// longest stretch of a line that is shown, longer lines are cut around the
+// span with an ellipsis
+const MAX_WIDTH: usize = 80;
+// columns kept to the left of the span when a long line is cut
+const CONTEXT: usize = 20;
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+// Whether rendered diagnostics are colored with ANSI escapes. `Auto` colors
+// them when stderr is a terminal and the NO_COLOR variable is not set.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ColorChoice {
+    Always,
+    Never,
+    #[default]
+    Auto,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && std::io::stderr().is_terminal()
+            }
+        }
+    }
+}
+
+// `text` in `color` when coloring, as is otherwise
+fn paint(text: &str, color: &str, enabled: bool) -> String {
+    if enabled {
+        format!("{color}{text}{RESET}")
+    } else {
+        text.to_owned()
+    }
+}
+
+impl ErrorInfo {
+    // The message with the code of the error, followed by the line of
+    // `source` it points at, with a gutter holding the line number and a ^^^
+    // underline beneath the span:
+    //
+    //   ZeroDivisionError[E0401]: division by zero
+    //    --> main.rl:3:11
+    //     |
+    //   3 | let b = 1 / 0;
+    //     |           ^
+    //
+    // The position reads `line 3, column 11` when the source has no name.
+    // `source` must be the text the span was taken from. An error without a
+    // position is rendered as the message alone.
+    pub fn render(&self, source: &str) -> String {
+        self.render_with_color(source, ColorChoice::Never)
+    }
+
+    // Same as `render`, with the kind and underline in red for errors or
+    // yellow for warnings and the code in blue when `color` is enabled.
+    pub fn render_with_color(&self, source: &str, color: ColorChoice) -> String {
+        self.render_with_index(&LineIndex::new(source), color)
+    }
+
+    // Same as `render_with_color` given the lines of the source, which a
+    // host reporting many errors in one source builds once, or takes from
+    // the parser that found them.
+    pub fn render_with_index(&self, index: &LineIndex, color: ColorChoice) -> String {
+        let color = color.enabled();
+        let highlight = match self.severity() {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+        let header = format!(
+            "{}[{}]: {}",
+            paint(self.error.kind(), highlight, color),
+            paint(self.error.code(), BLUE, color),
+            self.error.message()
+        );
+        if self.span == Span::default() {
+            return header;
+        }
+        let (number, column) = index.line_column(self.span.start);
+        let text = index.line(number);
+        let line_end = index.line_start(number) + text.len();
+        let line: Vec<char> = text.chars().collect();
+        // the span in characters of the line, at least one wide so a
+        // position at the end of the line still gets a caret after it
+        let start = column - 1;
+        // a span may start on the `\r` the line is shown without
+        let end = index
+            .line_column(self.span.end.min(line_end).max(self.span.start))
+            .1
+            - 1;
+        let end = end.max(start + 1);
+        let line = line.as_slice();
+
+        // the line with tabs expanded, and the display column of each
+        // character including one past the last
+        let mut text = String::new();
+        let mut columns = Vec::with_capacity(line.len() + 1);
+        let mut width = 0;
+        for ch in line {
+            columns.push(width);
+            match ch {
+                '\t' => {
+                    text.push_str(&" ".repeat(TAB_WIDTH));
+                    width += TAB_WIDTH;
+                }
+                ch => {
+                    text.push(*ch);
+                    width += 1;
+                }
+            }
+        }
+        columns.push(width);
+        let display_column = |index: usize| {
+            columns
+                .get(index)
+                .copied()
+                .unwrap_or(width + index - line.len())
+        };
+        let (underline_start, underline_end) = (display_column(start), display_column(end));
+
+        let (from, to) = if width <= MAX_WIDTH {
+            (0, width)
+        } else {
+            let from = underline_start.saturating_sub(CONTEXT);
+            (from, (from + MAX_WIDTH).min(width))
+        };
+        let mut shown: String = text.chars().skip(from).take(to - from).collect();
+        let mut offset = underline_start - from;
+        if from > 0 {
+            shown.insert_str(0, "...");
+            offset += 3;
+        }
+        if to < width {
+            shown.push_str("...");
+        }
+        let underline = underline_end.min(to.max(underline_start + 1)) - underline_start;
+
+        let gutter = " ".repeat(number.to_string().len());
+        let source_line = format!("{number} | {shown}");
+        let position = match self.file() {
+            Some(file) => format!("{file}:{number}:{column}"),
+            None => format!("line {number}, column {column}"),
+        };
+        format!(
+            "{header}\n{gutter}--> {position}\n{gutter} |\n{}\n{gutter} | {}{}",
+            source_line.trim_end(),
+            " ".repeat(offset),
+            paint(&"^".repeat(underline.max(1)), highlight, color),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ColorChoice;
+    use crate::{Error, ErrorInfo, Interpretor, Lexer, Parser, Span};
+
+    // `error` at the bytes `start..end` of `source`
+    fn render(source: &str, start: usize, end: usize) -> String {
+        let error = Error::Syntax("bad".to_string());
+        ErrorInfo::new(error, 1, start, end).render(source)
+    }
+
+    #[test]
+    fn test_render() {
+        let source = "let a = 1;\nlet b = a +* 2;\n";
+        assert_eq!(
+            render(source, 22, 24),
+            "SyntaxError[E0100]: bad\n --> line 2, column 12\n  |\n2 | let b = a +* 2;\n  |            ^^"
+        );
+        // the gutter grows with the line number
+        let source = format!("{}x = ;", "\n".repeat(11));
+        assert_eq!(
+            render(&source, 15, 16),
+            "SyntaxError[E0100]: bad\n  --> line 12, column 5\n   |\n12 | x = ;\n   |     ^"
+        );
+    }
+
+    #[test]
+    fn test_tabs() {
+        assert_eq!(
+            render("\tif (x) {\t}", 9, 10),
+            "SyntaxError[E0100]: bad\n --> line 1, column 10\n  |\n1 |     if (x) {    }\n  |             ^^^^"
+        );
+    }
+
+    #[test]
+    fn test_multibyte() {
+        // the underline is placed by characters though the span is in bytes
+        let source = "print \"é\" +* 1;";
+        let start = source.find('*').unwrap();
+        assert_eq!(
+            render(source, start, start + 1),
+            "SyntaxError[E0100]: bad\n --> line 1, column 12\n  |\n1 | print \"é\" +* 1;\n  |            ^"
+        );
+    }
+
+    #[test]
+    fn test_end_of_file() {
+        // a missing token after the last character of the file
+        assert_eq!(
+            render("print 1", 7, 7),
+            "SyntaxError[E0100]: bad\n --> line 1, column 8\n  |\n1 | print 1\n  |        ^"
+        );
+        // past a trailing newline the line is empty
+        assert_eq!(
+            render("print 1\n", 8, 8),
+            "SyntaxError[E0100]: bad\n --> line 2, column 1\n  |\n2 |\n  | ^"
+        );
+        // at a carriage return ending the file, which the line is shown without
+        assert_eq!(
+            render("{\r", 2, 2),
+            "SyntaxError[E0100]: bad\n --> line 1, column 3\n  |\n1 | {\n  |   ^"
+        );
+    }
+
+    #[test]
+    fn test_long_line() {
+        let source = format!("let a = {}oops{};", "1 + ".repeat(30), " + 1".repeat(30));
+        let start = source.find("oops").unwrap();
+        let rendered = render(&source, start, start + 4);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert!(lines[3].starts_with("1 | ...") && lines[3].ends_with("..."));
+        assert!(lines[3].len() <= "1 | ".len() + 80 + 6);
+        // the underline sits under the span in the shortened line
+        let caret = lines[4].find('^').unwrap();
+        assert_eq!(&lines[3][caret..caret + 4], "oops");
+        assert!(lines[4].ends_with("^^^^"));
+    }
+
+    #[test]
+    fn test_without_position() {
+        let error = ErrorInfo::new_with_span(Error::Interrupted, Span::default());
+        assert_eq!(
+            error.render("anything"),
+            "Interrupted[E0404]: execution was cancelled"
+        );
+    }
+
+    #[test]
+    fn test_file_name() {
+        let source = "let a = 1;\nprint a / 0;";
+        let mut parser = Parser::new(Lexer::new(source.to_string()).with_name("main.rl"));
+        let program = parser.parse_program().unwrap();
+        let mut interpretor = Interpretor::new();
+        let err = program
+            .stmts
+            .iter()
+            .try_for_each(|stmt| interpretor.exec_in(&program, *stmt))
+            .unwrap_err();
+        assert_eq!(err.file(), Some("main.rl"));
+        assert_eq!(
+            err.render(source),
+            "ZeroDivisionError[E0401]: division by zero\n --> main.rl:2:9\n  |\n2 | print a / 0;\n  |         ^"
+        );
+        assert_eq!(
+            err.to_string(),
+            "ZeroDivisionError: division by zero (main.rl, line 2, column 9)"
+        );
+    }
+
+    #[test]
+    fn test_code() {
+        let error = Error::InvalidAssignmentTarget;
+        let rendered = ErrorInfo::new_with_span(error, Span::new(1, 0, 1)).render("1 = 2;");
+        assert!(rendered.starts_with("ParseError[E0203]: Invalid assignment target\n"));
+    }
+
+    #[test]
+    fn test_color() {
+        let error = ErrorInfo::new(Error::ZeroDivision, 1, 6, 7);
+        assert_eq!(
+            error.render_with_color("print 1 / 0;", ColorChoice::Always),
+            "\x1b[1;31mZeroDivisionError\x1b[0m[\x1b[1;34mE0401\x1b[0m]: division by zero\n \
+             --> line 1, column 7\n  |\n1 | print 1 / 0;\n  |       \x1b[1;31m^\x1b[0m"
+        );
+        let warning = ErrorInfo::new(Error::UnusedVariable("a".to_string()), 1, 6, 7);
+        let rendered = warning.render_with_color("{ let a = 1; }", ColorChoice::Always);
+        assert!(rendered.starts_with("\x1b[1;33mWarning\x1b[0m[\x1b[1;34mW0301\x1b[0m]"));
+        assert!(rendered.ends_with("\x1b[1;33m^\x1b[0m"));
+        let plain = error.render_with_color("print 1 / 0;", ColorChoice::Never);
+        assert!(!plain.contains('\x1b'));
+        assert_eq!(plain, error.render("print 1 / 0;"));
+    }
+}
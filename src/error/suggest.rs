@@ -0,0 +1,93 @@
+// Picks at most two names from `candidates` that are close enough to `name`
+// to be a plausible typo. Only the closest matches are kept so a near miss
+// is not drowned out by a more distant one.
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let length = name.chars().count();
+    // allow roughly one edit for every three characters, but at least one
+    let limit = (length / 3).max(1);
+    let mut matches: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .filter_map(|candidate| {
+            let distance = if candidate.to_lowercase() == name.to_lowercase() {
+                0
+            } else {
+                edit_distance(name, candidate)
+            };
+            // and keep a character of the name, or any one letter name would
+            // be a typo of every other
+            let longer = length.max(candidate.chars().count());
+            (distance <= limit && distance < longer).then_some((distance, candidate))
+        })
+        .collect();
+    matches.sort();
+    matches.dedup();
+    let best = matches.first().map(|(distance, _)| *distance);
+    matches
+        .into_iter()
+        .take_while(|(distance, _)| Some(*distance) == best)
+        .take(2)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+// Levenshtein distance counting adjacent transpositions as a single edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("length", "length"), 0);
+        assert_eq!(edit_distance("lenght", "length"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let names = ["length", "width", "height", "print_all"];
+        assert_eq!(suggest("lenght", names), vec!["length"]);
+        assert_eq!(suggest("Width", names), vec!["width"]);
+        assert_eq!(suggest("x", names), Vec::<String>::new());
+        assert_eq!(suggest("banana", names), Vec::<String>::new());
+        assert_eq!(suggest("ab", ["ac", "ad", "ae"]), vec!["ac", "ad"]);
+    }
+
+    #[test]
+    fn test_suggest_short() {
+        // any one letter name is a single edit from `E`
+        let builtins = ["E", "PI", "len", "str", "c"];
+        for name in ["x", "g", "e2", "pa"] {
+            assert_eq!(suggest(name, builtins), Vec::<String>::new(), "{name}");
+        }
+        assert_eq!(suggest("e", builtins), vec!["E"]);
+        assert_eq!(suggest("pi", builtins), vec!["PI"]);
+        assert_eq!(suggest("cc", builtins), vec!["c"]);
+    }
+}
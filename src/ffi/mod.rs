@@ -0,0 +1,223 @@
+// A C interface to the interpreter, built into the cdylib with the `ffi`
+// feature and declared for C in include/yai.h.
+//
+// An interpreter is made by `yai_new` and owned by the host until it hands
+// it to `yai_free`. Every other function takes a pointer `yai_new` returned
+// that has not been freed, and may be given null, which they treat as an
+// error rather than reading it. The strings returned belong to the
+// interpreter and stay valid until its next `yai_run`, or until it is
+// freed. No panic unwinds into the host: a function that panics returns
+// what it does for any other failure. A stack overflow cannot be caught
+// like a panic, so runs keep to a call depth that fits the 8MB main thread
+// of a typical host, even in a debug build.
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use crate::{interpretor::Capture, run_source, ColorChoice, Error, Interpretor};
+
+// the statuses of sysexits.h, as the command line exits with
+const EX_USAGE: c_int = 64;
+const EX_DATAERR: c_int = 65;
+const EX_SOFTWARE: c_int = 70;
+
+// how deep the calls of a run may nest, see above
+const MAX_CALL_DEPTH: usize = 400;
+
+pub struct Yai {
+    interpretor: Interpretor,
+    // what the last run printed and the errors it had, as C strings
+    output: CString,
+    error: Option<CString>,
+}
+
+impl Yai {
+    fn new() -> Self {
+        Self {
+            interpretor: Interpretor::new()
+                .with_color(ColorChoice::Never)
+                .with_max_call_depth(MAX_CALL_DEPTH),
+            output: CString::default(),
+            error: None,
+        }
+    }
+
+    // Runs `source`, whose globals stay for the runs after it, returning 0
+    // when it ran without errors, 65 when it could not start, 70 when one
+    // of its statements failed, and the status it exited with otherwise.
+    unsafe fn run(&mut self, source: *const c_char) -> c_int {
+        self.output = CString::default();
+        self.error = None;
+        if source.is_null() {
+            self.error = Some(c_string("error: the source is null".to_string()));
+            return EX_USAGE;
+        }
+        let Ok(source) = CStr::from_ptr(source).to_str() else {
+            self.error = Some(c_string("error: the source is not UTF-8".to_string()));
+            return EX_DATAERR;
+        };
+        let (output, diagnostics) = (Capture::default(), Capture::default());
+        self.interpretor.output = Box::new(output.clone());
+        self.interpretor.diagnostics = Box::new(diagnostics.clone());
+
+        let errors_reported = self.interpretor.errors_reported();
+        let result = run_source(source, &mut self.interpretor);
+        let mut errors = diagnostics.text();
+        let status = match result {
+            Ok(()) if self.interpretor.errors_reported() == errors_reported => 0,
+            Ok(()) => EX_SOFTWARE,
+            Err(stopped) => match stopped[0].error {
+                Error::Exit(code) => code,
+                _ => {
+                    let index = self.interpretor.line_index().expect("indexed by the parse");
+                    for err in &stopped {
+                        let rendered = err.render_with_index(&index, ColorChoice::Never);
+                        errors.push_str(&format!("{rendered}\n"));
+                    }
+                    // only the host's limits stop a program once it runs
+                    if stopped[0].error.code().starts_with("E04") {
+                        EX_SOFTWARE
+                    } else {
+                        EX_DATAERR
+                    }
+                }
+            },
+        };
+        self.output = c_string(output.text());
+        self.error = (!errors.is_empty()).then(|| c_string(errors));
+        status
+    }
+}
+
+// C strings end at the first NUL, so any in the text are left out
+fn c_string(text: String) -> CString {
+    CString::new(text.replace('\0', "")).unwrap()
+}
+
+// `f`, or `failed` when it panics
+fn guard<T>(failed: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(failed)
+}
+
+// A new interpreter, or null when it could not be made.
+#[no_mangle]
+pub extern "C" fn yai_new() -> *mut Yai {
+    guard(ptr::null_mut(), || Box::into_raw(Box::new(Yai::new())))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn yai_free(yai: *mut Yai) {
+    if !yai.is_null() {
+        guard((), || drop(Box::from_raw(yai)));
+    }
+}
+
+// Runs the NUL-terminated `source` on `yai`. A null argument is 64.
+#[no_mangle]
+pub unsafe extern "C" fn yai_run(yai: *mut Yai, source: *const c_char) -> c_int {
+    guard(EX_SOFTWARE, || match yai.as_mut() {
+        Some(yai) => yai.run(source),
+        None => EX_USAGE,
+    })
+}
+
+// The errors and warnings of the last run, rendered as the command line
+// prints them, or null when it had none.
+#[no_mangle]
+pub unsafe extern "C" fn yai_last_error(yai: *const Yai) -> *const c_char {
+    guard(ptr::null(), || match yai.as_ref() {
+        Some(Yai {
+            error: Some(error), ..
+        }) => error.as_ptr(),
+        _ => ptr::null(),
+    })
+}
+
+// What the last run printed, empty before the first, or null for a null
+// interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn yai_get_output(yai: *const Yai) -> *const c_char {
+    guard(ptr::null(), || match yai.as_ref() {
+        Some(yai) => yai.output.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ffi::{CStr, CString},
+        ptr,
+    };
+
+    use super::{yai_free, yai_get_output, yai_last_error, yai_new, yai_run};
+    use crate::interpretor::on_large_stack;
+
+    fn text(ptr: *const std::ffi::c_char) -> Option<String> {
+        (!ptr.is_null()).then(|| unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn test_run() {
+        // the stack of a test thread is too small for the calls a run may
+        // nest in a debug build
+        on_large_stack(|| unsafe {
+            let yai = yai_new();
+            assert_eq!(text(yai_get_output(yai)).as_deref(), Some(""));
+            let source = CString::new("let a = 2;\nprint a * 3;").unwrap();
+            assert_eq!(yai_run(yai, source.as_ptr()), 0);
+            assert_eq!(text(yai_get_output(yai)).as_deref(), Some("6\n"));
+            assert_eq!(text(yai_last_error(yai)), None);
+
+            // the globals are kept, the output is that of the last run
+            let source = CString::new("print a;\nprint a / 0;\nprint \"after\";").unwrap();
+            assert_eq!(yai_run(yai, source.as_ptr()), 70);
+            assert_eq!(text(yai_get_output(yai)).as_deref(), Some("2\nafter\n"));
+            let error = text(yai_last_error(yai)).unwrap();
+            assert!(error.contains("ZeroDivisionError[E0401]"), "{error}");
+            assert!(error.contains("--> <input>:2:"), "{error}");
+
+            // recursing without end is a failed statement, not a crash
+            let source = CString::new("fn f() { return 1 + f(); }\nf();").unwrap();
+            assert_eq!(yai_run(yai, source.as_ptr()), 70);
+            let error = text(yai_last_error(yai)).unwrap();
+            assert!(
+                error.contains("maximum recursion depth exceeded"),
+                "{error}"
+            );
+
+            let source = CString::new("print 1;\nlet = 2;").unwrap();
+            assert_eq!(yai_run(yai, source.as_ptr()), 65);
+            assert_eq!(text(yai_get_output(yai)).as_deref(), Some(""));
+            assert!(text(yai_last_error(yai)).unwrap().contains("[E0202]"));
+
+            let source = CString::new("print 1;\nexit(3);").unwrap();
+            assert_eq!(yai_run(yai, source.as_ptr()), 3);
+            assert_eq!(text(yai_last_error(yai)), None);
+            yai_free(yai);
+        });
+    }
+
+    #[test]
+    fn test_bad_arguments() {
+        unsafe {
+            let source = CString::new("print 1;").unwrap();
+            assert_eq!(yai_run(ptr::null_mut(), source.as_ptr()), 64);
+            assert_eq!(yai_last_error(ptr::null()), ptr::null());
+            assert_eq!(yai_get_output(ptr::null()), ptr::null());
+            yai_free(ptr::null_mut());
+
+            let yai = yai_new();
+            assert_eq!(yai_run(yai, ptr::null()), 64);
+            let error = text(yai_last_error(yai));
+            assert_eq!(error.as_deref(), Some("error: the source is null"));
+            let invalid = CString::new(vec![b'"', 0xff, b'"']).unwrap();
+            assert_eq!(yai_run(yai, invalid.as_ptr()), 65);
+            yai_free(yai);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashSet;
+
+use crate::{Lexer, Span, Symbol, TokenInfo, TokenType};
+
+// What a piece of source is, for coloring it in an editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenClass {
+    Keyword,
+    Identifier,
+    // the name of a function declared with `fn`, of a method, or of
+    // anything called
+    FunctionName,
+    // a name declared by a class, wherever it is used
+    ClassName,
+    Number,
+    String,
+    Operator,
+    Comment,
+    Punctuation,
+    // what the lexer could not read, such as an unknown character or a
+    // string left open, and anything after a NUL, where it stops reading
+    Invalid,
+}
+
+// Classifies every piece of `source`, in order. Together the spans cover
+// every byte of it once, leaving out only the whitespace between them.
+// Names are told apart from the tokens around them rather than by parsing,
+// so a source that does not parse is classified as well as one that does.
+pub fn highlight(source: &str) -> Vec<(Span, TokenClass)> {
+    let mut classified = Vec::new();
+    for token in Lexer::from_str(source).with_trivia(true).tokens() {
+        match token {
+            Ok(TokenInfo {
+                token: TokenType::Eof,
+                span,
+            }) => {
+                if span.start < source.len() {
+                    let rest = Span::new(span.line, span.start, source.len());
+                    classified.push((rest, TokenClass::Invalid, None));
+                }
+            }
+            Ok(TokenInfo { token, span }) => {
+                let class = class(&token);
+                classified.push((span, class, Some(token)));
+            }
+            Err(err) => classified.push((err.span, TokenClass::Invalid, None)),
+        }
+    }
+    name_classes(&mut classified);
+    classified
+        .into_iter()
+        .map(|(span, class, _)| (span, class))
+        .collect()
+}
+
+// the class of `token` on its own, before looking at its neighbours
+fn class(token: &TokenType) -> TokenClass {
+    match token {
+        TokenType::Identifier(_) => TokenClass::Identifier,
+        TokenType::String(_) => TokenClass::String,
+        TokenType::Number(_) => TokenClass::Number,
+        TokenType::Comment(_) => TokenClass::Comment,
+        TokenType::True
+        | TokenType::False
+        | TokenType::Nil
+        | TokenType::This
+        | TokenType::Super
+        | TokenType::Function
+        | TokenType::Let
+        | TokenType::Const
+        | TokenType::Return
+        | TokenType::If
+        | TokenType::Else
+        | TokenType::For
+        | TokenType::While
+        | TokenType::Break
+        | TokenType::Continue
+        | TokenType::Print
+        | TokenType::Import
+        | TokenType::Class => TokenClass::Keyword,
+        TokenType::Comma
+        | TokenType::Semicolon
+        | TokenType::Colon
+        | TokenType::Dot
+        | TokenType::LParen
+        | TokenType::RParen
+        | TokenType::LBrace
+        | TokenType::RBrace
+        | TokenType::LCurly
+        | TokenType::RCurly => TokenClass::Punctuation,
+        TokenType::Assign
+        | TokenType::Plus
+        | TokenType::Minus
+        | TokenType::Times
+        | TokenType::Divide
+        | TokenType::Mod
+        | TokenType::LShift
+        | TokenType::RShift
+        | TokenType::And
+        | TokenType::Or
+        | TokenType::Not
+        | TokenType::Xor
+        | TokenType::Lt
+        | TokenType::Gt
+        | TokenType::Eq
+        | TokenType::Ne
+        | TokenType::Lte
+        | TokenType::Gte
+        | TokenType::PlusEq
+        | TokenType::DivideEq
+        | TokenType::MinusEq
+        | TokenType::TimesEq
+        | TokenType::AndEq
+        | TokenType::OrEq
+        | TokenType::XorEq
+        | TokenType::ModEq
+        | TokenType::LogicalAnd
+        | TokenType::LogicalOr => TokenClass::Operator,
+        // not classified by this, as it takes up a byte only at a NUL
+        TokenType::Eof => TokenClass::Invalid,
+    }
+}
+
+// Tells the names of classes and functions from the other identifiers by
+// the tokens next to them, comments and what the lexer could not read
+// aside: the name after `class`, or after the `<` of its header, is a class
+// everywhere in the source, and any other name after `fn` or before `(` a
+// function.
+fn name_classes(classified: &mut [(Span, TokenClass, Option<TokenType>)]) {
+    let tokens: Vec<(usize, &TokenType)> = classified
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, class, _))| *class != TokenClass::Comment)
+        .filter_map(|(at, (_, _, token))| Some((at, token.as_ref()?)))
+        .collect();
+    let name = |i: usize| match tokens.get(i) {
+        Some((_, TokenType::Identifier(name))) => Some(*name),
+        _ => None,
+    };
+    let is = |i: Option<usize>, expected: TokenType| {
+        i.and_then(|i| tokens.get(i))
+            .is_some_and(|(_, token)| **token == expected)
+    };
+
+    let mut classes = HashSet::<Symbol>::new();
+    for i in 0..tokens.len() {
+        let declares = is(i.checked_sub(1), TokenType::Class)
+            || (is(i.checked_sub(1), TokenType::Lt) && is(i.checked_sub(3), TokenType::Class));
+        if let Some(name) = name(i).filter(|_| declares) {
+            classes.insert(name);
+        }
+    }
+    let names: Vec<_> = (0..tokens.len())
+        .filter_map(|i| {
+            let name = name(i)?;
+            let class = if classes.contains(&name) {
+                TokenClass::ClassName
+            } else if is(i.checked_sub(1), TokenType::Function)
+                || is(Some(i + 1), TokenType::LParen)
+            {
+                TokenClass::FunctionName
+            } else {
+                return None;
+            };
+            Some((tokens[i].0, class))
+        })
+        .collect();
+    for (at, class) in names {
+        classified[at].1 = class;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"# shapes
+class Shape { area() { return 0; } }
+class Square < Shape {
+  init(side) { this.side = side; }
+  area() { return this.side * this.side; } # squared
+}
+fn total(shapes, n) {
+  let sum = 0;
+  for (let i = 0; i < n; i += 1) sum = sum + shapes.area();
+  return sum;
+}
+const s = Square(2);
+print total(s, 3) >= 1.5, !nil, "done";
+"#;
+
+    // `source` with each piece it is classified into marked with its class
+    fn marked(source: &str) -> String {
+        let mut marked = String::new();
+        let mut end = 0;
+        for (span, class) in highlight(source) {
+            marked.push_str(&source[end..span.start]);
+            let tag = match class {
+                TokenClass::Keyword => "kw",
+                TokenClass::Identifier => "id",
+                TokenClass::FunctionName => "fn",
+                TokenClass::ClassName => "class",
+                TokenClass::Number => "num",
+                TokenClass::String => "str",
+                TokenClass::Operator => "op",
+                TokenClass::Comment => "comment",
+                TokenClass::Punctuation => "p",
+                TokenClass::Invalid => "invalid",
+            };
+            marked.push_str(&format!("{tag}[{}]", &source[span.start..span.end]));
+            end = span.end;
+        }
+        marked.push_str(&source[end..]);
+        marked
+    }
+
+    #[test]
+    fn test_highlight() {
+        let expected = "comment[# shapes]
+kw[class] class[Shape] p[{] fn[area]p[(]p[)] p[{] kw[return] num[0]p[;] p[}] p[}]
+kw[class] class[Square] op[<] class[Shape] p[{]
+  fn[init]p[(]id[side]p[)] p[{] kw[this]p[.]id[side] op[=] id[side]p[;] p[}]
+  fn[area]p[(]p[)] p[{] kw[return] kw[this]p[.]id[side] op[*] kw[this]p[.]id[side]p[;] p[}] \
+comment[# squared]
+p[}]
+kw[fn] fn[total]p[(]id[shapes]p[,] id[n]p[)] p[{]
+  kw[let] id[sum] op[=] num[0]p[;]
+  kw[for] p[(]kw[let] id[i] op[=] num[0]p[;] id[i] op[<] id[n]p[;] id[i] op[+=] num[1]p[)] \
+id[sum] op[=] id[sum] op[+] id[shapes]p[.]fn[area]p[(]p[)]p[;]
+  kw[return] id[sum]p[;]
+p[}]
+kw[const] id[s] op[=] class[Square]p[(]num[2]p[)]p[;]
+kw[print] fn[total]p[(]id[s]p[,] num[3]p[)] op[>=] num[1.5]p[,] op[!]kw[nil]p[,] str[\"done\"]p[;]
+";
+        assert_eq!(marked(SCRIPT), expected);
+    }
+
+    #[test]
+    fn test_highlight_invalid() {
+        // names are still told apart in a source that does not parse
+        assert_eq!(
+            marked("fn f( @ 1.2.3 g(\n\"open"),
+            "kw[fn] fn[f]p[(] invalid[@] invalid[1.2.3] fn[g]p[(]\ninvalid[\"open]"
+        );
+        assert_eq!(marked("a\0b c"), "id[a]invalid[\0b c]");
+        assert_eq!(marked(" \t\n"), " \t\n");
+        assert_eq!(marked("π # ∞"), "invalid[π] comment[# ∞]");
+    }
+}
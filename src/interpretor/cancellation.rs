@@ -0,0 +1,82 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+// Handle that lets another thread stop a running interpreter. The
+// interpreter polls it at loop iterations and function calls and unwinds
+// with `Error::Interrupted` once it is cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    // Clears the cancellation so the interpreter can run again, as a REPL
+    // does after the line that was interrupted.
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread, time::Duration};
+
+    use crate::{Error, Interpretor, Lexer, Parser, Span};
+
+    #[test]
+    fn test_cancel_infinite_loop() {
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let mut parser = Parser::new(Lexer::new("while (true) {}".to_string()));
+            let program = parser.parse_program().unwrap();
+            let mut interpretor = Interpretor::new();
+            sender.send(interpretor.cancellation_token()).unwrap();
+            // errors hold runtime values, which stay on this thread
+            interpretor
+                .interpret(program)
+                .map_err(|err| err.error.to_string())
+        });
+
+        let token = receiver.recv().unwrap();
+        thread::sleep(Duration::from_millis(50));
+        token.cancel();
+
+        let result = handle.join().unwrap();
+        assert_eq!(result.unwrap_err(), Error::Interrupted.to_string());
+    }
+
+    #[test]
+    fn test_cancel_recursion() {
+        let mut parser = Parser::new(Lexer::new("fn f() { return f(); } f();".to_string()));
+        let program = parser.parse_program().unwrap();
+        let mut interpretor = Interpretor::new();
+        interpretor.cancellation_token().cancel();
+        let result = interpretor.interpret(program);
+        assert_eq!(result.unwrap_err().error, Error::Interrupted);
+    }
+
+    #[test]
+    fn test_cancel_at_loop() {
+        let mut parser = Parser::new(Lexer::new("let a = 1;\nwhile (true) {}".to_string()));
+        let program = parser.parse_program().unwrap();
+        let mut interpretor = Interpretor::new();
+        interpretor.cancellation_token().cancel();
+        let err = interpretor.interpret(program).unwrap_err();
+        assert_eq!(err.error, Error::Interrupted);
+        assert_eq!(err.span, Span::new(2, 11, 16).with_columns(1, 6));
+    }
+}
@@ -0,0 +1,183 @@
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use crate::{Ast, Stmt, StmtId};
+
+// what a file without a name is reported as
+const UNNAMED: &str = "<input>";
+
+// The lines of the programs run so far that hold a statement, and how many
+// times the statements on each ran, file by file. Only the tree-walker
+// counts them; a line is covered once any statement on it has run. The
+// count of a line is the sum of its statements' runs, so a line holding a
+// loop and its body counts both, more than the times the line was reached.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    files: BTreeMap<Option<Arc<str>>, File>,
+}
+
+#[derive(Debug, Default)]
+struct File {
+    // runs of the statements on each line holding one
+    lines: BTreeMap<usize, u64>,
+    source: Option<String>,
+}
+
+impl Coverage {
+    // keeps the source of `name` for the listing of its lines
+    pub(crate) fn add_source(&mut self, name: &str, source: &str) {
+        self.files.entry(Some(Arc::from(name))).or_default().source = Some(source.to_string());
+    }
+
+    // Counts the lines of `stmts` and of every statement they hold, function
+    // bodies included, as not run yet.
+    pub(crate) fn add_program(&mut self, ast: &Ast, stmts: &[StmtId]) {
+        for stmt in stmts {
+            match &ast[*stmt] {
+                Stmt::Block { stmts } => self.add_program(ast, stmts),
+                // a method is not a statement of its own, only its body runs
+                Stmt::Class { methods, .. } => {
+                    self.add_line(ast, *stmt);
+                    for method in methods {
+                        if let Stmt::Function { body, .. } = &ast[*method] {
+                            self.add_program(ast, body);
+                        }
+                    }
+                }
+                Stmt::Function { body, .. } => {
+                    self.add_line(ast, *stmt);
+                    self.add_program(ast, body);
+                }
+                Stmt::If { truthy, falsy, .. } => {
+                    self.add_line(ast, *stmt);
+                    self.add_program(ast, std::slice::from_ref(truthy));
+                    if let Some(falsy) = falsy {
+                        self.add_program(ast, std::slice::from_ref(falsy));
+                    }
+                }
+                Stmt::While { body, .. } => {
+                    self.add_line(ast, *stmt);
+                    self.add_program(ast, std::slice::from_ref(body));
+                }
+                _ => self.add_line(ast, *stmt),
+            }
+        }
+    }
+
+    fn add_line(&mut self, ast: &Ast, stmt: StmtId) {
+        if let Some(span) = ast[stmt].span(ast) {
+            let file = self.files.entry(span.file.clone()).or_default();
+            file.lines.entry(span.line).or_default();
+        }
+    }
+
+    // a block is left out as the statements in it are counted
+    pub(crate) fn hit(&mut self, ast: &Ast, stmt: StmtId) {
+        let stmt = &ast[stmt];
+        if let (Some(span), false) = (stmt.span(ast), matches!(stmt, Stmt::Block { .. })) {
+            let file = self.files.entry(span.file.clone()).or_default();
+            *file.lines.entry(span.line).or_default() += 1;
+        }
+    }
+
+    // name, lines run and lines holding a statement of every file
+    pub fn summary(&self) -> Vec<(&str, usize, usize)> {
+        self.files
+            .iter()
+            .filter(|(_, file)| !file.lines.is_empty())
+            .map(|(name, file)| {
+                let run = file.lines.values().filter(|hits| **hits > 0).count();
+                (name.as_deref().unwrap_or(UNNAMED), run, file.lines.len())
+            })
+            .collect()
+    }
+}
+
+// Each file's summary followed by its source, when it is known, marked the
+// way gcov does: the runs of the statements on each line holding one,
+// `#####` for one that never ran and `-` for one without statements. A
+// header line 0, as gcov writes them, says the counts are of statements.
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ((name, run, total), file) in self
+            .summary()
+            .into_iter()
+            .zip(self.files.values().filter(|file| !file.lines.is_empty()))
+        {
+            let percent = 100.0 * run as f64 / total as f64;
+            writeln!(f, "{name}: {run} of {total} lines run ({percent:.1}%)")?;
+            let Some(source) = &file.source else {
+                continue;
+            };
+            writeln!(f, "{:>9}:{:>5}:Counts:statements run on the line", "-", 0)?;
+            for (number, line) in source.lines().enumerate().map(|(i, line)| (i + 1, line)) {
+                let hits = match file.lines.get(&number) {
+                    Some(0) => "#####".to_string(),
+                    Some(hits) => hits.to_string(),
+                    None => "-".to_string(),
+                };
+                writeln!(f, "{hits:>9}:{number:>5}:{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::Interpretor;
+
+    fn coverage(source: &str) -> Interpretor {
+        let mut interpretor = Interpretor::new().with_output(Box::new(io::sink()));
+        interpretor.set_coverage(true);
+        let program = interpretor.parse(source, "cover.rl").unwrap();
+        interpretor.interpret(program).unwrap();
+        interpretor
+    }
+
+    #[test]
+    fn test_coverage() {
+        let interpretor = coverage(
+            "fn f(a) {
+  if (a) {
+    print \"yes\";
+  } else {
+    print \"no\";
+  }
+}
+let i = 0;
+while (i < 3) { i = i + 1; }
+f(true);",
+        );
+        let coverage = interpretor.coverage().unwrap();
+        assert_eq!(coverage.summary(), [("cover.rl", 6, 7)]);
+        // line 9 counts the loop once and its body three times
+        let expected = "\
+cover.rl: 6 of 7 lines run (85.7%)
+        -:    0:Counts:statements run on the line
+        1:    1:fn f(a) {
+        1:    2:  if (a) {
+        1:    3:    print \"yes\";
+        -:    4:  } else {
+    #####:    5:    print \"no\";
+        -:    6:  }
+        -:    7:}
+        1:    8:let i = 0;
+        4:    9:while (i < 3) { i = i + 1; }
+        1:   10:f(true);
+";
+        assert_eq!(coverage.to_string(), expected);
+    }
+
+    #[test]
+    fn test_several_files() {
+        let mut interpretor = coverage("fn g() { return 1; }");
+        let program = interpretor
+            .parse("print g();\nfn h() {\n  print 2;\n}", "main.rl")
+            .unwrap();
+        interpretor.interpret(program).unwrap();
+        let coverage = interpretor.coverage().unwrap();
+        assert_eq!(coverage.summary(), [("cover.rl", 1, 1), ("main.rl", 2, 3)]);
+    }
+}
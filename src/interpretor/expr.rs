@@ -1,124 +1,163 @@
-use crate::{visitor, ErrorInfo, Expr, Interpretor, LiteralType, Object, Span, TokenType, TokenInfo, Error};
+use std::sync::Arc;
+
+use super::unsupported;
+use crate::{
+    builtins, visitor, Ast, ErrorInfo, ExprId, Interpretor, LiteralType, Object, Slot, Span,
+    Symbol, TokenInfo,
+};
 
 impl visitor::Expr for Interpretor {
-    fn visit_literal_expr(&mut self, value: &LiteralType) -> Result<Object, ErrorInfo> {
+    fn visit_literal_expr(
+        &mut self,
+        _ast: &Arc<Ast>,
+        value: &LiteralType,
+    ) -> Result<Object, ErrorInfo> {
         Ok(match value {
             LiteralType::Nil => Object::Nil,
             LiteralType::Boolean(b) => Object::Boolean(*b),
             LiteralType::Number(n) => Object::Number(*n),
-            LiteralType::String(s) => Object::String(s.clone()),
+            LiteralType::String(s) => Object::String(s.clone().into()),
         })
     }
 
     fn visit_unary_expr(
         &mut self,
+        ast: &Arc<Ast>,
         op: &TokenInfo,
-        right: &Box<Expr>,
+        right: ExprId,
     ) -> Result<Object, ErrorInfo> {
-        self.eval(right)?
+        self.eval(ast, right)?
             .to_unary(&op.token)
             .map_err(|e| ErrorInfo::new_with_span(e, op.span.to_owned()))
     }
 
     fn visit_binary_expr(
         &mut self,
-        left: &Box<Expr>,
+        ast: &Arc<Ast>,
+        left: ExprId,
         op: &TokenInfo,
-        right: &Box<Expr>,
+        right: ExprId,
     ) -> Result<Object, ErrorInfo> {
-        let left = self.eval(left)?;
-        let right = self.eval(right)?;
-        Object::binary(left, &op.token, right).map_err(|e| ErrorInfo::new_with_span(e, op.span.to_owned()))
+        let left = self.eval(ast, left)?;
+        let right = self.eval(ast, right)?;
+        // accounted for before the operation so a huge result is never built
+        self.allocate(Object::binary_size(&left, &op.token, &right), &op.span)?;
+        Object::binary(left, &op.token, right)
+            .map_err(|e| ErrorInfo::new_with_span(e, op.span.to_owned()))
     }
 
-
-    fn visit_grouping_expr(&mut self, expr: &Box<Expr>, span: &Span) -> Result<Object, ErrorInfo> {
-        self.eval(expr)
+    fn visit_grouping_expr(
+        &mut self,
+        ast: &Arc<Ast>,
+        expr: ExprId,
+        _span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        self.eval(ast, expr)
     }
 
     fn visit_assign_expr(
         &mut self,
-        name: &String,
-        value: &Box<Expr>,
+        ast: &Arc<Ast>,
+        name: Symbol,
+        value: ExprId,
         span: &Span,
+        slot: Option<Slot>,
     ) -> Result<Object, ErrorInfo> {
-        let value = self.eval(value)?;
-        self.environment
-            .borrow_mut()
-            .assign(name, value)
-            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+        let value = self.eval(ast, value)?;
+        match slot {
+            Some(slot) => self.environment.borrow_mut().assign_at(slot, name, value),
+            None => self.globals.borrow_mut().assign(name, value),
+        }
+        .map_err(|e| ErrorInfo::new_with_span(self.with_suggestions(e), span.to_owned()))
     }
     fn visit_call_expr(
         &mut self,
-        callee: &Box<Expr>,
-        args: &Vec<Expr>,
+        ast: &Arc<Ast>,
+        callee: ExprId,
+        args: &[ExprId],
         span: &Span,
     ) -> Result<Object, ErrorInfo> {
-        let callee = self.eval(callee)?;
-        let mut arguments = Vec::new();
-        for arg in args {
-            arguments.push(self.eval(arg)?);
-        }
-        match callee {
-            Object::Function(f) => f.call(self, &arguments),
-            x => Err(ErrorInfo::new_with_span(
-                Error::Type(format!("{x} is not callable")),
-                span.to_owned(),
-            )),
-        }
+        self.consume_fuel(span)?;
+        self.check_cancelled(span)?;
+        let callee = self.eval(ast, callee)?;
+        let arguments = self.eval_arguments(ast, args)?;
+        self.call(callee, &arguments, span)
     }
 
     fn visit_get_expr(
         &mut self,
-        object: &Box<Expr>,
-        name: &String,
+        ast: &Arc<Ast>,
+        object: ExprId,
+        name: Symbol,
         span: &Span,
     ) -> Result<Object, ErrorInfo> {
-        let object = self.eval(object)?;
-        let output = self
-            .environment
-            .borrow_mut()
-            .get(name)
-            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
-        Ok(output)
+        let object = self.eval(ast, object)?;
+        builtins::property(&object, name.as_str())
+            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
     }
 
     fn visit_set_expr(
         &mut self,
-        object: &Box<Expr>,
-        name: &String,
-        value: &Box<Expr>,
+        _ast: &Arc<Ast>,
+        _object: ExprId,
+        _name: Symbol,
+        _value: ExprId,
         span: &Span,
     ) -> Result<Object, ErrorInfo> {
-        todo!();
+        Err(unsupported("property assignments", span))
     }
-    fn visit_super_expr(&mut self, name: &String, span: &Span) -> Result<Object, ErrorInfo> {
-        todo!();
+
+    fn visit_super_expr(
+        &mut self,
+        _ast: &Arc<Ast>,
+        _name: Symbol,
+        span: &Span,
+        _slot: Option<Slot>,
+    ) -> Result<Object, ErrorInfo> {
+        Err(unsupported("`super`", span))
     }
 
-    fn visit_variable_expr(&mut self, name: &String, span: &Span) -> Result<Object, ErrorInfo> {
-        self.environment
-            .borrow_mut()
-            .get(name)
-            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+    fn visit_variable_expr(
+        &mut self,
+        _ast: &Arc<Ast>,
+        name: Symbol,
+        span: &Span,
+        slot: Option<Slot>,
+    ) -> Result<Object, ErrorInfo> {
+        match slot {
+            Some(slot) => self.environment.borrow_mut().get_at(slot, name),
+            None => self.globals.borrow_mut().get(name),
+        }
+        .map_err(|e| ErrorInfo::new_with_span(self.with_suggestions(e), span.to_owned()))
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::interpretor::Interpretor;
+    use std::rc::Rc;
+
+    use crate::interpretor::{on_large_stack, Interpretor, MAX_CALL_DEPTH};
     use crate::lexer::Lexer;
+    use crate::object::{Closure, Function};
     use crate::parser::Parser;
-    use crate::Object;
+    use crate::{Error, ErrorInfo, Object, Resolver, Symbol};
 
-    #[test]
-    fn test_literal() {
-        let input = "nil; true; false; 123; \"Hello, world!\";";
+    fn interpret(input: &str) -> Interpretor {
         let lexer = Lexer::new(input.to_string());
         let mut parser = Parser::new(lexer);
         let program = parser.parse_program().unwrap();
         let mut interpretor = Interpretor::new();
-        interpretor.interpret(program);
+        interpretor.interpret(program).unwrap();
+        interpretor
+    }
+
+    fn get(interpretor: &Interpretor, name: &str) -> Object {
+        interpretor.globals.borrow_mut().get(name.into()).unwrap()
+    }
+
+    #[test]
+    fn test_literal() {
+        interpret("nil; true; false; 123; \"Hello, world!\";");
     }
 
     #[test]
@@ -127,16 +166,377 @@ mod test {
         const a = 1 + 2 * 3;  # declaring a variable
         const a = 100;        # redeclaring the const variable
         ";
-        let lexer = Lexer::new(input.to_string());
-        let mut parser = Parser::new(lexer);
-        let program = parser.parse_program().unwrap();
-        let mut interpretor = Interpretor::new();
-        interpretor.interpret(program);
+        let interpretor = interpret(input);
         let output = interpretor
             .environment
             .borrow_mut()
-            .get(&"a".to_string())
+            .get("a".into())
             .unwrap();
         assert_eq!(output, Object::Number(7.0));
     }
+
+    fn run(input: &str) -> Result<(), ErrorInfo> {
+        let lexer = Lexer::new(input.to_string());
+        let mut parser = Parser::new(lexer);
+        let mut program = parser.parse_program().unwrap();
+        Resolver::new().resolve(&mut program)?;
+        let mut interpretor = Interpretor::new();
+        for stmt in &program.stmts {
+            interpretor.exec_in(&program, *stmt)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let err = run("let length = 3; print lenght;").unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Name("lenght".to_string(), vec!["length".to_string()])
+        );
+        assert_eq!(
+            err.error.to_string(),
+            "NameError: undefined variable \"lenght\", did you mean \"length\"?"
+        );
+
+        let err = run("fn f() { let count = 1; { count_ = 2; } } f();").unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Name("count_".to_string(), vec!["count".to_string()])
+        );
+
+        let err = run("let apple = 1; print zebra;").unwrap_err();
+        assert_eq!(err.error, Error::Name("zebra".to_string(), vec![]));
+    }
+
+    #[test]
+    fn test_mutual_recursion() {
+        // `is_even` refers to `is_odd` before it is declared; global names are
+        // looked up when the call happens
+        let input = "
+        fn is_even(n) { if (n == 0) return true; return is_odd(n - 1); }
+        fn is_odd(n) { if (n == 0) return false; return is_even(n - 1); }
+        let a = is_even(10);
+        let b = is_odd(7);
+        let c;
+        {
+            fn ping(n) { if (n == 0) return \"ping\"; return pong(n - 1); }
+            fn pong(n) { if (n == 0) return \"pong\"; return ping(n - 1); }
+            c = ping(3);
+        }
+        ";
+        let interpretor = interpret(input);
+        assert_eq!(get(&interpretor, "a"), Object::Boolean(true));
+        assert_eq!(get(&interpretor, "b"), Object::Boolean(true));
+        assert_eq!(get(&interpretor, "c"), Object::String("pong".into()));
+    }
+
+    #[test]
+    fn test_mutual_recursion_typo() {
+        let input = "
+        fn is_even(n) { if (n == 0) return true; return is_od(n - 1); }
+        fn is_odd(n) { if (n == 0) return false; return is_even(n - 1); }
+        is_even(2);
+        ";
+        let err = run(input).unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Name("is_od".to_string(), vec!["is_odd".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_escaped_closure() {
+        let input = "
+        fn counter() {
+            let count = 0;
+            fn increment() { count = count + 1; return count; }
+            return increment;
+        }
+        let next = counter();
+        next();
+        let value = next();
+        ";
+        let interpretor = interpret(input);
+        assert_eq!(get(&interpretor, "value"), Object::Number(2.0));
+    }
+
+    #[test]
+    fn test_factory_scopes_released() {
+        let input = "
+        fn make() { fn rec(n) { if (n > 0) rec(n - 1); } return rec; }
+        for (let i = 0; i < 1000; i = i + 1) make()(3);
+        let last = make();
+        ";
+        let mut interpretor = interpret(input);
+        let Object::Function(function) = get(&interpretor, "last") else {
+            panic!("make() returns a function");
+        };
+        let Function::User {
+            closure: Closure::Strong(scope),
+            ..
+        } = function.as_ref()
+        else {
+            panic!("a function read out of a scope holds its closure");
+        };
+        // held by `last` alone, not by `rec` stored in it
+        assert_eq!(Rc::strong_count(scope), 1);
+        let scope = Rc::downgrade(scope);
+        drop(function);
+
+        let mut parser = Parser::new(Lexer::new("last = nil;".to_string()));
+        interpretor
+            .interpret(parser.parse_program().unwrap())
+            .unwrap();
+        assert!(scope.upgrade().is_none());
+    }
+
+    fn interpret_with_fuel(input: &str, fuel: u64) -> Result<(), ErrorInfo> {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let program = parser.parse_program().unwrap();
+        Interpretor::new().with_fuel(fuel).interpret(program)
+    }
+
+    #[test]
+    fn test_fuel_infinite_loop() {
+        let err = interpret_with_fuel("while (true) {} print \"unreachable\";", 1000).unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Runtime("execution budget exhausted".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fuel_recursion() {
+        let err = interpret_with_fuel("fn f(n) { return f(n + 1); } f(0);", 100).unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Runtime("execution budget exhausted".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fuel_sufficient() {
+        assert!(interpret_with_fuel("let a = 0; while (a < 10) { a = a + 1; }", 1000).is_ok());
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let input = "let s = \"x\"; while (true) { s = s + s; }";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let program = parser.parse_program().unwrap();
+        let mut interpretor = Interpretor::new().with_memory_limit(1 << 20);
+        let err = interpretor.interpret(program).unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Runtime("memory limit exceeded".to_string())
+        );
+
+        let input = "let s = \"x\" * 1000000000000;";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let program = parser.parse_program().unwrap();
+        let mut interpretor = Interpretor::new().with_memory_limit(1 << 20);
+        let err = interpretor.interpret(program).unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Runtime("memory limit exceeded".to_string())
+        );
+        assert!(interpretor.allocated() > 1 << 20);
+    }
+
+    #[test]
+    fn test_call_function() {
+        let mut interpretor = interpret(
+            "let calls = 0;
+            fn handle(event) { calls = calls + 1; return \"got \" + event; }
+            let answer = 42;",
+        );
+        for _ in 0..2 {
+            let value = interpretor.call_function("handle", &["tick".into()]);
+            assert_eq!(value, Ok("got tick".into()));
+        }
+        assert_eq!(get(&interpretor, "calls"), Object::Number(2.0));
+        assert_eq!(
+            interpretor.call_function("len", &["abc".into()]),
+            Ok(3.0.into())
+        );
+
+        let error = |name: &str, args: &[Object]| {
+            let mut interpretor = interpret("fn handle(event) {} let answer = 42;");
+            interpretor
+                .call_function(name, args)
+                .unwrap_err()
+                .error
+                .to_string()
+        };
+        assert_eq!(
+            error("answer", &[]),
+            "TypeError: 'answer' is a number rather than a function"
+        );
+        assert_eq!(
+            error("handel", &[]),
+            "NameError: undefined variable \"handel\", did you mean \"handle\"?"
+        );
+        assert_eq!(
+            error("handle", &[]),
+            "TypeError: handle() takes 1 arguments but 0 were given"
+        );
+        // a name the host made up is not interned by looking for it
+        assert_eq!(
+            error("hnadle", &[]),
+            "NameError: undefined variable \"hnadle\", did you mean \"handle\"?"
+        );
+        assert_eq!(Symbol::lookup("hnadle"), None);
+    }
+
+    #[test]
+    fn test_call_function_limits() {
+        let program = Parser::new(Lexer::new("fn f(n) { return f(n + 1); }".to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new().with_fuel(100);
+        interpretor.interpret(program).unwrap();
+        let err = interpretor.call_function("f", &[0.0.into()]).unwrap_err();
+        let exhausted = Error::Runtime("execution budget exhausted".to_string());
+        assert_eq!(err.error, exhausted);
+
+        let mut interpretor = interpret("fn f() { return 1; }");
+        interpretor.cancellation_token().cancel();
+        let err = interpretor.call_function("f", &[]).unwrap_err();
+        assert_eq!(err.error, Error::Interrupted);
+    }
+
+    #[test]
+    fn test_tail_call() {
+        // far deeper than the Rust stack allows without reusing the frame
+        let input = "
+        fn count(i, n) { if (i == n) return i; return count(i + 1, n); }
+        let a = count(0, 100000);
+        ";
+        let interpretor = interpret(input);
+        assert_eq!(get(&interpretor, "a"), Object::Number(100000.0));
+    }
+
+    #[test]
+    fn test_mutual_tail_call() {
+        let input = "
+        fn is_even(n) { if (n == 0) return true; return is_odd(n - 1); }
+        fn is_odd(n) { if (n == 0) return false; return is_even(n - 1); }
+        let a = is_even(100001);
+        ";
+        let interpretor = interpret(input);
+        assert_eq!(get(&interpretor, "a"), Object::Boolean(false));
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        let input = "fn depth(n) { if (n == 0) { return 0; } return 1 + depth(n - 1); }";
+        on_large_stack(move || {
+            let mut interpretor = interpret(input);
+            let too_deep = Object::Number(MAX_CALL_DEPTH as f64);
+            let err = interpretor.call_function("depth", &[too_deep]).unwrap_err();
+            assert_eq!(
+                err.error,
+                Error::Runtime("maximum recursion depth exceeded".to_string())
+            );
+            // the calls that failed are no longer counted
+            let limit = (MAX_CALL_DEPTH - 1) as f64;
+            let value = interpretor.call_function("depth", &[Object::Number(limit)]);
+            assert_eq!(value, Ok(Object::Number(limit)));
+        });
+
+        let mut interpretor = interpret(input).with_max_call_depth(10);
+        let mut depth = |n: f64| interpretor.call_function("depth", &[Object::Number(n)]);
+        assert!(depth(9.0).is_ok());
+        assert!(depth(10.0).is_err());
+    }
+
+    #[test]
+    fn test_closure_binding() {
+        // the closure keeps seeing the variable that was in scope when it was
+        // declared, even after the block declares a shadowing one
+        let input = "
+        let a = \"global\";
+        let first;
+        let second;
+        {
+            fn show() { return a; }
+            first = show();
+            let a = \"block\";
+            second = show();
+        }
+        ";
+        let interpretor = interpret(input);
+        assert_eq!(get(&interpretor, "first"), Object::String("global".into()));
+        assert_eq!(get(&interpretor, "second"), Object::String("global".into()));
+    }
+
+    #[test]
+    fn test_slot_per_iteration() {
+        // each pass of the loop runs its body in a scope of its own, so the
+        // closures keep the slots of the pass that declared them
+        let input = "
+        let first;
+        let second;
+        for (let i = 0; i < 2; i = i + 1) {
+            let j = i * 10;
+            fn get() { return j; }
+            if (i == 0) first = get; else second = get;
+        }
+        let a = first();
+        let b = second();
+        ";
+        let interpretor = interpret(input);
+        assert_eq!(get(&interpretor, "a"), Object::Number(0.0));
+        assert_eq!(get(&interpretor, "b"), Object::Number(10.0));
+
+        // a hoisted function has its slot before its declaration has run
+        let err = run("{ print f(); fn f() { return 1; } }").unwrap_err();
+        assert!(matches!(err.error, Error::Name(name, _) if name == "f"));
+    }
+
+    #[test]
+    fn test_local_assignment() {
+        let input = "
+        let a = 1;
+        let b;
+        {
+            let a = 10;
+            fn inc() { a = a + 1; }
+            inc();
+            inc();
+            b = a;
+        }
+        ";
+        let interpretor = interpret(input);
+        assert_eq!(get(&interpretor, "a"), Object::Number(1.0));
+        assert_eq!(get(&interpretor, "b"), Object::Number(12.0));
+    }
+
+    #[test]
+    fn test_globals_from_host() {
+        let program = |input: &str| {
+            Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap()
+        };
+        let mut interpretor = Interpretor::new();
+        interpretor
+            .set_global("width", Object::Number(3.0))
+            .unwrap();
+        interpretor
+            .set_global("height", Object::Number(4.0))
+            .unwrap();
+        interpretor
+            .interpret(program("let area = width * height; let height = 10;"))
+            .unwrap();
+        assert_eq!(interpretor.get_global("area"), Some(Object::Number(12.0)));
+        // the script's own declaration replaces the seeded value
+        assert_eq!(interpretor.get_global("height"), Some(Object::Number(10.0)));
+        assert_eq!(interpretor.get_global("missing"), None);
+
+        // globals carry over to the next program run by the same interpreter
+        interpretor.interpret(program("area = area + 1;")).unwrap();
+        assert_eq!(interpretor.get_global("area"), Some(Object::Number(13.0)));
+    }
 }
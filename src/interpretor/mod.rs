@@ -1,68 +1,616 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::RefCell,
+    io::{self, BufRead, Write},
+    rc::Rc,
+    sync::Arc,
+};
 
 use crate::{
-    ast::Program, object::Function, Environment, Error, ErrorInfo, Expr, LiteralType, Object, Stmt,
-    TokenType,
+    ast::Program, builtins, builtins::Rng, error::suggest, object::Arity, Ast, ColorChoice,
+    Compiler, Debugger, Environment, Error, ErrorFormat, ErrorInfo, ExprId, Lexer, LineIndex, Node,
+    Object, Parser, Resolver, Severity, Span, Stmt, StmtId, Symbol, Vm,
 };
 mod expr;
 mod stmt;
 
+mod cancellation;
+pub use cancellation::CancellationToken;
+
+mod stats;
+pub use stats::RunStats;
+pub(crate) use stats::Stopwatch;
+
+mod profile;
+pub use profile::{Profile, ProfileEntry};
+
+mod coverage;
+pub use coverage::Coverage;
+
+mod run;
+pub use run::{run_reader, run_source};
+
 pub struct Interpretor {
     pub globals: Rc<RefCell<Environment>>,
+    // read only scope enclosing the globals that holds the natives
+    pub(crate) builtins: Rc<RefCell<Environment>>,
     pub environment: Rc<RefCell<Environment>>,
-    pub locals: std::collections::HashMap<Expr, usize>,
+    // remaining number of statements and calls the program may execute
+    fuel: Option<u64>,
+    cancellation: CancellationToken,
+    // upper bound on the bytes of string data the program may create
+    memory_limit: Option<usize>,
+    allocated: usize,
+    // where `print` writes, stdout unless the host redirects it
+    pub(crate) output: Box<dyn Write>,
+    // where `input()` reads lines from
+    input: Input,
+    // lines of the program, for showing the offending line of reported errors
+    source: Option<Rc<LineIndex>>,
+    // whether errors reported with the source are colored
+    color: ColorChoice,
+    error_format: ErrorFormat,
+    // whether the file builtins may touch the filesystem
+    pub(crate) filesystem_access: bool,
+    // whether warnings stop the program before it runs
+    deny_warnings: bool,
+    errors_reported: usize,
+    // whether each statement is written to `trace_output` before it runs
+    trace: bool,
+    trace_output: Box<dyn Write>,
+    // where reported errors and warnings are written, stderr by default
+    pub(crate) diagnostics: Box<dyn Write>,
+    // number of calls to user functions being run, and how many may be
+    // before the next is an error rather than a stack overflow
+    pub(crate) call_depth: usize,
+    pub(crate) max_call_depth: usize,
+    stats: RunStats,
+    // calls made so far, while profiling
+    pub(crate) profile: Option<Profile>,
+    coverage: Option<Coverage>,
+    pub(crate) debugger: Option<Debugger>,
+    pub(crate) rng: Rng,
+}
+
+enum Input {
+    // read a line at a time so nothing past it is taken from stdin
+    Stdin,
+    Reader(Box<dyn BufRead>),
+    Disabled,
+}
+
+// Whether the interpreter may reach the process's stdio and filesystem
+// unless told otherwise. Built for wasm it only reaches what the host gives
+// it, printing and reporting nowhere and with `input()` and the file
+// builtins disabled, without a clock and with `random()` seeded the same
+// for every run.
+pub(crate) const HOSTED: bool = cfg!(not(feature = "wasm"));
+
+// Each call to a user function recurses on the native stack, through the
+// statements and expressions of its body, and calls of compiled functions
+// count against the same limit. This many simple calls take about 6MB of stack
+// in a release build and 30MB in a debug one, so hosts running the
+// interpreter on a smaller stack, like the 2MB of a spawned thread, should
+// lower it.
+pub(crate) const MAX_CALL_DEPTH: usize = 2000;
+
+// Runs `f` on a thread with the stack `MAX_CALL_DEPTH` calls need in a debug
+// build, for the tests recursing that deep.
+#[cfg(test)]
+pub(crate) fn on_large_stack<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
 }
 
 impl Interpretor {
     pub fn new() -> Self {
-        let globals = Rc::new(RefCell::new(Environment::new()));
-        let time = Object::Function(Function::Inbuilt {
-            arity: 0,
-            func: Box::new(|_args| {
-                let time = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_nanos() as f64;
-                Ok(Object::Number(time as f64))
-            }),
-        });
-        globals.borrow_mut().define("time".to_string(), time, true).unwrap();
+        let builtins = Rc::new(RefCell::new(Environment::new()));
+        builtins::register(&mut builtins.borrow_mut());
+        builtins.borrow_mut().freeze();
+        let globals = Rc::new(RefCell::new(Environment::new_from_closure(&builtins)));
         let environment = globals.clone();
         Self {
             globals,
+            builtins,
             environment,
-            locals: std::collections::HashMap::new(),
+            fuel: None,
+            cancellation: CancellationToken::new(),
+            memory_limit: None,
+            allocated: 0,
+            output: stdio(io::stdout()),
+            input: if HOSTED {
+                Input::Stdin
+            } else {
+                Input::Disabled
+            },
+            source: None,
+            color: ColorChoice::Auto,
+            error_format: ErrorFormat::Human,
+            filesystem_access: HOSTED,
+            deny_warnings: false,
+            errors_reported: 0,
+            trace: false,
+            trace_output: stdio(io::stderr()),
+            diagnostics: stdio(io::stderr()),
+            call_depth: 0,
+            max_call_depth: MAX_CALL_DEPTH,
+            stats: RunStats::default(),
+            profile: None,
+            coverage: None,
+            debugger: None,
+            rng: Rng::from_time(),
+        }
+    }
+
+    // An interpreter for untrusted programs, with the builtins reaching the
+    // files, the environment, stdin and `sleep()` replaced by ones raising
+    // `Error::NotPermitted`, and with both limits set, as a sandbox without
+    // them could still be made to run or allocate forever. It keeps the
+    // default call depth limit too, which holds for compiled functions as
+    // well, as nothing can stop a program that overflows the stack.
+    pub fn sandboxed(fuel: u64, memory_limit: usize) -> Self {
+        let interpretor = Self::new()
+            .with_fuel(fuel)
+            .with_memory_limit(memory_limit)
+            .with_max_call_depth(MAX_CALL_DEPTH)
+            .without_input()
+            .with_filesystem_access(false);
+        builtins::sandbox(&mut interpretor.builtins.borrow_mut());
+        interpretor
+    }
+
+    // Bounds the number of statements and function calls the interpreter
+    // executes, after which evaluation stops with a runtime error.
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    // Sets how many calls to user and compiled functions may be running at
+    // once, past which a call fails with "maximum recursion depth exceeded".
+    // Calls in tail position replace the caller, so they do not count. Hosts
+    // running on a larger stack can raise it, and ones on a smaller stack
+    // should lower it.
+    pub fn with_max_call_depth(mut self, depth: usize) -> Self {
+        self.max_call_depth = depth;
+        self
+    }
+
+    // counts a call, on either backend, failing past `max_call_depth`
+    pub(crate) fn enter_call(&mut self, span: &Span) -> Result<(), ErrorInfo> {
+        if self.call_depth == self.max_call_depth {
+            let error = Error::Runtime("maximum recursion depth exceeded".to_string());
+            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+        }
+        self.call_depth += 1;
+        Ok(())
+    }
+
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    // Bounds the total number of bytes of string data created while running,
+    // after which evaluation stops with a runtime error. The count only ever
+    // grows, so it is an estimate of allocation pressure rather than of the
+    // memory live at any one time.
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    pub fn allocated(&self) -> usize {
+        self.allocated
+    }
+
+    // Sends the output of `print` to `output` instead of stdout. Output is
+    // flushed once the program finishes, and a failed write is a runtime
+    // error of the statement doing the printing.
+    pub fn with_output(mut self, output: Box<dyn Write>) -> Self {
+        self.output = output;
+        self
+    }
+
+    // Seeds the generator behind `random()` and `random_int()` so runs are
+    // reproducible; otherwise it is seeded from the clock, or with a fixed
+    // seed when built for wasm.
+    pub fn with_rng_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    // Makes `input()` read its lines from `input` instead of stdin.
+    pub fn with_input(mut self, input: Box<dyn BufRead>) -> Self {
+        self.input = Input::Reader(input);
+        self
+    }
+
+    // Makes every call to `input()` a runtime error.
+    pub fn without_input(mut self) -> Self {
+        self.input = Input::Disabled;
+        self
+    }
+
+    // Hands over stdin, stdout, stderr and the filesystem, as the command
+    // line does. These are the defaults except when built for wasm.
+    pub fn with_stdio(mut self) -> Self {
+        self.input = Input::Stdin;
+        self.output = Box::new(io::stdout());
+        self.trace_output = Box::new(io::stderr());
+        self.diagnostics = Box::new(io::stderr());
+        self.filesystem_access = true;
+        self
+    }
+
+    // Allows or forbids `read_file()`, `write_file()`, `append_file()` and
+    // `file_exists()`, which raise a runtime error while forbidden. Access is
+    // allowed unless the host turns it off, as it should for untrusted code.
+    pub fn with_filesystem_access(mut self, allowed: bool) -> Self {
+        self.filesystem_access = allowed;
+        self
+    }
+
+    // Makes warnings stop the program before it runs, once they have all
+    // been reported, as a CI run would want. By default they are reported
+    // and the program runs anyway.
+    pub fn with_deny_warnings(mut self, deny: bool) -> Self {
+        self.deny_warnings = deny;
+        self
+    }
+
+    // Gives the text of the programs about to run so reported errors show
+    // the line they point at.
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.set_source(source);
+        self
+    }
+
+    // `with_source` for the next program to run on the same interpreter
+    pub fn set_source(&mut self, source: &str) {
+        self.source = Some(Rc::new(LineIndex::new(source)));
+    }
+
+    // `set_source` with the lines of a source already indexed, such as by
+    // the parser of the program
+    pub fn set_line_index(&mut self, index: Rc<LineIndex>) {
+        self.source = Some(index);
+    }
+
+    // the lines of the source reported errors show, if there is one
+    pub fn line_index(&self) -> Option<Rc<LineIndex>> {
+        self.source.clone()
+    }
+
+    // Reports errors and warnings as one JSON object per line instead of
+    // rendering them for people.
+    pub fn with_error_format(mut self, format: ErrorFormat) -> Self {
+        self.error_format = format;
+        self
+    }
+
+    // Colors the errors reported with the source always, never, or by
+    // default only when stderr is a terminal and NO_COLOR is not set.
+    pub fn with_color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    // Writes each statement to stderr, or where `with_trace_output` says,
+    // on one line before running it, after its line number and indented by
+    // the depth of calls it is in. Only the tree-walker traces.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    // Counts the calls of every function and the time they take from now
+    // on, or stops and forgets what was counted.
+    pub fn set_profile(&mut self, profile: bool) {
+        self.profile = profile.then(Profile::default);
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    // Counts the lines the programs run from now on hold, and which of them
+    // the tree-walker runs, or stops and forgets what was counted.
+    pub fn set_coverage(&mut self, coverage: bool) {
+        self.coverage = coverage.then(Coverage::default);
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn with_trace_output(mut self, output: Box<dyn Write>) -> Self {
+        self.trace_output = output;
+        self
+    }
+
+    // Writes the errors and warnings `interpret` reports to `output`
+    // instead of stderr, in the format `with_error_format` picks.
+    pub fn with_diagnostics(mut self, output: Box<dyn Write>) -> Self {
+        self.diagnostics = output;
+        self
+    }
+
+    // Defines a global visible to every program run afterwards, which the
+    // program may redeclare with `let` like any other global. A name of a
+    // builtin is shadowed rather than replaced.
+    pub fn set_global(&mut self, name: &str, value: Object) -> Result<(), Error> {
+        self.globals
+            .borrow_mut()
+            .define(Symbol::intern(name), value, false)
+    }
+
+    // Defines a native function called `name` for every program run
+    // afterwards, taking as many arguments as `arity` allows, where it can
+    // be passed around like any other function. A call with another number
+    // of arguments is a runtime error, and so is an error `func` returns,
+    // raised at the call. It is defined among the builtins, which are
+    // defined this way too, so `reset()` keeps it.
+    pub fn register_native(
+        &mut self,
+        name: &str,
+        arity: impl Into<Arity>,
+        func: impl Fn(&[Object]) -> Result<Object, Error> + 'static,
+    ) {
+        builtins::define_native(&mut self.builtins.borrow_mut(), name, arity, func);
+    }
+
+    // `register_native` for a function that needs the interpreter calling
+    // it, such as one calling back into the program with `invoke`.
+    pub fn register_native_with_interpretor(
+        &mut self,
+        name: &str,
+        arity: impl Into<Arity>,
+        func: impl Fn(&mut Interpretor, &[Object]) -> Result<Object, Error> + 'static,
+    ) {
+        builtins::define(&mut self.builtins.borrow_mut(), name, arity, func);
+    }
+
+    // The value of a global after running a program, or None if it was never
+    // defined.
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        let name = Symbol::lookup(name)?;
+        self.globals.borrow_mut().get(name).ok()
+    }
+
+    // Calls the global function `name` with `args` and returns what it
+    // returns, as a call in a program would, under the same fuel, memory
+    // and cancellation limits. A name that is not defined or not a
+    // function is an error.
+    pub fn call_function(&mut self, name: &str, args: &[Object]) -> Result<Object, ErrorInfo> {
+        let span = Span::default();
+        // a name never interned is defined nowhere, and is not worth keeping
+        let callee = match Symbol::lookup(name) {
+            Some(name) => self.globals.borrow_mut().get(name),
+            None => Err(Error::Name(name.to_string(), Vec::new())),
+        };
+        match callee {
+            Ok(function @ Object::Function(_)) => self.invoke(&function, args),
+            Ok(value) => {
+                let error = Error::Type(format!(
+                    "'{name}' is a {} rather than a function",
+                    value.type_name()
+                ));
+                Err(ErrorInfo::new_with_span(error, span))
+            }
+            Err(e) => Err(ErrorInfo::new_with_span(self.with_suggestions(e), span)),
+        }
+    }
+
+    // Calls `function`, such as a callback a program handed to a native,
+    // with `args` the way `call_function` does. A native may call it while
+    // it runs, and the program may in turn call natives again.
+    pub fn invoke(&mut self, function: &Object, args: &[Object]) -> Result<Object, ErrorInfo> {
+        let span = Span::default();
+        let Object::Function(function) = function else {
+            let error = Error::Type(format!("{function} is not callable"));
+            return Err(ErrorInfo::new_with_span(error, span));
+        };
+        function.check_arity(args.len(), &span)?;
+        self.consume_fuel(&span)?;
+        self.check_cancelled(&span)?;
+        let result = function.call(self, args, &span);
+        self.flush()?;
+        result
+    }
+
+    // Drops every global, whether a program or `set_global` defined it,
+    // leaving only the builtins.
+    pub fn reset(&mut self) {
+        self.globals.borrow_mut().clear();
+        self.environment = self.globals.clone();
+    }
+
+    // Number of errors `interpret` and `run_compiled` have reported and
+    // carried on after, warnings aside.
+    pub fn errors_reported(&self) -> usize {
+        self.errors_reported
+    }
+
+    pub fn stats(&self) -> &RunStats {
+        &self.stats
+    }
+
+    // Parses `source`, naming it `name` in diagnostics, and counts the time
+    // taken in the stats. The errors reported from then on show the lines
+    // of `source`, as they would after `set_source`.
+    pub fn parse(&mut self, source: &str, name: &str) -> Result<Program, Vec<ErrorInfo>> {
+        let started = Stopwatch::start();
+        let lexer = Lexer::from_str(source).with_name(name);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program();
+        self.stats.parse += started.elapsed();
+        self.source = Some(parser.line_index());
+        if let Some(coverage) = &mut self.coverage {
+            coverage.add_source(name, source);
+        }
+        program
+    }
+
+    // Returns a handle that can stop this interpreter from another thread.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    // Runs every top level statement, reporting errors as they happen and
+    // carrying on with the next statement. Errors that stop the whole program
+    // (resolution errors, denied warnings, an exhausted budget or memory limit, cancellation,
+    // and `Error::Exit` from a call to `exit()`) are returned instead.
+    pub fn interpret(&mut self, mut program: Program) -> Result<(), ErrorInfo> {
+        self.resolve(&mut program)?;
+        if let Some(coverage) = &mut self.coverage {
+            coverage.add_program(&program.ast, &program.stmts);
+        }
+        let started = Stopwatch::start();
+        let result = self.run_stmts(&program);
+        self.stats.evaluate += started.elapsed();
+        result
+    }
+
+    fn run_stmts(&mut self, program: &Program) -> Result<(), ErrorInfo> {
+        for stmt in &program.stmts {
+            if let Err(err) = self.exec_in(program, *stmt) {
+                if self.is_halted() || matches!(err.error, Error::Exit(_)) {
+                    return Err(err);
+                }
+                self.report(&err);
+            }
         }
+        self.flush()
+    }
+
+    // Same as `interpret` but runs the program on the bytecode `Vm`. The
+    // whole program is compiled before anything runs, so compile errors stop
+    // it the way resolution errors do.
+    pub fn run_compiled(&mut self, mut program: Program) -> Result<(), ErrorInfo> {
+        self.resolve(&mut program)?;
+        let started = Stopwatch::start();
+        let result = self.run_chunks(&program);
+        self.stats.evaluate += started.elapsed();
+        result
     }
 
-    pub fn interpret(&mut self, program: Program) {
-        for stmt in program.stmts {
-            let res = self.exec(&stmt);
-            if res.is_err() {
-                res.err().unwrap().report();
+    fn run_chunks(&mut self, program: &Program) -> Result<(), ErrorInfo> {
+        let chunks = program
+            .stmts
+            .iter()
+            .map(|stmt| Compiler::compile(&program.ast, std::slice::from_ref(stmt)).map(Rc::new))
+            .collect::<Result<Vec<_>, _>>()?;
+        for chunk in chunks {
+            self.consume_fuel(&Span::default())?;
+            if let Err(err) = Vm::new(self).run(chunk) {
+                if self.is_halted() || matches!(err.error, Error::Exit(_)) {
+                    return Err(err);
+                }
+                self.report(&err);
             }
         }
+        self.flush()
+    }
+
+    // Evaluates `expr` of `program`, which should have been resolved, as a
+    // statement holding it would.
+    pub fn eval_in(&mut self, program: &Program, expr: ExprId) -> Result<Object, ErrorInfo> {
+        self.eval(&program.ast, expr)
+    }
+
+    // Runs `stmt` of `program`, which should have been resolved, handing
+    // back its error rather than reporting it as `interpret` does.
+    pub fn exec_in(&mut self, program: &Program, stmt: StmtId) -> Result<(), ErrorInfo> {
+        self.exec(&program.ast, stmt)
+    }
+
+    // `ast` is the tree `expr` is a node of, passed down rather than kept on
+    // the interpreter since the visitors borrow it mutably
+    pub(crate) fn eval(&mut self, ast: &Arc<Ast>, expr: ExprId) -> Result<Object, ErrorInfo> {
+        ast[expr].accept(ast, self)
+    }
+
+    pub(crate) fn exec(&mut self, ast: &Arc<Ast>, stmt: StmtId) -> Result<(), ErrorInfo> {
+        self.consume_fuel(&Span::default())?;
+        self.stats.statements += 1;
+        if self.trace {
+            self.trace(ast.node(stmt));
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.hit(ast, stmt);
+        }
+        if self.debugger.is_some() {
+            self.debug(ast.node(stmt))?;
+        }
+        ast[stmt].accept(ast, self)
+    }
+
+    // a block is left out as the statements in it are traced
+    fn trace(&mut self, stmt: Node<'_, StmtId>) {
+        let ast = stmt.ast;
+        if let Stmt::Block { .. } = ast[stmt.id] {
+            return;
+        }
+        let line = ast[stmt.id]
+            .span(ast)
+            .map_or("?".to_string(), |span| span.line.to_string());
+        let indent = "  ".repeat(self.call_depth);
+        // tracing is a debugging aid, so failing to write it is not an error
+        let _ = writeln!(self.trace_output, "{indent}{line}: {}", stmt.summary());
     }
 
-    pub fn eval(&mut self, expr: &Expr) -> Result<Object, ErrorInfo> {
-        expr.accept(self)
+    // defines `name` in the current scope, at the slot the resolver gave a
+    // local and by name otherwise
+    fn declare(
+        &mut self,
+        name: Symbol,
+        value: Object,
+        is_const: bool,
+        slot: Option<usize>,
+    ) -> Result<(), Error> {
+        let mut environment = self.environment.borrow_mut();
+        match slot {
+            Some(slot) => environment.define_at(slot, name, value, is_const),
+            None => environment.define(name, value, is_const),
+        }
+    }
+
+    pub(crate) fn eval_arguments(
+        &mut self,
+        ast: &Arc<Ast>,
+        args: &[ExprId],
+    ) -> Result<Vec<Object>, ErrorInfo> {
+        args.iter().map(|arg| self.eval(ast, *arg)).collect()
     }
 
-    pub fn exec(&mut self, stmt: &Stmt) -> Result<(), ErrorInfo> {
-        stmt.accept(self)
+    pub fn call(
+        &mut self,
+        callee: Object,
+        arguments: &[Object],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        match callee {
+            Object::Function(f) => f.call(self, arguments, span),
+            x => Err(ErrorInfo::new_with_span(
+                Error::Type(format!("{x} is not callable")),
+                span.to_owned(),
+            )),
+        }
     }
 
-    pub fn exec_block(
+    pub(crate) fn exec_block(
         &mut self,
-        stmts: &Vec<Stmt>,
+        ast: &Arc<Ast>,
+        stmts: &[StmtId],
         environment: Rc<RefCell<Environment>>,
     ) -> Result<(), ErrorInfo> {
-        let parent = self.environment.clone();
-        self.environment = environment;
+        let parent = std::mem::replace(&mut self.environment, environment);
 
         let result = (|| -> Result<(), ErrorInfo> {
             for stmt in stmts {
-                self.exec(stmt)?;
+                self.exec(ast, *stmt)?;
             }
             Ok(())
         })();
@@ -72,8 +620,212 @@ impl Interpretor {
     }
 }
 
+impl Interpretor {
+    pub(crate) fn consume_fuel(&mut self, span: &Span) -> Result<(), ErrorInfo> {
+        match self.fuel {
+            Some(0) => {
+                let error = Error::Runtime("execution budget exhausted".to_string());
+                Err(ErrorInfo::new_with_span(error, span.to_owned()))
+            }
+            Some(fuel) => {
+                self.fuel = Some(fuel - 1);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn allocate(&mut self, bytes: usize, span: &Span) -> Result<(), ErrorInfo> {
+        self.reserve(bytes)
+            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+    }
+
+    // `allocate` for natives, whose errors get the span of their call
+    pub(crate) fn reserve(&mut self, bytes: usize) -> Result<(), Error> {
+        self.allocated = self.allocated.saturating_add(bytes);
+        match self.memory_limit {
+            Some(limit) if self.allocated > limit => {
+                Err(Error::Runtime("memory limit exceeded".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // writes `values` separated by spaces and followed by `end`
+    pub(crate) fn print(
+        &mut self,
+        values: &[Object],
+        end: &str,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        self.write_values(values, end)
+            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+    }
+
+    pub(crate) fn write_values(&mut self, values: &[Object], end: &str) -> Result<(), Error> {
+        let mut line = String::new();
+        for (i, value) in values.iter().enumerate() {
+            if i > 0 {
+                line.push(' ');
+            }
+            line.push_str(&value.to_string());
+        }
+        line.push_str(end);
+        self.output.write_all(line.as_bytes()).map_err(output_error)
+    }
+
+    // Next line of input without its line ending, or None at the end of the
+    // input. The prompt is written to the output first.
+    pub(crate) fn read_line(&mut self, prompt: Option<&Object>) -> Result<Option<String>, Error> {
+        if let Input::Disabled = self.input {
+            return Err(Error::Runtime("input() is disabled".to_string()));
+        }
+        if let Some(prompt) = prompt {
+            write!(self.output, "{prompt}")
+                .and_then(|()| self.output.flush())
+                .map_err(output_error)?;
+        }
+        let mut line = String::new();
+        let read = match &mut self.input {
+            Input::Stdin => io::stdin().read_line(&mut line),
+            Input::Reader(reader) => reader.read_line(&mut line),
+            Input::Disabled => unreachable!(),
+        };
+        match read {
+            Ok(0) => Ok(None),
+            Ok(_) => {
+                let trimmed = line.strip_suffix('\n').unwrap_or(&line);
+                Ok(Some(
+                    trimmed.strip_suffix('\r').unwrap_or(trimmed).to_string(),
+                ))
+            }
+            Err(e) => Err(Error::Runtime(format!("failed to read input: {e}"))),
+        }
+    }
+
+    // resolves the program and reports its warnings
+    fn resolve(&mut self, program: &mut Program) -> Result<(), ErrorInfo> {
+        let started = Stopwatch::start();
+        let mut resolver = Resolver::new();
+        let result = resolver.resolve(program);
+        self.stats.resolve += started.elapsed();
+        result?;
+        let warnings = resolver.warnings();
+        for warning in warnings {
+            self.report(warning);
+        }
+        if self.deny_warnings && !warnings.is_empty() {
+            let error = Error::WarningsDenied(warnings.len());
+            return Err(ErrorInfo::new_with_span(error, Span::default()));
+        }
+        Ok(())
+    }
+
+    fn report(&mut self, err: &ErrorInfo) {
+        if err.severity() == Severity::Error {
+            self.errors_reported += 1;
+        }
+        let index = self.source.as_deref();
+        let text = match (self.error_format, index) {
+            (ErrorFormat::Json, _) => err.to_json_with_index(index),
+            (ErrorFormat::Human, Some(index)) => err.render_with_index(index, self.color),
+            (ErrorFormat::Human, None) => err.to_string(),
+        };
+        // like the trace, failing to write a diagnostic is not an error
+        let _ = writeln!(self.diagnostics, "{text}");
+    }
+
+    fn flush(&mut self) -> Result<(), ErrorInfo> {
+        self.output
+            .flush()
+            .map_err(|e| ErrorInfo::new_with_span(output_error(e), Span::default()))
+    }
+
+    // whether one of the host imposed limits has stopped the program
+    fn is_halted(&self) -> bool {
+        self.fuel == Some(0)
+            || self.cancellation.is_cancelled()
+            || self
+                .memory_limit
+                .is_some_and(|limit| self.allocated > limit)
+    }
+
+    pub(crate) fn check_cancelled(&self, span: &Span) -> Result<(), ErrorInfo> {
+        if self.cancellation.is_cancelled() {
+            Err(ErrorInfo::new_with_span(
+                Error::Interrupted,
+                span.to_owned(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    // fills in "did you mean" candidates for an undefined name from the
+    // names visible in the current scope
+    pub(crate) fn with_suggestions(&self, error: Error) -> Error {
+        match error {
+            Error::Name(name, _) => {
+                let names = self.environment.borrow().names();
+                let suggestions = suggest(&name, names.iter().map(String::as_str));
+                Error::Name(name, suggestions)
+            }
+            error => error,
+        }
+    }
+}
+
+// `stream`, or somewhere that discards what is written when built for wasm
+fn stdio(stream: impl Write + 'static) -> Box<dyn Write> {
+    if HOSTED {
+        Box::new(stream)
+    } else {
+        Box::new(io::sink())
+    }
+}
+
+fn output_error(error: io::Error) -> Error {
+    Error::Runtime(format!("failed to write output: {error}"))
+}
+
+// the error for a form the parser reads but the tree-walker cannot run yet
+fn unsupported(feature: &str, span: &Span) -> ErrorInfo {
+    let error = Error::Runtime(format!("{feature} not supported by the interpreter"));
+    ErrorInfo::new_with_span(error, span.to_owned())
+}
+
+impl Drop for Interpretor {
+    fn drop(&mut self) {
+        // a closure assigned to a variable outside the scope it captures can
+        // still keep the globals alive
+        self.globals.borrow_mut().clear();
+        let _ = self.output.flush();
+    }
+}
+
 impl Default for Interpretor {
     fn default() -> Self {
         Self::new()
     }
 }
+
+// writer that keeps what was printed readable after the interpreter that
+// owns it is gone, for the tests and for hosts collecting the output
+#[derive(Clone, Default)]
+pub struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl Capture {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use std::{collections::HashMap, fmt, time::Duration};
+
+use super::Stopwatch;
+
+// Calls and time of every function called while profiling, scripts' and
+// natives' alike, told apart by name. The time of a call includes that of
+// the calls it makes, and a function calling itself is only timed by its
+// outermost call, so recursion does not count the same time twice.
+#[derive(Debug, Default)]
+pub struct Profile {
+    entries: HashMap<String, ProfileEntry>,
+    // calls of each function still running
+    running: HashMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProfileEntry {
+    pub calls: u64,
+    pub total: Duration,
+}
+
+impl ProfileEntry {
+    pub fn average(&self) -> Duration {
+        self.total
+            .checked_div(self.calls.try_into().unwrap_or(u32::MAX))
+            .unwrap_or_default()
+    }
+}
+
+impl Profile {
+    // Counts a call of `name` starting now, and returns whether it is the
+    // outermost one running.
+    pub(crate) fn enter(&mut self, name: &str) -> bool {
+        self.entries.entry(name.to_string()).or_default().calls += 1;
+        let running = self.running.entry(name.to_string()).or_default();
+        *running += 1;
+        *running == 1
+    }
+
+    pub(crate) fn exit(&mut self, name: &str, started: Stopwatch, outermost: bool) {
+        if let Some(running) = self.running.get_mut(name) {
+            *running -= 1;
+        }
+        if outermost {
+            if let Some(entry) = self.entries.get_mut(name) {
+                entry.total += started.elapsed();
+            }
+        }
+    }
+
+    // every function called, taking the most time first
+    pub fn entries(&self) -> Vec<(&str, ProfileEntry)> {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(name, entry)| (name.as_str(), *entry))
+            .collect();
+        entries.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(b.0)));
+        entries
+    }
+}
+
+// a table of the entries, a row each under a header
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.entries();
+        let width = entries
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["function".len()])
+            .max()
+            .unwrap_or_default();
+        writeln!(
+            f,
+            "{:<width$} {:>8} {:>12} {:>12}",
+            "function", "calls", "total", "average"
+        )?;
+        for (name, entry) in entries {
+            let (total, average) = (
+                format!("{:.3?}", entry.total),
+                format!("{:.3?}", entry.average()),
+            );
+            writeln!(
+                f,
+                "{name:<width$} {:>8} {total:>12} {average:>12}",
+                entry.calls
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{Interpretor, Lexer, Parser};
+
+    // times `sleep()`, which is not available when built for wasm
+    #[cfg(not(feature = "wasm"))]
+    fn profile(input: &str) -> Interpretor {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new().with_output(Box::new(io::sink()));
+        interpretor.set_profile(true);
+        interpretor.interpret(program).unwrap();
+        interpretor
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn test_profile() {
+        let interpretor = profile(
+            "fn slow() { sleep(20); }
+            fn fib(n) { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); }
+            slow(); slow(); print fib(10);",
+        );
+        let profile = interpretor.profile().unwrap();
+        let entries = profile.entries();
+        let calls: Vec<(&str, u64)> = entries.iter().map(|(name, e)| (*name, e.calls)).collect();
+        assert_eq!(calls, [("slow", 2), ("sleep", 2), ("fib", 177)]);
+        let (slow, sleep) = (entries[0].1, entries[1].1);
+        assert!(slow.total >= sleep.total && sleep.total.as_millis() >= 40);
+        // timed once however deep the recursion goes
+        assert!(entries[2].1.total < sleep.total);
+        assert_eq!(slow.average(), slow.total / 2);
+
+        let table = profile.to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("function    calls"), "{table}");
+        assert!(lines[3].starts_with("fib           177"), "{table}");
+    }
+
+    #[test]
+    fn test_off() {
+        let program = Parser::new(Lexer::new("print 1;".to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new().with_output(Box::new(io::sink()));
+        interpretor.interpret(program).unwrap();
+        assert!(interpretor.profile().is_none());
+    }
+}
@@ -0,0 +1,58 @@
+use std::io::Read;
+
+use crate::{Error, ErrorInfo, Interpretor, Span};
+
+// what errors in a source without a name say they come from
+const SOURCE_NAME: &str = "<input>";
+
+// Parses `source` and runs it on `interpretor`, which reports the errors
+// it carries on after as `interpret` does. The errors returned are those
+// that kept it from running: every syntax error, or the one that stopped
+// it part way.
+pub fn run_source(source: &str, interpretor: &mut Interpretor) -> Result<(), Vec<ErrorInfo>> {
+    let program = interpretor.parse(source, SOURCE_NAME)?;
+    interpretor.interpret(program).map_err(|err| vec![err])
+}
+
+// `run_source` for a program read from `reader` to its end, where failing
+// to read it, or it not being UTF-8, is an error too.
+pub fn run_reader(
+    mut reader: impl Read,
+    interpretor: &mut Interpretor,
+) -> Result<(), Vec<ErrorInfo>> {
+    let mut source = String::new();
+    if let Err(e) = reader.read_to_string(&mut source) {
+        let error = Error::Runtime(format!("failed to read the program: {e}"));
+        return Err(vec![ErrorInfo::new_with_span(error, Span::default())]);
+    }
+    run_source(&source, interpretor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_reader, run_source};
+    use crate::{interpretor::Capture, Error, Interpretor};
+
+    #[test]
+    fn test_run_source() {
+        let output = Capture::default();
+        let mut interpretor = Interpretor::new().with_output(Box::new(output.clone()));
+        run_source("let a = 1 + 2;", &mut interpretor).unwrap();
+        run_reader("print a * 2;".as_bytes(), &mut interpretor).unwrap();
+        assert_eq!(output.text(), "6\n");
+
+        let errors = run_source("let = 1;\nprint (;", &mut interpretor).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].file(), Some("<input>"));
+        let errors = run_source("exit(2);", &mut interpretor).unwrap_err();
+        assert_eq!(errors[0].error, Error::Exit(2));
+    }
+
+    #[test]
+    fn test_unreadable() {
+        let mut interpretor = Interpretor::new();
+        let errors = run_reader(&[0xff, 0xfe][..], &mut interpretor).unwrap_err();
+        let message = "failed to read the program: stream did not contain valid UTF-8";
+        assert_eq!(errors[0].error, Error::Runtime(message.to_string()));
+    }
+}
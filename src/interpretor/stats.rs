@@ -0,0 +1,57 @@
+use std::time::{Duration, Instant};
+
+use super::HOSTED;
+
+// Where the time of the programs run so far went, summed over every one of
+// them, for hosts timing their scripts.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunStats {
+    // lexing and parsing, by `Interpretor::parse`
+    pub parse: Duration,
+    pub resolve: Duration,
+    // running the program, including compiling it for the vm
+    pub evaluate: Duration,
+    // statements the tree-walker has executed
+    pub statements: u64,
+}
+
+// Times what the interpreter does, except when built for wasm, where std
+// has no clock and panics when asked for one, and everything takes no time.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Stopwatch(Option<Instant>);
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Self(HOSTED.then(Instant::now))
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.0.map(|started| started.elapsed()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{interpretor::HOSTED, Interpretor};
+
+    #[test]
+    fn test_stats() {
+        let source = "fn fib(n) { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); }\n"
+            .repeat(50)
+            + "let i = 0; while (i < 3) { i = i + 1; } print fib(10);";
+        let mut interpretor = Interpretor::new().with_output(Box::new(io::sink()));
+        let program = interpretor.parse(&source, "stats.rl").unwrap();
+        interpretor.interpret(program).unwrap();
+        let stats = *interpretor.stats();
+        // built for wasm there is no clock to time them with
+        assert_eq!(stats.parse.is_zero(), !HOSTED);
+        assert_eq!(stats.resolve.is_zero(), !HOSTED);
+        assert_eq!(stats.evaluate.is_zero(), !HOSTED);
+        // 50 declarations, let, while, print, 3 times the loop's block and
+        // its assignment, then 177 calls running an if and a return each,
+        // with the block inside the if for 89 of them
+        assert_eq!(stats.statements, 50 + 3 + 3 * 2 + 177 * 2 + 89);
+    }
+}
@@ -1,60 +1,102 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, sync::Arc};
 
+use super::unsupported;
 use crate::{
-    object::Function, visitor, Environment, Error, ErrorInfo, Expr, Interpretor, Object, Span, Stmt,
+    object::{Closure, Declaration, Function},
+    visitor, Ast, Environment, Error, ErrorInfo, Expr, ExprId, Interpretor, Object, Span, StmtId,
+    Symbol,
 };
 
 impl visitor::Stmt for Interpretor {
-    fn visit_print_stmt(&mut self, expr: &Expr) -> Result<(), ErrorInfo> {
-        let out = self.eval(expr)?;
-        println!("{}", out);
-        Ok(())
+    fn visit_print_stmt(
+        &mut self,
+        ast: &Arc<Ast>,
+        exprs: &[ExprId],
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        let values = self.eval_arguments(ast, exprs)?;
+        self.print(&values, "\n", span)
     }
 
-    fn visit_expr_stmt(&mut self, expr: &Expr) -> Result<(), ErrorInfo> {
-        self.eval(expr)?;
+    fn visit_expr_stmt(&mut self, ast: &Arc<Ast>, expr: ExprId) -> Result<(), ErrorInfo> {
+        self.eval(ast, expr)?;
         Ok(())
     }
 
     fn visit_let_stmt(
         &mut self,
-        name: &String,
-        value: &Option<Expr>,
+        ast: &Arc<Ast>,
+        name: Symbol,
+        value: Option<ExprId>,
         is_const: bool,
         span: &Span,
+        slot: Option<usize>,
     ) -> Result<(), ErrorInfo> {
         let value = value
-            .as_ref()
-            .map(|v| self.eval(v))
+            .map(|v| self.eval(ast, v))
             .unwrap_or(Ok(Object::Nil))?;
 
-        self.environment
-            .borrow_mut()
-            .define(name.to_owned(), value, is_const)
+        self.declare(name, value, is_const, slot)
             .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
     }
 
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &Box<Stmt>) -> Result<(), ErrorInfo> {
-        let mut flag = self.eval(condition)?;
-        while flag.to_boolean() {
-            self.exec(body)?;
-            flag = self.eval(condition)?;
+    fn visit_while_stmt(
+        &mut self,
+        ast: &Arc<Ast>,
+        condition: ExprId,
+        body: StmtId,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        let mut flag = self.eval(ast, condition)?;
+        while flag.is_truthy() {
+            self.check_cancelled(span)?;
+            self.exec(ast, body)?;
+            flag = self.eval(ast, condition)?;
         }
         Ok(())
     }
 
-    fn visit_return_stmt(&mut self, value: &Option<Expr>, span: &Span) -> Result<(), ErrorInfo> {
-        Err(ErrorInfo::new_with_span(
-            Error::Return(if let Some(expr) = value {
-                self.eval(expr)?
-            } else {
-                Object::Nil
-            }),
-            span.to_owned(),
-        ))
+    fn visit_return_stmt(
+        &mut self,
+        ast: &Arc<Ast>,
+        value: Option<ExprId>,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        let Some(value) = value else {
+            return Err(ErrorInfo::new_with_span(
+                Error::Return(Object::Nil),
+                span.to_owned(),
+            ));
+        };
+        let error = match &ast[value] {
+            // a call in tail position is handed back to `Function::call`,
+            // which runs it in place of the current frame instead of
+            // nesting another one on the Rust stack
+            Expr::Call {
+                callee,
+                args,
+                span: call_span,
+            } => {
+                self.consume_fuel(call_span)?;
+                self.check_cancelled(call_span)?;
+                let callee = self.eval(ast, *callee)?;
+                let arguments = self.eval_arguments(ast, args)?;
+                match callee {
+                    // at the call, for the errors of the function it calls
+                    Object::Function(function) if function.is_user() => {
+                        let error = Error::TailCall(function, arguments);
+                        return Err(ErrorInfo::new_with_span(error, call_span.to_owned()));
+                    }
+                    callee => Error::Return(self.call(callee, &arguments, call_span)?),
+                }
+            }
+            _ => Error::Return(self.eval(ast, value)?),
+        };
+        Err(ErrorInfo::new_with_span(error, span.to_owned()))
     }
-    fn visit_block_stmt(&mut self, stmts: &Vec<Stmt>) -> Result<(), ErrorInfo> {
+    fn visit_block_stmt(&mut self, ast: &Arc<Ast>, stmts: &[StmtId]) -> Result<(), ErrorInfo> {
         self.exec_block(
+            ast,
             stmts,
             Rc::new(RefCell::new(Environment::new_from_closure(
                 &self.environment,
@@ -64,14 +106,15 @@ impl visitor::Stmt for Interpretor {
 
     fn visit_if_stmt(
         &mut self,
-        condition: &Expr,
-        truthy: &Box<Stmt>,
-        falsy: &Option<Box<Stmt>>,
+        ast: &Arc<Ast>,
+        condition: ExprId,
+        truthy: StmtId,
+        falsy: Option<StmtId>,
     ) -> Result<(), ErrorInfo> {
-        if self.eval(condition)?.to_boolean() {
-            self.exec(truthy)
+        if self.eval(ast, condition)?.is_truthy() {
+            self.exec(ast, truthy)
         } else if let Some(expr) = falsy {
-            self.exec(expr)
+            self.exec(ast, expr)
         } else {
             Ok(())
         }
@@ -79,40 +122,134 @@ impl visitor::Stmt for Interpretor {
 
     fn visit_function_stmt(
         &mut self,
-        name: &String,
-        params: &Vec<String>,
-        body: &Vec<Stmt>,
+        ast: &Arc<Ast>,
+        name: Symbol,
+        params: &[Symbol],
+        body: &[StmtId],
         span: &Span,
+        slot: Option<usize>,
     ) -> Result<(), ErrorInfo> {
-        let function = Function::User {
-            name: name.to_owned(),
+        let declaration = Declaration {
+            name,
             span: span.to_owned(),
             params: params.to_owned(),
+            ast: Arc::clone(ast),
             body: body.to_owned(),
-            closure: self.environment.clone(),
             is_initializer: false,
         };
-        self.environment
-            .borrow_mut()
-            .define(name.to_owned(), Object::Function(function), false)
+        let function = Function::User {
+            declaration: Rc::new(declaration),
+            closure: Closure::Strong(self.environment.clone()),
+        };
+        self.declare(name, Object::Function(Rc::new(function)), false, slot)
             .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
     }
 
     fn visit_class_stmt(
         &mut self,
-        name: &String,
-        super_class: &Option<String>,
-        methods: &Vec<Stmt>,
+        _ast: &Arc<Ast>,
+        _name: Symbol,
+        _super_class: Option<Symbol>,
+        _methods: &[StmtId],
         span: &Span,
     ) -> Result<(), ErrorInfo> {
-        todo!();
+        Err(unsupported("classes", span))
+    }
+
+    fn visit_break_stmt(&mut self, _ast: &Arc<Ast>, span: &Span) -> Result<(), ErrorInfo> {
+        Err(unsupported("`break`", span))
     }
 
-    fn visit_break_stmt(&mut self, span: &Span) -> Result<(), ErrorInfo> {
-        todo!();
+    fn visit_continue_stmt(&mut self, _ast: &Arc<Ast>, span: &Span) -> Result<(), ErrorInfo> {
+        Err(unsupported("`continue`", span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use crate::{interpretor::Capture, Error, Interpretor, Lexer, Parser, Span};
+
+    fn output(input: &str) -> String {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let capture = Capture::default();
+        let mut interpretor = Interpretor::new().with_output(Box::new(capture.clone()));
+        interpretor.interpret(program).unwrap();
+        capture.text()
+    }
+
+    #[test]
+    fn test_print_output() {
+        assert_eq!(
+            output("print 1 + 2; print \"a\" + \"b\"; print nil; print time == time;"),
+            "3\nab\nnil\ntrue\n"
+        );
+        assert_eq!(
+            output("print 1, \"a\", nil; write(\"b\", 2); write(); write(\"!\"); print \"\";"),
+            "1 a nil\nb 2!\n"
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let input = "\
+fn f(n) {
+  if (n > 0) {
+    return f(n - 1) + 1;
+  }
+  return n;
+}
+let x = 1;
+print f(x);";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let trace = Capture::default();
+        let mut interpretor = Interpretor::new()
+            .with_output(Box::new(io::sink()))
+            .with_trace_output(Box::new(trace.clone()));
+        interpretor.set_trace(true);
+        interpretor.interpret(program).unwrap();
+        let expected = "\
+1: (fn f (n) ...)
+7: (let x 1)
+8: (print (call f x))
+  2: (if (> n 0) ...)
+  3: (return (+ (call f (- n 1)) 1))
+    2: (if (> n 0) ...)
+    5: (return n)
+";
+        assert_eq!(trace.text(), expected);
+    }
+
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
     }
 
-    fn visit_continue_stmt(&mut self, span: &Span) -> Result<(), ErrorInfo> {
-        todo!();
+    #[test]
+    fn test_output_error() {
+        // a failed print stops its statement like any other runtime error
+        let program = Parser::new(Lexer::new("print 1;".to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new().with_output(Box::new(Broken));
+        let err = interpretor.exec_in(&program, program.stmts[0]).unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Runtime("failed to write output: closed".to_string())
+        );
+        // at the statement that printed
+        assert_eq!(err.span, Span::new(1, 0, 5).with_columns(1, 6));
     }
 }
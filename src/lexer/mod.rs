@@ -1,43 +1,156 @@
-use crate::token::{self, TokenInfo, TokenType};
-use crate::{Error, ErrorInfo};
+use std::{collections::VecDeque, rc::Rc, sync::Arc};
+
+use crate::token::{self, LineIndex, TokenInfo, TokenKind, TokenType};
+use crate::{Error, ErrorInfo, Span};
 
 pub struct Lexer {
+    // indices into `data` of the token being scanned and the next character
     start: usize,
-    curr: usize,    
+    curr: usize,
+    // the same positions as byte offsets into the source, which spans use
+    start_offset: usize,
+    offset: usize,
     line: usize,
+    // indices into `data` of the first character of the line the token
+    // being scanned starts on and of the current line, for the columns
+    start_line: usize,
     line_start: usize,
     data: Vec<char>,
+    // characters that could not be scanned, which are skipped
+    errors: Vec<ErrorInfo>,
+    // given to the span of every token
+    name: Option<Arc<str>>,
+    // the lines of the source, built once for everything that reports on it
+    line_index: Rc<LineIndex>,
+    // whether comments come out as tokens rather than being skipped
+    trivia: bool,
+}
+
+pub struct Tokens {
+    lexer: Lexer,
+    // errors met scanning the token already taken from the lexer, and then
+    // that token
+    pending: VecDeque<Result<TokenInfo, ErrorInfo>>,
+    done: bool,
+}
+
+impl Iterator for Tokens {
+    type Item = Result<TokenInfo, ErrorInfo>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_empty() && !self.done {
+            let token = self.lexer.next();
+            self.done = token.is(TokenKind::Eof);
+            self.pending
+                .extend(self.lexer.take_errors().into_iter().map(Err));
+            self.pending.push_back(Ok(token));
+        }
+        self.pending.pop_front()
+    }
 }
 
 //  methods
 impl Lexer {
     pub fn new(data: String) -> Self {
+        Self::from_str(&data)
+    }
+
+    // `new` for a source that is borrowed, which is scanned without first
+    // being copied into a string of its own
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(data: &str) -> Self {
         Self {
             start: 0,
             curr: 0,
+            start_offset: 0,
+            offset: 0,
             line: 1,
+            start_line: 0,
             line_start: 0,
             data: data.chars().collect(),
+            errors: Vec::new(),
+            name: None,
+            line_index: Rc::new(LineIndex::new(data)),
+            trivia: false,
+        }
+    }
+
+    // Names the source, a path or something like "<stdin>", so errors about
+    // it can say where they come from.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(Arc::from(name));
+        self
+    }
+
+    // Scans each comment into a `TokenType::Comment` instead of skipping
+    // it, for the tools that keep them, such as the formatter.
+    pub fn with_trivia(mut self, trivia: bool) -> Self {
+        self.trivia = trivia;
+        self
+    }
+
+    // the lines of the source, for rendering the errors found in it
+    pub fn line_index(&self) -> Rc<LineIndex> {
+        Rc::clone(&self.line_index)
+    }
+
+    // the errors met since the last call, in the order of the source
+    pub fn take_errors(&mut self) -> Vec<ErrorInfo> {
+        std::mem::take(&mut self.errors)
+    }
+
+    // Every token up to and including `Eof`, with the errors in between
+    // given in their place.
+    pub fn tokens(self) -> Tokens {
+        Tokens {
+            lexer: self,
+            pending: VecDeque::new(),
+            done: false,
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> TokenInfo {
         loop {
-            let result = self.scan();
-            if result.is_ok() {
-                return TokenInfo::new(result.unwrap(), self.start, self.curr, self.line, self.line_start);
-            } else {
-                let err = ErrorInfo::new(result.unwrap_err(), self.line, self.line_start, self.start, self.curr);
-                err.report();
+            match self.scan() {
+                Ok(token) => {
+                    let mut token =
+                        TokenInfo::new(token, self.start_offset, self.offset, self.line);
+                    token.span = self.spanned(token.span);
+                    return token;
+                }
+                Err(error) => {
+                    let mut err = ErrorInfo::new(error, self.line, self.start_offset, self.offset);
+                    err.span = self.spanned(err.span);
+                    self.errors.push(err);
+                }
             }
         }
     }
 
+    // `span` of the token just scanned with its columns and the name of the
+    // source
+    fn spanned(&self, span: Span) -> Span {
+        let column = self.start - self.start_line + 1;
+        let end_column = if self.line_start == self.start_line {
+            self.curr - self.line_start + 1
+        } else {
+            0
+        };
+        let mut span = span.with_columns(column, end_column);
+        span.file = self.name.clone();
+        span
+    }
+
     pub fn scan(&mut self) -> Result<TokenType, Error> {
+        self.skip_whitespace();
         self.start = self.curr;
+        self.start_offset = self.offset;
+        self.start_line = self.line_start;
         match self.next_char() {
             '\0' => Ok(TokenType::Eof),
             ',' => Ok(TokenType::Comma),
+            '.' => Ok(TokenType::Dot),
             '[' => Ok(TokenType::LBrace),
             ']' => Ok(TokenType::RBrace),
             '{' => Ok(TokenType::LCurly),
@@ -140,12 +253,12 @@ impl Lexer {
             '\"' => {
                 while self.peek_char() != '\"' {
                     if self.is_eof() {
-                        return Err(Error::Syntax(format!("unterminated string",)));
+                        return Err(Error::UnterminatedString);
                     }
                     self.next_char();
                 }
-                let data = self.data[self.start+1..self.curr]
-                    .into_iter()
+                let data = self.data[self.start + 1..self.curr]
+                    .iter()
                     .collect::<String>();
                 self.next_char();
                 Ok(TokenType::String(data))
@@ -157,42 +270,64 @@ impl Lexer {
                     self.next_char();
                 }
 
-                let data = self.data[self.start..self.curr]
-                    .into_iter()
-                    .collect::<String>();
+                let data = self.data[self.start..self.curr].iter().collect::<String>();
                 Ok(token::lookup_identifier(data))
             }
-            ' ' | '\r' | '\t' | '\n' => {
-                while self.is_whitespace(self.peek_char()) {
-                    self.next_char();
-                }
-                return self.scan();
-            }
+            // only reached when comments are kept
             '#' => {
-                while self.peek_char() != '\n' && !self.is_eof() {
-                    self.next_char();
-                }
-                return self.scan();
+                self.skip_comment();
+                let text = self.data[self.start + 1..self.curr]
+                    .iter()
+                    .collect::<String>();
+                Ok(TokenType::Comment(text))
             }
             '0'..='9' => {
                 let start = self.curr;
-                while self.is_digit(self.peek_char()) || self.peek_char() == '.' {
+                // a dot not followed by a digit is a property access such
+                // as `2.to_fixed`
+                while self.is_digit(self.peek_char())
+                    || (self.peek_char() == '.'
+                        && self
+                            .data
+                            .get(self.curr + 1)
+                            .is_some_and(|ch| self.is_digit(*ch)))
+                {
                     self.next_char();
                 }
-                let data = self.data[start - 1..self.curr]
-                    .into_iter()
-                    .collect::<String>();
+                let data = self.data[start - 1..self.curr].iter().collect::<String>();
                 match data.parse::<f64>() {
                     Ok(x) => Ok(TokenType::Number(x)),
-                    Err(_) => Err(Error::Value(format!("invalid number:'{}'", data,))),
+                    Err(_) => Err(Error::InvalidNumber(data)),
                 }
             }
-            ch => Err(Error::Syntax(format!("unknown character:'{ch}'"))),
+            ch => Err(Error::UnknownCharacter(ch)),
         }
     }
 }
 
 impl Lexer {
+    // Skips the whitespace before the next token, and the comments unless
+    // they are kept, in a loop so a long run of them takes no more stack
+    // than one.
+    fn skip_whitespace(&mut self) {
+        loop {
+            match self.peek_char() {
+                ch if self.is_whitespace(ch) => {
+                    self.next_char();
+                }
+                '#' if !self.trivia => self.skip_comment(),
+                _ => return,
+            }
+        }
+    }
+
+    // up to the end of the line, leaving the newline
+    fn skip_comment(&mut self) {
+        while self.peek_char() != '\n' && !self.is_eof() {
+            self.next_char();
+        }
+    }
+
     fn is_eof(&self) -> bool {
         self.curr >= self.data.len()
     }
@@ -204,9 +339,10 @@ impl Lexer {
         let ch = self.data[self.curr];
         if ch == '\n' {
             self.line += 1;
-            self.line_start = self.curr;
+            self.line_start = self.curr + 1;
         }
         self.curr += 1;
+        self.offset += ch.len_utf8();
         ch
     }
 
@@ -218,11 +354,11 @@ impl Lexer {
     }
 
     fn is_digit(&self, ch: char) -> bool {
-        return '0' <= ch && ch <= '9';
+        ch.is_ascii_digit()
     }
 
     fn is_identifier(&self, ch: char) -> bool {
-        return ('a' <= ch && ch <= 'z') || ('A' <= ch && ch <= 'Z') || '_' == ch;
+        ch.is_ascii_alphabetic() || '_' == ch
     }
 
     fn is_next_char(&mut self, ch: char) -> bool {
@@ -230,11 +366,11 @@ impl Lexer {
             self.next_char();
             return true;
         }
-        return false;
+        false
     }
 
     fn is_whitespace(&self, ch: char) -> bool {
-        return ch == ' ' || ch == '\r' || ch == '\t' || ch == '\n';
+        ch == ' ' || ch == '\r' || ch == '\t' || ch == '\n'
     }
 }
 
@@ -256,6 +392,69 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_tokens() {
+        let source = "let s = \"é\";\n@ x1 >= 2.50";
+        let lines: Vec<String> = Lexer::new(source.to_string())
+            .tokens()
+            .map(|token| match token {
+                Ok(token) => token.describe(source),
+                Err(err) => err.to_string(),
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "1:0-3 Let let",
+                "1:4-5 Identifier s",
+                "1:6-7 Assign =",
+                "1:8-12 String \"é\"",
+                "1:12-13 Semicolon ;",
+                "SyntaxError: unknown character:'@' (line 2, column 1)",
+                "2:16-18 Identifier x1",
+                "2:19-21 Gte >=",
+                "2:22-26 Number 2.50",
+                "2:26-26 Eof ",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trivia() {
+        let source = "# first\nlet a; # after\n#\n";
+        let tokens = |lexer: Lexer| -> Vec<String> {
+            lexer
+                .tokens()
+                .map(|token| token.unwrap().describe(source))
+                .collect()
+        };
+        assert_eq!(
+            tokens(Lexer::from_str(source).with_trivia(true)),
+            [
+                "1:0-7 Comment # first",
+                "2:8-11 Let let",
+                "2:12-13 Identifier a",
+                "2:13-14 Semicolon ;",
+                "2:15-22 Comment # after",
+                "3:23-24 Comment #",
+                "4:25-25 Eof ",
+            ]
+        );
+        let mut lexer = Lexer::from_str(source).with_trivia(true);
+        assert_eq!(lexer.scan(), Ok(TokenType::Comment(" first".to_string())));
+        assert_eq!(tokens(Lexer::from_str(source)).len(), 4);
+    }
+
+    #[test]
+    fn test_long_trivia() {
+        // each of them once took a frame of the stack to skip
+        let source = format!("{}print", "# a comment\n  \n".repeat(200_000));
+        let mut lexer = Lexer::new(source.clone());
+        assert_eq!(lexer.next().token, TokenType::Print);
+        let mut lexer = Lexer::new(source).with_trivia(true);
+        assert!(lexer.next().is(TokenKind::Comment));
+    }
+
     #[test]
     fn test_symbols() {
         let input = "(){}[],;+-*/% =&|!^<>
@@ -308,9 +507,9 @@ mod tests {
             TokenType::While,
             TokenType::For,
             TokenType::Return,
-            TokenType::Identifier("abc".to_string()),
-            TokenType::Identifier("def_".to_string()),
-            TokenType::Identifier("_ghi".to_string()),
+            TokenType::Identifier("abc".into()),
+            TokenType::Identifier("def_".into()),
+            TokenType::Identifier("_ghi".into()),
         ];
         test_lexers(input, expected);
     }
@@ -333,27 +532,27 @@ mod tests {
         add(a, 2);";
         let expected = vec![
             TokenType::Function,
-            TokenType::Identifier("add".to_string()),
+            TokenType::Identifier("add".into()),
             TokenType::LParen,
-            TokenType::Identifier("a".to_string()),
+            TokenType::Identifier("a".into()),
             TokenType::Comma,
-            TokenType::Identifier("b".to_string()),
+            TokenType::Identifier("b".into()),
             TokenType::RParen,
             TokenType::LCurly,
             TokenType::Return,
-            TokenType::Identifier("a".to_string()),
+            TokenType::Identifier("a".into()),
             TokenType::Plus,
-            TokenType::Identifier("b".to_string()),
+            TokenType::Identifier("b".into()),
             TokenType::Semicolon,
             TokenType::RCurly,
             TokenType::Let,
-            TokenType::Identifier("a".to_string()),
+            TokenType::Identifier("a".into()),
             TokenType::Assign,
             TokenType::Number(1.0),
             TokenType::Semicolon,
-            TokenType::Identifier("add".to_string()),
+            TokenType::Identifier("add".into()),
             TokenType::LParen,
-            TokenType::Identifier("a".to_string()),
+            TokenType::Identifier("a".into()),
             TokenType::Comma,
             TokenType::Number(2.0),
             TokenType::RParen,
@@ -362,13 +561,26 @@ mod tests {
         test_lexers(input, expected);
     }
 
+    #[test]
+    fn test_number_property() {
+        let expected = vec![
+            TokenType::Number(1.5),
+            TokenType::Dot,
+            TokenType::Identifier("to_fixed".into()),
+            TokenType::Number(2.0),
+            TokenType::Dot,
+            TokenType::Identifier("a".into()),
+        ];
+        test_lexers("1.5.to_fixed 2.a", expected);
+    }
+
     #[test]
     fn test_unknown_character() {
         let input = "@ 1.2.3 \"this is untermintated string";
         let expected = vec![
-            Error::Syntax(format!("unknown character:'@'")),
-            Error::Value(format!("invalid number:'1.2.3'")),
-            Error::Syntax(format!("unterminated string")),
+            Error::UnknownCharacter('@'),
+            Error::InvalidNumber("1.2.3".to_string()),
+            Error::UnterminatedString,
         ];
         let mut lexer = Lexer::new(input.to_string());
         let mut result = Vec::new();
@@ -1,25 +1,57 @@
 mod token;
-pub use token::Span;
-pub use token::{TokenInfo, TokenType};
+pub use token::{LineIndex, Span, Symbol};
+pub use token::{TokenInfo, TokenKind, TokenType};
 
 mod error;
-pub use error::{Error, ErrorInfo};
+pub use error::{ColorChoice, Error, ErrorFormat, ErrorInfo, Severity};
 
 mod lexer;
-pub use lexer::Lexer;
+pub use lexer::{Lexer, Tokens};
 
 mod ast;
-pub use ast::visitor;
-pub use ast::{Expr, LiteralType, Stmt};
+pub use ast::{query, visit, visitor};
+pub use ast::{
+    Ast, Comments, Expr, ExprId, LiteralType, Node, Program, ReplLine, Slot, Stmt, StmtId,
+};
 
 mod parser;
 pub use parser::Parser;
 
+mod highlight;
+pub use highlight::{highlight, TokenClass};
+
 mod object;
 pub use object::Object;
+pub use object::Object as Value;
+pub use object::{Arity, ConversionError};
 
 mod environment;
 pub use environment::Environment;
 
+mod resolver;
+pub use resolver::Resolver;
+
+mod compiler;
+pub use compiler::{disassemble, Chunk, Compiler, OpCode};
+
+mod vm;
+pub use vm::Vm;
+
+mod builtins;
+
 mod interpretor;
-pub use interpretor::Interpretor;
+pub use interpretor::{
+    run_reader, run_source, CancellationToken, Capture, Coverage, Interpretor, Profile,
+    ProfileEntry, RunStats,
+};
+
+mod repl;
+pub use repl::{is_complete, Editor, ReadLine, Repl};
+
+mod debugger;
+pub use debugger::Debugger;
+
+pub mod playground;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
@@ -1,20 +1,441 @@
-use std::{fs::File, io::Read};
+use rlisp::{
+    disassemble, ColorChoice, Compiler, Debugger, Editor, Error, ErrorFormat, ErrorInfo,
+    Interpretor, Lexer, LineIndex, Parser, Repl, Resolver, TokenKind, TokenType,
+};
 use std::{env, process};
-use rlisp::{Interpretor, Lexer, Parser};
+use std::{
+    fs,
+    io::{self, IsTerminal, Read},
+    path::PathBuf,
+    thread,
+};
+
+// statuses of the sysexits convention, beside 0 and the one given to `exit()`
+const EX_USAGE: i32 = 64;
+// the program does not parse or resolve
+const EX_DATAERR: i32 = 65;
+const EX_NOINPUT: i32 = 66;
+// the program failed while running
+const EX_SOFTWARE: i32 = 70;
+// `fmt --check` found a file that formatting would change
+const EX_UNFORMATTED: i32 = 1;
+
+// the file name that stands for stdin
+const STDIN: &str = "-";
+
+// The programs run here may recurse as deep as the library's default call
+// depth allows, on a thread with a stack big enough for that many calls even
+// in a debug build.
+const STACK_SIZE: usize = 256 << 20;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("[usuage] rlisp <file_name>");
-        process::exit(0);
-    }
-    let file_name = &args[1];
-    let mut data = String::new();
-    let mut f = File::open(file_name).expect("Unable to open file");
-    f.read_to_string(&mut data).expect("Unable to read string");
-    let lexer = Lexer::new(data);
-    let mut parser = Parser::new(lexer);
-    let program = parser.parse_program().unwrap();
-    let mut interpretor = Interpretor::new();
-    interpretor.interpret(program);
+    let cli = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(cli)
+        .expect("Unable to start the interpreter thread");
+    // the panic has been reported by the thread
+    if cli.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn cli() {
+    // --dis            print the compiled bytecode instead of running
+    // --tokens         print what the lexer makes of the program
+    // --ast            print what the parser makes of it
+    // --check          report the diagnostics of the files without running
+    // --trace          write each statement to stderr as it runs
+    // --time           write where the time of the run went once it is over
+    // --profile        write the calls of each function and the time they took
+    // --coverage       write which lines of the files ran
+    // --debug          read debugger commands on stdin at start and at stops
+    // --deny-warnings  stop before running a program with warnings
+    // --no-history     keep the REPL from saving the lines entered
+    // --color=WHEN     whether diagnostics are colored: always, never or auto
+    // --error-format   human, or json to write one JSON object per line
+    let (mut disassemble_only, mut deny_warnings, mut check_only) = (false, false, false);
+    let (mut save_history, mut tokens_only, mut ast_only) = (true, false, false);
+    let (mut trace, mut time, mut profile, mut coverage) = (false, false, false, false);
+    let mut debug = false;
+    let mut color = ColorChoice::Auto;
+    let mut error_format = ErrorFormat::Human;
+    let mut file_names = Vec::new();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "fmt") {
+        process::exit(fmt(&args[1..]));
+    }
+    for arg in args {
+        match arg.as_str() {
+            "--dis" => disassemble_only = true,
+            "--tokens" => tokens_only = true,
+            "--ast" => ast_only = true,
+            "--check" => check_only = true,
+            "--trace" => trace = true,
+            "--time" => time = true,
+            "--profile" => profile = true,
+            "--coverage" => coverage = true,
+            "--debug" => debug = true,
+            "--deny-warnings" => deny_warnings = true,
+            "--no-history" => save_history = false,
+            "--color=always" => color = ColorChoice::Always,
+            "--color=never" => color = ColorChoice::Never,
+            "--color=auto" => color = ColorChoice::Auto,
+            "--error-format=human" => error_format = ErrorFormat::Human,
+            "--error-format=json" => error_format = ErrorFormat::Json,
+            flag if flag.starts_with("--") => usage(),
+            _ => file_names.push(arg),
+        }
+    }
+    // a program piped in runs rather than starting the REPL
+    if file_names.is_empty() && !io::stdin().is_terminal() {
+        file_names.push(STDIN.to_string());
+    }
+    // there is nothing to check without files or a program piped in
+    if check_only {
+        if file_names.is_empty() {
+            usage();
+        }
+        let format = (color, error_format);
+        process::exit(check(&file_names, deny_warnings, format));
+    }
+    if file_names.is_empty() {
+        repl(color, save_history);
+    }
+    let format = (color, error_format);
+    let dump = match (tokens_only, ast_only, disassemble_only) {
+        (true, _, _) => Some(Dump::Tokens),
+        (_, true, _) => Some(Dump::Ast),
+        (_, _, true) => Some(Dump::Bytecode),
+        _ => None,
+    };
+    if let Some(dump) = dump {
+        let [file_name] = file_names.as_slice() else {
+            usage()
+        };
+        process::exit(dump_file(file_name, dump, format));
+    }
+    let mut interpretor = Interpretor::new()
+        .with_stdio()
+        .with_deny_warnings(deny_warnings)
+        .with_color(color)
+        .with_error_format(error_format);
+    if debug {
+        let debugger = Debugger::new(Box::new(Editor::new(None)), Box::new(io::stdout()));
+        interpretor = interpretor.with_debugger(debugger);
+    }
+    interpretor.set_trace(trace);
+    interpretor.set_profile(profile);
+    interpretor.set_coverage(coverage);
+    let status = run(&mut interpretor, &file_names, format);
+    if time {
+        let stats = interpretor.stats();
+        eprintln!("parse:      {:.3?}", stats.parse);
+        eprintln!("resolve:    {:.3?}", stats.resolve);
+        eprintln!("evaluate:   {:.3?}", stats.evaluate);
+        eprintln!("statements: {}", stats.statements);
+    }
+    if let Some(profile) = interpretor.profile() {
+        eprint!("{profile}");
+    }
+    if let Some(coverage) = interpretor.coverage() {
+        eprint!("{coverage}");
+    }
+    // flushes the output before the process exits
+    drop(interpretor);
+    process::exit(status);
+}
+
+// what to print of a program instead of running it
+enum Dump {
+    Tokens,
+    Ast,
+    Bytecode,
+}
+
+fn dump_file(file_name: &str, dump: Dump, format: (ColorChoice, ErrorFormat)) -> i32 {
+    let (data, file_name) = match read_source(file_name) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("error: cannot read '{file_name}': {e}");
+            return EX_NOINPUT;
+        }
+    };
+    let lexer = Lexer::from_str(&data).with_name(file_name);
+    let index = lexer.line_index();
+    let report = |err: &ErrorInfo| report(err, &index, format);
+    if let Dump::Tokens = dump {
+        for token in lexer.tokens() {
+            match token {
+                Ok(token) => println!("{}", token.describe(&data)),
+                Err(err) => {
+                    report(&err);
+                    return EX_DATAERR;
+                }
+            }
+        }
+        return 0;
+    }
+    let mut program = match Parser::new(lexer).parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            errors.iter().for_each(report);
+            return EX_DATAERR;
+        }
+    };
+    if let Dump::Ast = dump {
+        print!("{}", program.pretty());
+        return 0;
+    }
+    let mut resolver = Resolver::new();
+    let chunk = resolver
+        .resolve(&mut program)
+        .and_then(|()| Compiler::compile(&program.ast, &program.stmts));
+    resolver.warnings().iter().for_each(report);
+    match chunk {
+        Ok(chunk) => {
+            print!("{}", disassemble(&chunk, file_name));
+            0
+        }
+        Err(err) => {
+            report(&err);
+            EX_DATAERR
+        }
+    }
+}
+
+// Runs the files in order on `interpretor`, so later files see the globals
+// of earlier ones, and stops after the first to fail. Returns the status to
+// exit with.
+fn run(
+    interpretor: &mut Interpretor,
+    file_names: &[String],
+    format: (ColorChoice, ErrorFormat),
+) -> i32 {
+    for file_name in file_names {
+        let (data, file_name) = match read_source(file_name) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("error: cannot read '{file_name}': {e}");
+                return EX_NOINPUT;
+            }
+        };
+        let parsed = interpretor.parse(&data, file_name);
+        let index = interpretor.line_index().expect("indexed by the parse");
+        let program = match parsed {
+            Ok(program) => program,
+            Err(errors) => {
+                errors.iter().for_each(|err| report(err, &index, format));
+                return EX_DATAERR;
+            }
+        };
+        let errors_reported = interpretor.errors_reported();
+        match interpretor.interpret(program) {
+            Ok(()) if interpretor.errors_reported() == errors_reported => {}
+            Ok(()) => return EX_SOFTWARE,
+            Err(ErrorInfo {
+                error: Error::Exit(code),
+                ..
+            }) => return code,
+            Err(err) => {
+                report(&err, &index, format);
+                return err.error.exit_status();
+            }
+        }
+    }
+    0
+}
+
+// The text of the file and the name to report it by, which for `-` is
+// stdin read to its end.
+fn read_source(file_name: &str) -> io::Result<(String, &str)> {
+    if file_name == STDIN {
+        let mut data = String::new();
+        io::stdin().read_to_string(&mut data)?;
+        Ok((data, "<stdin>"))
+    } else {
+        Ok((fs::read_to_string(file_name)?, file_name))
+    }
+}
+
+fn report(err: &ErrorInfo, index: &LineIndex, (color, error_format): (ColorChoice, ErrorFormat)) {
+    match error_format {
+        ErrorFormat::Human => eprintln!("{}", err.render_with_index(index, color)),
+        ErrorFormat::Json => eprintln!("{}", err.to_json_with_index(Some(index))),
+    }
+}
+
+// `rlisp fmt [--check] file...` rewrites each file in the formatting of
+// `Program::format`, or with `--check` only names those it would change.
+// A file that does not parse is left as it is, as is one with a comment
+// formatting would lose, any but those on lines of their own before a
+// statement. `-` reads stdin and writes to stdout.
+fn fmt(args: &[String]) -> i32 {
+    let mut check = false;
+    let mut file_names = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            flag if flag.starts_with("--") => usage(),
+            _ => file_names.push(arg.as_str()),
+        }
+    }
+    if file_names.is_empty() {
+        usage();
+    }
+    let mut status = 0;
+    for file_name in file_names {
+        let (data, name) = match read_source(file_name) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("error: cannot read '{file_name}': {e}");
+                status = status.max(EX_NOINPUT);
+                continue;
+            }
+        };
+        let mut parser = Parser::new(Lexer::from_str(&data).with_name(name).with_trivia(true));
+        let index = parser.line_index();
+        let program = match parser.parse_program() {
+            Ok(program) => program,
+            Err(errors) => {
+                let format = (ColorChoice::Auto, ErrorFormat::Human);
+                errors.iter().for_each(|err| report(err, &index, format));
+                status = status.max(EX_DATAERR);
+                continue;
+            }
+        };
+        let formatted = program.format(&data, &parser.take_comments());
+        if comments(&formatted) != comments(&data) {
+            eprintln!(
+                "error: cannot format '{name}': only comments on lines of their own before a \
+                 statement can be formatted yet"
+            );
+            status = status.max(EX_DATAERR);
+            continue;
+        }
+        if check {
+            if formatted != data {
+                eprintln!("{name} is not formatted");
+                status = status.max(EX_UNFORMATTED);
+            }
+        } else if file_name == STDIN {
+            print!("{formatted}");
+        } else if formatted != data {
+            if let Err(e) = fs::write(file_name, formatted) {
+                eprintln!("error: cannot write '{file_name}': {e}");
+                status = status.max(EX_NOINPUT);
+            }
+        }
+    }
+    status
+}
+
+// the text of every comment in a source that lexes
+fn comments(source: &str) -> Vec<TokenType> {
+    let tokens = Lexer::from_str(source).with_trivia(true).tokens().flatten();
+    tokens
+        .map(|token| token.token)
+        .filter(|token| token.kind() == TokenKind::Comment)
+        .collect()
+}
+
+// Lexes, parses and resolves every file, reporting all they have to say
+// without running any of them, and returns the status to exit with.
+fn check(file_names: &[String], deny_warnings: bool, format: (ColorChoice, ErrorFormat)) -> i32 {
+    let mut status = 0;
+    for file_name in file_names {
+        let (data, file_name) = match read_source(file_name) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("error: cannot read '{file_name}': {e}");
+                status = status.max(EX_NOINPUT);
+                continue;
+            }
+        };
+        let mut parser = Parser::new(Lexer::from_str(&data).with_name(file_name));
+        let index = parser.line_index();
+        let mut program = match parser.parse_program() {
+            Ok(program) => program,
+            Err(errors) => {
+                errors.iter().for_each(|err| report(err, &index, format));
+                status = status.max(EX_DATAERR);
+                continue;
+            }
+        };
+        let mut resolver = Resolver::new();
+        let result = resolver.resolve(&mut program);
+        resolver
+            .warnings()
+            .iter()
+            .for_each(|warning| report(warning, &index, format));
+        if let Err(err) = result {
+            report(&err, &index, format);
+            status = status.max(EX_DATAERR);
+        } else if deny_warnings && !resolver.warnings().is_empty() {
+            status = status.max(EX_DATAERR);
+        }
+    }
+    status
+}
+
+// Without a file and on a terminal the program is read a line at a time
+// from stdin. Ctrl-D ends the session and Ctrl-C stops the line that is
+// running or being typed. What is entered is saved to ~/.rlisp_history
+// unless told not to.
+fn repl(color: ColorChoice, save_history: bool) -> ! {
+    let interpretor = Interpretor::new().with_stdio();
+    let mut repl = Repl::new(interpretor).with_color(color);
+    interrupt::cancel_on_interrupt(repl.interpretor().cancellation_token());
+    let history = env::var_os("HOME")
+        .filter(|_| save_history)
+        .map(|home| PathBuf::from(home).join(".rlisp_history"));
+    let status = repl
+        .run_with(&mut Editor::new(history))
+        .expect("Unable to read input");
+    // flushes the output before the process exits
+    drop(repl);
+    process::exit(status);
+}
+
+#[cfg(unix)]
+mod interrupt {
+    use std::sync::OnceLock;
+
+    use rlisp::CancellationToken;
+
+    const SIGINT: i32 = 2;
+
+    static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+
+    extern "C" fn on_interrupt(_: i32) {
+        if let Some(token) = TOKEN.get() {
+            token.cancel();
+        }
+    }
+
+    pub fn cancel_on_interrupt(token: CancellationToken) {
+        if TOKEN.set(token).is_ok() {
+            // SAFETY: the handler only stores to an atomic
+            unsafe { signal(SIGINT, on_interrupt) };
+        }
+    }
+}
+
+// Ctrl-C keeps its default of ending the process elsewhere
+#[cfg(not(unix))]
+mod interrupt {
+    pub fn cancel_on_interrupt(_: rlisp::CancellationToken) {}
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "[usuage] rlisp [--dis] [--tokens] [--ast] [--check] [--trace] [--time] [--profile] \
+         [--coverage] [--debug] [--deny-warnings] [--color=always|never|auto] \
+         [--error-format=human|json] [--no-history] [file_name...]\n\
+         [usuage] rlisp fmt [--check] file_name..."
+    );
+    process::exit(EX_USAGE);
 }
@@ -0,0 +1,188 @@
+use std::{fmt, rc::Rc};
+
+use crate::{Error, Object};
+
+// A value that was not of the type a conversion out of it wanted, such as
+// the argument of a native. It becomes a type error when raised with `?`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionError {
+    pub expected: &'static str,
+    // the type of the value, or the value itself when its type was right
+    // but the value was not, as for a number with a fraction
+    pub found: String,
+}
+
+impl ConversionError {
+    fn new(expected: &'static str, value: &Object) -> Self {
+        ConversionError {
+            expected,
+            found: value.type_name().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {}, found {}", self.expected, self.found)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl From<ConversionError> for Error {
+    fn from(error: ConversionError) -> Self {
+        Error::Type(error.to_string())
+    }
+}
+
+impl Object {
+    // This value as a `T`, leaving it where it is.
+    pub fn get<T: TryFrom<Object, Error = ConversionError>>(&self) -> Result<T, ConversionError> {
+        T::try_from(self.clone())
+    }
+}
+
+impl From<f64> for Object {
+    fn from(value: f64) -> Self {
+        Object::Number(value)
+    }
+}
+
+impl From<bool> for Object {
+    fn from(value: bool) -> Self {
+        Object::Boolean(value)
+    }
+}
+
+impl From<&str> for Object {
+    fn from(value: &str) -> Self {
+        Object::String(value.into())
+    }
+}
+
+impl From<String> for Object {
+    fn from(value: String) -> Self {
+        Object::String(value.into())
+    }
+}
+
+impl From<Rc<str>> for Object {
+    fn from(value: Rc<str>) -> Self {
+        Object::String(value)
+    }
+}
+
+impl TryFrom<Object> for f64 {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Number(n) => Ok(n),
+            value => Err(ConversionError::new("number", &value)),
+        }
+    }
+}
+
+// numbers without a fraction that fit in an i64
+impl TryFrom<Object> for i64 {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            // 2^63 itself is the first float past i64::MAX
+            Object::Number(n)
+                if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 =>
+            {
+                Ok(n as i64)
+            }
+            Object::Number(n) => Err(ConversionError {
+                expected: "integer",
+                found: n.to_string(),
+            }),
+            value => Err(ConversionError::new("integer", &value)),
+        }
+    }
+}
+
+impl TryFrom<Object> for bool {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Boolean(b) => Ok(b),
+            value => Err(ConversionError::new("boolean", &value)),
+        }
+    }
+}
+
+impl TryFrom<Object> for String {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::String(s) => Ok(s.to_string()),
+            value => Err(ConversionError::new("string", &value)),
+        }
+    }
+}
+
+// the string shared with the value rather than a copy of it
+impl TryFrom<Object> for Rc<str> {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::String(s) => Ok(s),
+            value => Err(ConversionError::new("string", &value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConversionError;
+    use crate::{Error, Object};
+
+    #[test]
+    fn test_into_value() {
+        assert_eq!(Object::from(1.5), Object::Number(1.5));
+        assert_eq!(Object::from(true), Object::Boolean(true));
+        assert_eq!(Object::from("a"), Object::String("a".into()));
+        assert_eq!(Object::from("b".to_string()), Object::String("b".into()));
+    }
+
+    #[test]
+    fn test_from_value() {
+        assert_eq!(Object::from(2.5).get::<f64>(), Ok(2.5));
+        assert_eq!(Object::from(-3.0).get::<i64>(), Ok(-3));
+        assert_eq!(Object::from(false).get::<bool>(), Ok(false));
+        assert_eq!(String::try_from(Object::from("x")), Ok("x".to_string()));
+
+        let found = |expected, found: &str| ConversionError {
+            expected,
+            found: found.to_string(),
+        };
+        assert_eq!(Object::Nil.get::<f64>(), Err(found("number", "nil")));
+        assert_eq!(Object::from(1.5).get::<i64>(), Err(found("integer", "1.5")));
+        let too_big = found("integer", "10000000000000000000");
+        assert_eq!(Object::from(1e19).get::<i64>(), Err(too_big));
+        assert_eq!(
+            Object::from("1").get::<i64>(),
+            Err(found("integer", "string"))
+        );
+        assert_eq!(
+            Object::from(1.0).get::<bool>(),
+            Err(found("boolean", "number"))
+        );
+        assert_eq!(
+            Object::from(true).get::<String>(),
+            Err(found("string", "boolean"))
+        );
+    }
+
+    #[test]
+    fn test_into_error() {
+        let error: Error = Object::Nil.get::<String>().unwrap_err().into();
+        assert_eq!(error, Error::Type("expected string, found nil".to_string()));
+    }
+}
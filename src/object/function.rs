@@ -1,62 +1,343 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::RefCell,
+    fmt,
+    ops::{RangeFrom, RangeInclusive},
+    rc::{Rc, Weak},
+    sync::Arc,
+};
 
-use crate::{Environment, Error, ErrorInfo, Interpretor, Object, Span, Stmt, environment};
+use crate::{
+    interpretor::Stopwatch, Ast, Chunk, Environment, Error, ErrorInfo, Interpretor, Object, Span,
+    StmtId, Symbol, Vm,
+};
+
+// natives get the interpreter so they can reach its state and host hooks
+pub type NativeFunction = dyn Fn(&mut Interpretor, &[Object]) -> Result<Object, Error>;
+
+// methods of builtin values also get the value they were looked up on
+pub type NativeMethod = fn(&mut Interpretor, &Object, &[Object]) -> Result<Object, Error>;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Function {
     Inbuilt {
-        arity: usize,
-        func: Box<fn(Vec<Object>) -> Result<Object, ErrorInfo>>,
+        name: String,
+        arity: RangeInclusive<usize>,
+        func: Native,
     },
 
     User {
+        declaration: Rc<Declaration>,
+        closure: Closure,
+    },
+
+    // a native method bound to the value it was looked up on, as in
+    // `"abc".upper`
+    Method {
+        name: &'static str,
+        arity: RangeInclusive<usize>,
+        receiver: Object,
+        func: Box<NativeMethod>,
+    },
+
+    // a function lowered by `Compiler`, run on the `Vm`
+    Compiled {
         name: String,
-        params: Vec<String>,
-        body: Vec<Stmt>,
-        closure: Rc<RefCell<Environment>>,
-        is_initializer: bool,
-        span: Span,
+        arity: usize,
+        chunk: Rc<Chunk>,
     },
 }
 
+// What a `fn` statement declares, shared by the copies of the function
+// made as it is stored in and read out of scopes.
+#[derive(Debug, PartialEq)]
+pub struct Declaration {
+    pub name: Symbol,
+    pub params: Vec<Symbol>,
+    // the nodes of the program that declared it, which `body` is in
+    pub ast: Arc<Ast>,
+    pub body: Vec<StmtId>,
+    pub is_initializer: bool,
+    pub span: Span,
+}
+
+// The scope a user function was declared in. Functions are mostly stored in
+// that same scope, which would then keep itself alive through them, so the
+// copy kept there only holds it weakly. Reading the function out of the
+// scope gives a copy holding it again, which is what lets a closure outlive
+// the call that made it.
+#[derive(Debug, Clone)]
+pub enum Closure {
+    Strong(Rc<RefCell<Environment>>),
+    Weak(Weak<RefCell<Environment>>),
+}
+
+impl Closure {
+    fn as_ptr(&self) -> *const RefCell<Environment> {
+        match self {
+            Closure::Strong(environment) => Rc::as_ptr(environment),
+            Closure::Weak(environment) => environment.as_ptr(),
+        }
+    }
+}
+
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.as_ptr(), other.as_ptr())
+    }
+}
+
+// The implementation of a native, shared by the copies of the function. Two
+// are only equal if they are the same closure.
+#[derive(Clone)]
+pub struct Native(Rc<NativeFunction>);
+
+impl Native {
+    pub fn new(
+        func: impl Fn(&mut Interpretor, &[Object]) -> Result<Object, Error> + 'static,
+    ) -> Self {
+        Native(Rc::new(func))
+    }
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Native")
+    }
+}
+
+impl PartialEq for Native {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// The number of arguments a native takes: `2` for exactly two, `1..=2` for
+// one or two, and `1..` for one or more.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arity(pub(crate) RangeInclusive<usize>);
+
+impl From<usize> for Arity {
+    fn from(count: usize) -> Self {
+        Arity(count..=count)
+    }
+}
+
+impl From<RangeInclusive<usize>> for Arity {
+    fn from(range: RangeInclusive<usize>) -> Self {
+        Arity(range)
+    }
+}
+
+impl From<RangeFrom<usize>> for Arity {
+    fn from(range: RangeFrom<usize>) -> Self {
+        Arity(range.start..=usize::MAX)
+    }
+}
+
 impl Function {
-    pub fn arity(&self) -> usize {
+    // the number of arguments the function accepts, a range for natives
+    // with optional arguments
+    pub fn arity(&self) -> RangeInclusive<usize> {
         match self {
-            Function::Inbuilt { arity, .. } => *arity,
-            Function::User { params, .. } => params.len(),
+            Function::Inbuilt { arity, .. } | Function::Method { arity, .. } => arity.clone(),
+            Function::User { declaration, .. } => {
+                declaration.params.len()..=declaration.params.len()
+            }
+            Function::Compiled { arity, .. } => *arity..=*arity,
+        }
+    }
+
+    // whether both are the same declaration run in the same scope, the
+    // copies of a user function being the one function
+    pub fn same(&self, other: &Function) -> bool {
+        match (self, other) {
+            (
+                Function::User { declaration, .. },
+                Function::User {
+                    declaration: other, ..
+                },
+            ) => Rc::ptr_eq(declaration, other),
+            _ => std::ptr::eq(self, other),
+        }
+    }
+
+    // The copy of `function` to store in `environment`, holding it weakly if
+    // it is the scope the function captures. None if the function can be
+    // stored as it is.
+    pub(crate) fn stored_in(function: &Rc<Function>, environment: &Environment) -> Option<Self> {
+        match function.as_ref() {
+            Function::User {
+                declaration,
+                closure: Closure::Strong(closure),
+            } if std::ptr::eq(closure.as_ptr(), environment) => Some(Function::User {
+                declaration: declaration.clone(),
+                closure: Closure::Weak(Rc::downgrade(closure)),
+            }),
+            _ => None,
+        }
+    }
+
+    // The copy of a function read out of the scope it was stored in, holding
+    // that scope again. None if the function can be read as it is.
+    pub(crate) fn loaded(&self) -> Option<Self> {
+        match self {
+            Function::User {
+                declaration,
+                closure: Closure::Weak(closure),
+            } => Some(Function::User {
+                declaration: declaration.clone(),
+                closure: Closure::Strong(closure.upgrade()?),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn is_user(&self) -> bool {
+        matches!(self, Function::User { .. })
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Function::Method { name, .. } => name,
+            Function::User { declaration, .. } => declaration.name.as_str(),
+            Function::Inbuilt { name, .. } | Function::Compiled { name, .. } => name,
+        }
+    }
+
+    // Every function needs as many arguments as it accepts, on the
+    // tree-walker as on the VM.
+    pub fn check_arity(&self, count: usize, span: &Span) -> Result<(), ErrorInfo> {
+        let arity = self.arity();
+        if arity.contains(&count) {
+            return Ok(());
         }
+        let expected = if arity.start() == arity.end() {
+            arity.start().to_string()
+        } else if *arity.end() == usize::MAX {
+            format!("at least {}", arity.start())
+        } else {
+            format!("{} to {}", arity.start(), arity.end())
+        };
+        let error = Error::Type(format!(
+            "{}() takes {expected} arguments but {count} were given",
+            self.name()
+        ));
+        Err(ErrorInfo::new_with_span(error, span.to_owned()))
     }
 
+    // `span` is the position of the call, used for errors raised by natives
     pub fn call(
         &self,
         interpreter: &mut Interpretor,
-        args: &Vec<Object>,
+        args: &[Object],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        // tail calls replace the function being run rather than recursing
+        let mut tail_call: Option<(Rc<Function>, Vec<Object>, Span)> = None;
+        loop {
+            let (function, args, span) = match &tail_call {
+                Some((function, args, span)) => (function.as_ref(), args.as_slice(), span),
+                None => (self, args, span),
+            };
+            let result = if interpreter.profile.is_some() {
+                function.profiled_call_frame(interpreter, args, span)
+            } else {
+                function.call_frame(interpreter, args, span)
+            };
+            match result {
+                Err(ErrorInfo {
+                    error: Error::TailCall(function, args),
+                    span,
+                }) => tail_call = Some((function, args, span)),
+                result => return result,
+            }
+        }
+    }
+
+    fn profiled_call_frame(
+        &self,
+        interpreter: &mut Interpretor,
+        args: &[Object],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        let outermost = interpreter
+            .profile
+            .as_mut()
+            .is_some_and(|p| p.enter(self.name()));
+        let started = Stopwatch::start();
+        let result = self.call_frame(interpreter, args, span);
+        // a native may have turned profiling off
+        if let Some(profile) = &mut interpreter.profile {
+            profile.exit(self.name(), started, outermost);
+        }
+        result
+    }
+
+    fn call_frame(
+        &self,
+        interpreter: &mut Interpretor,
+        args: &[Object],
+        span: &Span,
     ) -> Result<Object, ErrorInfo> {
         match self {
-            Function::Inbuilt { func, .. } => Ok(func(args.to_vec())?),
+            Function::Inbuilt { func, .. } => {
+                self.check_arity(args.len(), span)?;
+                (func.0)(interpreter, args)
+                    .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+            }
+            Function::Method { receiver, func, .. } => {
+                self.check_arity(args.len(), span)?;
+                func(interpreter, receiver, args)
+                    .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+            }
+            Function::Compiled { chunk, .. } => {
+                self.check_arity(args.len(), span)?;
+                Vm::new(interpreter).call(chunk.clone(), args, span)
+            }
             Function::User {
-                params,
-                body,
+                declaration,
                 closure,
-                ..
             } => {
-                let mut environment = Environment::new_from_closure(closure);
-                for (param, argument) in params.iter().zip(args) {
+                let Declaration {
+                    name,
+                    params,
+                    ast,
+                    body,
+                    span: declared_at,
+                    ..
+                } = declaration.as_ref();
+                self.check_arity(args.len(), span)?;
+                let closure = match closure {
+                    Closure::Strong(closure) => closure.clone(),
+                    // only reachable through the scope, which is then alive
+                    Closure::Weak(closure) => closure.upgrade().expect("the scope is alive"),
+                };
+                let mut environment = Environment::new_from_closure(&closure);
+                for (slot, (param, argument)) in params.iter().zip(args).enumerate() {
                     environment
-                        .define(param
-                            .clone(), argument.to_owned(), false);
+                        .define_at(slot, *param, argument.to_owned(), false)
+                        .map_err(|e| ErrorInfo::new_with_span(e, declared_at.to_owned()))?;
                 }
                 let environment = Rc::new(RefCell::new(environment));
-                match  interpreter.exec_block(body, environment) {
-                Ok(()) => Ok(Object::Nil),
-                Err(x) => {
-                    if let Error::Return(value) = x.error {
-                        Ok(value)
-                    } else {
-                        Err(x)
-                    }                }
+                interpreter.enter_call(span)?;
+                if let Some(debugger) = &mut interpreter.debugger {
+                    debugger.frames.push((name.to_string(), span.line));
+                }
+                let result = interpreter.exec_block(ast, body, environment);
+                if let Some(debugger) = &mut interpreter.debugger {
+                    debugger.frames.pop();
+                }
+                interpreter.call_depth -= 1;
+                match result {
+                    Ok(()) => Ok(Object::Nil),
+                    Err(x) => {
+                        if let Error::Return(value) = x.error {
+                            Ok(value)
+                        } else {
+                            Err(x)
+                        }
+                    }
                 }
-               
             }
         }
     }
@@ -1,17 +1,50 @@
-use std::fmt;
+use std::{fmt, rc::Rc};
+mod convert;
 mod function;
 pub mod utils;
-pub use function::Function;
+pub use convert::ConversionError;
+pub use function::{Arity, Closure, Declaration, Function, Native, NativeMethod};
 
-#[derive(Debug, PartialEq, Clone)]
+// Runtime value of the language, exported to embedders as `Value`. Display
+// is what `print` shows and equality is the language's `==`. Strings are
+// immutable and shared, so copying a value never copies its text.
+#[derive(Debug, Clone)]
 pub enum Object {
     Boolean(bool),
     Number(f64),
-    String(String),
-    Function(Function),
+    String(Rc<str>),
+    Function(Rc<Function>),
     Nil,
 }
 
+impl Object {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Boolean(_) => "boolean",
+            Object::Number(_) => "number",
+            Object::String(_) => "string",
+            Object::Function(_) => "function",
+            Object::Nil => "nil",
+        }
+    }
+}
+
+// Values of different types are never equal, numbers compare as floats (so
+// NaN is not equal to itself) and functions are equal only to themselves.
+// Strings are compared by contents, which copies of one value skip.
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Boolean(l), Object::Boolean(r)) => l == r,
+            (Object::Number(l), Object::Number(r)) => l == r,
+            (Object::String(l), Object::String(r)) => Rc::ptr_eq(l, r) || l == r,
+            (Object::Function(l), Object::Function(r)) => Rc::ptr_eq(l, r) || l.same(r),
+            (Object::Nil, Object::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -23,3 +56,52 @@ impl fmt::Display for Object {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equality() {
+        let function = Rc::new(Function::Inbuilt {
+            name: "nil".to_string(),
+            arity: 0..=0,
+            func: Native::new(|_, _| Ok(Object::Nil)),
+        });
+        let other = Rc::new(function.as_ref().clone());
+        assert_eq!(
+            Object::Function(function.clone()),
+            Object::Function(function.clone())
+        );
+        assert_ne!(Object::Function(function), Object::Function(other));
+        assert_ne!(Object::Number(f64::NAN), Object::Number(f64::NAN));
+        assert_ne!(Object::Number(1.0), Object::String("1".into()));
+        assert_ne!(Object::Number(0.0), Object::Boolean(false));
+        assert_eq!(Object::Nil, Object::Nil);
+    }
+
+    #[test]
+    fn test_shared_strings() {
+        let text = Object::from("text");
+        let (Object::String(a), Object::String(b)) = (&text, &text.clone()) else {
+            panic!("not strings")
+        };
+        assert!(Rc::ptr_eq(a, b));
+        // equal by contents whether shared or not
+        assert_eq!(text, Object::from("te".to_string() + "xt"));
+        assert_ne!(text, Object::from("other"));
+    }
+
+    #[test]
+    fn test_truthiness() {
+        let falsy = [
+            Object::Nil,
+            Object::Boolean(false),
+            Object::Number(0.0),
+            Object::String("".into()),
+        ];
+        assert!(falsy.iter().all(|value| !value.is_truthy()));
+        assert!(Object::String("0".into()).is_truthy());
+        assert_eq!(Object::Number(1.0).type_name(), "number");
+    }
+}
@@ -2,13 +2,11 @@ use crate::{Error, Object, TokenType};
 
 impl Object {
     pub fn is_nil(&self) -> bool {
-        match self {
-            Object::Nil => true,
-            _ => false,
-        }
+        matches!(self, Object::Nil)
     }
 
-    pub fn to_boolean(&self) -> bool {
+    // nil, false, 0 and "" are falsy, everything else is truthy
+    pub fn is_truthy(&self) -> bool {
         match self {
             Object::Nil => false,
             Object::Boolean(b) => *b,
@@ -29,39 +27,52 @@ impl Object {
                         -val
                     }))
                 } else {
-                    Err(Error::Runtime("Operand must be a number.".to_string()))
+                    Err(Error::Type(format!(
+                        "cannot apply '{op}' to {}",
+                        operand(self)
+                    )))
                 }
             }
-            TokenType::Not => Ok(Object::Boolean(!self.to_boolean())),
+            TokenType::Not => Ok(Object::Boolean(!self.is_truthy())),
             _ => Err(Error::Runtime("Invalid unary operator.".to_string())),
         }
     }
 
+    // bytes of string data `binary` would allocate for these operands
+    pub fn binary_size(left: &Object, op: &TokenType, right: &Object) -> usize {
+        match (op, left, right) {
+            (TokenType::Plus, Object::String(l), Object::String(r)) => l.len() + r.len(),
+            (TokenType::Times, Object::String(s), Object::Number(n))
+            | (TokenType::Times, Object::Number(n), Object::String(s)) => {
+                s.len().saturating_mul(n.max(0.0) as usize)
+            }
+            _ => 0,
+        }
+    }
+
     pub fn binary(left: Object, op: &TokenType, right: Object) -> Result<Object, Error> {
         match op {
             TokenType::Plus => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Number(l + r)),
-                (Object::String(l), Object::String(r)) => Ok(Object::String(l + &r)),
-                _ => Err(Error::Runtime(
-                    "Operands must be two numbers or two strings.".to_string(),
-                )),
+                (Object::String(l), Object::String(r)) => Ok([l, r].concat().into()),
+                (left, right) => Err(operands_error(op, &left, &right)),
             },
             TokenType::Minus => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Number(l - r)),
-                _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
+                (left, right) => Err(operands_error(op, &left, &right)),
             },
             TokenType::Times => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Number(l * r)),
                 (Object::String(l), Object::Number(r)) | (Object::Number(r), Object::String(l)) => {
-                    Ok(Object::String(l.repeat(r as usize)))
+                    let count = r as usize;
+                    repeated_len(&l, count)?;
+                    Ok(Object::String(l.repeat(count).into()))
                 }
                 (Object::Number(l), Object::Boolean(r))
                 | (Object::Boolean(r), Object::Number(l)) => {
                     Ok(Object::Number(if r { l } else { 0.0 }))
                 }
-                _ => Err(Error::Runtime(
-                    "Operands must be two numbers or a string and a number.".to_string(),
-                )),
+                (left, right) => Err(operands_error(op, &left, &right)),
             },
             TokenType::Divide => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => {
@@ -75,43 +86,124 @@ impl Object {
                 | (Object::Boolean(r), Object::Number(l)) => {
                     Ok(Object::Number(if r { l } else { 0.0 }))
                 }
-                _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
+                (left, right) => Err(operands_error(op, &left, &right)),
             },
             TokenType::And => match (left, right) {
                 (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(l && r)),
-                _ => Err(Error::Runtime("Operands must be two booleans.".to_string())),
+                (left, right) => Err(operands_error(op, &left, &right)),
             },
             TokenType::Gt => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Boolean(l > r)),
-                _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
+                (left, right) => Err(operands_error(op, &left, &right)),
             },
             TokenType::Gte => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Boolean(l >= r)),
-                _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
+                (left, right) => Err(operands_error(op, &left, &right)),
             },
             TokenType::Lt => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Boolean(l < r)),
-                _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
+                (left, right) => Err(operands_error(op, &left, &right)),
             },
             TokenType::Lte => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Boolean(l <= r)),
-                _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
-            },
-            TokenType::Eq => match (left, right) {
-                (Object::Number(l), Object::Number(r)) => Ok(Object::Boolean(l == r)),
-                (Object::String(l), Object::String(r)) => Ok(Object::Boolean(l == r)),
-                (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(l == r)),
-                (Object::Nil, Object::Nil) => Ok(Object::Boolean(true)),
-                _ => Ok(Object::Boolean(false)),
-            },
-            TokenType::Ne => match (left, right) {
-                (Object::Number(l), Object::Number(r)) => Ok(Object::Boolean(l != r)),
-                (Object::String(l), Object::String(r)) => Ok(Object::Boolean(l != r)),
-                (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(l != r)),
-                (Object::Nil, Object::Nil) => Ok(Object::Boolean(false)),
-                _ => Ok(Object::Boolean(true)),
+                (left, right) => Err(operands_error(op, &left, &right)),
             },
+            TokenType::Eq => Ok(Object::Boolean(left == right)),
+            TokenType::Ne => Ok(Object::Boolean(left != right)),
             _ => Err(Error::Runtime("Invalid Logical operator.".to_string())),
         }
     }
 }
+
+// longest string `repeat` and `*` build, far more than a script needs but
+// well short of running the host out of memory
+pub const MAX_REPEAT: usize = 1 << 30;
+
+// the length of `s` repeated `count` times, an error past `MAX_REPEAT`
+pub fn repeated_len(s: &str, count: usize) -> Result<usize, Error> {
+    match s.len().checked_mul(count) {
+        Some(len) if len <= MAX_REPEAT => Ok(len),
+        _ => Err(Error::Value(format!(
+            "repeated string would be longer than {MAX_REPEAT} bytes"
+        ))),
+    }
+}
+
+// longest representation of an operand shown in an error, so a huge string
+// does not flood the terminal
+const MAX_OPERAND: usize = 40;
+
+// `cannot apply '-' to string ("abc") and number (3)`
+fn operands_error(op: &TokenType, left: &Object, right: &Object) -> Error {
+    Error::Type(format!(
+        "cannot apply '{op}' to {} and {}",
+        operand(left),
+        operand(right)
+    ))
+}
+
+// type and value of an operand, strings quoted to tell "1" from 1
+fn operand(value: &Object) -> String {
+    let repr = match value {
+        Object::String(s) => format!("\"{s}\""),
+        value => value.to_string(),
+    };
+    let repr = if repr.chars().count() > MAX_OPERAND {
+        let cut: String = repr.chars().take(MAX_OPERAND - 3).collect();
+        format!("{cut}...")
+    } else {
+        repr
+    };
+    format!("{} ({repr})", value.type_name())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::eval;
+    use crate::{Error, Object};
+
+    #[test]
+    fn test_operand_errors() {
+        let error = |message: &str| Err(Error::Type(message.to_string()));
+        assert_eq!(
+            eval("\"abc\" - 3"),
+            error("cannot apply '-' to string (\"abc\") and number (3)")
+        );
+        assert_eq!(
+            eval("1 + nil"),
+            error("cannot apply '+' to number (1) and nil (nil)")
+        );
+        assert_eq!(
+            eval("true <= 2.5"),
+            error("cannot apply '<=' to boolean (true) and number (2.5)")
+        );
+        assert_eq!(eval("-\"x\""), error("cannot apply '-' to string (\"x\")"));
+        assert_eq!(
+            eval("len * \"a\""),
+            error("cannot apply '*' to function (<function>) and string (\"a\")")
+        );
+        // long values are cut short
+        let long = "x".repeat(100);
+        let Err(Error::Type(message)) = eval(&format!("\"{long}\" / 2")) else {
+            panic!("dividing a string is an error")
+        };
+        assert_eq!(
+            message,
+            format!(
+                "cannot apply '/' to string (\"{}...) and number (2)",
+                "x".repeat(36)
+            )
+        );
+        assert_eq!(eval("1 + 2"), Ok(Object::Number(3.0)));
+    }
+
+    #[test]
+    fn test_repeat_too_long() {
+        let error = Err(Error::Value(
+            "repeated string would be longer than 1073741824 bytes".to_string(),
+        ));
+        assert_eq!(eval("\"ab\" * 10000000000000000000"), error);
+        assert_eq!(eval("10000000000000000000 * \"ab\""), error);
+        assert_eq!(eval("\"ab\" * 3"), Ok(Object::String("ababab".into())));
+    }
+}
@@ -1,239 +1,478 @@
-use crate::ast::Program;
+use std::rc::Rc;
+
+use crate::ast::{Ast, Comments, ExprId, Program, ReplLine, StmtId};
 use crate::Error;
 use crate::ErrorInfo;
-use crate::Lexer;
-use crate::Span;
 use crate::{Expr, LiteralType, Stmt};
-use crate::{TokenInfo, TokenType};
+use crate::{Lexer, LineIndex};
+use crate::{Span, Symbol};
+use crate::{TokenInfo, TokenKind, TokenType};
 
 pub struct Parser {
     lexer: Lexer,
-    prev: TokenInfo,
+    // the kind and position of the token before `curr`
+    prev_kind: TokenKind,
+    prev_span: Span,
     curr: TokenInfo,
+    // the nodes parsed so far, handed to the program at the end
+    ast: Ast,
+    // lexer and syntax errors recovered from so far
+    errors: Vec<ErrorInfo>,
+    // whether the last expression statement may leave out its semicolon
+    repl_line: bool,
+    // how deeply the statements and expressions being parsed are nested,
+    // and how deep they may go before parsing them could overflow the stack
+    depth: usize,
+    max_depth: usize,
+    // set once nesting went past `max_depth`, after which nothing is parsed
+    gave_up: bool,
+    // the comments of a lexer keeping trivia, and those read before `curr`
+    // that lead the statement if one starts there
+    comments: Comments,
+    pending_comments: Vec<TokenInfo>,
 }
 
+// Deep enough for the programs people write, and shallow enough for a debug
+// build to parse and run them on a thread with the default stack of 2MB.
+const MAX_DEPTH: usize = 100;
+
 impl Parser {
-    pub fn new(mut lexer: Lexer) -> Self {
-        Self {
-            prev: TokenInfo::new(TokenType::Eof, 0, 0, 0, 0),
-            curr: lexer.next(),
+    // a parser of `source` as a whole, without a name for its errors
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(source: &str) -> Self {
+        Self::new(Lexer::from_str(source))
+    }
+
+    pub fn new(lexer: Lexer) -> Self {
+        let mut parser = Self {
+            prev_kind: TokenKind::Eof,
+            prev_span: Span::new(0, 0, 0),
+            curr: TokenInfo::new(TokenType::Eof, 0, 0, 0),
+            ast: Ast::new(),
+            errors: Vec::new(),
             lexer,
-        }
+            repl_line: false,
+            depth: 0,
+            max_depth: MAX_DEPTH,
+            gave_up: false,
+            comments: Comments::default(),
+            pending_comments: Vec::new(),
+        };
+        parser.curr = parser.next_token();
+        parser
+    }
+
+    // Sets how deeply statements and expressions may nest, counting each
+    // statement in the body of another and each expression inside another
+    // through parentheses, arguments or a unary operator.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    // the lines of the source, built by the lexer, for rendering the errors
+    // found in it
+    pub fn line_index(&self) -> Rc<LineIndex> {
+        self.lexer.line_index()
     }
 
-    pub fn parse_program(&mut self) -> Result<Program, ErrorInfo> {
+    // The comments met so far when the lexer keeps trivia, by the
+    // statements of the program they lead.
+    pub fn take_comments(&mut self) -> Comments {
+        self.comments.push_others(self.pending_comments.drain(..));
+        std::mem::take(&mut self.comments)
+    }
+
+    // Parses every statement, skipping ahead to the next statement after a
+    // syntax error so one run reports all the independent mistakes. Any
+    // error means there is no program to run.
+    pub fn parse_program(&mut self) -> Result<Program, Vec<ErrorInfo>> {
         let mut stmt = Vec::new();
-        while !self.curr.is(TokenType::Eof) {
-            stmt.push(self.declaration()?);
+        while !self.curr.is(TokenKind::Eof) {
+            if let Some(declaration) = self.recovering_declaration() {
+                stmt.push(declaration);
+            }
+        }
+        if self.errors.is_empty() {
+            Ok(Program::new(std::mem::take(&mut self.ast), stmt))
+        } else {
+            // the lexer runs a token ahead of the parser, so its errors are
+            // put back in the order of the source
+            self.errors.sort_by_key(|err| err.span.start);
+            Err(std::mem::take(&mut self.errors))
         }
+    }
 
-        Ok(Program::new(stmt))
+    // Parses a line typed at a REPL, where the semicolon ending the line is
+    // optional after an expression. A line holding one expression other
+    // than an assignment comes back on its own so its value can be shown,
+    // and anything else parses as a program would.
+    pub fn parse_repl_line(&mut self) -> Result<ReplLine, Vec<ErrorInfo>> {
+        self.repl_line = true;
+        let program = self.parse_program()?;
+        let expr = match program.stmts.as_slice() {
+            [stmt] => match program.ast[*stmt] {
+                Stmt::Expr { expr } => Some(expr),
+                _ => None,
+            },
+            _ => None,
+        };
+        match expr {
+            Some(expr) if !matches!(program.ast[expr], Expr::Assign { .. } | Expr::Set { .. }) => {
+                Ok(ReplLine::Expr(program, expr))
+            }
+            _ => Ok(ReplLine::Stmts(program)),
+        }
     }
 
-    fn declaration(&mut self) -> Result<Stmt, ErrorInfo> {
-        match self.curr.token {
-            TokenType::Let | TokenType::Const => self.let_declaration(),
-            TokenType::Class => self.class_declaration(),
-            TokenType::Function => {
-                self.advance();
-                self.function_declaration()
+    // a declaration, or None after recording its error and skipping past it
+    fn recovering_declaration(&mut self) -> Option<StmtId> {
+        let start = self.curr.span.start;
+        match self.declaration() {
+            Ok(declaration) => Some(declaration),
+            // the statements around one nested too deeply are cut short,
+            // and their errors would only be noise
+            Err(_) if self.gave_up => None,
+            Err(err) => {
+                if let Error::NestingTooDeep(_) = err.error {
+                    self.gave_up = true;
+                    while !self.curr.is(TokenKind::Eof) {
+                        self.advance();
+                    }
+                } else {
+                    self.synchronize(self.curr.span.start != start);
+                }
+                self.errors.push(err);
+                None
+            }
+        }
+    }
+
+    // Discards tokens up to the end of the broken statement, a semicolon or
+    // a token that can only start a new one, so the mistake is not reported
+    // again as errors in the rest of its statement. Braces are skipped in
+    // pairs so a broken function header takes its body with it, and a `}`
+    // with no `{` before it is left for the enclosing block.
+    fn synchronize(&mut self, progressed: bool) {
+        let mut depth = usize::from(progressed && self.prev_kind == TokenKind::LCurly);
+        let mut skipped = progressed;
+        while !self.curr.is(TokenKind::Eof) {
+            if depth == 0 && skipped {
+                if let TokenKind::Semicolon | TokenKind::RCurly = self.prev_kind {
+                    return;
+                }
+                if let TokenType::Let
+                | TokenType::Const
+                | TokenType::Class
+                | TokenType::Function
+                | TokenType::If
+                | TokenType::While
+                | TokenType::For
+                | TokenType::Return
+                | TokenType::Print
+                | TokenType::LCurly
+                | TokenType::RCurly = self.curr.token
+                {
+                    return;
+                }
+            }
+            match self.curr.token {
+                TokenType::LCurly => depth += 1,
+                TokenType::RCurly => depth = depth.saturating_sub(1),
+                _ => {}
             }
-            _ => self.statement(),
+            self.advance();
+            skipped = true;
         }
     }
 
-    fn let_declaration(&mut self) -> Result<Stmt, ErrorInfo> {
-        let is_const = self.curr.is(TokenType::Const);
-        self.advance();
+    fn declaration(&mut self) -> Result<StmtId, ErrorInfo> {
+        self.commented(|parser| match parser.curr.token {
+            TokenType::Let | TokenType::Const => parser.let_declaration(),
+            TokenType::Class => parser.class_declaration(),
+            TokenType::Function => {
+                let start = parser.advance().span;
+                parser.function_declaration(start)
+            }
+            _ => parser.statement(),
+        })
+    }
+
+    // parses a statement starting at `curr`, which the comments read before
+    // it lead
+    fn commented(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<StmtId, ErrorInfo>,
+    ) -> Result<StmtId, ErrorInfo> {
+        let comments = std::mem::take(&mut self.pending_comments);
+        let stmt = parse(self)?;
+        self.comments.attach(stmt, comments);
+        Ok(stmt)
+    }
+
+    fn let_declaration(&mut self) -> Result<StmtId, ErrorInfo> {
+        let is_const = self.curr.is(TokenKind::Const);
+        let start = self.advance().span;
         let (name, span) = self.get_identifier()?;
         let mut value = None;
-        if self.curr.is(TokenType::Assign) {
+        if self.curr.is(TokenKind::Assign) {
             self.advance();
             value = Some(self.expression()?);
         }
-        self.should_be(TokenType::Semicolon)?;
-        Ok(Stmt::Let {
+        self.semicolon_after("variable declaration")?;
+        let stmt = Stmt::Let {
             name,
             value,
             is_const,
             span,
-        })
+            slot: None,
+        };
+        Ok(self.add_stmt(&start, stmt))
     }
 
-    fn class_declaration(&mut self) -> Result<Stmt, ErrorInfo> {
+    fn class_declaration(&mut self) -> Result<StmtId, ErrorInfo> {
+        let start = self.advance().span;
         let (name, span) = self.get_identifier()?;
-        let super_class = if self.curr.is(TokenType::Lt) {
+        let super_class = if self.curr.is(TokenKind::Lt) {
             self.advance();
             let (super_class_name, span) = self.get_identifier()?;
             if name == super_class_name {
-                let error = Error::Parse("Cannot inherit from itself".to_string());
+                let error = Error::InheritsFromItself;
                 return Err(ErrorInfo::new_with_span(error, span));
             }
             Some(super_class_name)
         } else {
             None
         };
-        self.should_be(TokenType::LBrace)?;
+        self.should_be(TokenKind::LCurly)?;
         let mut methods = Vec::new();
-        while !self.curr.is(TokenType::RBrace) && !self.curr.is(TokenType::Eof) {
-            methods.push(self.function_declaration()?);
+        while !self.curr.is(TokenKind::RCurly) && !self.curr.is(TokenKind::Eof) {
+            if !matches!(self.curr.token, TokenType::Identifier(_)) {
+                return Err(self.expected_one_of(&["a method name", "'}'"]));
+            }
+            methods.push(
+                self.commented(|parser| parser.function_declaration(parser.curr.span.clone()))?,
+            );
         }
-        self.should_be(TokenType::RBrace)?;
-        Ok(Stmt::Class {
+        self.should_be(TokenKind::RCurly)?;
+        let stmt = Stmt::Class {
             name,
             super_class,
             methods,
             span,
-        })
+        };
+        Ok(self.add_stmt(&start, stmt))
+    }
+
+    // a function or method, from its name on, which starts at `start` with
+    // the `fn` of a function
+    fn function_declaration(&mut self, start: Span) -> Result<StmtId, ErrorInfo> {
+        self.nested("statement", |parser| parser.function(&start))
     }
 
-    fn function_declaration(&mut self) -> Result<Stmt, ErrorInfo> {
+    fn function(&mut self, start: &Span) -> Result<StmtId, ErrorInfo> {
         let (name, span) = self.get_identifier()?;
-        self.should_be(TokenType::LParen)?;
+        self.should_be(TokenKind::LParen)?;
         let mut params = Vec::new();
-        if !self.curr.is(TokenType::RParen) {
+        if !self.curr.is(TokenKind::RParen) {
             let (param, _) = self.get_identifier()?;
             params.push(param);
-            while self.curr.is(TokenType::Comma) {
+            while self.curr.is(TokenKind::Comma) {
                 self.advance();
                 let (param, _) = self.get_identifier()?;
                 params.push(param);
             }
         }
-        self.should_be(TokenType::RParen)?;
-        if let Stmt::Block { stmts: body } = self.block_statement()? {
-            Ok(Stmt::Function {
-                name,
-                params,
-                body,
-                span,
+        self.should_be(TokenKind::RParen)?;
+        let body = self.block()?;
+        let stmt = Stmt::Function {
+            name,
+            params,
+            body,
+            span,
+            slot: None,
+        };
+        Ok(self.add_stmt(start, stmt))
+    }
+
+    fn statement(&mut self) -> Result<StmtId, ErrorInfo> {
+        self.commented(|parser| {
+            parser.nested("statement", |parser| match parser.curr.token {
+                TokenType::Print => parser.print_statement(),
+                TokenType::If => parser.if_statement(),
+                TokenType::While => parser.while_statement(),
+                TokenType::For => parser.for_statement(),
+                TokenType::Return => parser.return_statement(),
+                TokenType::LCurly => parser.block_statement(),
+                _ => {
+                    parser.statement_start()?;
+                    parser.expression_statement()
+                }
             })
-        } else {
-            unreachable!()
-        }
+        })
     }
 
-    fn statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        match self.curr.token {
-            TokenType::Print => self.print_statement(),
-            TokenType::If => self.if_statement(),
-            TokenType::While => self.while_statement(),
-            TokenType::For => self.for_statement(),
-            TokenType::Return => self.return_statement(),
-            TokenType::LCurly => self.block_statement(),
-            _ => self.expression_statement(),
+    // a token that no statement or expression can start with, reported with
+    // all that could have been there instead
+    fn statement_start(&self) -> Result<(), ErrorInfo> {
+        if can_start_expression(&self.curr.token) {
+            return Ok(());
         }
+        let mut expected: Vec<&str> = STATEMENT_STARTS.to_vec();
+        expected.push("an expression");
+        Err(self.expected_one_of(&expected))
     }
 
-    fn expression_statement(&mut self) -> Result<Stmt, ErrorInfo> {
+    fn expression_statement(&mut self) -> Result<StmtId, ErrorInfo> {
         let expr = self.expression()?;
-        self.should_be(TokenType::Semicolon)?;
-        Ok(Stmt::Expr { expr })
+        let start = self.start_of(expr);
+        if self.repl_line && self.curr.is(TokenKind::Eof) {
+            return Ok(self.add_stmt(&start, Stmt::Expr { expr }));
+        }
+        self.semicolon_after("expression")?;
+        Ok(self.add_stmt(&start, Stmt::Expr { expr }))
     }
 
-    fn print_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.advance();
-        let expr = self.expression()?;
-        self.should_be(TokenType::Semicolon)?;
-        Ok(Stmt::Print { expr })
+    fn print_statement(&mut self) -> Result<StmtId, ErrorInfo> {
+        let span = self.advance().span;
+        let mut exprs = vec![self.expression()?];
+        while self.curr.is(TokenKind::Comma) {
+            self.advance();
+            exprs.push(self.expression()?);
+        }
+        self.semicolon_after("print statement")?;
+        let start = span.clone();
+        Ok(self.add_stmt(&start, Stmt::Print { exprs, span }))
     }
 
-    fn return_statement(&mut self) -> Result<Stmt, ErrorInfo> {
+    fn return_statement(&mut self) -> Result<StmtId, ErrorInfo> {
         let val = self.advance();
         let mut value = None;
-        if !self.curr.is(TokenType::Semicolon) {
+        if !self.curr.is(TokenKind::Semicolon) {
             value = Some(self.expression()?);
         }
-        self.should_be(TokenType::Semicolon)?;
-        Ok(Stmt::Return {
+        self.semicolon_after("return statement")?;
+        let start = val.span.clone();
+        let stmt = Stmt::Return {
             value,
             span: val.span,
-        })
+        };
+        Ok(self.add_stmt(&start, stmt))
     }
 
-    fn for_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.advance();
+    fn for_statement(&mut self) -> Result<StmtId, ErrorInfo> {
+        let span = self.advance().span;
         let mut stmts = Vec::new();
-        self.should_be(TokenType::LParen)?;
+        self.should_be(TokenKind::LParen)?;
 
         match self.curr.token {
-            TokenType::Semicolon => {}
+            TokenType::Semicolon => {
+                self.advance();
+            }
             TokenType::Let | TokenType::Const => stmts.push(self.let_declaration()?),
+            ref token if !can_start_expression(token) => {
+                return Err(self.expected_one_of(&["';'", "'let'", "an expression"]))
+            }
             _ => stmts.push(self.expression_statement()?),
         };
 
-        let condition = if self.curr.is(TokenType::Semicolon) {
-            Expr::Literal {
+        let condition = if self.curr.is(TokenKind::Semicolon) {
+            self.ast.add_expr(Expr::Literal {
                 value: LiteralType::Boolean(true),
-            }
+            })
         } else {
             self.expression()?
         };
-        self.should_be(TokenType::Semicolon)?;
+        self.semicolon_after("loop condition")?;
         let increment = match self.curr.token {
             TokenType::RParen => None,
             _ => Some(self.expression()?),
         };
-        self.should_be(TokenType::RParen)?;
+        self.should_be(TokenKind::RParen)?;
 
         let mut body = self.statement()?;
         if let Some(expr) = increment {
-            body = Stmt::Block {
-                stmts: vec![body, Stmt::Expr { expr }],
-            }
+            // made up rather than written, so neither has an extent
+            let increment = self.ast.add_stmt(Stmt::Expr { expr });
+            body = self.ast.add_stmt(Stmt::Block {
+                stmts: vec![body, increment],
+            });
         }
-        let while_stmt = Stmt::While {
-            condition,
-            body: Box::new(body),
-        };
+        let start = span.clone();
+        let while_stmt = self.add_stmt(
+            &start,
+            Stmt::While {
+                condition,
+                body,
+                span,
+            },
+        );
         stmts.push(while_stmt);
-        Ok(Stmt::Block { stmts })
+        Ok(self.add_stmt(&start, Stmt::Block { stmts }))
     }
 
-    fn if_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.advance();
-        self.should_be(TokenType::LParen)?;
+    fn if_statement(&mut self) -> Result<StmtId, ErrorInfo> {
+        let span = self.advance().span;
+        self.should_be(TokenKind::LParen)?;
         let condition = self.expression()?;
-        self.should_be(TokenType::RParen)?;
-        let truthy = Box::new(self.statement()?);
+        self.should_be(TokenKind::RParen)?;
+        let truthy = self.statement()?;
         let mut falsy = None;
-        if self.curr.is(TokenType::Else) {
+        if self.curr.is(TokenKind::Else) {
             self.advance();
-            falsy = Some(Box::new(self.statement()?));
+            falsy = Some(self.statement()?);
         }
-        Ok(Stmt::If {
+        let start = span.clone();
+        let stmt = Stmt::If {
             condition,
             truthy,
             falsy,
-        })
+            span,
+        };
+        Ok(self.add_stmt(&start, stmt))
     }
 
-    fn while_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.advance();
-        self.should_be(TokenType::LParen)?;
+    fn while_statement(&mut self) -> Result<StmtId, ErrorInfo> {
+        let span = self.advance().span;
+        self.should_be(TokenKind::LParen)?;
         let condition = self.expression()?;
-        self.should_be(TokenType::RParen)?;
-        let body = Box::new(self.statement()?);
-        Ok(Stmt::While { condition, body })
+        self.should_be(TokenKind::RParen)?;
+        let body = self.statement()?;
+        let start = span.clone();
+        let stmt = Stmt::While {
+            condition,
+            body,
+            span,
+        };
+        Ok(self.add_stmt(&start, stmt))
     }
 
-    fn block_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.should_be(TokenType::LCurly)?;
+    fn block_statement(&mut self) -> Result<StmtId, ErrorInfo> {
+        let start = self.curr.span.clone();
+        let stmts = self.block()?;
+        Ok(self.add_stmt(&start, Stmt::Block { stmts }))
+    }
+
+    // the statements between a pair of braces
+    fn block(&mut self) -> Result<Vec<StmtId>, ErrorInfo> {
+        self.should_be(TokenKind::LCurly)?;
         let mut stmts = Vec::new();
-        while !self.curr.is(TokenType::RCurly) && !self.curr.is(TokenType::Eof) {
-            stmts.push(self.declaration()?);
+        while !self.curr.is(TokenKind::RCurly) && !self.curr.is(TokenKind::Eof) {
+            if let Some(declaration) = self.recovering_declaration() {
+                stmts.push(declaration);
+            }
         }
-        self.should_be(TokenType::RCurly)?;
-        Ok(Stmt::Block { stmts })
+        self.should_be(TokenKind::RCurly)?;
+        Ok(stmts)
     }
 }
 
 impl Parser {
-    fn expression(&mut self) -> Result<Expr, ErrorInfo> {
-        self.assignment()
+    fn expression(&mut self) -> Result<ExprId, ErrorInfo> {
+        self.nested("expression", Self::assignment)
     }
 
-    fn assignment(&mut self) -> Result<Expr, ErrorInfo> {
+    fn assignment(&mut self) -> Result<ExprId, ErrorInfo> {
         let left = self.or()?;
         if let TokenType::Assign
         | TokenType::PlusEq
@@ -244,97 +483,86 @@ impl Parser {
         | TokenType::OrEq
         | TokenType::XorEq = self.curr.token
         {
-            let mut op = self.advance();
+            let TokenInfo { token, span } = self.advance();
             let mut right = self.or()?;
-            if let Some(token) = desugar_assign(op.token) {
-                op.token = token;
-                right = Expr::Binary {
-                    left: Box::new(left.clone()),
-                    op: op.clone(),
-                    right: Box::new(right),
-                };
+            if !matches!(self.ast[left], Expr::Variable { .. } | Expr::Get { .. }) {
+                let error = Error::InvalidAssignmentTarget;
+                return Err(ErrorInfo::new_with_span(error, span));
+            }
+            let start = self.start_of(left);
+            // the target is read back through the same node
+            if let Some(token) = desugar_assign(token) {
+                let op = TokenInfo { token, span };
+                right = self.add_expr(&start, Expr::Binary { left, op, right });
             }
 
-            return match left {
-                Expr::Variable { name, span } => Ok(Expr::Assign {
-                    name,
-                    value: Box::new(right),
-                    span,
-                }),
-                Expr::Get { object, name, span } => Ok(Expr::Set {
-                    object,
-                    name,
-                    value: Box::new(right),
-                    span,
-                }),
-                _ => {
-                    let error = Error::Parse("Invalid assignment target".to_string());
-                    return Err(ErrorInfo::new_with_span(error, op.span));
-                }
+            let assign = match &self.ast[left] {
+                Expr::Variable { name, span, .. } => Expr::Assign {
+                    name: *name,
+                    value: right,
+                    span: span.clone(),
+                    slot: None,
+                },
+                Expr::Get { object, name, span } => Expr::Set {
+                    object: *object,
+                    name: *name,
+                    value: right,
+                    span: span.clone(),
+                },
+                _ => unreachable!(),
             };
+            return Ok(self.add_expr(&start, assign));
         }
 
         Ok(left)
     }
 
-    fn or(&mut self) -> Result<Expr, ErrorInfo> {
+    fn or(&mut self) -> Result<ExprId, ErrorInfo> {
         let mut left = self.and()?;
-        while self.curr.is(TokenType::Or) {
+        while self.curr.is(TokenKind::Or) {
             let op = self.advance();
             let right = self.and()?;
-            left = Expr::Binary {
-                left: Box::new(left),
-                op,
-                right: Box::new(right),
-            };
+            let start = self.start_of(left);
+            left = self.add_expr(&start, Expr::Binary { left, op, right });
         }
         Ok(left)
     }
 
-    fn and(&mut self) -> Result<Expr, ErrorInfo> {
+    fn and(&mut self) -> Result<ExprId, ErrorInfo> {
         let mut left = self.equality()?;
-        while self.curr.is(TokenType::LogicalAnd) {
+        while self.curr.is(TokenKind::LogicalAnd) {
             let op = self.advance();
             let right = self.equality()?;
-            left = Expr::Binary {
-                left: Box::new(left),
-                op,
-                right: Box::new(right),
-            };
+            let start = self.start_of(left);
+            left = self.add_expr(&start, Expr::Binary { left, op, right });
         }
         Ok(left)
     }
 
-    fn equality(&mut self) -> Result<Expr, ErrorInfo> {
+    fn equality(&mut self) -> Result<ExprId, ErrorInfo> {
         let mut left = self.comparison()?;
         while let TokenType::Eq | TokenType::Ne = self.curr.token {
             let op = self.advance();
             let right = self.comparison()?;
-            left = Expr::Binary {
-                left: Box::new(left),
-                op,
-                right: Box::new(right),
-            };
+            let start = self.start_of(left);
+            left = self.add_expr(&start, Expr::Binary { left, op, right });
         }
         Ok(left)
     }
 
-    fn comparison(&mut self) -> Result<Expr, ErrorInfo> {
+    fn comparison(&mut self) -> Result<ExprId, ErrorInfo> {
         let mut left = self.term()?;
         while let TokenType::Gt | TokenType::Gte | TokenType::Lt | TokenType::Lte = self.curr.token
         {
             let op = self.advance();
             let right = self.term()?;
-            left = Expr::Binary {
-                left: Box::new(left),
-                op,
-                right: Box::new(right),
-            };
+            let start = self.start_of(left);
+            left = self.add_expr(&start, Expr::Binary { left, op, right });
         }
         Ok(left)
     }
 
-    fn term(&mut self) -> Result<Expr, ErrorInfo> {
+    fn term(&mut self) -> Result<ExprId, ErrorInfo> {
         let mut left = self.factor()?;
         while let TokenType::Plus
         | TokenType::Minus
@@ -345,174 +573,304 @@ impl Parser {
         {
             let op = self.advance();
             let right = self.factor()?;
-            left = Expr::Binary {
-                left: Box::new(left),
-                op,
-                right: Box::new(right),
-            };
+            let start = self.start_of(left);
+            left = self.add_expr(&start, Expr::Binary { left, op, right });
         }
         Ok(left)
     }
 
-    fn factor(&mut self) -> Result<Expr, ErrorInfo> {
+    fn factor(&mut self) -> Result<ExprId, ErrorInfo> {
         let mut left = self.unary()?;
         while let TokenType::Times | TokenType::Divide = self.curr.token {
             let op = self.advance();
             let right = self.unary()?;
-            left = Expr::Binary {
-                left: Box::new(left),
-                op,
-                right: Box::new(right),
-            };
+            let start = self.start_of(left);
+            left = self.add_expr(&start, Expr::Binary { left, op, right });
         }
         Ok(left)
     }
 
-    fn unary(&mut self) -> Result<Expr, ErrorInfo> {
+    fn unary(&mut self) -> Result<ExprId, ErrorInfo> {
         if let TokenType::Minus | TokenType::Not | TokenType::Plus = self.curr.token {
             let op = self.advance();
-            let right = self.unary()?;
-            Ok(Expr::Unary {
-                op,
-                right: Box::new(right),
-            })
+            let start = op.span.clone();
+            let right = self.nested("expression", Self::unary)?;
+            Ok(self.add_expr(&start, Expr::Unary { op, right }))
         } else {
             self.call()
         }
     }
 
-    fn call(&mut self) -> Result<Expr, ErrorInfo> {
+    fn call(&mut self) -> Result<ExprId, ErrorInfo> {
         let mut expr = self.primary()?;
+        let start = self.start_of(expr);
         loop {
-            if self.curr.is(TokenType::LParen) {
+            if self.curr.is(TokenKind::LParen) {
                 let span = self.curr.span.clone();
                 let args = self.get_argument_list()?;
-                break Ok(Expr::Call {
-                    callee: Box::new(expr),
+                let call = Expr::Call {
+                    callee: expr,
                     args,
                     span,
-                });
-            } else if self.curr.is(TokenType::Dot) {
+                };
+                expr = self.add_expr(&start, call);
+            } else if self.curr.is(TokenKind::Dot) {
                 self.advance();
                 let (name, span) = self.get_identifier()?;
-                expr = Expr::Get {
-                    object: Box::new(expr),
+                let get = Expr::Get {
+                    object: expr,
                     name,
                     span,
                 };
+                expr = self.add_expr(&start, get);
             } else {
                 break Ok(expr);
             }
         }
     }
 
-    fn get_argument_list(&mut self) -> Result<Vec<Expr>, ErrorInfo> {
+    fn get_argument_list(&mut self) -> Result<Vec<ExprId>, ErrorInfo> {
         let mut args = Vec::new();
-        self.should_be(TokenType::LParen)?;
-        if !self.curr.is(TokenType::RParen) {
+        self.should_be(TokenKind::LParen)?;
+        if !self.curr.is(TokenKind::RParen) {
             loop {
                 if args.len() >= 127 {
                     let error = Error::TooManyParamerters;
                     return Err(ErrorInfo::new_with_span(error, self.curr.span.clone()));
                 }
                 args.push(self.expression()?);
-                if !self.curr.is(TokenType::Comma) {
+                if !self.curr.is(TokenKind::Comma) {
                     break;
                 }
-                self.should_be(TokenType::Comma)?;
+                self.should_be(TokenKind::Comma)?;
             }
         }
-        self.should_be(TokenType::RParen)?;
+        self.should_be(TokenKind::RParen)?;
         Ok(args)
     }
 
-    fn primary(&mut self) -> Result<Expr, ErrorInfo> {
-        let tok = self.curr.clone();
-        let span = tok.span;
-        match tok.token {
+    fn primary(&mut self) -> Result<ExprId, ErrorInfo> {
+        let span = self.curr.span.clone();
+        match self.curr.token {
             TokenType::True => {
                 self.advance();
                 let value = LiteralType::Boolean(true);
-                Ok(Expr::Literal { value })
+                Ok(self.add_expr(&span, Expr::Literal { value }))
             }
             TokenType::False => {
                 self.advance();
                 let value = LiteralType::Boolean(false);
-                Ok(Expr::Literal { value })
+                Ok(self.add_expr(&span, Expr::Literal { value }))
             }
             TokenType::Nil => {
                 self.advance();
                 let value = LiteralType::Nil;
-                Ok(Expr::Literal { value })
+                Ok(self.add_expr(&span, Expr::Literal { value }))
             }
             TokenType::Number(x) => {
                 self.advance();
                 let value = LiteralType::Number(x);
-                Ok(Expr::Literal { value })
+                Ok(self.add_expr(&span, Expr::Literal { value }))
             }
-            TokenType::String(x) => {
-                self.advance();
+            TokenType::String(_) => {
+                let TokenType::String(x) = self.advance().token else {
+                    unreachable!()
+                };
                 let value = LiteralType::String(x);
-                Ok(Expr::Literal { value })
+                Ok(self.add_expr(&span, Expr::Literal { value }))
             }
             TokenType::Identifier(name) => {
                 self.advance();
-                Ok(Expr::Variable { name, span })
+                let variable = Expr::Variable {
+                    name,
+                    span: span.clone(),
+                    slot: None,
+                };
+                Ok(self.add_expr(&span, variable))
             }
             TokenType::LParen => {
                 self.advance();
-                let expr = Box::new(self.expression()?);
-                self.should_be(TokenType::RParen)?;
-                Ok(Expr::Grouping { expr, span })
+                let expr = self.expression()?;
+                self.should_be(TokenKind::RParen)?;
+                let grouping = Expr::Grouping {
+                    expr,
+                    span: span.clone(),
+                };
+                Ok(self.add_expr(&span, grouping))
             }
             TokenType::Super => {
-                self.should_be(TokenType::Dot)?;
-                let (name, span) = self.get_identifier()?;
-                Ok(Expr::Super { name, span })
+                self.advance();
+                self.should_be(TokenKind::Dot)?;
+                let (name, name_span) = self.get_identifier()?;
+                let expr = Expr::Super {
+                    name,
+                    span: name_span,
+                    slot: None,
+                };
+                Ok(self.add_expr(&span, expr))
             }
             TokenType::This => {
-                let name = "this".to_string();
-                Ok(Expr::Variable { name, span })
-            }
-            _ => {
-                let error = Error::Parse(format!("Expect expression found \"{}\"", tok.token));
-                Err(ErrorInfo::new_with_span(error, span))
+                self.advance();
+                let name = Symbol::intern("this");
+                let variable = Expr::Variable {
+                    name,
+                    span: span.clone(),
+                    slot: None,
+                };
+                Ok(self.add_expr(&span, variable))
             }
+            _ => Err(self.expected_one_of(&[
+                "a number", "a string", "a name", "'true'", "'false'", "'nil'", "'this'",
+                "'super'", "'('", "'-'", "'+'", "'!'",
+            ])),
         }
     }
 }
 
 impl Parser {
-    fn should_be(&mut self, token_type: TokenType) -> Result<Span, ErrorInfo> {
+    // Adds `expr` to the tree, parsed from the token at `start` to the last
+    // one taken.
+    fn add_expr(&mut self, start: &Span, expr: Expr) -> ExprId {
+        let id = self.ast.add_expr(expr);
+        self.ast.set_expr_extent(id, self.extent_from(start));
+        id
+    }
+
+    fn add_stmt(&mut self, start: &Span, stmt: Stmt) -> StmtId {
+        let id = self.ast.add_stmt(stmt);
+        self.ast.set_stmt_extent(id, self.extent_from(start));
+        id
+    }
+
+    fn extent_from(&self, start: &Span) -> Span {
+        start.extend_to(&self.prev_span)
+    }
+
+    // the first token of `expr`, which is the first of what contains it
+    fn start_of(&self, expr: ExprId) -> Span {
+        self.ast.expr_extent(expr).cloned().unwrap_or_default()
+    }
+
+    // an error at the current token, which is not any of `expected`
+    fn expected_one_of(&self, expected: &[&str]) -> ErrorInfo {
+        let expected = expected.iter().map(|item| item.to_string()).collect();
+        let error = Error::ExpectedOneOf(expected, self.curr.token.to_string());
+        ErrorInfo::new_with_span(error, self.curr.span.clone())
+    }
+
+    // Takes the `;` ending a statement. A missing one is reported just past
+    // the end of what it should have ended, on the same line, rather than at
+    // the next token which is often on a later line.
+    fn semicolon_after(&mut self, what: &str) -> Result<(), ErrorInfo> {
+        if self.curr.is(TokenKind::Semicolon) {
+            self.advance();
+            return Ok(());
+        }
+        let mut span = self.prev_span.clone();
+        span.start = span.end;
+        let error = Error::MissingSemicolon(what.to_string());
+        Err(ErrorInfo::new_with_span(error, span))
+    }
+
+    fn should_be(&mut self, kind: TokenKind) -> Result<Span, ErrorInfo> {
         let val = self.advance();
-        if val.token == token_type {
+        if val.is(kind) {
             Ok(val.span)
         } else {
-            let error = Error::Syntax(format!(
-                "Expected: \"{}\" Found: \"{}\"",
-                token_type, val.token
-            ));
+            let error = Error::UnexpectedToken(kind.to_string(), val.token.to_string());
             Err(ErrorInfo::new_with_span(error, val.span))
         }
     }
 
-    fn get_identifier(&mut self) -> Result<(String, Span), ErrorInfo> {
+    fn get_identifier(&mut self) -> Result<(Symbol, Span), ErrorInfo> {
         let val = self.advance();
         if let TokenType::Identifier(name) = val.token {
             Ok((name, val.span))
         } else {
-            let error = Error::Syntax(format!("Expected: \"Identifier\" Found: \"{}\"", val.token));
+            let error =
+                Error::UnexpectedToken(TokenKind::Identifier.to_string(), val.token.to_string());
             Err(ErrorInfo::new_with_span(error, val.span))
         }
     }
 
+    // Parses with `parse` one level deeper, failing once that is deeper
+    // than `max_depth` rather than recursing until the stack overflows.
+    fn nested<T>(
+        &mut self,
+        what: &str,
+        parse: impl FnOnce(&mut Self) -> Result<T, ErrorInfo>,
+    ) -> Result<T, ErrorInfo> {
+        if self.depth == self.max_depth {
+            let error = Error::NestingTooDeep(what.to_string());
+            return Err(ErrorInfo::new_with_span(error, self.curr.span.clone()));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    // Moves on to the next token and hands back the one that was current,
+    // so a token is moved into the AST rather than cloned.
     fn advance(&mut self) -> TokenInfo {
-        self.prev = self.curr.clone();
-        self.curr = self.lexer.next();
-        self.prev.clone()
+        let next = self.next_token();
+        let token = std::mem::replace(&mut self.curr, next);
+        self.prev_kind = token.token.kind();
+        self.prev_span = token.span.clone();
+        token
+    }
+}
+
+impl Parser {
+    // The token after `curr` that is not a comment. The comments before it
+    // lead what starts there if it is a statement, the others lead nothing.
+    fn next_token(&mut self) -> TokenInfo {
+        self.comments.push_others(self.pending_comments.drain(..));
+        loop {
+            let token = self.lexer.next();
+            self.errors.extend(self.lexer.take_errors());
+            if !token.is(TokenKind::Comment) {
+                return token;
+            }
+            let index = self.lexer.line_index();
+            let (line, column) = index.line_column(token.span.start);
+            if index
+                .line(line)
+                .chars()
+                .take(column - 1)
+                .all(char::is_whitespace)
+            {
+                self.pending_comments.push(token);
+            } else {
+                self.comments.push_others([token]);
+            }
+        }
     }
 }
 
+// keywords and symbols a statement other than an expression can start with
+const STATEMENT_STARTS: [&str; 10] = [
+    "'let'", "'const'", "'fn'", "'class'", "'if'", "'while'", "'for'", "'return'", "'print'", "'{'",
+];
+
+fn can_start_expression(token: &TokenType) -> bool {
+    matches!(
+        token,
+        TokenType::Number(_)
+            | TokenType::String(_)
+            | TokenType::Identifier(_)
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil
+            | TokenType::This
+            | TokenType::Super
+            | TokenType::LParen
+            | TokenType::Minus
+            | TokenType::Plus
+            | TokenType::Not
+    )
+}
+
 pub fn desugar_assign(tok: TokenType) -> Option<TokenType> {
     match tok {
         TokenType::PlusEq => Some(TokenType::Plus),
@@ -537,6 +895,12 @@ mod tests {
         let mut parser = Parser::new(Lexer::new(input.into()));
         let expr = parser.parse_program().unwrap();
         assert_eq!(expr.to_string(), "((print \"Hello, World!\"))");
+
+        let input = "print 1, a + 2, \"b\";";
+        let lexer = Lexer::new(input.to_string());
+        let mut parser = Parser::new(lexer);
+        let expr = parser.parse_program().unwrap();
+        assert_eq!(expr.to_string(), "((print 1 (+ a 2) \"b\"))");
     }
     #[test]
     fn test_negative_unary() {
@@ -571,4 +935,270 @@ mod tests {
             "((if (== a 1) then ((print a)) else ((print b))))"
         );
     }
+
+    #[test]
+    fn test_for() {
+        let parse = |input: &str| Parser::from_str(input).parse_program().unwrap().to_string();
+        assert_eq!(
+            parse("for (let i = 0; i < 2; i = i + 1) print i;"),
+            "(((let i 0)(while ((< i 2)) ((print i)(= i (+ i 1))))))"
+        );
+        // any part can be left out, the condition standing for true
+        assert_eq!(parse("for (;;) print 1;"), "(((while (true) (print 1))))");
+        assert_eq!(parse("for (; a;) print 1;"), "(((while (a) (print 1))))");
+    }
+
+    #[test]
+    fn test_recovery() {
+        let input = "
+        let a = ;
+        print a;
+        fn f( { return 1; }
+        let b = 2;
+        while (b > ) b = b - 1;
+        print b;";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let Err(errors) = parser.parse_program() else {
+            panic!("the program has errors")
+        };
+        let lines: Vec<usize> = errors.iter().map(|err| err.span.line).collect();
+        assert_eq!(lines, [2, 4, 6]);
+
+        // blocks recover inside themselves and keep their closing brace
+        let input = "if (a) { let c = ; print 1; }\nprint 2 +;\n}";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let Err(errors) = parser.parse_program() else {
+            panic!("the program has errors")
+        };
+        let lines: Vec<usize> = errors.iter().map(|err| err.span.line).collect();
+        assert_eq!(lines, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_lexer_errors() {
+        // the character is skipped, and reported with the parser's errors
+        let input = "let a = 1 @ 2;\nlet b = \"open";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let Err(errors) = parser.parse_program() else {
+            panic!("the program has errors")
+        };
+        let errors: Vec<(Error, usize)> = errors
+            .into_iter()
+            .map(|err| (err.error, err.span.start))
+            .collect();
+        // the missing semicolon is reported after the 1
+        assert_eq!(errors[0].1, 9);
+        assert_eq!(errors[1], (Error::UnknownCharacter('@'), 10));
+        assert!(errors.contains(&(Error::UnterminatedString, 23)));
+    }
+
+    #[test]
+    fn test_expected_one_of() {
+        let first_error = |input: &str| {
+            let mut parser = Parser::new(Lexer::new(input.to_string()));
+            let Err(errors) = parser.parse_program() else {
+                panic!("{input} has errors")
+            };
+            errors[0].error.to_string()
+        };
+        assert_eq!(
+            first_error("print 1; ) 2;"),
+            "ParseError: Expected one of 'let', 'const', 'fn', 'class', 'if', 'while', 'for', \
+             'return', 'print', '{', or an expression, found ')'"
+        );
+        assert_eq!(
+            first_error("for (class A {}; ;) {}"),
+            "ParseError: Expected one of ';', 'let', or an expression, found 'class'"
+        );
+        assert_eq!(
+            first_error("class A { 1 }"),
+            "ParseError: Expected one of a method name or '}', found '1'"
+        );
+        assert_eq!(
+            first_error("let a = 1 + ;"),
+            "ParseError: Expected one of a number, a string, a name, 'true', 'false', 'nil', \
+             'this', 'super', '(', '-', '+', or '!', found ';'"
+        );
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        let errors = |input: &str| {
+            let mut parser = Parser::new(Lexer::new(input.to_string()));
+            let Err(errors) = parser.parse_program() else {
+                panic!("{input} has errors")
+            };
+            errors
+                .into_iter()
+                .map(|err| (err.error.to_string(), err.span.line, err.span.start))
+                .collect::<Vec<_>>()
+        };
+        // at the end of the line rather than at the next statement
+        assert_eq!(
+            errors("let a = 1\nprint a;"),
+            [(
+                "ParseError: Expected ';' after variable declaration".to_string(),
+                1,
+                9
+            )]
+        );
+        assert_eq!(
+            errors("fn f() {\n  return 1\n}"),
+            [(
+                "ParseError: Expected ';' after return statement".to_string(),
+                2,
+                19
+            )]
+        );
+        assert_eq!(
+            errors("{ print 1, 2 }\nf()\n\n"),
+            [
+                (
+                    "ParseError: Expected ';' after print statement".to_string(),
+                    1,
+                    12
+                ),
+                (
+                    "ParseError: Expected ';' after expression".to_string(),
+                    2,
+                    18
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_repl_line() {
+        let parse = |input: &str| Parser::new(Lexer::new(input.to_string())).parse_repl_line();
+        for input in ["1 + 2", "1 + 2;", "f()"] {
+            assert!(matches!(parse(input), Ok(ReplLine::Expr(..))), "{input}");
+        }
+        for input in [
+            "a = 1",
+            "let a = 1;",
+            "print 1;",
+            "a = 1; a + 1",
+            "f(); f()",
+        ] {
+            assert!(matches!(parse(input), Ok(ReplLine::Stmts(_))), "{input}");
+        }
+        // only the end of the line stands in for the semicolon
+        assert!(parse("1 2").is_err());
+        assert!(parse("{ 1 }").is_err());
+    }
+
+    #[test]
+    fn test_nesting_too_deep() {
+        let errors = |input: String| {
+            let Err(errors) = Parser::new(Lexer::new(input)).parse_program() else {
+                panic!("nested too deep")
+            };
+            errors.into_iter().map(|err| err.error).collect::<Vec<_>>()
+        };
+        let too_deep = |what: &str| vec![Error::NestingTooDeep(what.to_string())];
+        // one error, rather than a crash on the test's stack or an error
+        // for each brace left open
+        let n = 100_000;
+        assert_eq!(errors("(".repeat(n)), too_deep("expression"));
+        assert_eq!(
+            errors(format!("print {}1;", "-".repeat(n))),
+            too_deep("expression")
+        );
+        let calls = format!("{}1{};", "f(".repeat(n), ")".repeat(n));
+        assert_eq!(errors(calls), too_deep("expression"));
+        assert_eq!(errors("{".repeat(n)), too_deep("statement"));
+        assert_eq!(errors("while (true) ".repeat(n)), too_deep("expression"));
+        assert_eq!(errors("fn f() {".repeat(n)), too_deep("statement"));
+
+        // the print, its expression and each pair of parentheses
+        let nested = |depth: usize| format!("print {}1{};", "(".repeat(depth), ")".repeat(depth));
+        let parse = |input: String, max_depth| {
+            Parser::new(Lexer::new(input))
+                .with_max_depth(max_depth)
+                .parse_program()
+        };
+        assert!(parse(nested(2), 4).is_ok());
+        assert!(parse(nested(3), 4).is_err());
+        assert!(parse(nested(MAX_DEPTH - 2), MAX_DEPTH).is_ok());
+    }
+
+    #[test]
+    fn test_line_index() {
+        let lexer = Lexer::from_str("print 1;\nprint (;");
+        let index = lexer.line_index();
+        let mut parser = Parser::new(lexer);
+        // built once by the lexer, for the errors the parser finds
+        assert!(Rc::ptr_eq(&index, &parser.line_index()));
+        let errors = parser.parse_program().unwrap_err();
+        let rendered = errors[0].render_with_index(&index, crate::ColorChoice::Never);
+        assert_eq!(rendered, errors[0].render("print 1;\nprint (;"));
+        assert!(rendered.contains("2 | print (;"));
+    }
+
+    #[test]
+    fn test_comments() {
+        let source = "# one
+# two
+let a = 1; # after a
+class A {
+    # a method
+    m() {
+        # in a block
+        print f(a, # in a call
+            2);
+    }
+    # before a brace
+}
+if (a)
+    # a body
+    print a;
+# at the end";
+        let mut parser = Parser::new(Lexer::from_str(source).with_trivia(true));
+        let program = parser.parse_program().unwrap();
+        let comments = parser.take_comments();
+        let texts = |comments: &[TokenInfo]| -> Vec<String> {
+            comments
+                .iter()
+                .map(|comment| comment.token.to_string())
+                .collect()
+        };
+        let ast = &program.ast;
+        let [let_a, class, if_a] = program.stmts[..] else {
+            panic!("three statements")
+        };
+        assert_eq!(texts(comments.leading(let_a)), ["# one", "# two"]);
+        assert!(comments.leading(class).is_empty());
+        let Stmt::Class { methods, .. } = &ast[class] else {
+            panic!("a class")
+        };
+        assert_eq!(texts(comments.leading(methods[0])), ["# a method"]);
+        let Stmt::Function { body, .. } = &ast[methods[0]] else {
+            panic!("a method")
+        };
+        assert_eq!(texts(comments.leading(body[0])), ["# in a block"]);
+        let Stmt::If { truthy, .. } = &ast[if_a] else {
+            panic!("an if")
+        };
+        assert_eq!(texts(comments.leading(*truthy)), ["# a body"]);
+        assert_eq!(
+            texts(comments.others()),
+            [
+                "# after a",
+                "# in a call",
+                "# before a brace",
+                "# at the end"
+            ]
+        );
+        assert_eq!(comments.len(), 9);
+        // with the spans of the comments
+        assert_eq!(
+            comments.others()[0].span,
+            Span::new(3, 23, 32).with_columns(12, 21)
+        );
+
+        // the same program, without them
+        let mut parser = Parser::from_str(source);
+        assert_eq!(parser.parse_program().unwrap(), program);
+        assert!(parser.take_comments().is_empty());
+    }
 }
@@ -0,0 +1,70 @@
+use crate::{
+    builtins::json::quote, interpretor::Capture, run_source, Error, ErrorFormat, Interpretor,
+};
+
+// Runs `source` on an interpreter of its own that reaches nothing outside
+// of it, and returns what the program printed and every diagnostic it had
+// as `{"output": "...", "diagnostics": [...]}`. The diagnostics are the
+// objects `ErrorInfo::to_json` writes. This is what a browser playground
+// built with the `wasm` feature calls, handing the result to `JSON.parse`.
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+pub fn run(source: &str) -> String {
+    let (output, diagnostics) = (Capture::default(), Capture::default());
+    let mut interpretor = Interpretor::new()
+        .with_output(Box::new(output.clone()))
+        .with_diagnostics(Box::new(diagnostics.clone()))
+        .with_error_format(ErrorFormat::Json)
+        .with_filesystem_access(false)
+        .without_input();
+    let result = run_source(source, &mut interpretor);
+    let index = interpretor.line_index().expect("indexed by the parse");
+    // flushes what was printed
+    drop(interpretor);
+    let mut found: Vec<String> = diagnostics.text().lines().map(str::to_string).collect();
+    if let Err(errors) = result {
+        let stopped = errors
+            .iter()
+            .filter(|err| !matches!(err.error, Error::Exit(_)));
+        found.extend(stopped.map(|err| err.to_json_with_index(Some(&index))));
+    }
+    format!(
+        "{{\"output\": {}, \"diagnostics\": [{}]}}",
+        quote(&output.text()),
+        found.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+
+    #[test]
+    fn test_output() {
+        assert_eq!(
+            run("print \"hi\";\nprint 1 + 1;"),
+            "{\"output\": \"hi\\n2\\n\", \"diagnostics\": []}"
+        );
+        assert_eq!(
+            run("print 1; exit(1); print 2;"),
+            "{\"output\": \"1\\n\", \"diagnostics\": []}"
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let result = run("print 1 / 0;\nprint \"after\";\nfn f() { let unused = 1; }");
+        assert!(
+            result.starts_with("{\"output\": \"after\\n\", \"diagnostics\": [{"),
+            "{result}"
+        );
+        assert_eq!(result.matches("\"severity\"").count(), 2, "{result}");
+        assert!(result.contains("\"code\": \"W0301\""), "{result}");
+        assert!(result.contains("\"code\": \"E0401\""), "{result}");
+
+        let result = run("let = 1;\nprint (;");
+        assert_eq!(result.matches("\"code\": \"E02").count(), 2, "{result}");
+        // nothing reaches the host
+        let result = run("read_file(\"/etc/hostname\");");
+        assert!(result.contains("\"code\": \"E04"), "{result}");
+    }
+}
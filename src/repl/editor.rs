@@ -0,0 +1,432 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, IsTerminal, Read, Write},
+    path::PathBuf,
+};
+
+use super::ReadLine;
+
+// A minimal line editor for the REPL on a terminal: the cursor moves with
+// the arrow keys, Home and End keys (or Ctrl-A and Ctrl-E), backspace and
+// delete work anywhere on the line, and up and down step through the lines
+// entered before. History is appended to `history` when a file is given,
+// and read back from it at the start of the next session. Off a terminal
+// lines are read as they come, without any editing.
+pub struct Editor {
+    history: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl Editor {
+    pub fn new(history: Option<PathBuf>) -> Self {
+        let lines = history
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        Self {
+            history: lines.lines().map(str::to_string).collect(),
+            path: history,
+        }
+    }
+
+    fn remember(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if let Some(path) = &self.path {
+            // history is a convenience, so failing to save it is not an error
+            let file = OpenOptions::new().create(true).append(true).open(path);
+            let _ = file.and_then(|mut file| writeln!(file, "{line}"));
+        }
+    }
+
+    fn read_plain(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let mut stdout = io::stdout();
+        write!(stdout, "{prompt}")?;
+        stdout.flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            writeln!(stdout)?;
+            return Ok(None);
+        }
+        Ok(Some(line))
+    }
+}
+
+impl ReadLine for Editor {
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let stdin = io::stdin();
+        if !stdin.is_terminal() {
+            return self.read_plain(prompt);
+        }
+        let Some(_raw) = raw::RawMode::enable() else {
+            return self.read_plain(prompt);
+        };
+        let mut stdout = io::stdout();
+        let mut line = Line::new(&self.history);
+        line.draw(&mut stdout, prompt)?;
+        let mut input = stdin.lock();
+        loop {
+            match line.apply(read_key(&mut input)?) {
+                None => line.draw(&mut stdout, prompt)?,
+                Some(done) => {
+                    write!(stdout, "\r\n")?;
+                    stdout.flush()?;
+                    return match done {
+                        Done::Line(text) => {
+                            self.remember(&text);
+                            Ok(Some(text + "\n"))
+                        }
+                        Done::Eof => Ok(None),
+                        Done::Interrupt => Err(io::ErrorKind::Interrupted.into()),
+                    };
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    Char(char),
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+    Enter,
+    // Ctrl-C
+    Interrupt,
+    // Ctrl-D
+    Eof,
+    Ignored,
+}
+
+// Decodes the next key pressed from the bytes a terminal sends for it.
+fn read_key(input: &mut dyn Read) -> io::Result<Key> {
+    let byte = read_byte(input)?;
+    let key = match byte {
+        None | Some(4) => Key::Eof,
+        Some(1) => Key::Home,
+        Some(3) => Key::Interrupt,
+        Some(5) => Key::End,
+        Some(8) | Some(127) => Key::Backspace,
+        Some(b'\r') | Some(b'\n') => Key::Enter,
+        Some(0x1b) => read_escape(input)?,
+        Some(byte) if byte < 0x20 => Key::Ignored,
+        Some(byte) => {
+            // the rest of a character of several bytes
+            let len = match byte {
+                0xf0.. => 4,
+                0xe0.. => 3,
+                0xc0.. => 2,
+                _ => 1,
+            };
+            let mut bytes = vec![byte];
+            for _ in 1..len {
+                bytes.extend(read_byte(input)?);
+            }
+            match std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(c) => Key::Char(c),
+                None => Key::Ignored,
+            }
+        }
+    };
+    Ok(key)
+}
+
+// the key of an escape sequence, such as `ESC [ A` for up
+fn read_escape(input: &mut dyn Read) -> io::Result<Key> {
+    if !matches!(read_byte(input)?, Some(b'[') | Some(b'O')) {
+        return Ok(Key::Ignored);
+    }
+    let key = match read_byte(input)? {
+        Some(b'A') => Key::Up,
+        Some(b'B') => Key::Down,
+        Some(b'C') => Key::Right,
+        Some(b'D') => Key::Left,
+        Some(b'H') => Key::Home,
+        Some(b'F') => Key::End,
+        Some(digit @ b'0'..=b'9') => {
+            // `ESC [ 3 ~` and the like, up to their final `~`
+            let mut last = read_byte(input)?;
+            while matches!(last, Some(b'0'..=b'9')) {
+                last = read_byte(input)?;
+            }
+            match (digit, last) {
+                (b'3', Some(b'~')) => Key::Delete,
+                (b'1' | b'7', Some(b'~')) => Key::Home,
+                (b'4' | b'8', Some(b'~')) => Key::End,
+                _ => Key::Ignored,
+            }
+        }
+        _ => Key::Ignored,
+    };
+    Ok(key)
+}
+
+fn read_byte(input: &mut dyn Read) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match input.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Done {
+    Line(String),
+    Eof,
+    Interrupt,
+}
+
+// the line being edited, and where it is in the history
+struct Line<'a> {
+    chars: Vec<char>,
+    cursor: usize,
+    history: &'a [String],
+    // index of the entry of the history shown, or its length for the line
+    // being typed, which is kept in `draft` while an older one is shown
+    entry: usize,
+    draft: Vec<char>,
+}
+
+impl<'a> Line<'a> {
+    fn new(history: &'a [String]) -> Self {
+        Self {
+            chars: Vec::new(),
+            cursor: 0,
+            history,
+            entry: history.len(),
+            draft: Vec::new(),
+        }
+    }
+
+    // edits the line for `key`, or returns how the line ended
+    fn apply(&mut self, key: Key) -> Option<Done> {
+        match key {
+            Key::Char(c) => {
+                self.chars.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.chars.remove(self.cursor);
+            }
+            Key::Delete if self.cursor < self.chars.len() => {
+                self.chars.remove(self.cursor);
+            }
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(self.chars.len()),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.chars.len(),
+            Key::Up if self.entry > 0 => self.show(self.entry - 1),
+            Key::Down if self.entry < self.history.len() => self.show(self.entry + 1),
+            Key::Enter => return Some(Done::Line(self.chars.iter().collect())),
+            Key::Interrupt => return Some(Done::Interrupt),
+            // as in a shell, Ctrl-D deletes unless the line is empty
+            Key::Eof if self.chars.is_empty() => return Some(Done::Eof),
+            Key::Eof => return self.apply(Key::Delete),
+            _ => {}
+        }
+        None
+    }
+
+    fn show(&mut self, entry: usize) {
+        if self.entry == self.history.len() {
+            self.draft = self.chars.clone();
+        }
+        self.entry = entry;
+        self.chars = match self.history.get(entry) {
+            Some(line) => line.chars().collect(),
+            None => self.draft.clone(),
+        };
+        self.cursor = self.chars.len();
+    }
+
+    // redraws the prompt and the line, leaving the cursor where it is in it
+    fn draw(&self, output: &mut dyn Write, prompt: &str) -> io::Result<()> {
+        let text: String = self.chars.iter().collect();
+        write!(output, "\r{prompt}{text}\x1b[K")?;
+        let after = self.chars.len() - self.cursor;
+        if after > 0 {
+            write!(output, "\x1b[{after}D")?;
+        }
+        output.flush()
+    }
+}
+
+// Turns off the terminal's own line editing and echo while a line is read,
+// and turns them back on once it has been. Only Linux has the layout of
+// `termios` declared here, so elsewhere lines are read without editing.
+#[cfg(target_os = "linux")]
+mod raw {
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Termios {
+        iflag: u32,
+        oflag: u32,
+        cflag: u32,
+        lflag: u32,
+        line: u8,
+        cc: [u8; 32],
+        ispeed: u32,
+        ospeed: u32,
+    }
+
+    extern "C" {
+        fn tcgetattr(fd: i32, termios: *mut Termios) -> i32;
+        fn tcsetattr(fd: i32, action: i32, termios: *const Termios) -> i32;
+    }
+
+    const STDIN: i32 = 0;
+    const TCSANOW: i32 = 0;
+    const ISIG: u32 = 0o1;
+    const ICANON: u32 = 0o2;
+    const ECHO: u32 = 0o10;
+    const IEXTEN: u32 = 0o100000;
+    const ICRNL: u32 = 0o400;
+    const IXON: u32 = 0o2000;
+    const VTIME: usize = 5;
+    const VMIN: usize = 6;
+
+    pub struct RawMode(Termios);
+
+    impl RawMode {
+        pub fn enable() -> Option<Self> {
+            let mut original = Termios {
+                iflag: 0,
+                oflag: 0,
+                cflag: 0,
+                lflag: 0,
+                line: 0,
+                cc: [0; 32],
+                ispeed: 0,
+                ospeed: 0,
+            };
+            // SAFETY: both calls only read and write the struct passed in
+            unsafe {
+                if tcgetattr(STDIN, &mut original) != 0 {
+                    return None;
+                }
+                let mut raw = original;
+                raw.lflag &= !(ECHO | ICANON | ISIG | IEXTEN);
+                raw.iflag &= !(ICRNL | IXON);
+                raw.cc[VMIN] = 1;
+                raw.cc[VTIME] = 0;
+                if tcsetattr(STDIN, TCSANOW, &raw) != 0 {
+                    return None;
+                }
+            }
+            Some(Self(original))
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            // SAFETY: restores the settings read in `enable`
+            unsafe { tcsetattr(STDIN, TCSANOW, &self.0) };
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod raw {
+    pub struct RawMode;
+
+    impl RawMode {
+        pub fn enable() -> Option<Self> {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(mut bytes: &[u8]) -> Vec<Key> {
+        let mut keys = Vec::new();
+        while !bytes.is_empty() {
+            keys.push(read_key(&mut bytes).unwrap());
+        }
+        keys
+    }
+
+    // the line that typing `bytes` ends with
+    fn edit(history: &[String], bytes: &[u8]) -> Option<Done> {
+        let mut line = Line::new(history);
+        keys(bytes).into_iter().find_map(|key| line.apply(key))
+    }
+
+    #[test]
+    fn test_read_key() {
+        assert_eq!(
+            keys(b"a\x1b[A\x1b[B\x1b[C\x1b[D\x1b[3~\x1bOH\x7f\r\x03\x04"),
+            [
+                Key::Char('a'),
+                Key::Up,
+                Key::Down,
+                Key::Right,
+                Key::Left,
+                Key::Delete,
+                Key::Home,
+                Key::Backspace,
+                Key::Enter,
+                Key::Interrupt,
+                Key::Eof
+            ]
+        );
+        assert_eq!(keys("é".as_bytes()), [Key::Char('é')]);
+        assert_eq!(keys(b""), []);
+        assert_eq!(read_key(&mut &b""[..]).unwrap(), Key::Eof);
+    }
+
+    #[test]
+    fn test_editing() {
+        let line = |text: &str| Some(Done::Line(text.to_string()));
+        assert_eq!(
+            edit(&[], b"pint 1\x1b[D\x1b[D\x1b[D\x1b[D\x1b[Dr\r"),
+            line("print 1")
+        );
+        assert_eq!(edit(&[], b"abc\x1b[D\x7f\x7f\r"), line("c"));
+        assert_eq!(edit(&[], b"abc\x01\x1b[3~\x05d\r"), line("bcd"));
+        assert_eq!(edit(&[], b"ab\x01\x04\r"), line("b"));
+        assert_eq!(edit(&[], b"\x7f\x1b[D\r"), line(""));
+        assert_eq!(edit(&[], b"ab\x03"), Some(Done::Interrupt));
+        assert_eq!(edit(&[], b"\x04"), Some(Done::Eof));
+    }
+
+    #[test]
+    fn test_history() {
+        let history = ["let a = 1;".to_string(), "print a;".to_string()];
+        let line = |text: &str| Some(Done::Line(text.to_string()));
+        assert_eq!(edit(&history, b"\x1b[A\r"), line("print a;"));
+        assert_eq!(edit(&history, b"\x1b[A\x1b[A\x1b[A\r"), line("let a = 1;"));
+        // coming back down restores what was being typed
+        assert_eq!(edit(&history, b"pr\x1b[A\x1b[Bint\r"), line("print"));
+        assert_eq!(edit(&history, b"\x1b[A\x1b[D\x7f\r"), line("print ;"));
+    }
+
+    #[test]
+    fn test_persistent_history() {
+        let path = std::env::temp_dir().join(format!("repl-history-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut editor = Editor::new(Some(path.clone()));
+        for line in ["let a = 1;", "let a = 1;", " ", "print a;"] {
+            editor.remember(line);
+        }
+        assert_eq!(editor.history, ["let a = 1;", "print a;"]);
+        let editor = Editor::new(Some(path.clone()));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(editor.history, ["let a = 1;", "print a;"]);
+        assert!(Editor::new(None).history.is_empty());
+    }
+}
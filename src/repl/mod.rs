@@ -0,0 +1,501 @@
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+};
+
+use crate::{
+    ColorChoice, Error, ErrorInfo, ExprId, Interpretor, Lexer, LineIndex, Object, Parser, Program,
+    ReplLine, Resolver, Span, Stmt, TokenType,
+};
+
+mod editor;
+pub use editor::Editor;
+
+const PROMPT: &str = ">> ";
+// prompt for the lines that continue unfinished input
+const CONTINUATION: &str = "... ";
+// global holding the value last shown
+const RESULT: &str = "_";
+// name the diagnostics give the line they point into
+const SOURCE_NAME: &str = "<repl>";
+
+const HELP: &str = "\
+:help          show this help
+:quit          end the session
+:env           list the globals and their values
+:load <path>   run a file in this session
+:reset         drop every global
+:type <expr>   show the type of the value of an expression
+";
+
+// what to do with the value of a line that is a single expression
+#[derive(Clone, Copy, PartialEq)]
+enum Show {
+    Nothing,
+    Value,
+    Type,
+}
+
+// Reads and runs a line at a time on one interpreter, so the globals,
+// functions and classes a line declares stay visible on the lines after it.
+// Errors are reported to `errors` and the session carries on.
+pub struct Repl {
+    interpretor: Interpretor,
+    errors: Box<dyn Write>,
+    color: ColorChoice,
+    // whether a line declared `_` itself, which then holds what it was given
+    // rather than the last result
+    underscore_declared: bool,
+}
+
+impl Repl {
+    pub fn new(interpretor: Interpretor) -> Self {
+        Self {
+            interpretor,
+            errors: Box::new(io::stderr()),
+            color: ColorChoice::Auto,
+            underscore_declared: false,
+        }
+    }
+
+    // Reports errors to `errors` instead of stderr.
+    pub fn with_errors(mut self, errors: Box<dyn Write>) -> Self {
+        self.errors = errors;
+        self
+    }
+
+    pub fn with_color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn interpretor(&mut self) -> &mut Interpretor {
+        &mut self.interpretor
+    }
+
+    // Runs one line, reporting its errors. A line that is an expression
+    // shows its value unless it is nil, so calls made for their effects do
+    // not echo anything, and the value shown last is the global `_`. A line
+    // starting with `:` is a command. Returns the status the session should
+    // end with when the line called `exit()` or was `:quit`. A line that was
+    // cancelled stops with an error and the next one runs as usual.
+    pub fn eval_line(&mut self, line: &str) -> io::Result<Option<i32>> {
+        self.interpretor.cancellation_token().reset();
+        match line.trim().strip_prefix(':') {
+            Some(command) => self.command(command),
+            None => self.eval(line, SOURCE_NAME, Show::Value),
+        }
+    }
+
+    fn command(&mut self, command: &str) -> io::Result<Option<i32>> {
+        let (name, argument) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        let argument = argument.trim();
+        match name {
+            "quit" => return Ok(Some(0)),
+            "env" => {
+                let globals = self.interpretor.globals.borrow().entries();
+                for (name, value) in globals {
+                    match value {
+                        Object::String(value) => writeln!(self.output(), "{name} = {value:?}")?,
+                        value => writeln!(self.output(), "{name} = {value}")?,
+                    }
+                }
+            }
+            "load" => match fs::read_to_string(argument) {
+                Ok(source) => return self.eval(&source, argument, Show::Nothing),
+                Err(e) => writeln!(self.errors, "cannot read '{argument}': {e}")?,
+            },
+            "reset" => {
+                self.interpretor.reset();
+                self.underscore_declared = false;
+            }
+            "type" => return self.eval(argument, SOURCE_NAME, Show::Type),
+            _ => write!(self.output(), "{HELP}")?,
+        }
+        self.output().flush()?;
+        Ok(None)
+    }
+
+    // Runs `source`, showing what `show` asks for when it is one expression.
+    fn eval(&mut self, source: &str, name: &str, show: Show) -> io::Result<Option<i32>> {
+        let mut parser = Parser::new(Lexer::from_str(source).with_name(name));
+        let index = parser.line_index();
+        let (mut program, expr) = match parser.parse_repl_line() {
+            Ok(ReplLine::Stmts(program)) => (program, None),
+            Ok(ReplLine::Expr(program, expr)) => (program, Some(expr)),
+            Err(errors) => {
+                for err in &errors {
+                    self.report(&index, err)?;
+                }
+                return Ok(None);
+            }
+        };
+        if show == Show::Type && expr.is_none() {
+            writeln!(self.errors, ":type takes an expression")?;
+            return Ok(None);
+        }
+        self.underscore_declared |= program.stmts.iter().any(|stmt| match &program.ast[*stmt] {
+            Stmt::Let { name, .. } | Stmt::Function { name, .. } => name == RESULT,
+            _ => false,
+        });
+        let result = Resolver::new()
+            .resolve(&mut program)
+            .and_then(|()| match expr {
+                Some(expr) => self.show(&program, expr, show),
+                None => program
+                    .stmts
+                    .iter()
+                    .try_for_each(|stmt| self.interpretor.exec_in(&program, *stmt)),
+            });
+        match result {
+            Ok(()) => {}
+            Err(ErrorInfo {
+                error: Error::Exit(code),
+                ..
+            }) => return Ok(Some(code)),
+            Err(err) => self.report(&index, &err)?,
+        }
+        self.output().flush()?;
+        Ok(None)
+    }
+
+    fn show(&mut self, program: &Program, expr: ExprId, show: Show) -> Result<(), ErrorInfo> {
+        let value = self.interpretor.eval_in(program, expr)?;
+        let text = match (show, value) {
+            (Show::Nothing, _) | (Show::Value, Object::Nil) => return Ok(()),
+            (Show::Value, value) => {
+                if !self.underscore_declared {
+                    let _ = self.interpretor.set_global(RESULT, value.clone());
+                }
+                value
+            }
+            (Show::Type, value) => Object::String(value.type_name().into()),
+        };
+        self.interpretor.print(&[text], "\n", &Span::default())
+    }
+
+    fn output(&mut self) -> &mut dyn Write {
+        &mut self.interpretor.output
+    }
+
+    // Prompts on `prompt` for lines of `input` until it ends, and returns the
+    // status the session ends with: 0 at the end of the input, or the one
+    // passed to `exit()`.
+    pub fn run(&mut self, input: &mut dyn BufRead, prompt: &mut dyn Write) -> io::Result<i32> {
+        self.run_with(&mut Plain { input, prompt })
+    }
+
+    // `run` for lines from `lines`, such as the `Editor`. Lines are gathered
+    // under the `...` prompt until what was typed is complete, so a function
+    // can be entered over several.
+    pub fn run_with(&mut self, lines: &mut dyn ReadLine) -> io::Result<i32> {
+        let mut buffer = String::new();
+        loop {
+            let prompt = if buffer.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION
+            };
+            match lines.read_line(prompt) {
+                Ok(Some(line)) => buffer.push_str(&line),
+                Ok(None) => {
+                    // what is left unfinished is run for its errors
+                    if !buffer.trim().is_empty() {
+                        if let Some(code) = self.eval_line(&buffer)? {
+                            return Ok(code);
+                        }
+                    }
+                    return Ok(0);
+                }
+                // an interrupt while reading drops what was typed so far
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                    buffer.clear();
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+            // commands are a line each
+            if !buffer.trim_start().starts_with(':') && !is_complete(&buffer) {
+                continue;
+            }
+            let line = std::mem::take(&mut buffer);
+            if let Some(code) = self.eval_line(&line)? {
+                return Ok(code);
+            }
+        }
+    }
+
+    fn report(&mut self, index: &LineIndex, err: &ErrorInfo) -> io::Result<()> {
+        // what was printed before the error comes before it
+        self.output().flush()?;
+        writeln!(self.errors, "{}", err.render_with_index(index, self.color))
+    }
+}
+
+// Where the REPL takes its lines from. Interrupting the line being typed
+// is an error of kind `Interrupted`, which drops it.
+pub trait ReadLine {
+    // The next line, with its line ending, after showing `prompt`, or None
+    // once there are no more.
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>>;
+}
+
+// lines read as they come, with the prompt written before each
+struct Plain<'a> {
+    input: &'a mut dyn BufRead,
+    prompt: &'a mut dyn Write,
+}
+
+impl ReadLine for Plain<'_> {
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        write!(self.prompt, "{prompt}")?;
+        self.prompt.flush()?;
+        let mut line = String::new();
+        match self.input.read_line(&mut line) {
+            Ok(0) => {
+                // so the shell's prompt starts on a line of its own
+                writeln!(self.prompt)?;
+                Ok(None)
+            }
+            Ok(_) => Ok(Some(line)),
+            Err(e) => {
+                writeln!(self.prompt)?;
+                Err(e)
+            }
+        }
+    }
+}
+
+// Whether `input` is ready to be parsed: every bracket it opens has been
+// closed and no string is left open. A bracket closed too many times makes it
+// complete as well, for the parser to report.
+pub fn is_complete(input: &str) -> bool {
+    let mut lexer = Lexer::from_str(input);
+    let mut depth = 0;
+    loop {
+        match lexer.next().token {
+            TokenType::LParen | TokenType::LBrace | TokenType::LCurly => depth += 1,
+            TokenType::RParen | TokenType::RBrace | TokenType::RCurly => depth -= 1,
+            TokenType::Eof => break,
+            _ => {}
+        }
+        if depth < 0 {
+            return true;
+        }
+    }
+    let errors = lexer.take_errors();
+    depth == 0
+        && !errors
+            .iter()
+            .any(|err| err.error == Error::UnterminatedString)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Duration};
+
+    use crate::{interpretor::Capture, Object};
+
+    // what the session printed, what it reported and the status it ended with
+    fn session(input: &str) -> (String, String, i32) {
+        let (output, errors) = (Capture::default(), Capture::default());
+        let interpretor = Interpretor::new().with_output(Box::new(output.clone()));
+        let mut repl = Repl::new(interpretor)
+            .with_errors(Box::new(errors.clone()))
+            .with_color(ColorChoice::Never);
+        let mut prompt = Vec::new();
+        let status = repl.run(&mut input.as_bytes(), &mut prompt).unwrap();
+        assert!(String::from_utf8(prompt).unwrap().starts_with(PROMPT));
+        (output.text(), errors.text(), status)
+    }
+
+    #[test]
+    fn test_persistent_environment() {
+        let (output, errors, status) = session(
+            "let x = 1;\n\
+             fn add(a, b) { return a + b; }\n\
+             print add(x, 2);\n\
+             x = x + 1; print x;\n",
+        );
+        assert_eq!(output, "3\n2\n");
+        assert_eq!(errors, "");
+        assert_eq!(status, 0);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        // the session carries on after a form the interpreter cannot run
+        let (output, errors, status) = session("class A {}\nlet a = 1;\na.b = 2;\nprint a;\n");
+        assert_eq!(output, "1\n");
+        assert!(errors.contains("classes not supported"), "{errors}");
+        assert!(
+            errors.contains("property assignments not supported"),
+            "{errors}"
+        );
+        assert_eq!(status, 0);
+    }
+
+    #[test]
+    fn test_is_complete() {
+        for input in ["", "1 + 2", "fn f() {\n}", "print \"{\";", "f())", "(]"] {
+            assert!(is_complete(input), "{input}");
+        }
+        for input in [
+            "fn f() {",
+            "print (1 +",
+            "[1, 2,",
+            "{ if (a) {}",
+            "print \"a",
+        ] {
+            assert!(!is_complete(input), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_continuation() {
+        let (output, errors, _) = session(
+            "fn add(a, b) {\n\
+             return a + b;\n\
+             }\n\
+             add(1,\n\
+             2)\n\
+             let = 1;\n\
+             print \"still here\";\n",
+        );
+        assert_eq!(output, "3\nstill here\n");
+        // malformed but balanced input is an error straight away
+        assert!(errors.contains("[E0202]"), "{errors}");
+    }
+
+    #[test]
+    fn test_echo() {
+        let (output, errors, _) = session(
+            "1 + 2\n\
+             \"a\" + \"b\";\n\
+             fn f() {}\n\
+             f()\n\
+             let x = 1; x + 1\n\
+             x = 5\n\
+             x\n",
+        );
+        // nil and assignments stay silent, and so do several statements
+        assert_eq!(output, "3\nab\n5\n");
+        assert_eq!(errors, "");
+    }
+
+    #[test]
+    fn test_last_result() {
+        let (output, errors, _) = session(
+            "1 + 2\n\
+             _ * 10\n\
+             let a = 5;\n\
+             print 7;\n\
+             1 / 0\n\
+             fn f() {}\n\
+             f()\n\
+             _\n\
+             let _ = \"mine\";\n\
+             4\n\
+             _\n",
+        );
+        // only values that were shown replace it, until a line declares it
+        assert_eq!(output, "3\n30\n7\n30\n4\nmine\n");
+        assert!(errors.contains("ZeroDivisionError"), "{errors}");
+    }
+
+    #[test]
+    fn test_commands() {
+        let (output, errors, status) = session(
+            "let b = \"x\"; fn f() {}\n\
+             let a = 1;\n\
+             :env\n\
+             :type a + 1\n\
+             :type f\n\
+             :type let c = 1;\n\
+             :reset\n\
+             :env\n\
+             print a;\n\
+             :quit\n\
+             print 2;\n",
+        );
+        assert_eq!(
+            output,
+            "a = 1\nb = \"x\"\nf = <function>\nnumber\nfunction\n"
+        );
+        assert!(errors.contains(":type takes an expression"), "{errors}");
+        assert!(errors.contains("undefined variable \"a\""), "{errors}");
+        assert_eq!(status, 0);
+    }
+
+    #[test]
+    fn test_help() {
+        for command in [":help", ":what"] {
+            let (output, _, _) = session(command);
+            assert_eq!(output, HELP, "{command}");
+        }
+    }
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join(format!("repl-load-{}.rl", std::process::id()));
+        fs::write(&path, "let loaded = 2;\nprint \"loaded\";\n1 / 0;\n").unwrap();
+        let input = format!(
+            ":load {}\nprint loaded * 2;\n:load missing.rl\n",
+            path.display()
+        );
+        let (output, errors, _) = session(&input);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(output, "loaded\n4\n");
+        // errors point into the file, by its name
+        assert!(
+            errors.contains(&format!("--> {}:3:", path.display())),
+            "{errors}"
+        );
+        assert!(errors.contains("cannot read 'missing.rl'"), "{errors}");
+    }
+
+    #[test]
+    fn test_errors_continue() {
+        let (output, errors, status) = session(
+            "print 1 / 0;\n\
+             let = 2;\n\
+             print y;\n\
+             print \"still here\";\n",
+        );
+        assert_eq!(output, "still here\n");
+        assert!(errors.contains("ZeroDivisionError"), "{errors}");
+        assert!(errors.contains("--> <repl>:1:"), "{errors}");
+        assert!(errors.contains("undefined variable \"y\""), "{errors}");
+        assert_eq!(status, 0);
+    }
+
+    #[test]
+    fn test_exit() {
+        let (output, _, status) = session("print 1;\nexit(3);\nprint 2;\n");
+        assert_eq!(output, "1\n");
+        assert_eq!(status, 3);
+    }
+
+    #[test]
+    fn test_cancelled_line() {
+        let errors = Capture::default();
+        let mut repl = Repl::new(Interpretor::new()).with_errors(Box::new(errors.clone()));
+        let token = repl.interpretor().cancellation_token();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            token.cancel();
+        });
+        assert_eq!(repl.eval_line("while (true) {}").unwrap(), None);
+        handle.join().unwrap();
+        assert!(errors.text().contains("Interrupted"), "{}", errors.text());
+        // the cancel does not carry over to the next line
+        repl.eval_line("let a = 1;").unwrap();
+        assert_eq!(
+            repl.interpretor().get_global("a"),
+            Some(Object::Number(1.0))
+        );
+    }
+}
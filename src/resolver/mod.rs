@@ -0,0 +1,584 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    visit::{walk_expr_mut, walk_stmt_mut, VisitorMut},
+    Ast, Error, ErrorInfo, Expr, ExprId, Program, Slot, Span, Stmt, StmtId, Symbol,
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum ClassType {
+    None,
+    Class,
+    SubClass,
+}
+
+// Walks the program once before it is evaluated and records on every
+// variable reference how many scopes away its declaration lives and at which
+// slot of that scope, and on every local declaration its slot. References
+// that are not found in any local scope are left unresolved and looked up in
+// the globals at runtime, so a function body may mention a global that is
+// only declared further down the file. Function declarations inside a local
+// scope are hoisted to the top of that scope for the same reason, which lets
+// local functions call each other regardless of declaration order.
+//
+// While walking it also collects warnings, which do not stop the program:
+// a local declared with `let` that is never read is reported unless its name
+// starts with an underscore, and so is the first statement of a block that
+// directly follows a `return`, `break` or `continue`. Parameters and globals are never reported, a
+// global may be read by a program run later or by the host.
+pub struct Resolver {
+    scopes: Vec<HashMap<Symbol, Local>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    warnings: Vec<ErrorInfo>,
+}
+
+#[derive(Default)]
+struct Local {
+    // index among the declarations of its scope, in the order the resolver
+    // met them
+    slot: usize,
+    // whether its initializer has been resolved
+    defined: bool,
+    read: bool,
+    // where a `let` declared it, only those are warned about
+    declared: Option<Span>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            warnings: Vec::new(),
+        }
+    }
+
+    // warnings found so far, in the order of the declarations within each
+    // scope
+    pub fn warnings(&self) -> &[ErrorInfo] {
+        &self.warnings
+    }
+
+    // Resolves the statements of `program`, writing the depths into its
+    // nodes. A program shared with a function that outlived an earlier run
+    // is copied first, so the function keeps the depths it was resolved with.
+    pub fn resolve(&mut self, program: &mut Program) -> Result<(), ErrorInfo> {
+        let ast = Arc::make_mut(&mut program.ast);
+        self.resolve_stmts(ast, &program.stmts)
+    }
+
+    fn resolve_stmts(&mut self, ast: &mut Ast, stmts: &[StmtId]) -> Result<(), ErrorInfo> {
+        if !self.scopes.is_empty() {
+            for stmt in stmts {
+                if let Stmt::Function { name, span, .. } = &ast[*stmt] {
+                    let (name, span) = (*name, span.clone());
+                    let index = self.declare(name, &span)?;
+                    self.define(name);
+                    if let Stmt::Function { slot, .. } = &mut ast[*stmt] {
+                        *slot = index;
+                    }
+                }
+            }
+        }
+        self.check_unreachable(ast, stmts);
+        for stmt in stmts {
+            self.visit_stmt(ast, *stmt)?;
+        }
+        Ok(())
+    }
+
+    // Only looks at the statement right after a terminator, so code after
+    // an `if` whose branches all return is not reported. A function
+    // declared there is hoisted and can still be called, so it is skipped.
+    fn check_unreachable(&mut self, ast: &Ast, stmts: &[StmtId]) {
+        let unreachable = stmts.windows(2).find_map(|pair| {
+            let terminator = match &ast[pair[0]] {
+                Stmt::Return { span, .. } => ("return", span),
+                Stmt::Break { span } => ("break", span),
+                Stmt::Continue { span } => ("continue", span),
+                _ => return None,
+            };
+            match &ast[pair[1]] {
+                Stmt::Function { .. } => None,
+                stmt => Some((terminator, stmt.span(ast))),
+            }
+        });
+        if let Some(((terminator, terminator_span), span)) = unreachable {
+            // a statement without a position of its own, such as `1;`,
+            // is reported at the terminator
+            let span = span.unwrap_or(terminator_span).to_owned();
+            let warning = Error::Unreachable(terminator.to_string());
+            self.warnings.push(ErrorInfo::new_with_span(warning, span));
+        }
+    }
+
+    fn resolve_class(
+        &mut self,
+        ast: &mut Ast,
+        name: Symbol,
+        super_class: Option<Symbol>,
+        methods: &[StmtId],
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        self.declare(name, span)?;
+        self.define(name);
+
+        if super_class.is_some() {
+            self.current_class = ClassType::SubClass;
+            self.begin_scope();
+            self.define(Symbol::intern("super"));
+        }
+
+        self.begin_scope();
+        self.define(Symbol::intern("this"));
+        let mut result = Ok(());
+        for method in methods {
+            if let Stmt::Function {
+                name,
+                params,
+                body,
+                span,
+                ..
+            } = ast[*method].clone()
+            {
+                let function_type = if name == "init" {
+                    FunctionType::Initializer
+                } else {
+                    FunctionType::Method
+                };
+                result = self.resolve_function(ast, &params, &body, &span, function_type);
+                if result.is_err() {
+                    break;
+                }
+            }
+        }
+        self.end_scope();
+
+        if super_class.is_some() {
+            self.end_scope();
+        }
+        result
+    }
+
+    fn resolve_function(
+        &mut self,
+        ast: &mut Ast,
+        params: &[Symbol],
+        body: &[StmtId],
+        span: &Span,
+        function_type: FunctionType,
+    ) -> Result<(), ErrorInfo> {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+
+        // parameters and body share one scope, mirroring `Function::call`,
+        // which puts the arguments in its first slots
+        self.begin_scope();
+        let mut result = Ok(());
+        for param in params {
+            result = self.declare(*param, span).map(|_| ());
+            if result.is_err() {
+                break;
+            }
+            self.define(*param);
+        }
+        if result.is_ok() {
+            result = self.resolve_stmts(ast, body);
+        }
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+        result
+    }
+}
+
+impl VisitorMut for Resolver {
+    type Error = ErrorInfo;
+
+    fn visit_stmt(&mut self, ast: &mut Ast, stmt: StmtId) -> Result<(), ErrorInfo> {
+        // the children are copied out so the nodes can be written to while
+        // walking them
+        match ast[stmt].clone() {
+            Stmt::Let {
+                name, value, span, ..
+            } => {
+                let index = self.declare(name, &span)?;
+                if let Some(value) = value {
+                    self.visit_expr(ast, value)?;
+                }
+                self.define(name);
+                if let Some(local) = self
+                    .scopes
+                    .last_mut()
+                    .and_then(|scope| scope.get_mut(&name))
+                {
+                    local.declared = Some(span);
+                }
+                if let Stmt::Let { slot, .. } = &mut ast[stmt] {
+                    *slot = index;
+                }
+                Ok(())
+            }
+            Stmt::Block { stmts } => {
+                self.begin_scope();
+                let result = self.resolve_stmts(ast, &stmts);
+                self.end_scope();
+                result
+            }
+            // the name itself was already declared when `resolve` hoisted the
+            // functions of the enclosing scope
+            Stmt::Function {
+                params, body, span, ..
+            } => self.resolve_function(ast, &params, &body, &span, FunctionType::Function),
+            Stmt::Return { value, span } => {
+                if self.current_function == FunctionType::None {
+                    let error = Error::ReturnOutsideFunction;
+                    return Err(ErrorInfo::new_with_span(error, span));
+                }
+                if value.is_some() && self.current_function == FunctionType::Initializer {
+                    let error = Error::ReturnFromInitializer;
+                    return Err(ErrorInfo::new_with_span(error, span));
+                }
+                walk_stmt_mut(self, ast, stmt)
+            }
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+                span,
+            } => {
+                let enclosing_class = self.current_class;
+                self.current_class = ClassType::Class;
+                let result = self.resolve_class(ast, name, super_class, &methods, &span);
+                self.current_class = enclosing_class;
+                result
+            }
+            _ => walk_stmt_mut(self, ast, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, ast: &mut Ast, expr: ExprId) -> Result<(), ErrorInfo> {
+        let resolved = match &ast[expr] {
+            Expr::Assign { name, .. } => {
+                let name = *name;
+                walk_expr_mut(self, ast, expr)?;
+                self.resolve_local(name)
+            }
+            Expr::Super { span, .. } => {
+                if self.current_class != ClassType::SubClass {
+                    let error = if self.current_class == ClassType::None {
+                        Error::SuperOutsideClass
+                    } else {
+                        Error::SuperWithoutSuperclass
+                    };
+                    return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+                }
+                self.resolve_local(Symbol::intern("super"))
+            }
+            Expr::Variable { name, span, .. } => {
+                if *name == "this" && self.current_class == ClassType::None {
+                    let error = Error::ThisOutsideClass;
+                    return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+                }
+                let local = self.scopes.last().and_then(|scope| scope.get(name));
+                if local.is_some_and(|local| !local.defined) {
+                    let error = Error::ReadInOwnInitializer(name.to_string());
+                    return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+                }
+                let resolved = self.resolve_local(*name);
+                if let Some(slot) = resolved {
+                    let scope = self.scopes.len() - 1 - slot.depth;
+                    if let Some(local) = self.scopes[scope].get_mut(name) {
+                        local.read = true;
+                    }
+                }
+                resolved
+            }
+            _ => return walk_expr_mut(self, ast, expr),
+        };
+        // only variables, assignments and `super` get here
+        if let Expr::Assign { slot, .. } | Expr::Super { slot, .. } | Expr::Variable { slot, .. } =
+            &mut ast[expr]
+        {
+            *slot = resolved;
+        }
+        Ok(())
+    }
+}
+
+impl Resolver {
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        let mut unused: Vec<(Symbol, Span)> = scope
+            .into_iter()
+            .filter(|(name, local)| !local.read && !name.as_str().starts_with('_'))
+            .filter_map(|(name, local)| Some((name, local.declared?)))
+            .collect();
+        unused.sort_by_key(|(_, span)| span.start);
+        for (name, span) in unused {
+            let warning = Error::UnusedVariable(name.to_string());
+            self.warnings.push(ErrorInfo::new_with_span(warning, span));
+        }
+    }
+
+    // the slot `name` gets, None when it is a global
+    fn declare(&mut self, name: Symbol, span: &Span) -> Result<Option<usize>, ErrorInfo> {
+        let Some(scope) = self.scopes.last_mut() else {
+            return Ok(None);
+        };
+        if scope.contains_key(&name) {
+            let error = Error::AlreadyDeclared(name.to_string());
+            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+        }
+        let slot = scope.len();
+        scope.insert(
+            name,
+            Local {
+                slot,
+                ..Local::default()
+            },
+        );
+        Ok(Some(slot))
+    }
+
+    fn define(&mut self, name: Symbol) {
+        if let Some(scope) = self.scopes.last_mut() {
+            let slot = scope.len();
+            scope
+                .entry(name)
+                .or_insert_with(|| Local {
+                    slot,
+                    ..Local::default()
+                })
+                .defined = true;
+        }
+    }
+
+    fn resolve_local(&self, name: Symbol) -> Option<Slot> {
+        self.scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, scope)| {
+                let index = scope.get(&name)?.slot;
+                Some(Slot { depth, index })
+            })
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, Parser, Severity};
+
+    fn resolve(input: &str) -> Result<Program, ErrorInfo> {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let mut program = parser.parse_program().unwrap();
+        Resolver::new().resolve(&mut program)?;
+        Ok(program)
+    }
+
+    fn resolve_err(input: &str) -> Error {
+        resolve(input).unwrap_err().error
+    }
+
+    #[test]
+    fn test_slots() {
+        let program = resolve("let a = 1; { let b = 2; let c = 3; { a + c; } }").unwrap();
+        let ast = &program.ast;
+        assert!(matches!(
+            ast[program.stmts[0]],
+            Stmt::Let { slot: None, .. }
+        ));
+        let Stmt::Block { stmts } = &ast[program.stmts[1]] else {
+            panic!()
+        };
+        assert!(matches!(ast[stmts[1]], Stmt::Let { slot: Some(1), .. }));
+        let Stmt::Block { stmts } = &ast[stmts[2]] else {
+            panic!()
+        };
+        let Stmt::Expr { expr } = ast[stmts[0]] else {
+            panic!()
+        };
+        let Expr::Binary { left, right, .. } = ast[expr] else {
+            panic!()
+        };
+        // globals are left unresolved
+        assert!(matches!(ast[left], Expr::Variable { slot: None, .. }));
+        let Expr::Variable { slot, .. } = ast[right] else {
+            panic!()
+        };
+        assert_eq!(slot, Some(Slot { depth: 1, index: 1 }));
+    }
+
+    #[test]
+    fn test_top_level_return() {
+        assert_eq!(resolve_err("return 1;"), Error::ReturnOutsideFunction);
+    }
+
+    #[test]
+    fn test_this_outside_class() {
+        assert_eq!(resolve_err("print this;"), Error::ThisOutsideClass);
+    }
+
+    #[test]
+    fn test_super_without_superclass() {
+        assert_eq!(resolve_err("super.foo;"), Error::SuperOutsideClass);
+        assert_eq!(
+            resolve_err("class A { foo() { super.foo; } }"),
+            Error::SuperWithoutSuperclass
+        );
+    }
+
+    #[test]
+    fn test_duplicate_declaration() {
+        assert_eq!(
+            resolve_err("{ let a = 1; let a = 2; }"),
+            Error::AlreadyDeclared("a".to_string())
+        );
+        assert_eq!(
+            resolve_err("fn f(a, a) {}"),
+            Error::AlreadyDeclared("a".to_string())
+        );
+        // redeclaring a global is allowed
+        assert!(resolve("let a = 1; let a = 2;").is_ok());
+    }
+
+    #[test]
+    fn test_hoisted_local_functions() {
+        let program = resolve(
+            "{
+                fn is_even(n) { if (n == 0) return true; return is_odd(n - 1); }
+                fn is_odd(n) { if (n == 0) return false; return is_even(n - 1); }
+            }",
+        )
+        .unwrap();
+        let ast = &program.ast;
+        let Stmt::Block { stmts } = &ast[program.stmts[0]] else {
+            panic!()
+        };
+        let Stmt::Function { body, .. } = &ast[stmts[0]] else {
+            panic!()
+        };
+        let Stmt::Return {
+            value: Some(value), ..
+        } = ast[body[1]]
+        else {
+            panic!()
+        };
+        let Expr::Call { callee, .. } = ast[value] else {
+            panic!()
+        };
+        // `is_odd` lives in the block, one scope out from the function body,
+        // after `is_even`
+        let Expr::Variable { slot, .. } = ast[callee] else {
+            panic!()
+        };
+        assert_eq!(slot, Some(Slot { depth: 1, index: 1 }));
+        assert!(matches!(
+            ast[stmts[1]],
+            Stmt::Function { slot: Some(1), .. }
+        ));
+
+        assert_eq!(
+            resolve_err("{ fn f() {} let f = 1; }"),
+            Error::AlreadyDeclared("f".to_string())
+        );
+    }
+
+    #[test]
+    fn test_own_initializer() {
+        assert_eq!(
+            resolve_err("{ let a = a; }"),
+            Error::ReadInOwnInitializer("a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unused_variable() {
+        let warnings = |input: &str| {
+            let mut program = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap();
+            let mut resolver = Resolver::new();
+            resolver.resolve(&mut program).unwrap();
+            resolver
+                .warnings()
+                .iter()
+                .map(|warning| (warning.error.clone(), warning.span.start))
+                .collect::<Vec<_>>()
+        };
+        let unused = |name: &str| Error::UnusedVariable(name.to_string());
+        assert_eq!(
+            warnings("fn f(a) { let b = 1; let c = 2; let d = c; }"),
+            [(unused("b"), 14), (unused("d"), 36)]
+        );
+        // read from a closure, or assigned but never read
+        assert_eq!(
+            warnings("{ let a = 1; let b = 2; fn f() { return a; } f(); b = 3; }"),
+            [(unused("b"), 17)]
+        );
+        // parameters, globals and names with a leading underscore are skipped
+        assert!(warnings("let a = 1; fn f(b) { let _c = 1; }").is_empty());
+        assert_eq!(
+            warnings("{ let a = 1; }")[0].0.severity(),
+            Severity::Warning
+        );
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let warnings = |input: &str| {
+            let mut program = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap();
+            let mut resolver = Resolver::new();
+            resolver.resolve(&mut program).unwrap();
+            resolver
+                .warnings()
+                .iter()
+                .map(|warning| (warning.error.to_string(), warning.span.start))
+                .collect::<Vec<_>>()
+        };
+        let after = |terminator| format!("Warning: unreachable statement after \"{terminator}\"");
+        // only the first statement after the terminator
+        assert_eq!(
+            warnings("fn f(a) { return a; a + 1; a; }"),
+            [(after("return"), 20)]
+        );
+        assert_eq!(
+            warnings("fn f() { { return 1; print 2; } }"),
+            [(after("return"), 21)]
+        );
+        // `2;` has no position and is reported at the return
+        assert_eq!(
+            warnings("fn f() { { return 1; 2; } }"),
+            [(after("return"), 11)]
+        );
+        // branches that all return are not followed
+        assert!(warnings("fn f(a) { if (a) return 1; else return 2; return 3; }").is_empty());
+        assert!(warnings("fn f() { return g(); fn g() { return 1; } }").is_empty());
+    }
+}
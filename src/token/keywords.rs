@@ -1,6 +1,4 @@
-
-use crate::TokenType;
-
+use crate::{Symbol, TokenType};
 
 pub fn lookup_identifier(ident: String) -> TokenType {
     // 1. the language is case sensitive
@@ -24,15 +22,14 @@ pub fn lookup_identifier(ident: String) -> TokenType {
         "super" => TokenType::Super,
         "print" => TokenType::Print,
         "const" => TokenType::Const,
-        _ => TokenType::Identifier(ident),
+        _ => TokenType::Identifier(Symbol::intern(&ident)),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{TokenType};
     use super::lookup_identifier;
-
+    use crate::TokenType;
 
     #[test]
     fn test_keywords() {
@@ -57,7 +54,6 @@ mod tests {
             ("print", TokenType::Print),
             ("super", TokenType::Super),
             ("const", TokenType::Const),
-
         ];
         is_keyword.iter().for_each(|x| {
             let ident = x.0.to_string();
@@ -68,25 +64,15 @@ mod tests {
     #[test]
     fn test_identifier() {
         let is_keyword = vec![
-            "True", "TRUE", 
-            "False", "FALSE", 
-            "Fn", "fN", "FN", 
-            "If", "iF", "IF", 
-            "Let", "lEt",
-            "FOr", "FoR", "FOR",
-             "wHILe", "wHILE", "WHile",
-            "ReTURN", "RETURN", 
-            "Class", "cLass", 
-            "ThIS", "THIS", 
-            "Import", "iMport",
-             "Nil", "nIl", "niL", 
+            "True", "TRUE", "False", "FALSE", "Fn", "fN", "FN", "If", "iF", "IF", "Let", "lEt",
+            "FOr", "FoR", "FOR", "wHILe", "wHILE", "WHile", "ReTURN", "RETURN", "Class", "cLass",
+            "ThIS", "THIS", "Import", "iMport", "Nil", "nIl", "niL",
         ];
         is_keyword
             .iter()
-            .map(|x| (x.to_string(), TokenType::Identifier(x.to_string())))
+            .map(|x| (x.to_string(), TokenType::Identifier((*x).into())))
             .for_each(|x| assert_eq!(lookup_identifier(x.0), x.1));
     }
 }
 
-
-// 
\ No newline at end of file
+//
@@ -0,0 +1,132 @@
+// Start of every line of a source, built once so byte offsets from spans can
+// be turned into the lines and columns shown to people.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    source: String,
+    // byte offset of the first character of each line
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        Self {
+            source: source.to_owned(),
+            line_starts,
+        }
+    }
+
+    // 1-based line and column of `offset`, counting the column in
+    // characters. An offset past the end is the position after the last
+    // character, and one inside a character is the start of it.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = self.floor(offset);
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let column = self.source[self.line_starts[line - 1]..offset]
+            .chars()
+            .count();
+        (line, column + 1)
+    }
+
+    // text of the 1-based `line` without its line ending, empty past the end
+    pub fn line(&self, line: usize) -> &str {
+        let Some(start) = line.checked_sub(1).and_then(|i| self.line_starts.get(i)) else {
+            return "";
+        };
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.source.len(), |next| next - 1);
+        let text = &self.source[*start..end];
+        text.strip_suffix('\r').unwrap_or(text)
+    }
+
+    // byte offset of the first character of the 1-based `line`
+    pub fn line_start(&self, line: usize) -> usize {
+        line.checked_sub(1)
+            .and_then(|i| self.line_starts.get(i))
+            .map_or(self.source.len(), |start| *start)
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    fn floor(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_column() {
+        let index = LineIndex::new("let a = 1;\nlet é = \"ü\";\r\n\nx");
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_column(0), (1, 1));
+        assert_eq!(index.line_column(10), (1, 11));
+        assert_eq!(index.line_column(11), (2, 1));
+        // columns count characters, not bytes, and an offset inside "é" is
+        // its start
+        assert_eq!(index.line_column(17), (2, 6));
+        assert_eq!(index.line_column(16), (2, 5));
+        assert_eq!(index.line_column(22), (2, 10));
+        assert_eq!(index.line_column(27), (3, 1));
+        assert_eq!(index.line_column(28), (4, 1));
+        assert_eq!(index.line_column(100), (4, 2));
+    }
+
+    #[test]
+    fn test_line() {
+        let index = LineIndex::new("one\r\ntwo\n\nthree");
+        assert_eq!(index.line(1), "one");
+        assert_eq!(index.line(2), "two");
+        assert_eq!(index.line(3), "");
+        assert_eq!(index.line(4), "three");
+        assert_eq!(index.line(5), "");
+        assert_eq!(index.line_start(4), 10);
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let index = LineIndex::new("");
+        assert_eq!(index.line_count(), 1);
+        assert_eq!(index.line_column(0), (1, 1));
+        assert_eq!(index.line_column(5), (1, 1));
+        assert_eq!(index.line(1), "");
+        assert_eq!(index.line_start(1), 0);
+
+        // the newline ends its line, and a line after the last one is empty
+        let index = LineIndex::new("ab\ncd\n");
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.line_column(1), (1, 2));
+        assert_eq!(index.line_column(2), (1, 3));
+        assert_eq!(index.line_column(3), (2, 1));
+        assert_eq!(index.line_column(5), (2, 3));
+        assert_eq!(index.line_column(6), (3, 1));
+        assert_eq!(index.line(2), "cd");
+        assert_eq!(index.line(3), "");
+
+        // the last line without a newline runs to the end
+        let index = LineIndex::new("ab\ncd");
+        assert_eq!(index.line_count(), 2);
+        assert_eq!(index.line_column(5), (2, 3));
+        assert_eq!(index.line_column(6), (2, 3));
+        assert_eq!(index.line(2), "cd");
+        assert_eq!(index.line_start(2), 3);
+        assert_eq!(index.line_start(3), 5);
+
+        let index = LineIndex::new("\n\n");
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.line_column(0), (1, 1));
+        assert_eq!(index.line_column(1), (2, 1));
+        assert_eq!(index.line_column(2), (3, 1));
+    }
+}
@@ -1,8 +1,15 @@
 mod span;
 pub use span::Span;
 
+mod symbol;
+pub(crate) use symbol::join;
+pub use symbol::Symbol;
+
+mod line_index;
+pub use line_index::LineIndex;
+
 mod token_type;
-pub use token_type::TokenType;
+pub use token_type::{TokenKind, TokenType};
 
 mod token_info;
 pub use token_info::TokenInfo;
@@ -1,24 +1,61 @@
-use std::cmp;
+use std::sync::Arc;
 
-#[derive(Debug, PartialEq, Clone)]
+// Byte offsets into the source the span was taken from, `end` exclusive.
+// The line and columns are kept so a diagnostic can say where it is without
+// the source, the columns counted in characters from 1 with `end_column`
+// exclusive too. Either column is 0 when not known, `end_column` also when
+// the span ends on a later line than it starts.
+#[derive(Debug, PartialEq, Clone, Default)]
 pub struct Span {
     pub line: usize,
-    pub line_start: usize,
-    pub start: usize, // this is the postion wrt to data in form of string
+    pub start: usize,
     pub end: usize,
+    pub column: usize,
+    pub end_column: usize,
+    // name of the source, such as a path or "<stdin>", shared by all of its
+    // spans, and by the threads sharing a program
+    pub file: Option<Arc<str>>,
 }
 
 impl Span {
-    pub fn new(line: usize, line_start: usize, start: usize, end: usize) -> Self {
-        Self { line, line_start, start, end}
+    pub fn new(line: usize, start: usize, end: usize) -> Self {
+        Self {
+            line,
+            start,
+            end,
+            column: 0,
+            end_column: 0,
+            file: None,
+        }
+    }
+
+    pub fn with_columns(mut self, column: usize, end_column: usize) -> Self {
+        self.column = column;
+        self.end_column = end_column;
+        self
     }
 
+    // from the start of the earlier span to the end of the later one
     pub fn merge(&self, other: &Span) -> Self {
+        let (first, last) = if self.start <= other.start {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let end = if last.end >= first.end { last } else { first };
+        first.extend_to(end)
+    }
+
+    // from the start of this span to the end of `end`
+    pub fn extend_to(&self, end: &Span) -> Self {
         Self {
-            line: self.line,
-            line_start: cmp::min(self.line_start, other.line_start),
-            start: cmp::min(self.start, other.start),
-            end: cmp::max(self.end, other.end),
+            end: end.end,
+            end_column: if end.line == self.line {
+                end.end_column
+            } else {
+                0
+            },
+            ..self.clone()
         }
     }
-}
\ No newline at end of file
+}
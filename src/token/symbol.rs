@@ -0,0 +1,120 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    ptr,
+    sync::{Mutex, OnceLock},
+};
+
+// An interned name: every identifier with the same text is the same
+// symbol, so the lexer, the AST and the scopes share one copy of each name
+// and compare and hash them by its address. The table is shared by the
+// whole process rather than a parse, since the names of a program parsed in
+// one place are looked up in scopes filled by others: earlier REPL lines,
+// other files, or a host registering natives. Names are never freed, so it
+// grows with the distinct names seen, not with the programs run, and a
+// symbol holds its name to read it without the table.
+#[derive(Clone, Copy)]
+pub struct Symbol(&'static str);
+
+fn interner() -> &'static Mutex<HashSet<&'static str>> {
+    static INTERNER: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Symbol {
+        let mut names = interner().lock().unwrap();
+        if let Some(name) = names.get(name) {
+            return Symbol(name);
+        }
+        let name: &'static str = Box::leak(name.into());
+        names.insert(name);
+        Symbol(name)
+    }
+
+    // the symbol of `name` if it was ever interned, without interning it, for
+    // looking up a name that is likely not there
+    pub fn lookup(name: &str) -> Option<Symbol> {
+        interner()
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|name| Symbol(name))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.0
+    }
+}
+
+// only one copy of each name is interned, so equal names share an address
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        ptr::hash(self.0, state)
+    }
+}
+
+// the names of `symbols` with `separator` between them
+pub(crate) fn join(symbols: &[Symbol], separator: &str) -> String {
+    let names: Vec<&str> = symbols.iter().map(|symbol| symbol.as_str()).collect();
+    names.join(separator)
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Symbol;
+
+    #[test]
+    fn test_intern() {
+        let a = Symbol::intern("interned_a");
+        assert_eq!(Symbol::intern("interned_a"), a);
+        assert_ne!(Symbol::intern("interned_b"), a);
+        assert_eq!(a.as_str(), "interned_a");
+        assert_eq!(a.to_string(), "interned_a");
+        assert_eq!(format!("{a:?}"), "\"interned_a\"");
+        assert_eq!(a, "interned_a");
+
+        assert_eq!(Symbol::lookup("interned_a"), Some(a));
+        assert_eq!(Symbol::lookup("never_interned"), None);
+        assert_eq!(Symbol::lookup("never_interned"), None);
+    }
+}
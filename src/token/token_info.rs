@@ -1,4 +1,4 @@
-use crate::{TokenType, Span};
+use crate::{Span, TokenKind, TokenType};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct TokenInfo {
@@ -7,14 +7,24 @@ pub struct TokenInfo {
 }
 
 impl TokenInfo {
-    pub fn new(token: TokenType, start: usize, end: usize, line: usize, line_start: usize) -> Self {
-       let span = Span {start, end, line, line_start };
-        Self {
-            token,
-            span
-        }
+    pub fn new(token: TokenType, start: usize, end: usize, line: usize) -> Self {
+        let span = Span::new(line, start, end);
+        Self { token, span }
     }
-    pub fn is(&self, token: TokenType) -> bool {
-        self.token == token
+    pub fn is(&self, kind: TokenKind) -> bool {
+        self.token.kind() == kind
     }
-}
\ No newline at end of file
+
+    // `line:start-end Kind lexeme`, with the lexeme as written in `source`,
+    // for dumping the tokens of a program
+    pub fn describe(&self, source: &str) -> String {
+        let kind = self.token.kind();
+        let lexeme = source
+            .get(self.span.start..self.span.end)
+            .unwrap_or_default();
+        format!(
+            "{}:{}-{} {kind:?} {lexeme}",
+            self.span.line, self.span.start, self.span.end
+        )
+    }
+}
@@ -1,8 +1,10 @@
 use std::fmt;
 
+use crate::Symbol;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
-    Identifier(String), // variable
+    Identifier(Symbol), // variable
     String(String),     /* data types */
     Number(f64),
     True,
@@ -62,17 +64,168 @@ pub enum TokenType {
     Print,
     Dot,
     Super,
+    // the text after the `#`, only scanned by a lexer keeping trivia
+    Comment(String),
+}
+
+// What a token is without its payload, cheap to copy and compare, for
+// checking the kind of a token without cloning it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TokenKind {
+    Identifier,
+    String,
+    Number,
+    True,
+    False,
+    Comma,
+    Semicolon,
+    Colon,
+    Function,
+    Let,
+    Const,
+    Return,
+    If,
+    Else,
+    For,
+    While,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LCurly,
+    RCurly,
+    Assign,
+    Plus,
+    Minus,
+    Times,
+    Divide,
+    Mod,
+    LShift,
+    RShift,
+    And,
+    Or,
+    Not,
+    Xor,
+    Lt,
+    Gt,
+    Eq,
+    Ne,
+    Lte,
+    Gte,
+    Eof,
+    PlusEq,
+    DivideEq,
+    MinusEq,
+    TimesEq,
+    AndEq,
+    OrEq,
+    XorEq,
+    ModEq,
+    LogicalAnd,
+    LogicalOr,
+    Import,
+    Class,
+    Nil,
+    This,
+    Break,
+    Continue,
+    Print,
+    Dot,
+    Super,
+    Comment,
+}
+
+impl TokenType {
+    pub fn kind(&self) -> TokenKind {
+        use TokenType::*;
+
+        match self {
+            Identifier(_) => TokenKind::Identifier,
+            String(_) => TokenKind::String,
+            Number(_) => TokenKind::Number,
+            True => TokenKind::True,
+            False => TokenKind::False,
+            Comma => TokenKind::Comma,
+            Semicolon => TokenKind::Semicolon,
+            Colon => TokenKind::Colon,
+            Function => TokenKind::Function,
+            Let => TokenKind::Let,
+            Const => TokenKind::Const,
+            Return => TokenKind::Return,
+            If => TokenKind::If,
+            Else => TokenKind::Else,
+            For => TokenKind::For,
+            While => TokenKind::While,
+            LParen => TokenKind::LParen,
+            RParen => TokenKind::RParen,
+            LBrace => TokenKind::LBrace,
+            RBrace => TokenKind::RBrace,
+            LCurly => TokenKind::LCurly,
+            RCurly => TokenKind::RCurly,
+            Assign => TokenKind::Assign,
+            Plus => TokenKind::Plus,
+            Minus => TokenKind::Minus,
+            Times => TokenKind::Times,
+            Divide => TokenKind::Divide,
+            Mod => TokenKind::Mod,
+            LShift => TokenKind::LShift,
+            RShift => TokenKind::RShift,
+            And => TokenKind::And,
+            Or => TokenKind::Or,
+            Not => TokenKind::Not,
+            Xor => TokenKind::Xor,
+            Lt => TokenKind::Lt,
+            Gt => TokenKind::Gt,
+            Eq => TokenKind::Eq,
+            Ne => TokenKind::Ne,
+            Lte => TokenKind::Lte,
+            Gte => TokenKind::Gte,
+            Eof => TokenKind::Eof,
+            PlusEq => TokenKind::PlusEq,
+            DivideEq => TokenKind::DivideEq,
+            MinusEq => TokenKind::MinusEq,
+            TimesEq => TokenKind::TimesEq,
+            AndEq => TokenKind::AndEq,
+            OrEq => TokenKind::OrEq,
+            XorEq => TokenKind::XorEq,
+            ModEq => TokenKind::ModEq,
+            LogicalAnd => TokenKind::LogicalAnd,
+            LogicalOr => TokenKind::LogicalOr,
+            Import => TokenKind::Import,
+            Class => TokenKind::Class,
+            Nil => TokenKind::Nil,
+            This => TokenKind::This,
+            Break => TokenKind::Break,
+            Continue => TokenKind::Continue,
+            Print => TokenKind::Print,
+            Dot => TokenKind::Dot,
+            Super => TokenKind::Super,
+            Comment(_) => TokenKind::Comment,
+        }
+    }
 }
 
 impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenType::Identifier(x) => write!(f, "{}", x),
+            TokenType::String(x) => write!(f, "\"{}\"", x),
+            TokenType::Number(x) => write!(f, "{}", x),
+            TokenType::Comment(x) => write!(f, "#{}", x),
+            token => write!(f, "{}", token.kind()),
+        }
+    }
+}
+
+impl fmt::Display for TokenKind {
     #[rustfmt::skip]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use TokenType::*;
+        use TokenKind::*;
 
         match self {
-            Identifier(x) => write!(f, "{}", x),
-            String(x)     => write!(f, "\"{}\"", x),
-            Number(x)        => write!(f, "{}", x),
+            Identifier    => write!(f, "Identifier"),
+            String        => write!(f, "String"),
+            Number        => write!(f, "Number"),
             True          => write!(f, "True"),
             False         => write!(f, "False"),
             Dot           => write!(f, "."),
@@ -117,7 +270,7 @@ impl fmt::Display for TokenType {
             Lt            => write!(f, "<"),
             Eq            => write!(f, "=="),
             Ne            => write!(f, "!="),
-            Lte           => write!(f, "=<"),
+            Lte           => write!(f, "<="),
             Gte           => write!(f, ">="),
             LShift        => write!(f, "<<"),
             RShift        => write!(f, ">>"),
@@ -130,6 +283,7 @@ impl fmt::Display for TokenType {
             Continue      => write!(f, "continue"),
             Print         => write!(f, "print"),
             Super         => write!(f, "super"),
+            Comment       => write!(f, "Comment"),
 
         }
     }
@@ -141,7 +295,7 @@ mod tests {
 
     #[test]
     fn test_token_literal() {
-        let token = TokenType::Identifier("x".to_string());
+        let token = TokenType::Identifier(Symbol::intern("x"));
         assert_eq!(token.to_string(), "x");
 
         let token = TokenType::String("hello".to_string());
@@ -156,4 +310,20 @@ mod tests {
         let token = TokenType::False;
         assert_eq!(token.to_string(), "False");
     }
+
+    #[test]
+    fn test_kind() {
+        let token = TokenType::String("hello".to_string());
+        assert_eq!(token.kind(), TokenKind::String);
+        assert_eq!(
+            TokenType::Identifier(Symbol::intern("x")).kind(),
+            TokenKind::Identifier
+        );
+        assert_eq!(TokenType::Semicolon.kind(), TokenKind::Semicolon);
+
+        // the punctuation and keywords read the same either way
+        assert_eq!(TokenKind::LCurly.to_string(), TokenType::LCurly.to_string());
+        assert_eq!(TokenKind::Function.to_string(), "fn");
+        assert_eq!(TokenKind::Identifier.to_string(), "Identifier");
+    }
 }
@@ -0,0 +1,446 @@
+use std::rc::Rc;
+
+use crate::{
+    builtins, object::Function, Chunk, Error, ErrorInfo, Interpretor, Object, OpCode, Span,
+};
+
+struct CallFrame {
+    chunk: Rc<Chunk>,
+    ip: usize,
+    // stack index of slot 0
+    base: usize,
+}
+
+// Stack machine running the bytecode produced by `Compiler`. It shares the
+// globals, limits and cancellation token of the interpreter it borrows, and
+// functions from either backend can call each other.
+pub struct Vm<'a> {
+    interpretor: &'a mut Interpretor,
+    stack: Vec<Object>,
+    frames: Vec<CallFrame>,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(interpretor: &'a mut Interpretor) -> Self {
+        Self {
+            interpretor,
+            stack: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn run(&mut self, chunk: Rc<Chunk>) -> Result<Object, ErrorInfo> {
+        self.frames.push(CallFrame {
+            chunk,
+            ip: 0,
+            base: self.stack.len(),
+        });
+        self.stack.push(Object::Nil);
+        self.execute()
+    }
+
+    // Runs a compiled function whose arity the caller has already checked.
+    // `span` is the position of the call.
+    pub fn call(
+        &mut self,
+        chunk: Rc<Chunk>,
+        args: &[Object],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        self.interpretor.enter_call(span)?;
+        self.frames.push(CallFrame {
+            chunk,
+            ip: 0,
+            base: self.stack.len(),
+        });
+        self.stack.push(Object::Nil);
+        self.stack.extend_from_slice(args);
+        let result = self.execute();
+        self.interpretor.call_depth -= 1;
+        result
+    }
+
+    // Runs until the frame on top when it was called returns. Every frame
+    // pushed meanwhile counts as a call of the interpreter, so the two
+    // backends share its call depth limit.
+    fn execute(&mut self) -> Result<Object, ErrorInfo> {
+        let depth = self.interpretor.call_depth;
+        let result = self.execute_frames();
+        // the calls an error unwinds no longer count
+        self.interpretor.call_depth = depth;
+        result
+    }
+
+    fn execute_frames(&mut self) -> Result<Object, ErrorInfo> {
+        let entry = self.frames.len() - 1;
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let chunk = frame.chunk.clone();
+            let op = &chunk.code[frame.ip];
+            let span = &chunk.spans[frame.ip];
+            frame.ip += 1;
+            let base = frame.base;
+
+            match op {
+                OpCode::Constant(index) => self.stack.push(chunk.constants[*index].clone()),
+                OpCode::Nil => self.stack.push(Object::Nil),
+                OpCode::True => self.stack.push(Object::Boolean(true)),
+                OpCode::False => self.stack.push(Object::Boolean(false)),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::GetLocal(slot) => self.stack.push(self.stack[base + slot].clone()),
+                OpCode::SetLocal(slot) => self.stack[base + slot] = self.peek(0).clone(),
+                OpCode::DefineGlobal(name, is_const) => {
+                    let value = self.pop();
+                    self.interpretor
+                        .globals
+                        .borrow_mut()
+                        .define(*name, value, *is_const)
+                        .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+                }
+                OpCode::GetGlobal(name) => {
+                    let value = self.interpretor.globals.borrow_mut().get(*name);
+                    let value = value.map_err(|e| self.error(e, span))?;
+                    self.stack.push(value);
+                }
+                OpCode::SetGlobal(name) => {
+                    let value = self.peek(0).clone();
+                    let result = self.interpretor.globals.borrow_mut().assign(*name, value);
+                    result.map_err(|e| self.error(e, span))?;
+                }
+                OpCode::GetProperty(name) => {
+                    let value = builtins::property(&self.pop(), name.as_str())
+                        .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+                    self.stack.push(value);
+                }
+                OpCode::Unary(op) => {
+                    let value = self
+                        .pop()
+                        .to_unary(op)
+                        .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+                    self.stack.push(value);
+                }
+                OpCode::Binary(op) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.interpretor
+                        .allocate(Object::binary_size(&left, op, &right), span)?;
+                    let value = Object::binary(left, op, right)
+                        .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+                    self.stack.push(value);
+                }
+                OpCode::Print(count) => {
+                    let values = self.stack.split_off(self.stack.len() - count);
+                    self.interpretor.print(&values, "\n", span)?;
+                }
+                OpCode::Jump(target) => self.jump(*target),
+                OpCode::JumpIfFalse(target) => {
+                    if !self.pop().is_truthy() {
+                        self.jump(*target);
+                    }
+                }
+                OpCode::Loop(target) => {
+                    self.interpretor.consume_fuel(span)?;
+                    self.interpretor.check_cancelled(span)?;
+                    self.jump(*target);
+                }
+                OpCode::Call(count) => {
+                    self.interpretor.consume_fuel(span)?;
+                    self.interpretor.check_cancelled(span)?;
+                    self.call_value(*count, span)?;
+                }
+                OpCode::Return => {
+                    let value = self.pop();
+                    let frame = self.frames.pop().unwrap();
+                    self.stack.truncate(frame.base);
+                    if self.frames.len() == entry {
+                        return Ok(value);
+                    }
+                    self.interpretor.call_depth -= 1;
+                    self.stack.push(value);
+                }
+                OpCode::AssertValue => {
+                    if self.peek(0).is_nil() {
+                        let error = Error::ConstantWithoutValue;
+                        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+                    }
+                }
+                OpCode::ReassignConstant => {
+                    let error = Error::ConstantReassigned;
+                    return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+                }
+            }
+        }
+    }
+
+    fn call_value(&mut self, count: usize, span: &Span) -> Result<(), ErrorInfo> {
+        let base = self.stack.len() - count - 1;
+        let function = match &self.stack[base] {
+            Object::Function(function) => function.clone(),
+            callee => {
+                let error = Error::Type(format!("{callee} is not callable"));
+                return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+            }
+        };
+        match function.as_ref() {
+            // compiled functions run in a new frame of this loop
+            Function::Compiled { chunk, .. } => {
+                function.check_arity(count, span)?;
+                self.interpretor.enter_call(span)?;
+                self.frames.push(CallFrame {
+                    chunk: chunk.clone(),
+                    ip: 0,
+                    base,
+                });
+            }
+            function => {
+                let args = self.stack.split_off(base + 1);
+                self.pop();
+                let value = function.call(self.interpretor, &args, span)?;
+                self.stack.push(value);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Vm<'_> {
+    fn pop(&mut self) -> Object {
+        self.stack.pop().expect("stack underflow")
+    }
+
+    fn peek(&self, distance: usize) -> &Object {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    fn jump(&mut self, target: usize) {
+        self.frames.last_mut().unwrap().ip = target;
+    }
+
+    fn error(&self, error: Error, span: &Span) -> ErrorInfo {
+        ErrorInfo::new_with_span(self.interpretor.with_suggestions(error), span.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interpretor::Capture, Compiler, Lexer, Parser, Program, Resolver};
+
+    fn parse(input: &str) -> Program {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let mut program = parser.parse_program().unwrap();
+        Resolver::new().resolve(&mut program).unwrap();
+        program
+    }
+
+    // runs every statement on both backends and checks they agree on each
+    // statement's outcome, on what was printed and on the globals left behind
+    fn compare(input: &str, globals: &[&str]) -> Vec<Result<(), ErrorInfo>> {
+        compare_with(input, globals, Interpretor::new)
+    }
+
+    // the same on interpreters `new` makes
+    fn compare_with(
+        input: &str,
+        globals: &[&str],
+        new: impl Fn() -> Interpretor,
+    ) -> Vec<Result<(), ErrorInfo>> {
+        let program = parse(input);
+        let (walker_output, compiled_output) = (Capture::default(), Capture::default());
+        let mut walker = new().with_output(Box::new(walker_output.clone()));
+        let mut compiled = new().with_output(Box::new(compiled_output.clone()));
+        let mut results = Vec::new();
+        for stmt in &program.stmts {
+            let expected = walker.exec_in(&program, *stmt);
+            let chunk = Compiler::compile(&program.ast, std::slice::from_ref(stmt)).unwrap();
+            let actual = Vm::new(&mut compiled).run(Rc::new(chunk)).map(|_| ());
+            assert_eq!(actual, expected, "{}", program.ast.node(*stmt));
+            results.push(actual);
+        }
+        for name in globals {
+            let expected = walker.globals.borrow_mut().get((*name).into());
+            let actual = compiled.globals.borrow_mut().get((*name).into());
+            assert_eq!(actual, expected, "{name}");
+        }
+        assert_eq!(compiled_output.text(), walker_output.text());
+        results
+    }
+
+    fn get(interpretor: &Interpretor, name: &str) -> Object {
+        interpretor.globals.borrow_mut().get(name.into()).unwrap()
+    }
+
+    #[test]
+    fn test_expressions() {
+        compare(
+            "let a = 1 + 2 * 3 - -4 / 2;
+            let b = \"ab\" + \"cd\";
+            let c = \"x\" * 3;
+            let d = !(1 < 2) == false;
+            let f = \" Mixed \".trim().lower();
+            let e = (a >= 9) & (a != 8);",
+            &["a", "b", "c", "d", "e", "f"],
+        );
+    }
+
+    #[test]
+    fn test_control_flow() {
+        compare(
+            "let sum = 0;
+            let i = 0;
+            while (i < 10) {
+                let square = i * i;
+                print square;
+                if (i < 5) sum = sum + square; else { sum = sum - 1; }
+                i += 1;
+            }",
+            &["sum", "i"],
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        compare(
+            "fn fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+            let a = fib(15);
+            fn noop() {}
+            let b = noop();
+            print fib(10), \"done\";
+            let now = time() > 0;",
+            &["a", "b", "now"],
+        );
+    }
+
+    #[test]
+    fn test_arity() {
+        let results = compare(
+            "fn pair(a, b) { return a + b; }
+            let a = pair(1, 2);
+            let b = pair(1);
+            let c = pair(1, 2, 3);
+            fn outer() { return pair(1); }
+            let d = outer();",
+            &["a"],
+        );
+        let failed: Vec<bool> = results.iter().map(Result::is_err).collect();
+        assert_eq!(failed, [false, false, true, true, false, true]);
+        assert_eq!(
+            results[2].as_ref().unwrap_err().error,
+            Error::Type("pair() takes 2 arguments but 1 were given".to_string())
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let results = compare(
+            "let a = 1;
+            print aa;
+            a = \"a\" - 1;
+            const c = 1;
+            c = 2;
+            { const d = nil; }
+            { const e = 1; e = 2; }
+            1 / 0;
+            a();
+            len = 1;",
+            &["a", "c"],
+        );
+        let failed: Vec<bool> = results.iter().map(Result::is_err).collect();
+        assert_eq!(
+            failed,
+            [false, true, true, false, true, true, true, true, true, true]
+        );
+        assert_eq!(
+            results[1].as_ref().unwrap_err().error,
+            Error::Name("aa".to_string(), vec!["a".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_deep_recursion() {
+        // frames live on the heap, so a host can let compiled functions
+        // recurse far deeper than the native stack would allow
+        let mut interpretor = Interpretor::new().with_max_call_depth(20_001);
+        let program = Parser::new(Lexer::new(
+            "fn count(n) { if (n == 0) return 0; return 1 + count(n - 1); }
+            let a = count(20000);"
+                .to_string(),
+        ))
+        .parse_program()
+        .unwrap();
+        interpretor.run_compiled(program).unwrap();
+        assert_eq!(get(&interpretor, "a"), Object::Number(20000.0));
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        // both backends count the calls against the same limit
+        let input = "fn f(n) { if (n > 0) f(n - 1); } f(9); f(10); f(9);";
+        let results = compare_with(input, &[], || Interpretor::new().with_max_call_depth(10));
+        assert!(results[1].is_ok());
+        let err = results[2].as_ref().unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Runtime("maximum recursion depth exceeded".to_string())
+        );
+        // the calls that failed are no longer counted
+        assert!(results[3].is_ok());
+    }
+
+    #[test]
+    fn test_backends_share_functions() {
+        let mut interpretor = Interpretor::new();
+        let program = |input: &str| {
+            Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap()
+        };
+        interpretor
+            .interpret(program("fn double(n) { return n * 2; }"))
+            .unwrap();
+        interpretor
+            .run_compiled(program("fn quad(n) { return double(double(n)); }"))
+            .unwrap();
+        interpretor.interpret(program("let a = quad(3);")).unwrap();
+        assert_eq!(get(&interpretor, "a"), Object::Number(12.0));
+    }
+
+    #[test]
+    fn test_sandboxed_recursion() {
+        let program = |input: &str| {
+            Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap()
+        };
+        let diagnostics = Capture::default();
+        let mut interpretor = Interpretor::sandboxed(u64::MAX, 1 << 20)
+            .with_diagnostics(Box::new(diagnostics.clone()));
+        let input = "fn f(n) { if (n == 0) return 0; return 1 + f(n - 1); }
+            let a = f(1999);
+            let b = f(2000);";
+        interpretor.run_compiled(program(input)).unwrap();
+        assert_eq!(get(&interpretor, "a"), Object::Number(1999.0));
+        assert_eq!(interpretor.errors_reported(), 1);
+        assert!(diagnostics
+            .text()
+            .contains("maximum recursion depth exceeded"));
+        // and when the tree-walker calls the compiled function
+        interpretor.interpret(program("f(2000);")).unwrap();
+        assert_eq!(interpretor.errors_reported(), 2);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let program = Parser::new(Lexer::new(
+            "fn outer() { let a = 1; fn inner() { return a; } }".to_string(),
+        ))
+        .parse_program()
+        .unwrap();
+        assert_eq!(
+            Interpretor::new().run_compiled(program).unwrap_err().error,
+            Error::Syntax("closures not supported by the bytecode compiler".to_string())
+        );
+    }
+}
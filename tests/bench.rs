@@ -0,0 +1,89 @@
+use std::{io, time::Duration};
+
+use rlisp::Interpretor;
+
+// Times parsing and running generated programs on the tree-walker, for
+// comparing changes to the AST and the evaluator. They are not run with the
+// other tests:
+//
+//   cargo test --release --test bench -- --ignored --nocapture
+const RUNS: usize = 9;
+
+fn median(mut times: Vec<Duration>) -> Duration {
+    times.sort();
+    times[times.len() / 2]
+}
+
+// the median time `source` takes to parse and to evaluate
+fn time(source: &str) -> (Duration, Duration) {
+    let (mut parse, mut evaluate) = (Vec::new(), Vec::new());
+    for _ in 0..RUNS {
+        let mut interpretor = Interpretor::new().with_output(Box::new(io::sink()));
+        let program = interpretor.parse(source, "bench.rl").unwrap();
+        interpretor.interpret(program).unwrap();
+        parse.push(interpretor.stats().parse);
+        evaluate.push(interpretor.stats().evaluate);
+    }
+    (median(parse), median(evaluate))
+}
+
+// many functions of deeply nested arithmetic, two of them called in a loop
+#[test]
+#[ignore]
+fn bench_parse_and_eval() {
+    let mut source = String::new();
+    for i in 0..2000 {
+        let nested = format!("{}x{}", "(1 + ".repeat(20), ")".repeat(20));
+        source.push_str(&format!("fn f{i}(x) {{ return {nested} * 2 - x; }}\n"));
+    }
+    source.push_str(
+        "let total = 0;
+for (let i = 0; i < 100000; i = i + 1) { total = total + f1(i) - f2(i); }
+print total;
+",
+    );
+    let (parse, evaluate) = time(&source);
+    println!("parse {parse:?}, evaluate {evaluate:?}, median of {RUNS} runs");
+}
+
+// calls, parameters and locals
+#[test]
+#[ignore]
+fn bench_fib() {
+    let source = "
+fn fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+print fib(25);";
+    let (_, evaluate) = time(source);
+    println!("fib(25) {evaluate:?}, median of {RUNS} runs");
+}
+
+// reads and writes of the locals of a function
+#[test]
+#[ignore]
+fn bench_counting_loop() {
+    let source = "
+fn count(n) {
+    let total = 0;
+    for (let i = 0; i < n; i = i + 1) { total = total + i; }
+    return total;
+}
+print count(1000000);";
+    let (_, evaluate) = time(source);
+    println!("counting to a million {evaluate:?}, median of {RUNS} runs");
+}
+
+// a long string built by concatenation, then passed around and compared
+#[test]
+#[ignore]
+fn bench_strings() {
+    let source = "
+let s = \"\";
+for (let i = 0; i < 20000; i = i + 1) { s = s + \"x\"; }
+fn same(a, b) { return a == b; }
+let copy = s;
+let count = 0;
+for (let i = 0; i < 100000; i = i + 1) { if (same(s, copy)) count = count + 1; }
+print count;";
+    let (_, evaluate) = time(source);
+    println!("strings {evaluate:?}, median of {RUNS} runs");
+}
@@ -0,0 +1,427 @@
+use std::{
+    env, fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+// a script holding `source`, named after the test so they can run at once
+fn script(name: &str, source: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("rlisp-cli-{name}-{}.rl", std::process::id()));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rlisp"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+// runs the binary with `input` piped to its stdin
+fn run_stdin(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlisp"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// runs `source` as a file, returning its status, stdout and stderr
+fn run_script(name: &str, source: &str) -> (i32, String, String) {
+    let path = script(name, source);
+    let output = run(&["--color=never", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+// a usage error rather than the REPL, run on a terminal `script` makes
+#[test]
+#[cfg(target_os = "linux")]
+fn test_check_without_files() {
+    let command = format!("{} --check", env!("CARGO_BIN_EXE_rlisp"));
+    let Ok(output) = Command::new("script")
+        .args(["-qec", &command, "/dev/null"])
+        .stdin(Stdio::null())
+        .output()
+    else {
+        // util-linux is not installed
+        return;
+    };
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("[usuage] rlisp"));
+}
+
+#[test]
+fn test_success() {
+    let (status, stdout, stderr) = run_script("success", "print 1 + 2;");
+    assert_eq!((status, stdout.as_str(), stderr.as_str()), (0, "3\n", ""));
+}
+
+#[test]
+fn test_parse_error() {
+    let (status, stdout, stderr) = run_script("parse", "print 1;\nlet = 2;");
+    assert_eq!(status, 65);
+    // nothing runs once the program does not parse
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("[E0202]"), "{stderr}");
+}
+
+#[test]
+fn test_resolve_error() {
+    let (status, _, stderr) = run_script("resolve", "return 1;");
+    assert_eq!(status, 65);
+    assert!(stderr.contains("[E0301]"), "{stderr}");
+}
+
+#[test]
+fn test_runtime_error() {
+    let (status, stdout, stderr) = run_script("runtime", "print 1 / 0;\nprint \"after\";");
+    assert_eq!(status, 70);
+    // the statements after the one that failed still run
+    assert_eq!(stdout, "after\n");
+    assert!(stderr.contains("ZeroDivisionError[E0401]"), "{stderr}");
+}
+
+#[test]
+fn test_unsupported() {
+    let source = "class A {}\nlet a = \"a\";\na.b = 1;\nprint \"after\";";
+    let (status, stdout, stderr) = run_script("unsupported", source);
+    // reported as errors the program carries on after, rather than a panic
+    assert_eq!((status, stdout.as_str()), (70, "after\n"));
+    assert!(stderr.contains("classes not supported"), "{stderr}");
+    assert!(
+        stderr.contains("property assignments not supported"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
+
+#[test]
+fn test_recursion_limit() {
+    let source = "fn f(n) { return 1 + f(n + 1); }\nf(0);\nprint \"after\";";
+    let (status, stdout, stderr) = run_script("recursion", source);
+    // an error rather than the process overflowing its stack
+    assert_eq!((status, stdout.as_str()), (70, "after\n"));
+    assert!(
+        stderr.contains("maximum recursion depth exceeded"),
+        "{stderr}"
+    );
+    // as deep as the default allows
+    let source = "fn f(n) { if (n == 0) { return 0; } return 1 + f(n - 1); }\nprint f(1999);";
+    let (status, stdout, _) = run_script("deep-recursion", source);
+    assert_eq!((status, stdout.as_str()), (0, "1999\n"));
+}
+
+#[test]
+fn test_exit_code() {
+    let (status, stdout, _) = run_script("exit", "print 1;\nexit(3);\nprint 2;");
+    assert_eq!((status, stdout.as_str()), (3, "1\n"));
+    // even after a runtime error
+    let (status, _, _) = run_script("exit-after-error", "print 1 / 0;\nexit(0);");
+    assert_eq!(status, 0);
+}
+
+#[test]
+fn test_missing_file() {
+    let output = run(&["no-such-script.rl"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(output.status.code(), Some(66));
+    assert!(
+        stderr.contains("cannot read 'no-such-script.rl'"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
+
+#[test]
+fn test_usage() {
+    let output = run(&["--no-such-flag"]);
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("rlisp"));
+}
+
+#[test]
+fn test_tokens() {
+    let path = script("tokens", "print 1;\nx @");
+    let output = run(&["--tokens", "--color=never", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "1:0-5 Print print\n1:6-7 Number 1\n1:7-8 Semicolon ;\n2:9-10 Identifier x\n"
+    );
+    // stopping at the first error
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("[E0102]"), "{stderr}");
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn test_ast() {
+    let path = script("ast", "fn f(a) { return a * 2; }\nprint f(1 + 2);");
+    let output = run(&["--ast", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // nothing runs
+    assert_eq!(
+        stdout,
+        "(fn f (a)\n  (return (* a 2)))\n(print (call f (+ 1 2)))\n"
+    );
+    assert_eq!(output.status.code(), Some(0));
+
+    let path = script("ast-error", "print (1;");
+    let output = run(&["--ast", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_check() {
+    let good = script("check-good", "print \"ran\";\nfn f() { let unused = 1; }");
+    let bad = script("check-bad", "let = 1;\nprint (2;");
+    let (good, bad) = (good.to_str().unwrap(), bad.to_str().unwrap());
+
+    let output = run(&["--check", "--color=never", good]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // warnings are reported and nothing runs
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    assert!(stderr.contains("[W0301]"), "{stderr}");
+
+    let output = run(&["--check", "--deny-warnings", good]);
+    assert_eq!(output.status.code(), Some(65));
+
+    // every file is checked, with every error of each
+    let output = run(&["--check", "--color=never", bad, good]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(stderr.matches("[E02").count(), 2, "{stderr}");
+    assert!(stderr.contains("[W0301]"), "{stderr}");
+
+    fs::remove_file(good).unwrap();
+    fs::remove_file(bad).unwrap();
+}
+
+#[test]
+fn test_trace() {
+    let path = script("trace", "fn f() {\n  print 1;\n}\nf();");
+    let output = run(&["--trace", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // the trace goes to stderr, around what the program prints
+    assert_eq!(stderr, "1: (fn f () ...)\n4: (call f)\n  2: (print 1)\n");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+}
+
+#[test]
+fn test_stdin() {
+    for args in [&["-"][..], &[]] {
+        let output = run_stdin(args, "print 1 + 1;\nprint \"two\";");
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "2\ntwo\n");
+        assert_eq!(output.status.code(), Some(0));
+    }
+    let output = run_stdin(&["--color=never", "-"], "print 1;\nprint 1 / 0;");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stderr.contains("--> <stdin>:2:"), "{stderr}");
+
+    let output = run_stdin(&["--check"], "let = 1;");
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_several_files() {
+    let lib = script("lib", "fn double(x) { return x * 2; }\nlet base = 10;");
+    let main = script("main", "print double(base);");
+    let (lib, main) = (lib.to_str().unwrap(), main.to_str().unwrap());
+    let output = run(&[lib, main]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "20\n");
+    assert_eq!(output.status.code(), Some(0));
+
+    // a failure stops the files after it, and is reported in its own file
+    let broken = script("broken", "print \"first\";\nlet a = 1 / 0;");
+    let broken = broken.to_str().unwrap();
+    let output = run(&["--color=never", broken, main]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "first\n");
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stderr.contains(&format!("--> {broken}:2:")), "{stderr}");
+
+    let unparsable = script("unparsable", "print (;");
+    let unparsable = unparsable.to_str().unwrap();
+    let output = run(&[lib, unparsable, main]);
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+
+    for path in [lib, main, broken, unparsable] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_time() {
+    let path = script("time", "let i = 0;\nwhile (i < 2) i = i + 1;\nprint i;");
+    let output = run(&["--time", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2\n");
+    let names: Vec<&str> = stderr
+        .lines()
+        .filter_map(|line| line.split(':').next())
+        .collect();
+    assert_eq!(names, ["parse", "resolve", "evaluate", "statements"]);
+    assert!(stderr.ends_with("statements: 5\n"), "{stderr}");
+}
+
+#[test]
+fn test_profile() {
+    let path = script(
+        "profile",
+        "fn f(n) { if (n > 0) { return f(n - 1); } return len(\"ab\"); }\nprint f(3);",
+    );
+    let output = run(&["--profile", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2\n");
+    let rows: Vec<(&str, &str)> = stderr
+        .lines()
+        .map(|line| {
+            let mut columns = line.split_whitespace();
+            (columns.next().unwrap(), columns.next().unwrap())
+        })
+        .collect();
+    // the script's function holds the native's time
+    assert_eq!(rows, [("function", "calls"), ("f", "4"), ("len", "1")]);
+}
+
+#[test]
+fn test_coverage() {
+    let path = script("coverage", "let a = 1;\nif (a > 1) {\n  print a;\n}");
+    let name = path.to_str().unwrap();
+    let output = run(&["--coverage", name]);
+    fs::remove_file(&path).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let listing = [
+        "        -:    0:Counts:statements run on the line",
+        "        1:    1:let a = 1;",
+        "        1:    2:if (a > 1) {",
+        "    #####:    3:  print a;",
+        "        -:    4:}",
+    ];
+    let expected = format!("{name}: 2 of 3 lines run (66.7%)\n{}\n", listing.join("\n"));
+    assert_eq!(stderr, expected);
+}
+
+#[test]
+fn test_debug() {
+    let path = script("debug", "fn f(n) {\n  return n * 2;\n}\nprint f(4);");
+    let output = run_stdin(
+        &["--debug", path.to_str().unwrap()],
+        "break 2\nrun\nprint n\nbacktrace\ncontinue\n",
+    );
+    fs::remove_file(&path).unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("stopped at line 2: (return (* n 2))\n(debug) 4\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("#0 f at line 2\n#1 <script> at line 4\n"),
+        "{stdout}"
+    );
+    assert!(stdout.ends_with("(debug) 8\n"), "{stdout}");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+// the messy inputs under tests/fmt, each beside the `.out` it formats to
+fn fmt_fixtures() -> Vec<(String, String, String)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fmt");
+    let mut fixtures: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rl"))
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+            let input = fs::read_to_string(&path).unwrap();
+            let expected = fs::read_to_string(path.with_extension("out")).unwrap();
+            (name, input, expected)
+        })
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty());
+    fixtures
+}
+
+#[test]
+fn test_fmt() {
+    for (name, input, expected) in fmt_fixtures() {
+        let path = script(&format!("fmt-{name}"), &input);
+        let output = run(&["fmt", path.to_str().unwrap()]);
+        let formatted = fs::read_to_string(&path).unwrap();
+        assert_eq!(output.status.code(), Some(0), "{name}");
+        assert_eq!(formatted, expected, "{name}");
+
+        // formatting again changes nothing
+        let output = run(&["fmt", "--check", path.to_str().unwrap()]);
+        assert_eq!(output.status.code(), Some(0), "{name}");
+        fs::remove_file(&path).unwrap();
+
+        let output = run_stdin(&["fmt", "-"], &input);
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            expected,
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn test_fmt_check() {
+    let messy = "let  a=1;";
+    let path = script("fmt-check", messy);
+    let output = run(&["fmt", "--check", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("is not formatted"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), messy);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_fmt_refuses() {
+    // a file that does not parse, and one whose comments would be lost
+    for (name, source, message) in [
+        ("fmt-invalid", "let a = ;\nprint  a;", "ParseError[E0201]"),
+        (
+            "fmt-comments",
+            "# kept\nlet a = 1;  # one\nprint  a;",
+            "only comments on lines of their own before a statement can be formatted yet",
+        ),
+    ] {
+        let path = script(name, source);
+        let output = run(&["fmt", path.to_str().unwrap()]);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert_eq!(output.status.code(), Some(65), "{stderr}");
+        assert!(stderr.contains(message), "{stderr}");
+        assert_eq!(fs::read_to_string(&path).unwrap(), source);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+#![cfg(all(feature = "ffi", unix))]
+
+use std::{env, path::Path, process::Command};
+
+// Builds the cdylib and compiles tests/ffi/main.c against it, then runs
+// the program. The cdylib gets a target directory of its own, since the
+// one in the shared directory is from whichever build ran last.
+#[test]
+fn test_c_host() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let tmp = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let target = tmp.join("ffi-target");
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--lib", "--features", "ffi", "--target-dir"])
+        .arg(&target)
+        .current_dir(root)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let libs = target.join("debug");
+    let program = tmp.join("ffi-host");
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg(root.join("tests/ffi/main.c"))
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg("-L")
+        .arg(&libs)
+        .args(["-lrlisp", "-o"])
+        .arg(&program)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // cargo points the loader at the shared target directory
+    let output = Command::new(&program)
+        .env("LD_LIBRARY_PATH", &libs)
+        .env("DYLD_LIBRARY_PATH", &libs)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "ok\n");
+}
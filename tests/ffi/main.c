@@ -0,0 +1,48 @@
+/* Drives the C interface the way a host would, exiting with 1 at the first
+ * check that fails. Built and run by tests/ffi.rs. */
+#include <stdio.h>
+#include <string.h>
+
+#include "yai.h"
+
+static int failures = 0;
+
+static void check(int ok, const char *what) {
+    if (!ok) {
+        fprintf(stderr, "failed: %s\n", what);
+        failures++;
+    }
+}
+
+int main(void) {
+    Yai *yai = yai_new();
+    check(yai != NULL, "yai_new");
+
+    check(yai_run(yai, "let a = 20;\nprint a + 1;") == 0, "run succeeds");
+    check(strcmp(yai_get_output(yai), "21\n") == 0, "output is captured");
+    check(yai_last_error(yai) == NULL, "no error after success");
+
+    check(yai_run(yai, "print a / 0;\nprint \"after\";") == 70, "runtime error");
+    check(strcmp(yai_get_output(yai), "after\n") == 0, "output after the error");
+    const char *error = yai_last_error(yai);
+    check(error != NULL && strstr(error, "ZeroDivisionError") != NULL, "error text");
+
+    check(yai_run(yai, "fn f() { return 1 + f(); }\nf();") == 70, "deep recursion");
+    error = yai_last_error(yai);
+    check(error != NULL && strstr(error, "maximum recursion depth") != NULL, "recursion text");
+
+    check(yai_run(yai, "let = 1;") == 65, "parse error");
+    check(yai_last_error(yai) != NULL, "parse error text");
+    check(yai_run(yai, "exit(4);") == 4, "exit status");
+
+    check(yai_run(NULL, "print 1;") == 64, "null interpreter");
+    check(yai_run(yai, NULL) == 64, "null source");
+    check(yai_get_output(NULL) == NULL, "null output");
+
+    yai_free(yai);
+    yai_free(NULL);
+    if (failures == 0) {
+        printf("ok\n");
+    }
+    return failures == 0 ? 0 : 1;
+}
@@ -0,0 +1,4 @@
+class Shape{init(name){this.name=name;}describe(){return this.name;}}
+class Square<Shape{init(side){super.init("square");this.side=side;}
+area(){return this.side*this.side;}}
+let s=Square(3);print s.describe(),s.area();
@@ -0,0 +1,11 @@
+# what the script does
+let limit=3;
+fn count(n){
+  # down from n
+    while(n>0){print n;n=n-1;}}
+class Counter{
+# counts up
+  init(){this.n=0;}}
+if(limit>2)
+        # a body of its own
+  print count(limit);
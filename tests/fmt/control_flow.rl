@@ -0,0 +1,3 @@
+fn sign(n){if(n<0)return -1;else if (n==0) {return 0;} else return 1;}
+let i=0;while(i<3){print sign(i-1);i=i+1;}
+if (i > 2) { if (i > 5) print "big"; } else print "small";
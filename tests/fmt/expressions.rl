@@ -0,0 +1,4 @@
+let a=1+2*3;let b =(1+2)*3;
+let c   =   -  -a ;print a,b ,c;
+let d = ((a));  print !(a==b) && c<d;
+print "text"+str(1.5) , nil;
@@ -0,0 +1,4 @@
+let total=0;for(let i=0;i<10;i+=1){total+=i;}
+for (;total>0;) total /= 7;
+for(total=0;total<3;total=total+1)print total;
+let p=Square;p.n*=2;
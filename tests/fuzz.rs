@@ -0,0 +1,228 @@
+use std::{
+    env, fs,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+};
+
+use rlisp::{highlight, ColorChoice, ErrorInfo, Lexer, Parser};
+
+// Feeds the lexer and the parser inputs they were never meant to see: every
+// prefix of the sample scripts, those scripts with random edits, strings of
+// random tokens and bytes, and constructs nested far too deep. Whatever the
+// input, they must give back errors rather than panic, and the highlighter
+// must still classify all of it. The inputs come from
+// a fixed seed, so a failure shows up on every run until it is fixed. For a
+// longer run, `RLISP_FUZZ=<n>` makes n times as many edits and soups:
+//
+//   RLISP_FUZZ=20 cargo test --release --test fuzz
+const SEED: u64 = 0x5eed_1e55_f00d_cafe;
+const EDITS: usize = 300;
+const SOUPS: usize = 3000;
+
+// Every token the lexer knows, some of them glued to what comes after them,
+// and characters it does not.
+const TOKENS: &[&str] = &[
+    "let", "const", "fn", "class", "if", "else", "for", "while", "return", "print", "break",
+    "continue", "import", "this", "super", "nil", "true", "false", "a", "b", "init", "_x", "0",
+    "1.5", "2.", ".5", "\"s\"", "\"", "#", "\n", " ", "(", ")", "{", "}", "[", "]", ",", ";", ":",
+    ".", "=", "==", "!", "!=", "<", "<=", "<<", ">", ">=", ">>", "+", "+=", "-", "-=", "*", "*=",
+    "/", "/=", "%", "%=", "&", "&=", "&&", "|", "|=", "||", "^", "^=", "@", "$", "é", "\0", "\r",
+    "\t",
+];
+
+// xorshift64*, enough to pick edits and tokens
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn token(&mut self) -> &'static str {
+        TOKENS[self.below(TOKENS.len())]
+    }
+}
+
+// the sample scripts of the other tests, which are valid or close to it
+fn corpus() -> Vec<String> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let mut corpus = Vec::new();
+    for dir in ["scripts", "fmt"] {
+        let mut paths: Vec<_> = fs::read_dir(root.join(dir))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rl"))
+            .collect();
+        paths.sort();
+        corpus.extend(paths.iter().map(|path| fs::read_to_string(path).unwrap()));
+    }
+    assert!(!corpus.is_empty());
+    corpus
+}
+
+// `source` with a few random deletions, insertions, repeats and flipped
+// bytes, made valid UTF-8 again the way a lossy read of the file would
+fn edit(rng: &mut Rng, source: &str) -> String {
+    let mut bytes = source.as_bytes().to_vec();
+    for _ in 0..1 + rng.below(4) {
+        let at = rng.below(bytes.len() + 1);
+        let len = rng.below(bytes.len() - at + 1).min(16);
+        match rng.below(4) {
+            0 => drop(bytes.drain(at..at + len)),
+            1 => {
+                let token = rng.token().as_bytes().to_vec();
+                bytes.splice(at..at, token);
+            }
+            2 => {
+                let copied = bytes[at..at + len].to_vec();
+                bytes.splice(at..at, copied);
+            }
+            _ => {
+                if let Some(byte) = bytes.get_mut(at) {
+                    *byte ^= 1 << rng.below(8);
+                }
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// a run of random tokens, with no space between them half of the time
+fn soup(rng: &mut Rng) -> String {
+    let glue = if rng.below(2) == 0 { "" } else { " " };
+    let tokens: Vec<_> = (0..1 + rng.below(40)).map(|_| rng.token()).collect();
+    tokens.join(glue)
+}
+
+// Constructs that each open another level inside themselves, repeated well
+// past how deeply the parser lets them nest, with and without their ends.
+fn deep() -> Vec<String> {
+    let opens = [
+        ("(", ")"),
+        ("-", ""),
+        ("!", ""),
+        ("f(", ")"),
+        ("a.b(", ")"),
+        ("{", "}"),
+        ("if (a) ", ""),
+        ("while (a) ", ""),
+        ("for (;;) ", ""),
+        ("fn f() {", "}"),
+        ("class A { m() {", "}}"),
+        ("print (", ")"),
+        ("a = ", ""),
+        ("\"", ""),
+        ("#", ""),
+    ];
+    let mut inputs = Vec::new();
+    for (open, close) in opens {
+        let opened = open.repeat(500);
+        inputs.push(opened.clone());
+        inputs.push(format!("{opened}1;{}", close.repeat(500)));
+    }
+    // not nested, but once skipped a frame of the stack at a time
+    inputs.push("# a comment\n".repeat(30_000));
+    inputs
+}
+
+// whether the pieces `highlight` gives cover every byte of `input` once, in
+// order, but the whitespace between them
+fn covered(input: &str) -> bool {
+    let mut end = 0;
+    let mut gaps = Vec::new();
+    for (span, _) in highlight(input) {
+        if span.start < end || span.start >= span.end || span.end > input.len() {
+            return false;
+        }
+        gaps.push(&input[end..span.start]);
+        end = span.end;
+    }
+    gaps.push(&input[end..]);
+    let whitespace = |gap: &str| gap.chars().all(|ch| matches!(ch, ' ' | '\r' | '\t' | '\n'));
+    gaps.into_iter().all(whitespace)
+}
+
+// What the input made panic, if anything. The token stream, a program and
+// a REPL line are all taken from it, with and without comments kept, and
+// every error found on the way is rendered as it would be reported.
+fn panics(input: &str) -> Option<String> {
+    let run = || {
+        assert!(covered(input), "highlight does not cover the input");
+        let index = Lexer::from_str(input).line_index();
+        let report = |err: &ErrorInfo| {
+            err.render_with_index(&index, ColorChoice::Always);
+            err.to_json_with_index(Some(&index));
+        };
+        for trivia in [false, true] {
+            let lexer = || Lexer::from_str(input).with_trivia(trivia);
+            for token in lexer().tokens() {
+                if let Err(err) = token {
+                    report(&err);
+                }
+            }
+            let mut parser = Parser::new(lexer());
+            if let Err(errors) = parser.parse_program() {
+                errors.iter().for_each(report);
+            }
+            parser.take_comments();
+            if let Err(errors) = Parser::new(lexer()).parse_repl_line() {
+                errors.iter().for_each(report);
+            }
+        }
+    };
+    let err = panic::catch_unwind(AssertUnwindSafe(run)).err()?;
+    let message = err
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| err.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    Some(message)
+}
+
+#[test]
+fn test_no_panics() {
+    let scale: usize = env::var("RLISP_FUZZ").map_or(1, |n| n.parse().unwrap());
+    let mut rng = Rng(SEED);
+    let mut inputs = Vec::new();
+    for source in corpus() {
+        // ending inside every construct of the script
+        let ends = source.char_indices().map(|(i, _)| i);
+        inputs.extend(ends.map(|end| source[..end].to_string()));
+        inputs.extend((0..EDITS * scale).map(|_| edit(&mut rng, &source)));
+    }
+    inputs.extend((0..SOUPS * scale).map(|_| soup(&mut rng)));
+    inputs.extend(deep());
+
+    // the panics are reported together rather than each on its own
+    panic::set_hook(Box::new(|_| {}));
+    let mut failures: Vec<_> = inputs
+        .iter()
+        .filter_map(|input| Some((input, panics(input)?)))
+        .collect();
+    let _ = panic::take_hook();
+    failures.sort_by_key(|(input, _)| input.len());
+    let shown: Vec<_> = failures
+        .iter()
+        .take(10)
+        .map(|(input, message)| {
+            format!(
+                "{:?}: {message}",
+                input.chars().take(200).collect::<String>()
+            )
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {} inputs panicked, the shortest:\n{}",
+        failures.len(),
+        inputs.len(),
+        shown.join("\n")
+    );
+}
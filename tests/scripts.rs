@@ -0,0 +1,279 @@
+use std::{
+    fs, io, panic,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+};
+
+use rlisp::{
+    Capture, Error, ErrorInfo, Interpretor, Lexer, Parser, Resolver, TokenInfo, TokenType,
+};
+
+fn scripts_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scripts")
+}
+
+// every script in tests/scripts, in order
+fn scripts() -> Vec<PathBuf> {
+    let mut scripts: Vec<_> = fs::read_dir(scripts_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rl"))
+        .collect();
+    scripts.sort();
+    assert!(!scripts.is_empty());
+    scripts
+}
+
+// Runs every script in tests/scripts and compares what it prints with the
+// `.out` file next to it, and what it reports with the `.err` file, missing
+// when it reports nothing.
+#[test]
+fn test_scripts() {
+    for script in scripts() {
+        let name = script.file_name().unwrap().to_str().unwrap();
+        let output = Command::new(env!("CARGO_BIN_EXE_rlisp"))
+            .args(["--color=never", name])
+            .current_dir(scripts_dir())
+            .output()
+            .unwrap();
+        let expected = |ext| fs::read_to_string(script.with_extension(ext)).unwrap_or_default();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert_eq!(stdout, expected("out"), "{name}");
+        assert_eq!(stderr, expected("err"), "{name}");
+    }
+}
+
+// Every script that parses is printed back as source, which parses to the
+// same tree, leaving out the parentheses that do not change it, and prints
+// the same again.
+#[test]
+fn test_round_trip() {
+    let mut printed = 0;
+    for script in scripts() {
+        let name = script.file_name().unwrap().to_str().unwrap();
+        let Ok(program) = Parser::from_str(&fs::read_to_string(&script).unwrap()).parse_program()
+        else {
+            continue;
+        };
+        let source = program.to_source();
+        let reparsed = match Parser::from_str(&source).parse_program() {
+            Ok(reparsed) => reparsed,
+            Err(errors) => panic!("{name} printed as\n{source}\ndoes not parse: {errors:?}"),
+        };
+        assert_eq!(reparsed.to_string(), program.to_string(), "{name}");
+        assert_eq!(reparsed.to_source(), source, "{name}");
+        printed += 1;
+    }
+    assert!(printed > 0);
+}
+
+// What a golden script in tests/scripts/lang says it does, in comments:
+//
+//   print 1 + 2; # expect: 3
+//   print 1 / 0; # expect-error: ZeroDivisionError
+//   # expect-exit: 70
+//
+// `expect:` gives a line it prints, in order. `expect-error:` gives an error
+// reported on the line of the comment, by its kind alone or as the kind and
+// the message. `expect-exit:` gives the status the script exits with, which
+// is otherwise 0 unless an error is expected.
+#[derive(Default)]
+struct Expected {
+    output: String,
+    errors: Vec<(usize, String)>,
+    status: Option<i32>,
+}
+
+impl Expected {
+    fn read(source: &str) -> Result<Self, String> {
+        let mut expected = Self::default();
+        for token in Lexer::from_str(source).with_trivia(true).tokens() {
+            // a script may be about the errors of the lexer, which carries on
+            // after them
+            let Ok(TokenInfo {
+                token: TokenType::Comment(text),
+                span,
+            }) = &token
+            else {
+                continue;
+            };
+            let text = text.trim_start();
+            if let Some(line) = text.strip_prefix("expect:") {
+                let line = line.strip_prefix(' ').unwrap_or(line);
+                expected.output.push_str(line);
+                expected.output.push('\n');
+            } else if let Some(error) = text.strip_prefix("expect-error:") {
+                let error = error.trim().to_string();
+                expected.errors.push((span.line, error));
+            } else if let Some(status) = text.strip_prefix("expect-exit:") {
+                let status = status.trim().parse().map_err(|_| format!("bad {text:?}"))?;
+                expected.status = Some(status);
+            }
+        }
+        Ok(expected)
+    }
+}
+
+// What a script did: what it printed, the errors it reported with the
+// lines they were on, and the status the interpreter would exit with.
+struct Actual {
+    output: String,
+    errors: Vec<ErrorInfo>,
+    status: i32,
+}
+
+// Runs `source` the way `rlisp <file>` does, carrying on after the errors
+// of a statement, but keeps the errors rather than reporting them.
+fn run(source: &str, name: &str) -> Actual {
+    let output = Capture::default();
+    let mut interpretor = Interpretor::new()
+        .with_output(Box::new(output.clone()))
+        .with_diagnostics(Box::new(io::sink()))
+        .without_input()
+        .with_rng_seed(0);
+    let (errors, stopped) = match interpretor.parse(source, name) {
+        Err(errors) => (errors, true),
+        Ok(mut program) => match Resolver::new().resolve(&mut program) {
+            Err(err) => (vec![err], true),
+            Ok(()) => {
+                let mut errors = Vec::new();
+                let mut stopped = false;
+                for stmt in program.stmts.clone() {
+                    if let Err(err) = interpretor.exec_in(&program, stmt) {
+                        stopped = matches!(err.error, Error::Exit(_));
+                        errors.push(err);
+                        if stopped {
+                            break;
+                        }
+                    }
+                }
+                (errors, stopped)
+            }
+        },
+    };
+    // as in main, an error that stops the program gives the status and the
+    // errors carried on after give EX_SOFTWARE
+    let status = match errors.last() {
+        Some(err) if stopped => err.error.exit_status(),
+        Some(_) => 70,
+        None => 0,
+    };
+    let errors = errors
+        .into_iter()
+        .filter(|err| !matches!(err.error, Error::Exit(_)))
+        .collect();
+    Actual {
+        output: output.text(),
+        errors,
+        status,
+    }
+}
+
+// how `actual` differs from `expected`, one line per difference
+fn differences(expected: &Expected, actual: &Actual) -> Vec<String> {
+    let mut differences = Vec::new();
+    let (mut want, mut got) = (expected.output.lines(), actual.output.lines());
+    for line in 1.. {
+        match (want.next(), got.next()) {
+            (None, None) => break,
+            (want, got) if want == got => {}
+            (want, got) => {
+                differences.push(format!(
+                    "output line {line}: expected {want:?}, got {got:?}"
+                ));
+            }
+        }
+    }
+    let mut errors = expected.errors.iter();
+    for err in &actual.errors {
+        let (line, found) = (err.span.line, err.error.to_string());
+        match errors.next() {
+            Some((at, error)) if *at == line && (error == err.error.kind() || *error == found) => {}
+            Some((at, error)) => differences.push(format!(
+                "expected {error} on line {at}, got {found} on line {line}"
+            )),
+            None => differences.push(format!("unexpected {found} on line {line}")),
+        }
+    }
+    for (at, error) in errors {
+        differences.push(format!("expected {error} on line {at}, got nothing"));
+    }
+    let status = expected.status.or(expected.errors.is_empty().then_some(0));
+    if let Some(status) = status.filter(|status| *status != actual.status) {
+        differences.push(format!(
+            "expected exit status {status}, got {}",
+            actual.status
+        ));
+    }
+    differences
+}
+
+// every script in tests/scripts/lang and the directories under it, in order
+fn golden_scripts(dir: &Path) -> Vec<PathBuf> {
+    let mut scripts = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            scripts.extend(golden_scripts(&path));
+        } else if path.extension().is_some_and(|ext| ext == "rl") {
+            scripts.push(path);
+        }
+    }
+    scripts.sort();
+    scripts
+}
+
+// Runs every golden script and checks it against the expectations in its
+// comments, reporting every script that fails them at once. It runs on a
+// stack as big as the one of the command line, as the scripts may recurse as
+// deep as the interpreter allows.
+#[test]
+fn test_golden() {
+    let golden = thread::Builder::new()
+        .stack_size(256 << 20)
+        .spawn(check_golden)
+        .unwrap();
+    if let Err(panic) = golden.join() {
+        panic::resume_unwind(panic);
+    }
+}
+
+fn check_golden() {
+    let dir = scripts_dir().join("lang");
+    let scripts = golden_scripts(&dir);
+    assert!(!scripts.is_empty());
+    let mut failures = Vec::new();
+    for script in &scripts {
+        let name = script.strip_prefix(&dir).unwrap().display().to_string();
+        let source = fs::read_to_string(script).unwrap();
+        let expected = match Expected::read(&source) {
+            Ok(expected) => expected,
+            Err(err) => {
+                failures.push(format!("{name}:\n  cannot read the expectations: {err}"));
+                continue;
+            }
+        };
+        // a script that panics fails on its own rather than taking the rest
+        // down with it
+        let differences = match panic::catch_unwind(|| run(&source, &name)) {
+            Ok(actual) => differences(&expected, &actual),
+            Err(err) => {
+                let message = err.downcast_ref::<&str>().copied().unwrap_or_default();
+                let message = err.downcast_ref::<String>().map_or(message, String::as_str);
+                vec![format!("panicked: {message}")]
+            }
+        };
+        if !differences.is_empty() {
+            failures.push(format!("{name}:\n  {}", differences.join("\n  ")));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} golden scripts failed:\n{}",
+        failures.len(),
+        scripts.len(),
+        failures.join("\n")
+    );
+}
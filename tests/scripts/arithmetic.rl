@@ -0,0 +1,11 @@
+# precedence, grouping and the unary operators
+print 1 + 2 * 3, (1 + 2) * 3, 10 / 4, -(2 - 5), +3;
+print 7 - 2 - 1, 2 * 3 / 4, 1 < 2, 2 <= 2, 3 > 4, 4 >= 5;
+print 1 == 1, 1 != 1, !true, !nil, !!1;
+let a = 5;
+a += 2;
+a *= 3;
+a /= 7;
+print a;
+const big = 100000 * 100000 * 100000;
+print big, -big, 0.1 + 0.2;
@@ -0,0 +1,10 @@
+print "before";
+print 1 / 0;
+let x = undefined_name;
+const c = 1;
+c = 2;
+print "abc".nope;
+print len(1, 2);
+fn f() { return g(); }
+f();
+print "after";
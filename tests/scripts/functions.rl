@@ -0,0 +1,39 @@
+fn fib(n) {
+  if (n < 2) return n;
+  return fib(n - 1) + fib(n - 2);
+}
+print fib(15);
+
+fn counter() {
+  let count = 0;
+  fn increment() {
+    count = count + 1;
+    return count;
+  }
+  return increment;
+}
+let next = counter();
+next();
+next();
+print next();
+
+fn apply(f, x) { return f(f(x)); }
+fn double(x) { return x * 2; }
+print apply(double, 5), apply(str, 1);
+
+# mutual recursion between hoisted local functions
+fn parity(n) {
+  fn is_even(n) { if (n == 0) return true; return is_odd(n - 1); }
+  fn is_odd(n) { if (n == 0) return false; return is_even(n - 1); }
+  return is_even(n);
+}
+print parity(10), parity(7);
+
+# deep tail calls do not grow the stack
+fn count(n, total) {
+  if (n == 0) return total;
+  return count(n - 1, total + n);
+}
+print count(100000, 0);
+print fn_missing_args(1);
+fn fn_missing_args(a, b) { return b; }
@@ -0,0 +1,3 @@
+print assert(1 == 1); # expect: nil
+assert(false, "boom"); # expect-error: RuntimeError: assertion failed: boom
+print "carries on"; # expect: carries on
@@ -0,0 +1,7 @@
+print type(1), type(nil), type(true), type("x"); # expect: number nil boolean string
+print str(1.5), str(1.0), str(true), str(nil); # expect: 1.5 1 true nil
+print num("42") + 1, parse_int("12"), parse_float("1.5"); # expect: 43 12 1.5
+print ord("A"), chr(66); # expect: 65 B
+print len("four"), len(""); # expect: 4 0
+print num("x"); # expect-error: ValueError: invalid number:'x'
+print len(1); # expect-error: TypeError: object of type 'number' has no len()
@@ -0,0 +1,3 @@
+print format("{} + {} = {}", 1, 2, 3); # expect: 1 + 2 = 3
+print format("{}-{}", "a", 1); # expect: a-1
+print re_match("a+", "aaa"), re_replace("a", "banana", "o"); # expect: true bonono
@@ -0,0 +1,5 @@
+print sqrt(16), abs(-3), floor(2.7), ceil(2.1), round(2.5); # expect: 4 3 2 3 3
+print pow(2, 10), pow(2, 0.5); # expect: 1024 1.4142135623730951
+print min(3, 1), max(3, 1), sum(1, 2, 3); # expect: 1 3 6
+print log(1), exp(0); # expect: 0 1
+print abs("a"); # expect-error: TypeError: abs() argument must be a number, not 'string'
@@ -0,0 +1,11 @@
+let s = "";
+for (let i = 0; i < 5; i = i + 1) {
+  s = s + str(i);
+}
+print s; # expect: 01234
+let n = 0;
+for (; n < 3;) n = n + 1;
+print n; # expect: 3
+for (let i = 0; i < 2; i += 1) print i * 10;
+# expect: 0
+# expect: 10
@@ -0,0 +1,12 @@
+fn sign(n) {
+  if (n < 0) {
+    return "negative";
+  } else if (n == 0) {
+    return "zero";
+  } else {
+    return "positive";
+  }
+}
+print sign(-2), sign(0), sign(3); # expect: negative zero positive
+if (false) print "no";
+print "after"; # expect: after
@@ -0,0 +1,10 @@
+for (let i = 1; i <= 3; i = i + 1) {
+  let row = "";
+  for (let j = 1; j <= 3; j = j + 1) {
+    row = row + str(i * j) + " ";
+  }
+  print row.trim();
+}
+# expect: 1 2 3
+# expect: 2 4 6
+# expect: 3 6 9
@@ -0,0 +1,10 @@
+let i = 0;
+while (i < 3) {
+  print i;
+  i = i + 1;
+}
+# expect: 0
+# expect: 1
+# expect: 2
+while (false) print "never";
+print i; # expect: 3
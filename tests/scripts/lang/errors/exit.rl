@@ -0,0 +1,4 @@
+print "before"; # expect: before
+exit(3);
+print "never";
+# expect-exit: 3
@@ -0,0 +1,4 @@
+# the status given to exit wins over the errors reported before it
+print 1 / 0; # expect-error: ZeroDivisionError
+exit(0);
+# expect-exit: 0
@@ -0,0 +1,6 @@
+# the error is reported where it happens, not at the call
+fn f() {
+  return g(); # expect-error: NameError
+}
+f();
+print "after"; # expect: after
@@ -0,0 +1,4 @@
+# the lexer skips what it cannot read, and nothing runs
+print "never";
+print 1 @; # expect-error: SyntaxError: unknown character:'@'
+# expect-exit: 65
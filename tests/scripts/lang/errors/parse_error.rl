@@ -0,0 +1,5 @@
+# nothing runs when the program does not parse
+print "never";
+print 1 +; # expect-error: ParseError
+let = 2; # expect-error: ParseError: Expected: "Identifier" Found: "="
+# expect-exit: 65
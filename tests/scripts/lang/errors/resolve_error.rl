@@ -0,0 +1,4 @@
+# the resolver rejects the program before any of it runs
+print "never";
+return 1; # expect-error: SyntaxError: cannot return from top-level code
+# expect-exit: 65
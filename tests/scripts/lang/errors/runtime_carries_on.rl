@@ -0,0 +1,7 @@
+# an error stops its statement, and the program carries on with the next
+print "before"; # expect: before
+print 1 / 0; # expect-error: ZeroDivisionError: division by zero
+let x = undefined_name; # expect-error: NameError
+nil(); # expect-error: TypeError: nil is not callable
+print "after"; # expect: after
+# expect-exit: 70
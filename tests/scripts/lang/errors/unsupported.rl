@@ -0,0 +1,9 @@
+# the forms the interpreter cannot run yet are errors, not crashes
+class Point {} # expect-error: RuntimeError: classes not supported by the interpreter
+let s = "text";
+s.size = 1; # expect-error: RuntimeError: property assignments not supported by the interpreter
+# reported where the assignment is, inside the function called
+fn set(object) { object.field = 2; } # expect-error: RuntimeError
+set(s);
+print "after"; # expect: after
+# expect-exit: 70
@@ -0,0 +1,6 @@
+print 1 < 2, 2 <= 2, 3 > 4, 4 >= 5; # expect: true true false false
+print 1 == 1, 1 != 1; # expect: true false
+print nil == nil, true == 1, 1 == "1"; # expect: true false false
+print "abc" == "abc", "abc" != "abd"; # expect: true true
+print 3 < "a"; # expect-error: TypeError: cannot apply '<' to number (3) and string ("a")
+print "a" < "b"; # expect-error: TypeError
@@ -0,0 +1,6 @@
+print 10 / 2, 10 / 4, 1 / 3; # expect: 5 2.5 0.3333333333333333
+print 0.1 + 0.2; # expect: 0.30000000000000004
+print 2 * -0.5; # expect: -1
+const big = 100000 * 100000 * 100000;
+print big, -big; # expect: 1000000000000000 -1000000000000000
+print sqrt(-1); # expect: NaN
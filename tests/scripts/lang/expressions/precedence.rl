@@ -0,0 +1,8 @@
+# multiplication binds tighter than addition, grouping overrides both
+print 1 + 2 * 3; # expect: 7
+print (1 + 2) * 3; # expect: 9
+print 7 - 2 - 1; # expect: 4
+print 2 * 3 / 4; # expect: 1.5
+print -(2 - 5), +3; # expect: 3 3
+print 2 - - 2; # expect: 4
+print 1 + 2 < 4, 2 * 2 == 4; # expect: true true
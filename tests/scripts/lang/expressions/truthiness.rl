@@ -0,0 +1,6 @@
+# nil, false, 0 and the empty string are falsy, everything else is truthy
+print !true, !false, !nil; # expect: false true true
+print !0, !"", !!1; # expect: true true true
+print true & false, !true & true; # expect: false false
+if ("text") print "strings are truthy"; # expect: strings are truthy
+if (nil) print "unreachable"; else print "nil is falsy"; # expect: nil is falsy
@@ -0,0 +1,6 @@
+fn pair(a, b) { return a + b; }
+print pair(1, 2); # expect: 3
+print pair(1); # expect-error: TypeError: pair() takes 2 arguments but 1 were given
+print pair(1, 2, 3); # expect-error: TypeError: pair() takes 2 arguments but 3 were given
+print len(1, 2); # expect-error: TypeError: len() takes 1 arguments but 2 were given
+print min(); # expect-error: TypeError: min() takes at least 1 arguments but 0 were given
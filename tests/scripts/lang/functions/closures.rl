@@ -0,0 +1,12 @@
+fn counter() {
+  let count = 0;
+  fn increment() {
+    count = count + 1;
+    return count;
+  }
+  return increment;
+}
+let next = counter();
+let other = counter();
+next();
+print next(), other(); # expect: 2 1
@@ -0,0 +1,6 @@
+fn apply(f, x) { return f(f(x)); }
+fn double(x) { return x * 2; }
+print apply(double, 5), apply(str, 1); # expect: 20 1
+fn nothing() {}
+print nothing(); # expect: nil
+print type(double), type(len); # expect: function function
@@ -0,0 +1,7 @@
+# local functions see each other whichever comes first
+fn parity(n) {
+  fn is_even(n) { if (n == 0) return true; return is_odd(n - 1); }
+  fn is_odd(n) { if (n == 0) return false; return is_even(n - 1); }
+  return is_even(n);
+}
+print parity(10), parity(7); # expect: true false
@@ -0,0 +1,18 @@
+fn fib(n) {
+  if (n < 2) return n;
+  return fib(n - 1) + fib(n - 2);
+}
+print fib(15); # expect: 610
+# deep tail calls do not grow the stack
+fn count(n, total) {
+  if (n == 0) return total;
+  return count(n - 1, total + n);
+}
+print count(100000, 0); # expect: 5000050000
+# recursing without end is an error, rather than overflowing the stack
+fn forever(n) {
+  return 1 + forever(n + 1); # expect-error: RuntimeError: maximum recursion depth exceeded
+}
+forever(0);
+print "after"; # expect: after
+# expect-exit: 70
@@ -0,0 +1,6 @@
+let greeting = "hello";
+print greeting + ", " + "world"; # expect: hello, world
+print "x" * 3, "ab".repeat(2); # expect: xxx abab
+print chr(97) + chr(98); # expect: ab
+print 1 + "a"; # expect-error: TypeError: cannot apply '+' to number (1) and string ("a")
+print -"a"; # expect-error: TypeError
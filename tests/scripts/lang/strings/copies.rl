@@ -0,0 +1,7 @@
+# strings are values, so a copy is unaffected by what is later assigned
+let original = "ab";
+let copy = original;
+original = original + "c";
+print original, copy; # expect: abc ab
+fn shout(text) { text = text + "!"; return text; }
+print shout(copy), copy; # expect: ab! ab
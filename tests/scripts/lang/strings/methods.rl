@@ -0,0 +1,5 @@
+print "abc".upper(), "AB".lower(), "ab".upper().lower(); # expect: ABC ab ab
+print " pad ".trim() + "|"; # expect: pad|
+print "abc".replace("b", "x"), "abc".index_of("c"); # expect: axc 2
+print "rust".starts_with("ru"), "abc".ends_with("c"), "a,b".contains(","); # expect: true true true
+print "abc".len; # expect-error: TypeError: 'string' has no property "len"
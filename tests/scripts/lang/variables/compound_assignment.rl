@@ -0,0 +1,10 @@
+let a = 5;
+a += 2;
+print a; # expect: 7
+a *= 3;
+print a; # expect: 21
+a /= 7;
+print a; # expect: 3
+let s = "a";
+s += "b";
+print s; # expect: ab
@@ -0,0 +1,4 @@
+const c = 1;
+print c; # expect: 1
+c = 2; # expect-error: RuntimeError: cannot reassign to a constant variable
+print c; # expect: 1
@@ -0,0 +1,7 @@
+let a = 1;
+let b;
+print a, b; # expect: 1 nil
+a = a + 1;
+print a; # expect: 2
+let c = a = 5;
+print a, c; # expect: 5 5
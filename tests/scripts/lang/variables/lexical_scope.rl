@@ -0,0 +1,7 @@
+# a function sees the variables where it was declared, not where it is called
+let shadowed = 1;
+fn show() { return shadowed; }
+{
+  let shadowed = 2;
+  print show(); # expect: 1
+}
@@ -0,0 +1,12 @@
+let a = "global";
+{
+  let a = "outer";
+  {
+    let a = "inner";
+    print a; # expect: inner
+  }
+  print a; # expect: outer
+}
+print a; # expect: global
+let len = 3;
+print len, builtin("len")("four"); # expect: 3 4
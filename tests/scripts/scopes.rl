@@ -0,0 +1,33 @@
+let a = "global";
+{
+  let a = "outer";
+  {
+    let a = "inner";
+    print a;
+  }
+  print a;
+}
+print a;
+
+let shadowed = 1;
+fn show() { return shadowed; }
+{
+  let shadowed = 2;
+  print show();
+}
+
+let len = 3;
+print len, builtin("len")("four");
+
+let i = 0;
+while (i < 3) {
+  let j = i * 10;
+  if (j == 10) {
+    print "ten";
+  } else if (j > 10) {
+    print "more";
+  } else {
+    print j;
+  }
+  i = i + 1;
+}
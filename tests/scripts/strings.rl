@@ -0,0 +1,20 @@
+let greeting = "hello";
+let name = "world";
+print greeting + ", " + name;
+print len(greeting), "abc".upper(), " pad ".trim(), "ab".repeat(3);
+print "a,b".contains(","), "rust".starts_with("ru"), type("x"), str(1.5);
+print format("{} + {} = {}", 1, 2, 3);
+let s = "";
+for (let i = 0; i < 5; i = i + 1) {
+  s = s + str(i);
+}
+print s, s == "01234", s != "1";
+print ord("A"), chr(66), num("42") + 1;
+
+# a copy of a string is unaffected by what is later assigned to the original
+let original = "ab";
+let copy = original;
+original = original + "c";
+print original, copy, copy == "ab";
+fn shout(text) { text = text + "!"; return text; }
+print shout(copy), copy;
@@ -0,0 +1,44 @@
+use std::{sync::Arc, thread};
+
+use rlisp::{Interpretor, Parser, Program, Value};
+
+fn assert_send<T: Send>() {}
+fn assert_sync<T: Sync>() {}
+
+const SOURCE: &str = "
+fn fib(n) { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); }
+let result = fib(seed) + seed;
+";
+
+#[test]
+fn test_program_is_shareable() {
+    assert_send::<Program>();
+    assert_sync::<Program>();
+    assert_send::<Arc<Program>>();
+}
+
+#[test]
+fn test_threads() {
+    // parsed once, then run by every thread on an interpreter of its own
+    let program = Arc::new(Parser::from_str(SOURCE).parse_program().unwrap());
+    let workers: Vec<_> = (0..4)
+        .map(|seed| {
+            let program = Arc::clone(&program);
+            thread::spawn(move || {
+                let mut interpretor = Interpretor::new();
+                interpretor
+                    .set_global("seed", Value::from(seed as f64))
+                    .unwrap();
+                interpretor.interpret(Program::clone(&program)).unwrap();
+                // values leave the thread converted to Rust types
+                interpretor
+                    .get_global("result")
+                    .unwrap()
+                    .get::<f64>()
+                    .unwrap()
+            })
+        })
+        .collect();
+    let results: Vec<f64> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+    assert_eq!(results, [0.0, 2.0, 3.0, 5.0]);
+}
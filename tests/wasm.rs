@@ -0,0 +1,37 @@
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+// Calls the playground the way the browser does, from the module
+// wasm-bindgen exports, run in node by wasm-bindgen-test-runner:
+//
+//     cargo install wasm-bindgen-cli --version <the one in Cargo.lock>
+//     cargo test --target wasm32-unknown-unknown --features wasm --test wasm
+use rlisp::playground;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn test_run() {
+    assert_eq!(
+        playground::run("print \"hi\";\nprint 1 + 1;"),
+        "{\"output\": \"hi\\n2\\n\", \"diagnostics\": []}"
+    );
+    let result = playground::run("let = 1;");
+    assert!(result.contains("\"code\": \"E02"), "{result}");
+}
+
+#[wasm_bindgen_test]
+fn test_unavailable() {
+    // with no clock to read, seeded the same for every run
+    let random = "print random();";
+    assert_eq!(playground::run(random), playground::run(random));
+    for call in [
+        "clock()",
+        "time()",
+        "sleep(1)",
+        "input()",
+        "read_file(\"a\")",
+    ] {
+        let result = playground::run(&format!("{call};\nprint \"after\";"));
+        assert!(result.starts_with("{\"output\": \"after\\n\""), "{result}");
+        assert!(result.contains("\"code\": \"E0400\""), "{call}: {result}");
+    }
+}